@@ -0,0 +1,243 @@
+use crate::files::{self, Failure, Outcome, Target};
+use crate::reader::{self, TmpOptions};
+use crate::stats::Stats;
+use std::collections::HashSet;
+use std::fs::{self, File};
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Mutex;
+use std::thread;
+
+/// Applies `f` to every item on at most `workers` threads, returning results in item order.
+///
+/// Workers take the next pending item as they free up, so one slow item does not hold
+/// back the rest of its share.
+pub(crate) fn map_bounded<T, R, F>(items: &[T], workers: usize, f: F) -> Vec<R>
+where
+    T: Sync,
+    R: Send,
+    F: Fn(&T) -> R + Sync,
+{
+    let next = AtomicUsize::new(0);
+    let results: Vec<Mutex<Option<R>>> = items.iter().map(|_| Mutex::new(None)).collect();
+
+    thread::scope(|scope| {
+        for _ in 0..workers.clamp(1, items.len().max(1)) {
+            scope.spawn(|| loop {
+                let idx = next.fetch_add(1, Ordering::Relaxed);
+                let Some(item) = items.get(idx) else {
+                    break;
+                };
+                let result = f(item);
+                *results[idx].lock().unwrap() = Some(result);
+            });
+        }
+    });
+
+    results
+        .into_iter()
+        .map(|result| result.into_inner().unwrap().unwrap())
+        .collect()
+}
+
+/// Exorcizes every file in `paths` in place, each with its own backup, on `workers` threads.
+///
+/// A failure is kept as that file's result and does not stop the others.
+pub(crate) fn exorcize_files(
+    paths: &[PathBuf],
+    workers: usize,
+    chunk_size: usize,
+    opts: &TmpOptions,
+) -> Vec<Result<Outcome<Stats>, Failure>> {
+    map_bounded(paths, workers, |path| {
+        let path = files::resolve_input(&path.to_string_lossy(), true)?;
+        files::exorcize_file(
+            &path,
+            Target::InPlace { atomic: true },
+            None,
+            |input: File, output, size| reader::exorcize_csv(input, output, size, chunk_size, opts),
+        )
+    })
+}
+
+/// The files under `root` whose path relative to it matches `pattern`, and the entries
+/// that could not be walked with why, each in sorted path order.
+///
+/// Symlinks are followed; a directory reached again through one, as in a loop, is
+/// skipped with an error. An unreadable directory is skipped the same way, with the
+/// walk going on past it.
+pub(crate) fn walk_tree(
+    root: &Path,
+    pattern: &glob::Pattern,
+) -> (Vec<PathBuf>, Vec<(PathBuf, io::Error)>) {
+    let mut files = Vec::new();
+    let mut errors = Vec::new();
+    let mut visited = HashSet::new();
+    walk_dir(root, root, pattern, &mut visited, &mut files, &mut errors);
+    files.sort();
+    errors.sort_by(|a, b| a.0.cmp(&b.0));
+    (files, errors)
+}
+
+fn walk_dir(
+    root: &Path,
+    dir: &Path,
+    pattern: &glob::Pattern,
+    visited: &mut HashSet<PathBuf>,
+    files: &mut Vec<PathBuf>,
+    errors: &mut Vec<(PathBuf, io::Error)>,
+) {
+    let entries = fs::canonicalize(dir).and_then(|canonical| {
+        if !visited.insert(canonical) {
+            let msg = "directory already visited, through a symlink loop";
+            return Err(io::Error::other(msg));
+        }
+        fs::read_dir(dir)?.collect::<io::Result<Vec<_>>>()
+    });
+    let mut entries = match entries {
+        Ok(entries) => entries,
+        Err(e) => return errors.push((dir.to_path_buf(), e)),
+    };
+    entries.sort_by_key(|entry| entry.file_name());
+
+    for entry in entries {
+        let path = entry.path();
+        // follows symlinks, unlike `DirEntry::file_type`
+        match fs::metadata(&path) {
+            Ok(meta) if meta.is_dir() => walk_dir(root, &path, pattern, visited, files, errors),
+            Ok(meta) if meta.is_file() => {
+                let relative = path.strip_prefix(root).unwrap_or(&path);
+                if pattern.matches_path(relative) {
+                    files.push(path);
+                }
+            }
+            Ok(_) => {}
+            Err(e) => errors.push((path, e)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rstest::*;
+    use std::fs;
+    use std::io;
+    use std::time::Duration;
+
+    #[rstest]
+    #[case(1)]
+    #[case(3)]
+    #[case(16)]
+    fn test_map_bounded_keeps_order(#[case] workers: usize) {
+        let items: Vec<u64> = (0..10).collect();
+        let out = map_bounded(&items, workers, |&n| {
+            // later items finish first
+            thread::sleep(Duration::from_millis(10 - n));
+            n * 2
+        });
+        assert_eq!(out, (0..10).map(|n| n * 2).collect::<Vec<_>>());
+    }
+
+    #[rstest]
+    fn test_map_bounded_empty() {
+        let out = map_bounded(&[] as &[u8], 4, |&n| n);
+        assert!(out.is_empty());
+    }
+
+    #[rstest]
+    fn test_exorcize_files_keeps_going_past_a_missing_file() {
+        let dir = std::env::temp_dir().join("bcp_exorcist_batch");
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+
+        let paths: Vec<PathBuf> = ["a.csv", "missing.csv", "b.csv", "c.csv"]
+            .iter()
+            .map(|name| dir.join(name))
+            .collect();
+        fs::write(&paths[0], b"a1\x1Ea2").unwrap();
+        fs::write(&paths[2], b"b1\x1Db2").unwrap();
+        fs::write(&paths[3], b"").unwrap();
+
+        let results = exorcize_files(&paths, 2, 3, &TmpOptions::default());
+        assert_eq!(results.len(), 4);
+        assert!(matches!(&results[0], Ok(Outcome::Exorcized(stats)) if stats.rows == 1));
+        assert!(matches!(
+            &results[1],
+            Err(Failure::Io(e)) if e.kind() == io::ErrorKind::NotFound
+        ));
+        assert!(matches!(&results[2], Ok(Outcome::Exorcized(stats)) if stats.rows == 2));
+        assert!(matches!(&results[3], Ok(Outcome::Empty)));
+
+        assert_eq!(fs::read(&paths[0]).unwrap(), b"\"a1\",\"a2\"");
+        assert_eq!(fs::read(&paths[2]).unwrap(), b"\"b1\"\n\"b2\"");
+        assert!(dir.join("a.csv.bak").exists());
+        assert!(dir.join("b.csv.bak").exists());
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[rstest]
+    fn test_walk_tree() {
+        let dir = std::env::temp_dir().join("bcp_exorcist_tree");
+        let _ = fs::remove_dir_all(&dir);
+        for sub in ["a/b", "c", "locked"] {
+            fs::create_dir_all(dir.join(sub)).unwrap();
+        }
+        for name in [
+            "top.csv",
+            "top.txt",
+            "a/one.csv",
+            "a/b/two.csv",
+            "c/three.CSV",
+        ] {
+            fs::write(dir.join(name), b"x\x1Ey").unwrap();
+        }
+        fs::write(dir.join("locked/hidden.csv"), b"x").unwrap();
+        #[cfg(unix)]
+        std::os::unix::fs::symlink(&dir, dir.join("a/b/loop")).unwrap();
+
+        #[cfg(unix)]
+        let locked = {
+            use std::os::unix::fs::PermissionsExt;
+            fs::set_permissions(dir.join("locked"), fs::Permissions::from_mode(0o000)).unwrap();
+            // root reads any directory
+            fs::read_dir(dir.join("locked")).is_err()
+        };
+        #[cfg(not(unix))]
+        let locked = false;
+
+        let pattern = glob::Pattern::new("*.csv").unwrap();
+        let (files, errors) = walk_tree(&dir, &pattern);
+
+        let mut exp: Vec<PathBuf> = ["a/b/two.csv", "a/one.csv", "top.csv"]
+            .iter()
+            .map(|name| dir.join(name))
+            .collect();
+        if !locked {
+            exp.push(dir.join("locked/hidden.csv"));
+            exp.sort();
+        }
+        assert_eq!(files, exp);
+
+        let mut exp_errors = Vec::new();
+        #[cfg(unix)]
+        exp_errors.push(dir.join("a/b/loop"));
+        if locked {
+            exp_errors.push(dir.join("locked"));
+        }
+        let error_paths: Vec<_> = errors.iter().map(|(path, _)| path.clone()).collect();
+        assert_eq!(error_paths, exp_errors);
+
+        let pattern = glob::Pattern::new("a/*/*.csv").unwrap();
+        assert_eq!(walk_tree(&dir, &pattern).0, vec![dir.join("a/b/two.csv")]);
+
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            fs::set_permissions(dir.join("locked"), fs::Permissions::from_mode(0o755)).unwrap();
+        }
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
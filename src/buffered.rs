@@ -0,0 +1,1280 @@
+use crate::reader::{
+    check_eof, extend_content, extend_extra_columns, is_kept, missing_fields, needs_quotes,
+    out_capacity, quote_field, trailing_backslashes, write_footer, write_header, ColType,
+    TmpOptions,
+};
+use crate::stats::{Partial, Stats};
+use memchr::{memchr2_iter, memchr_iter};
+use sha2::{Digest, Sha256};
+use std::collections::HashSet;
+use std::io::{self, BufReader, BufWriter, Read, Write};
+
+/// The distinct rows `dedup` tells apart by default, about 40 bytes each.
+pub(crate) const DEFAULT_DEDUP_ROWS: usize = 10_000_000;
+
+/// Receives the raw content of every field, by column index, before it is quoted.
+pub(crate) trait FieldHook {
+    fn on_field(&mut self, col: usize, field: &mut Vec<u8>) -> io::Result<()>;
+}
+
+/// A hook that leaves every field untouched.
+pub(crate) struct NoHook;
+
+impl FieldHook for NoHook {
+    fn on_field(&mut self, _col: usize, _field: &mut Vec<u8>) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+/// Buffered counterpart of `reader::exorcize_csv` that assembles each field in full.
+///
+/// Holding whole fields lets `hook` rewrite them by column, at the cost of an extra
+/// copy per field. The column counter resets on every `eol`. With a hook that leaves
+/// fields untouched, the output matches the streaming driver. Columns left out of
+/// `quote_columns` are written without quotes or quote escaping, unless a field holds
+/// an output delimiter, terminator or quote and would break the row: it is quoted then.
+/// With `column_order` each row is held whole, the hook run on its source columns, and
+/// written once complete in that order; quoting then follows the output columns. With
+/// `dedup` a row written exactly as an earlier one is dropped, and counted in
+/// `duplicate_rows`. With `length_prefixed` rows get their length first and no terminator.
+/// A field equal to `null_token` is written as `null_output`, as it is. `column_types`
+/// quotes by output column, and fails the run on a `Number` field that needs quotes.
+pub(crate) fn exorcize_csv<R, W, H>(
+    input: R,
+    output: W,
+    chunk_size: usize,
+    opts: &TmpOptions,
+    hook: &mut H,
+) -> io::Result<Stats>
+where
+    R: Read,
+    W: Write,
+    H: FieldHook,
+{
+    let mut stats = Stats::default();
+    stats.column_widths = opts.column_widths.then(Vec::new);
+    stats.duplicate_rows = opts.dedup.map(|_| 0);
+    match run(input, output, chunk_size, opts, hook, &mut stats) {
+        Ok(()) => Ok(stats),
+        Err(e) => Err(Partial::attach(stats, e)),
+    }
+}
+
+/// The body of `exorcize_csv`, leaving `stats` with the caller so a failure keeps them.
+fn run<R, W, H>(
+    input: R,
+    output: W,
+    chunk_size: usize,
+    opts: &TmpOptions,
+    hook: &mut H,
+    stats: &mut Stats,
+) -> io::Result<()>
+where
+    R: Read,
+    W: Write,
+    H: FieldHook,
+{
+    let mut reader = BufReader::new(input);
+    let mut writer = BufWriter::with_capacity(opts.write_size, output);
+
+    let mut buf = vec![0u8; chunk_size];
+    let mut out = Vec::new();
+    let mut field = Vec::new();
+    // the fields of the row so far, only held for `column_order`
+    let mut row = Vec::new();
+    let mut col = 0;
+    // row terminators are deferred so the last one can be dropped
+    let mut terminated = false;
+    let mut written = false;
+    let mut backslashes = 0;
+    let mut seen = opts.dedup.map(|_| HashSet::new());
+    let mut mark = Mark::default();
+    let out_eol: &[u8] = match opts.length_prefixed {
+        true => &[],
+        false => &opts.out_eol,
+    };
+
+    loop {
+        let read = reader.read(&mut buf)?;
+
+        if read == 0 {
+            break;
+        }
+
+        let haystack = &buf[..read];
+        out.reserve_exact(out_capacity(read, stats));
+        if stats.bytes_read == 0 {
+            write_header(&mut out, opts);
+        }
+        stats.observe(haystack);
+        backslashes = trailing_backslashes(backslashes, haystack);
+        stats.quotes += memchr_iter(opts.quote, haystack).count() as u64;
+
+        // a `sep` held from the last batch breaks before the first byte, unless it trailed
+        let held = stats.take_held_sep() && haystack[0] != opts.eol;
+        let breaks = memchr2_iter(opts.sep, opts.eol, haystack).map(|pos| (pos, pos + 1));
+        let mut idx = 0;
+        for (pos, next) in held.then_some((0, 0)).into_iter().chain(breaks) {
+            field.extend_from_slice(&haystack[idx..pos]);
+            let is_sep = next == pos || haystack[pos] == opts.sep;
+            if is_sep && next > pos && opts.ignore_trailing_sep {
+                match haystack.get(next) {
+                    Some(&byte) if byte != opts.eol => {}
+                    Some(_) => {
+                        stats.on_content();
+                        idx = next;
+                        continue;
+                    }
+                    None => {
+                        stats.hold_sep();
+                        idx = next;
+                        continue;
+                    }
+                }
+            }
+            if col == 0 {
+                mark = Mark::new(&out, terminated, written);
+            }
+            if terminated {
+                out.extend_from_slice(out_eol);
+                terminated = false;
+            }
+            if col == 0 {
+                out.extend(opts.record_prefix);
+            }
+            take_field(col, &mut field, &mut row, &mut out, opts, hook, stats)?;
+            written = true;
+
+            if is_sep {
+                if is_kept(opts, col as u64 + 1) && opts.column_order.is_none() {
+                    out.extend_from_slice(&opts.out_delim);
+                }
+                col += 1;
+                stats.on_sep();
+            } else {
+                emit_ordered(&mut row, &mut out, opts, stats)?;
+                pad_row(col, &mut out, opts, stats);
+                extend_extra_columns(&mut out, stats.rows + 1, false, opts);
+                terminated = true;
+                col = 0;
+                stats.on_eol();
+                if drop_repeat(&mut seen, &mut out, &mark, out_eol, stats, opts)? {
+                    (terminated, written) = (mark.terminated, mark.written);
+                } else {
+                    frame(&mut out, mark.at, opts)?;
+                }
+            }
+            idx = next;
+        }
+        field.extend_from_slice(&haystack[idx..]);
+        if !field.is_empty() {
+            stats.on_content();
+        }
+
+        // a row still being written stays until it is known not to repeat, or measured
+        let done = match seen.is_some() || opts.length_prefixed {
+            true if col > 0 => mark.at,
+            _ => out.len(),
+        };
+        writer.write_all(&out[..done])?;
+        stats.bytes_written += done as u64;
+        out.drain(..done);
+        mark.at -= mark.at.min(done);
+    }
+
+    check_eof(backslashes, opts, stats)?;
+
+    // a trailing `eol` already closed the last row, but not a trailing `sep`
+    let held = stats.take_held_sep();
+    if col > 0 || !field.is_empty() || held {
+        if col == 0 {
+            mark = Mark::new(&out, terminated, written);
+        }
+        if terminated {
+            out.extend_from_slice(out_eol);
+            terminated = false;
+        }
+        if col == 0 {
+            out.extend(opts.record_prefix);
+        }
+        take_field(col, &mut field, &mut row, &mut out, opts, hook, stats)?;
+        emit_ordered(&mut row, &mut out, opts, stats)?;
+        pad_row(col, &mut out, opts, stats);
+        extend_extra_columns(&mut out, stats.rows + 1, false, opts);
+        written = true;
+        // the row before stays unterminated, as the input ended here
+        if drop_repeat(&mut seen, &mut out, &mark, out_eol, stats, opts)? {
+            written = mark.written;
+        } else {
+            frame(&mut out, mark.at, opts)?;
+        }
+    }
+
+    let terminated = written && opts.final_newline.unwrap_or(terminated);
+    if terminated {
+        out.extend_from_slice(out_eol);
+    }
+    if written {
+        write_footer(&mut out, terminated, stats, opts);
+    }
+
+    writer.write_all(&out)?;
+    writer.flush()?;
+    stats.bytes_written += out.len() as u64;
+    stats.finish();
+    Ok(())
+}
+
+/// Runs the hook on `field` and writes it, unless `pad_to` drops its column.
+///
+/// Its width is taken after the hook and before any escaping: the value a loader reads.
+/// A `null_token` field skips the hook and is written as `null_output`.
+#[inline(always)]
+fn emit_field<H: FieldHook>(
+    col: usize,
+    field: &mut Vec<u8>,
+    out: &mut Vec<u8>,
+    opts: &TmpOptions,
+    hook: &mut H,
+    stats: &mut Stats,
+) -> io::Result<()> {
+    if !is_kept(opts, col as u64) {
+        return Ok(());
+    }
+    if opts.null_token.as_ref() == Some(field) {
+        stats.width(col, opts.null_output.len());
+        out.extend_from_slice(&opts.null_output);
+        return Ok(());
+    }
+    hook.on_field(col, field)?;
+    check_number(col, field, opts, stats)?;
+    stats.width(col, field.len());
+    match opts.quotes_field(col, field) {
+        true => quote_field(field, out, opts),
+        false => extend_content(out, field, opts),
+    }
+    Ok(())
+}
+
+/// Where the row being written starts in the output, before its deferred terminator,
+/// and the state to go back to if `dedup` drops it.
+#[derive(Default)]
+struct Mark {
+    at: usize,
+    terminated: bool,
+    written: bool,
+}
+
+impl Mark {
+    fn new(out: &[u8], terminated: bool, written: bool) -> Self {
+        Mark {
+            at: out.len(),
+            terminated,
+            written,
+        }
+    }
+}
+
+/// Cuts the row written to `out` since `mark` when it repeats an earlier one, returning
+/// whether it did; never without `dedup`.
+///
+/// Rows are told apart by a 128-bit digest of their fixed bytes, terminator aside, so
+/// memory grows with the distinct rows rather than their width. More than `dedup`
+/// distinct rows is an `InvalidInput` error.
+fn drop_repeat(
+    seen: &mut Option<HashSet<[u8; 16]>>,
+    out: &mut Vec<u8>,
+    mark: &Mark,
+    out_eol: &[u8],
+    stats: &mut Stats,
+    opts: &TmpOptions,
+) -> io::Result<bool> {
+    let (Some(seen), Some(max_rows)) = (seen, opts.dedup) else {
+        return Ok(false);
+    };
+    let body = mark.at + if mark.terminated { out_eol.len() } else { 0 };
+    let mut digest = [0u8; 16];
+    digest.copy_from_slice(&Sha256::digest(&out[body..])[..16]);
+    if seen.insert(digest) {
+        if seen.len() > max_rows {
+            let msg = format!("more than {max_rows} distinct rows to dedup");
+            return Err(io::Error::new(io::ErrorKind::InvalidInput, msg));
+        }
+        return Ok(false);
+    }
+    out.truncate(mark.at);
+    *stats.duplicate_rows.get_or_insert(0) += 1;
+    Ok(true)
+}
+
+/// Puts the length of the row written to `out` since `at` before it, as 4 little-endian
+/// bytes, with `length_prefixed`. A row too long for them is an `InvalidData` error.
+fn frame(out: &mut Vec<u8>, at: usize, opts: &TmpOptions) -> io::Result<()> {
+    if !opts.length_prefixed {
+        return Ok(());
+    }
+    let len = u32::try_from(out.len() - at).map_err(|_| {
+        let msg = format!(
+            "a row of {} bytes is too long for its length prefix",
+            out.len() - at
+        );
+        io::Error::new(io::ErrorKind::InvalidData, msg)
+    })?;
+    out.splice(at..at, len.to_le_bytes());
+    Ok(())
+}
+
+/// Writes the complete `field` of column `col`, or holds it in `row` for `column_order`
+/// once the hook ran on it; `field` is left empty for the next one.
+#[inline(always)]
+fn take_field<H: FieldHook>(
+    col: usize,
+    field: &mut Vec<u8>,
+    row: &mut Vec<Vec<u8>>,
+    out: &mut Vec<u8>,
+    opts: &TmpOptions,
+    hook: &mut H,
+    stats: &mut Stats,
+) -> io::Result<()> {
+    if opts.column_order.is_none() {
+        emit_field(col, field, out, opts, hook, stats)?;
+        field.clear();
+        return Ok(());
+    }
+    hook.on_field(col, field)?;
+    row.push(std::mem::take(field));
+    Ok(())
+}
+
+/// Writes the held `row` in `column_order`, if set, and clears it.
+///
+/// A column the row lacks is written empty, or fails the run with `strict_column_order`.
+fn emit_ordered(
+    row: &mut Vec<Vec<u8>>,
+    out: &mut Vec<u8>,
+    opts: &TmpOptions,
+    stats: &mut Stats,
+) -> io::Result<()> {
+    let Some(order) = &opts.column_order else {
+        return Ok(());
+    };
+    for (col, &source) in order.iter().enumerate() {
+        if col > 0 {
+            out.extend_from_slice(&opts.out_delim);
+        }
+        let field = match row.get(source) {
+            Some(field) => &field[..],
+            None if opts.strict_column_order => {
+                let msg = format!(
+                    "row {} has {} fields, column_order needs column {source}",
+                    stats.rows,
+                    row.len()
+                );
+                return Err(io::Error::new(io::ErrorKind::InvalidData, msg));
+            }
+            None => b"",
+        };
+        check_number(col, field, opts, stats)?;
+        stats.width(col, field.len());
+        match opts.quotes_field(col, field) {
+            true => quote_field(field, out, opts),
+            false => extend_content(out, field, opts),
+        }
+    }
+    row.clear();
+    Ok(())
+}
+
+/// Fails on a `field` of a `Number` column of `column_types` that cannot be written
+/// unquoted without breaking its row.
+#[inline(always)]
+fn check_number(col: usize, field: &[u8], opts: &TmpOptions, stats: &Stats) -> io::Result<()> {
+    if opts.column_type(col) == Some(ColType::Number) && needs_quotes(field, opts) {
+        let msg = format!(
+            "row {} column {col} is a number column but needs quotes",
+            stats.rows
+        );
+        return Err(io::Error::new(io::ErrorKind::InvalidData, msg));
+    }
+    Ok(())
+}
+
+/// Appends the empty fields `pad_to` requires after the last column `col`.
+#[inline(always)]
+fn pad_row(col: usize, out: &mut Vec<u8>, opts: &TmpOptions, stats: &mut Stats) {
+    let missing = missing_fields(opts, col as u64) as usize;
+    for col in col + 1..=col + missing {
+        stats.width(col, 0);
+        match opts.quotes_field(col, b"") {
+            true => out.extend_from_slice(&opts.empty_field()),
+            false => out.extend_from_slice(&opts.out_delim),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::reader::{self, Escapes, ExtraCol};
+    use rstest::*;
+    use std::collections::HashMap;
+    use std::io::Cursor;
+
+    struct Upper(usize);
+
+    impl FieldHook for Upper {
+        fn on_field(&mut self, col: usize, field: &mut Vec<u8>) -> io::Result<()> {
+            if col == self.0 {
+                field.make_ascii_uppercase();
+            }
+            Ok(())
+        }
+    }
+
+    struct Fail;
+
+    /// Fails on the `n`th field it sees.
+    struct FailOn(usize);
+
+    impl FieldHook for FailOn {
+        fn on_field(&mut self, _col: usize, _field: &mut Vec<u8>) -> io::Result<()> {
+            self.0 -= 1;
+            match self.0 {
+                0 => Err(io::Error::other("hook failed")),
+                _ => Ok(()),
+            }
+        }
+    }
+
+    impl FieldHook for Fail {
+        fn on_field(&mut self, _col: usize, _field: &mut Vec<u8>) -> io::Result<()> {
+            Err(io::Error::other("hook failed"))
+        }
+    }
+
+    #[rstest]
+    #[case(b"field1\x1Efield2\x1Dfield3", 1024)]
+    #[case(b"field1\x1Efield2\x1Dfield3", 3)]
+    #[case(b"field1\\\x1Efield2\\\x1Dfield3", 2)]
+    #[case(b"\"\"field\",\"field\",field\"\x1Efield3\x1D", 4)]
+    #[case(b"a\x1Db\x1D\x1D", 1)]
+    #[case(b"a\x1E", 1)]
+    #[case(b"\x1D\x1E", 1)]
+    #[case(b"a\\", 1)]
+    #[case(b"a\\\\\x1Eb\\\\\\\x1Dc\\\\", 1)]
+    #[case(b"a\"", 2)]
+    #[case(b"", 1024)]
+    fn test_exorcize_csv_matches_streaming(#[case] data: &[u8], #[case] chunk_size: usize) {
+        let opts = TmpOptions::default();
+
+        let mut streamed = Vec::new();
+        let size = data.len() as u64;
+        let exp =
+            reader::exorcize_csv(Cursor::new(data), &mut streamed, size, 1024, &opts).unwrap();
+
+        let mut buffered = Vec::new();
+        let stats = exorcize_csv(
+            Cursor::new(data),
+            &mut buffered,
+            chunk_size,
+            &opts,
+            &mut NoHook,
+        )
+        .unwrap();
+        assert_eq!(buffered, streamed);
+        assert_eq!(
+            (stats.rows, stats.fields, stats.quotes, stats.bytes_written),
+            (exp.rows, exp.fields, exp.quotes, exp.bytes_written)
+        );
+        assert_eq!(stats.anomalies, exp.anomalies);
+    }
+
+    #[rstest]
+    #[case(b"field1\x1Efield2\x1D\x1D")]
+    #[case(b"field1\"\x1E\"")]
+    #[case(b"a\\\\\x1Eb\\\\\\\x1Dc\\\\")]
+    #[case(b"a\\\"b\x1Ea\\\\\"b\x1E\\\"")]
+    #[case(b"\"\"field\",\"field\",field\"\x1Efield3\x1D")]
+    #[case(b"\xEF\xBB\xBFa\x1D\"\"\"")]
+    fn test_exorcize_csv_matches_at_small_chunk_sizes(
+        #[case] data: &[u8],
+        #[values(1, 2, 3, 1024)] chunk_size: usize,
+    ) {
+        let options = [
+            TmpOptions::default(),
+            TmpOptions {
+                quote_columns: Some(vec![0]),
+                quote_empty: false,
+                ..TmpOptions::default()
+            },
+            TmpOptions {
+                collapse_double_quotes: true,
+                pad_to: Some(2),
+                ..TmpOptions::default()
+            },
+        ];
+        for opts in options {
+            let mut exp = Vec::new();
+            exorcize_csv(Cursor::new(data), &mut exp, 1024, &opts, &mut NoHook).unwrap();
+            let mut output = Vec::new();
+            exorcize_csv(
+                Cursor::new(data),
+                &mut output,
+                chunk_size,
+                &opts,
+                &mut NoHook,
+            )
+            .unwrap();
+            assert_eq!(output, exp, "{opts:?}");
+        }
+    }
+
+    #[rstest]
+    #[case(b"a\x1Eb\x1Dc", None)]
+    #[case(b"a\x1Dc\x1D", None)]
+    #[case(b"a\x1Dc\x1D", Some(false))]
+    #[case(b"a\x1E", Some(true))]
+    #[case(b"", Some(true))]
+    fn test_exorcize_csv_footer_matches_streaming(
+        #[case] data: &[u8],
+        #[case] final_newline: Option<bool>,
+    ) {
+        let opts = TmpOptions {
+            final_newline,
+            header: Some(vec![b"h".to_vec()]),
+            footer: Some("#rows={rows}".to_string()),
+            ..TmpOptions::default()
+        };
+
+        let mut streamed = Vec::new();
+        let size = data.len() as u64;
+        reader::exorcize_csv(Cursor::new(data), &mut streamed, size, 1024, &opts).unwrap();
+
+        let mut buffered = Vec::new();
+        let result = exorcize_csv(Cursor::new(data), &mut buffered, 2, &opts, &mut NoHook);
+        assert!(result.is_ok());
+        assert_eq!(buffered, streamed);
+    }
+
+    #[rstest]
+    #[case(b"id\x1Ename\x1D1\x1Eada\x1D22\x1Eb\"o\\b", None, vec![2, 5])]
+    #[case(b"a\x1Dbb\x1Ec\x1Eddd\x1D\x1D", None, vec![2, 1, 3])]
+    #[case(b"a\x1Eb\x1Ec\x1Dd", Some(4), vec![1, 1, 1, 0])]
+    #[case(b"aa\x1Ebbb\x1Ec", Some(1), vec![2])]
+    #[case(b"", None, vec![])]
+    fn test_exorcize_csv_column_widths(
+        #[case] data: &[u8],
+        #[case] pad_to: Option<usize>,
+        #[case] exp: Vec<u64>,
+    ) {
+        let opts = TmpOptions {
+            pad_to,
+            column_widths: true,
+            ..TmpOptions::default()
+        };
+
+        for chunk_size in [1, 2, 1024] {
+            let stats = exorcize_csv(
+                Cursor::new(data),
+                io::sink(),
+                chunk_size,
+                &opts,
+                &mut NoHook,
+            )
+            .unwrap();
+            assert_eq!(
+                stats.column_widths.as_ref(),
+                Some(&exp),
+                "chunk_size {chunk_size}"
+            );
+        }
+        let stats = exorcize_csv(Cursor::new(data), io::sink(), 2, &opts, &mut Upper(0)).unwrap();
+        assert_eq!(stats.column_widths, Some(exp));
+    }
+
+    #[rstest]
+    fn test_exorcize_csv_copy_compatible() {
+        let data = b"1\x1Eplain\x1Dx,y\x1Esay \"hi\"\x1D\x1Ea\\b\\\x1D3\x1Eline\nbreak\x1E\\\"";
+        let opts = TmpOptions::default().copy_compatible();
+
+        let exp = concat!(
+            "1,plain\n",
+            "\"x,y\",\"say \"\"hi\"\"\"\n",
+            ",a\\b\\\n",
+            "3,\"line\nbreak\",\"\\\"\"\"",
+        );
+        for chunk_size in [1, 2, 1024] {
+            let mut out = Vec::new();
+            exorcize_csv(Cursor::new(data), &mut out, chunk_size, &opts, &mut NoHook).unwrap();
+            assert_eq!(
+                String::from_utf8(out).unwrap(),
+                exp,
+                "chunk_size {chunk_size}"
+            );
+        }
+    }
+
+    #[rstest]
+    #[case(0, "\"ABC\",\"def\"\n\"GHI\",\"jkl\"")]
+    #[case(1, "\"abc\",\"DEF\"\n\"ghi\",\"JKL\"")]
+    #[case(2, "\"abc\",\"def\"\n\"ghi\",\"jkl\"")]
+    fn test_exorcize_csv_hook_by_column(#[case] col: usize, #[case] exp: &str) {
+        let data = b"abc\x1Edef\x1Dghi\x1Ejkl";
+        let opts = TmpOptions::default();
+
+        let mut out = Vec::new();
+        let result = exorcize_csv(Cursor::new(data), &mut out, 2, &opts, &mut Upper(col));
+        assert!(result.is_ok());
+        assert_eq!(out, exp.as_bytes());
+    }
+
+    #[rstest]
+    #[case(b"field1\x1Efield2", Some(true))]
+    #[case(b"field1\x1Efield2\x1D", Some(true))]
+    #[case(b"field1\x1Efield2", Some(false))]
+    #[case(b"field1\x1Dfield2\x1D", Some(false))]
+    #[case(b"field1\x1Dfield2\x1D", None)]
+    #[case(b"", Some(true))]
+    fn test_exorcize_csv_final_newline_matches_streaming(
+        #[case] data: &[u8],
+        #[case] final_newline: Option<bool>,
+    ) {
+        let opts = TmpOptions {
+            final_newline,
+            ..TmpOptions::default()
+        };
+
+        let mut streamed = Vec::new();
+        let size = data.len() as u64;
+        reader::exorcize_csv(Cursor::new(data), &mut streamed, size, 1024, &opts).unwrap();
+
+        let mut buffered = Vec::new();
+        let result = exorcize_csv(Cursor::new(data), &mut buffered, 2, &opts, &mut NoHook);
+        assert!(result.is_ok());
+        assert_eq!(buffered, streamed);
+    }
+
+    #[rstest]
+    #[case(b"a\x1Db\x1Ec\x1D", 3)]
+    #[case(b"a\x1Db\x1Ec", 3)]
+    #[case(b"a\\\x1D\x1Db", 3)]
+    #[case(b"a\x1Eb\x1Ec\x1Dd\x1Ee", 2)]
+    #[case(b"a\x1Eb\\\x1E\"c\\\x1Dd\x1Ee\x1Ef", 2)]
+    #[case(b"aa\x1Eb\x1Ec", 1)]
+    fn test_exorcize_csv_pad_to_matches_streaming(#[case] data: &[u8], #[case] pad_to: usize) {
+        let opts = TmpOptions {
+            pad_to: Some(pad_to),
+            ..TmpOptions::default()
+        };
+
+        let mut streamed = Vec::new();
+        let size = data.len() as u64;
+        reader::exorcize_csv(Cursor::new(data), &mut streamed, size, 1024, &opts).unwrap();
+
+        let mut buffered = Vec::new();
+        let result = exorcize_csv(Cursor::new(data), &mut buffered, 2, &opts, &mut NoHook);
+        assert!(result.is_ok());
+        assert_eq!(buffered, streamed);
+    }
+
+    #[rstest]
+    #[case(b"a\x1Eb\x1Dc", None)]
+    #[case(b"a\x1Db\x1D\x1D", None)]
+    #[case(b"a\x1Eb\x1Ec\x1Dd", Some(2))]
+    #[case(b"a\x1Dd", Some(3))]
+    fn test_exorcize_csv_extra_columns_match_streaming(
+        #[case] data: &[u8],
+        #[case] pad_to: Option<usize>,
+    ) {
+        let opts = TmpOptions {
+            pad_to,
+            extra_columns: Some(vec![ExtraCol::Literal(b"f".to_vec()), ExtraCol::RowNumber]),
+            ..TmpOptions::default()
+        };
+
+        let mut streamed = Vec::new();
+        let size = data.len() as u64;
+        reader::exorcize_csv(Cursor::new(data), &mut streamed, size, 1024, &opts).unwrap();
+
+        let mut buffered = Vec::new();
+        let result = exorcize_csv(Cursor::new(data), &mut buffered, 2, &opts, &mut NoHook);
+        assert!(result.is_ok());
+        assert_eq!(buffered, streamed);
+    }
+
+    #[rstest]
+    #[case(b"a\\\x1Eb\\\x1Dc\\")]
+    #[case(b"a\\\\\x1E\\")]
+    fn test_exorcize_csv_no_reescape_matches_streaming(#[case] data: &[u8]) {
+        let opts = TmpOptions {
+            reescape_delims: false,
+            ..TmpOptions::default()
+        };
+
+        let mut streamed = Vec::new();
+        let size = data.len() as u64;
+        reader::exorcize_csv(Cursor::new(data), &mut streamed, size, 1024, &opts).unwrap();
+
+        let mut buffered = Vec::new();
+        let result = exorcize_csv(Cursor::new(data), &mut buffered, 2, &opts, &mut NoHook);
+        assert!(result.is_ok());
+        assert_eq!(buffered, streamed);
+    }
+
+    #[rstest]
+    #[case(b"a'b\x1Ec\x1Dd", b"|")]
+    #[case(b"a\x1Eb\x1D\x1D", b"|")]
+    #[case(b"a'b\x1Ec\x1Dd\x1D", b"\r\n")]
+    #[case(b"a\x1Eb\x1D\x1D", b"|\r\n")]
+    fn test_exorcize_csv_custom_output_bytes_match_streaming(
+        #[case] data: &[u8],
+        #[case] out_eol: &[u8],
+    ) {
+        let opts = TmpOptions {
+            quote: b'\'',
+            out_delim: b";".to_vec(),
+            out_eol: out_eol.to_vec(),
+            ..TmpOptions::default()
+        };
+
+        let mut streamed = Vec::new();
+        let size = data.len() as u64;
+        reader::exorcize_csv(Cursor::new(data), &mut streamed, size, 1024, &opts).unwrap();
+
+        let mut buffered = Vec::new();
+        let result = exorcize_csv(Cursor::new(data), &mut buffered, 2, &opts, &mut NoHook);
+        assert!(result.is_ok());
+        assert_eq!(buffered, streamed);
+    }
+
+    #[rstest]
+    #[case(b"a\x0Bb\x1E\x0B\x1Dc\\\x0C")]
+    #[case(b"\"\x0B\"\\\x1E\\")]
+    fn test_exorcize_csv_escapes_match_streaming(#[case] data: &[u8]) {
+        let escapes = [(b'\x0B', b"\\v".to_vec()), (b'\\', b"\\\\".to_vec())];
+        let opts = TmpOptions {
+            escapes: Some(Escapes::new(escapes)),
+            ..TmpOptions::default()
+        };
+
+        let mut streamed = Vec::new();
+        let size = data.len() as u64;
+        reader::exorcize_csv(Cursor::new(data), &mut streamed, size, 1024, &opts).unwrap();
+
+        let mut buffered = Vec::new();
+        let result = exorcize_csv(Cursor::new(data), &mut buffered, 2, &opts, &mut NoHook);
+        assert!(result.is_ok());
+        assert_eq!(buffered, streamed);
+    }
+
+    #[rstest]
+    #[case(
+        b"a\x1Eb\x1Ec\x1Ed\x1De\"\x1Ef\"\x1Eg\x1Eh",
+        None,
+        "\"a\",b,\"c\",d\n\"e\\\"\",\"f\\\"\",\"g\",h"
+    )]
+    #[case(b"a\x1Db\x1Ec", Some(4), "\"a\",,\"\",\n\"b\",c,\"\",")]
+    #[case(b"a\x1Eb\\\x1Ec\\", None, "\"a\",b\\,\"c\\\\\"")]
+    fn test_exorcize_csv_quote_columns(
+        #[case] data: &[u8],
+        #[case] pad_to: Option<usize>,
+        #[case] exp: &str,
+    ) {
+        let opts = TmpOptions {
+            pad_to,
+            quote_columns: Some(vec![0, 2]),
+            ..TmpOptions::default()
+        };
+
+        let mut out = Vec::new();
+        let result = exorcize_csv(Cursor::new(data), &mut out, 3, &opts, &mut NoHook);
+        assert!(result.is_ok());
+        assert_eq!(String::from_utf8(out).unwrap(), exp);
+    }
+
+    #[rstest]
+    #[case(b"a\x1Eb\x1Ec\x1Ed\x1Ee", "\"a\",\"b\",c,d,\"e\"")]
+    // quoted to keep the row intact, or left empty
+    #[case(b"a\x1Eb\x1Ec,\x1E\x1E", "\"a\",\"b\",\"c,\",,\"\"")]
+    fn test_exorcize_csv_quote_columns_adjacency(#[case] data: &[u8], #[case] exp: &str) {
+        // every pair of neighbors: quoted-quoted, quoted-unquoted, unquoted-unquoted and
+        // unquoted-quoted, each field owning its quotes with a bare delimiter between them
+        let opts = TmpOptions {
+            quote_columns: Some(vec![0, 1, 4]),
+            ..TmpOptions::default()
+        };
+
+        for chunk_size in 1..=data.len() {
+            let mut out = Vec::new();
+            exorcize_csv(Cursor::new(data), &mut out, chunk_size, &opts, &mut NoHook).unwrap();
+            assert_eq!(
+                String::from_utf8(out).unwrap(),
+                exp,
+                "chunk_size {chunk_size}"
+            );
+        }
+    }
+
+    #[rstest]
+    #[case(b"x\x1E-5\x1Ea,b\x1Ec", "\"x\",-5,\"a,b\",c")]
+    #[case(b"\x1E-0.5e3\x1E\x1D\x1E\x1E", "\"\",-0.5e3,\n\"\",,")]
+    #[case(b"say \"hi\"\x1E1\x1Eit's", "\"say \\\"hi\\\"\",1,it's")]
+    #[case(b"a\x1E1,5", "row 0 column 1 is a number column but needs quotes")]
+    #[case(
+        b"a\x1E1\x1Db\x1E\"2\"",
+        "row 1 column 1 is a number column but needs quotes"
+    )]
+    fn test_exorcize_csv_column_types(#[case] data: &[u8], #[case] exp: &str) {
+        let opts = TmpOptions {
+            column_types: Some(vec![ColType::Text, ColType::Number, ColType::Auto]),
+            ..TmpOptions::default()
+        };
+
+        for chunk_size in 1..=data.len() {
+            let mut out = Vec::new();
+            let result = exorcize_csv(Cursor::new(data), &mut out, chunk_size, &opts, &mut NoHook);
+            match result {
+                Ok(_) => assert_eq!(String::from_utf8(out).unwrap(), exp),
+                Err(e) => {
+                    assert_eq!(e.kind(), io::ErrorKind::InvalidData);
+                    assert_eq!(e.to_string(), exp, "chunk_size {chunk_size}");
+                }
+            }
+        }
+    }
+
+    #[rstest]
+    #[case(b"a\x1E\x1Eb", None, None, "\"a\",,\"b\"")]
+    #[case(b"\x1Ea\x1Db", None, None, ",\"a\"\n\"b\"")]
+    #[case(b"a\x1E\x1Db\x1E", None, None, "\"a\",\n\"b\",")]
+    #[case(b"a\x1Db", Some(3), None, "\"a\",,\n\"b\",,")]
+    #[case(b"\x1Ea\x1E\x1Eb", None, Some(vec![1]), ",\"a\",,b")]
+    fn test_exorcize_csv_quote_empty_off(
+        #[case] data: &[u8],
+        #[case] pad_to: Option<usize>,
+        #[case] quote_columns: Option<Vec<usize>>,
+        #[case] exp: &str,
+    ) {
+        let opts = TmpOptions {
+            quote_empty: false,
+            pad_to,
+            quote_columns,
+            ..TmpOptions::default()
+        };
+
+        let mut out = Vec::new();
+        let result = exorcize_csv(Cursor::new(data), &mut out, 2, &opts, &mut NoHook);
+        assert!(result.is_ok());
+        assert_eq!(String::from_utf8(out).unwrap(), exp);
+    }
+
+    #[rstest]
+    #[case(b"a\x1Eb\\", false)]
+    #[case(b"a\x1Eb\\\\", true)]
+    #[case(b"a\x1Eb\\\x1D", true)]
+    fn test_exorcize_csv_strict_eof(#[case] data: &[u8], #[case] ok: bool) {
+        let opts = TmpOptions {
+            strict_eof: true,
+            ..TmpOptions::default()
+        };
+
+        let result = exorcize_csv(Cursor::new(data), Vec::new(), 1, &opts, &mut NoHook);
+        assert_eq!(result.is_ok(), ok);
+    }
+
+    #[rstest]
+    #[case(b"\"\"field\",\"field\",field\"\x1Efield3\x1D", 4)]
+    #[case(b"a\"\"\"\"\"b\x1E\"\"\x1Dc\"\"\"d", 3)]
+    fn test_exorcize_csv_collapse_double_quotes_matches_streaming(
+        #[case] data: &[u8],
+        #[case] chunk_size: usize,
+    ) {
+        let opts = TmpOptions {
+            collapse_double_quotes: true,
+            ..TmpOptions::default()
+        };
+
+        let mut streamed = Vec::new();
+        let size = data.len() as u64;
+        reader::exorcize_csv(Cursor::new(data), &mut streamed, size, 1024, &opts).unwrap();
+
+        let mut buffered = Vec::new();
+        let result = exorcize_csv(
+            Cursor::new(data),
+            &mut buffered,
+            chunk_size,
+            &opts,
+            &mut NoHook,
+        );
+        assert!(result.is_ok());
+        assert_eq!(buffered, streamed);
+    }
+
+    #[rstest]
+    #[case(b"a,b\x1Ec", b',', "\"a,b\",c")]
+    #[case(b"a\tb\x1Ec", b'\t', "\"a\tb\"\tc")]
+    #[case(b"a\nb\x1Ec", b',', "\"a\nb\",c")]
+    #[case(b"a\"b\x1Ec,d", b',', "\"a\\\"b\",\"c,d\"")]
+    #[case(b"a,b\x1Ec", b'\t', "a,b\tc")]
+    fn test_exorcize_csv_unquoted_delimiter_in_content(
+        #[case] data: &[u8],
+        #[case] out_delim: u8,
+        #[case] exp: &str,
+    ) {
+        let opts = TmpOptions {
+            out_delim: vec![out_delim],
+            quote_columns: Some(vec![]),
+            ..TmpOptions::default()
+        };
+
+        let mut out = Vec::new();
+        let result = exorcize_csv(Cursor::new(data), &mut out, 1024, &opts, &mut NoHook);
+        assert!(result.is_ok());
+        assert_eq!(String::from_utf8(out).unwrap(), exp);
+    }
+
+    #[rstest]
+    #[case(b"::", b"a\x1Eb\x1Dc\x1E\x1E\\\x1D\x1D")]
+    #[case(b"|;|", b"a|b\x1E;\x1Dc\x1E\x1E\"\x1D")]
+    fn test_exorcize_csv_multi_byte_out_delim_matches_streaming(
+        #[case] out_delim: &[u8],
+        #[case] data: &[u8],
+    ) {
+        let opts = TmpOptions {
+            out_delim: out_delim.to_vec(),
+            pad_to: Some(3),
+            extra_columns: Some(vec![crate::reader::ExtraCol::RowNumber]),
+            header: Some(vec![b"x".to_vec(), b"y".to_vec()]),
+            ..TmpOptions::default()
+        };
+        let mut streamed = Vec::new();
+        let size = data.len() as u64;
+        reader::exorcize_csv(Cursor::new(data), &mut streamed, size, 3, &opts).unwrap();
+
+        for chunk_size in [1, 3, 1024] {
+            let mut buffered = Vec::new();
+            let input = Cursor::new(data);
+            exorcize_csv(input, &mut buffered, chunk_size, &opts, &mut NoHook).unwrap();
+            assert_eq!(buffered, streamed, "chunk_size {chunk_size}");
+        }
+    }
+
+    #[rstest]
+    #[case(b"a|;b\x1E\x1Ec", "\"a|;b\"|;|;c")]
+    #[case(b";\x1E|", "\";\"|;\"|\"")]
+    fn test_exorcize_csv_unquoted_multi_byte_out_delim(#[case] data: &[u8], #[case] exp: &str) {
+        let opts = TmpOptions {
+            out_delim: b"|;".to_vec(),
+            quote_columns: Some(vec![]),
+            quote_empty: false,
+            ..TmpOptions::default()
+        };
+
+        let mut out = Vec::new();
+        exorcize_csv(Cursor::new(data), &mut out, 1024, &opts, &mut NoHook).unwrap();
+        assert_eq!(String::from_utf8(out).unwrap(), exp);
+    }
+
+    #[rstest]
+    #[case(b"a,b\x1Ec\x1Dd\x1Ee", None)]
+    #[case(b"a,b\x1Ec\x1Dd\x1Ee", Some(vec![1]))]
+    #[case(b"", None)]
+    fn test_exorcize_csv_header_matches_streaming(
+        #[case] data: &[u8],
+        #[case] quote_columns: Option<Vec<usize>>,
+    ) {
+        let opts = TmpOptions {
+            header: Some(vec![b"id,x".to_vec(), b"name".to_vec()]),
+            quote_columns,
+            ..TmpOptions::default()
+        };
+
+        let mut buffered = Vec::new();
+        let result = exorcize_csv(Cursor::new(data), &mut buffered, 2, &opts, &mut NoHook);
+        assert!(result.is_ok());
+
+        if opts.quote_columns.is_some() {
+            assert_eq!(buffered, b"\"id,x\",\"name\"\n\"a,b\",\"c\"\nd,\"e\"");
+            return;
+        }
+        let mut streamed = Vec::new();
+        let size = data.len() as u64;
+        reader::exorcize_csv(Cursor::new(data), &mut streamed, size, 1024, &opts).unwrap();
+        assert_eq!(buffered, streamed);
+    }
+
+    #[rstest]
+    #[case(b"a\x1Eb\x1Dc\x1D", None)]
+    #[case(b"a\x1Dc", Some(true))]
+    #[case(b"\x1D\x1D", Some(false))]
+    fn test_exorcize_csv_record_prefix_matches_streaming(
+        #[case] data: &[u8],
+        #[case] final_newline: Option<bool>,
+    ) {
+        let opts = TmpOptions {
+            record_prefix: Some(b'\x1E'),
+            final_newline,
+            header: Some(vec![b"h".to_vec()]),
+            ..TmpOptions::default()
+        };
+
+        let mut streamed = Vec::new();
+        let size = data.len() as u64;
+        reader::exorcize_csv(Cursor::new(data), &mut streamed, size, 1024, &opts).unwrap();
+        assert!(streamed.starts_with(b"\x1E\"h\"\n\x1E\""));
+
+        let mut buffered = Vec::new();
+        let result = exorcize_csv(Cursor::new(data), &mut buffered, 2, &opts, &mut NoHook);
+        assert!(result.is_ok());
+        assert_eq!(buffered, streamed);
+    }
+
+    #[rstest]
+    #[case(b"a\x1Eb\x1Dc\x1Ed\x1De\x1Ef\x1Dg", vec![(2, 3), (1, 1)])]
+    #[case(b"a\x1Eb\x1D\x1D", vec![(2, 1), (1, 1)])]
+    #[case(b"", vec![])]
+    fn test_exorcize_csv_field_counts_match_streaming(
+        #[case] data: &[u8],
+        #[case] exp: Vec<(u64, u64)>,
+    ) {
+        let opts = TmpOptions::default();
+        let exp: HashMap<u64, u64> = exp.into_iter().collect();
+
+        let size = data.len() as u64;
+        let streamed = reader::exorcize_csv(Cursor::new(data), Vec::new(), size, 2, &opts).unwrap();
+        assert_eq!(streamed.field_counts, exp);
+
+        let buffered = exorcize_csv(Cursor::new(data), Vec::new(), 2, &opts, &mut NoHook).unwrap();
+        assert_eq!(buffered.field_counts, exp);
+    }
+
+    #[rstest]
+    // reordered, the middle column dropped, the first repeated
+    #[case(vec![2, 0, 0], b"a\x1Eb\x1Ec\x1Dd\x1Ee\x1Ef\x1D", "\"c\",\"a\",\"a\"\n\"f\",\"d\",\"d\"\n")]
+    #[case(vec![1], b"a\x1Eb\\\x1Ec\x1Dd\x1E\"e", "\"b\\\\\"\n\"\\\"e\"")]
+    // a ragged row gets the missing column empty
+    #[case(vec![1, 0], b"a\x1Eb\x1Dc", "\"b\",\"a\"\n\"\",\"c\"")]
+    #[case(vec![0], b"", "")]
+    fn test_exorcize_csv_column_order(
+        #[case] column_order: Vec<usize>,
+        #[case] data: &[u8],
+        #[case] exp: &str,
+    ) {
+        let opts = TmpOptions {
+            column_order: Some(column_order),
+            ..TmpOptions::default()
+        };
+
+        for chunk_size in 1..=data.len().max(1) {
+            let mut output = Vec::new();
+            let stats = exorcize_csv(
+                Cursor::new(data),
+                &mut output,
+                chunk_size,
+                &opts,
+                &mut NoHook,
+            )
+            .unwrap();
+            assert_eq!(
+                String::from_utf8(output).unwrap(),
+                exp,
+                "chunk_size {chunk_size}"
+            );
+            assert_eq!(stats.bytes_written, exp.len() as u64);
+        }
+    }
+
+    #[rstest]
+    fn test_exorcize_csv_column_order_hooks_and_quotes() {
+        let opts = TmpOptions {
+            column_order: Some(vec![1, 0]),
+            quote_columns: Some(vec![1]),
+            column_widths: true,
+            ..TmpOptions::default()
+        };
+
+        // the hook sees source columns, quoting and widths follow output ones
+        let mut output = Vec::new();
+        let data = b"a\x1Ebc\x1Dd\x1Eef";
+        let stats = exorcize_csv(Cursor::new(data), &mut output, 3, &opts, &mut Upper(0)).unwrap();
+        assert_eq!(output, b"bc,\"A\"\nef,\"D\"");
+        assert_eq!(stats.column_widths, Some(vec![2, 1]));
+    }
+
+    #[rstest]
+    fn test_exorcize_csv_strict_column_order() {
+        let opts = TmpOptions {
+            column_order: Some(vec![1, 0]),
+            strict_column_order: true,
+            ..TmpOptions::default()
+        };
+
+        let data = b"a\x1Eb\x1Dc\x1Dd\x1Ee";
+        let e = exorcize_csv(Cursor::new(data), Vec::new(), 4, &opts, &mut NoHook).unwrap_err();
+        assert_eq!(e.kind(), io::ErrorKind::InvalidData);
+        assert_eq!(
+            e.to_string(),
+            "row 1 has 1 fields, column_order needs column 1"
+        );
+        assert_eq!(Partial::stats(&e).unwrap().rows, 1);
+    }
+
+    #[rstest]
+    #[case(b"a\x1Eb\x1Dc\x1Da\x1Eb\x1Dc\x1Dd", "\"a\",\"b\"\n\"c\"\n\"d\"", 2)]
+    // the last row repeats: the one before keeps its terminator only if the input did
+    #[case(b"a\x1Db\x1Da", "\"a\"\n\"b\"", 1)]
+    #[case(b"a\x1Db\x1Da\x1D", "\"a\"\n\"b\"\n", 1)]
+    // rows are compared as written, not as read
+    #[case(
+        b"a\x1Eb\x1Da\x1Eb\x1E\x1Da\x1Eb\x1Ec",
+        "\"a\",\"b\"\n\"a\",\"b\",\"\"\n\"a\",\"b\",\"c\"",
+        0
+    )]
+    #[case(b"x\x1Dx\x1Dx\x1Dx", "\"x\"", 3)]
+    #[case(b"", "", 0)]
+    fn test_exorcize_csv_dedup(#[case] data: &[u8], #[case] exp: &str, #[case] duplicates: u64) {
+        let opts = TmpOptions {
+            dedup: Some(16),
+            ..TmpOptions::default()
+        };
+
+        for chunk_size in 1..=data.len().max(1) {
+            let mut output = Vec::new();
+            let stats = exorcize_csv(
+                Cursor::new(data),
+                &mut output,
+                chunk_size,
+                &opts,
+                &mut NoHook,
+            )
+            .unwrap();
+            assert_eq!(
+                String::from_utf8(output).unwrap(),
+                exp,
+                "chunk_size {chunk_size}"
+            );
+            assert_eq!(stats.duplicate_rows, Some(duplicates));
+            assert_eq!(stats.bytes_written, exp.len() as u64);
+        }
+    }
+
+    #[rstest]
+    fn test_exorcize_csv_dedup_after_hook_with_header() {
+        let opts = TmpOptions {
+            dedup: Some(16),
+            header: Some(vec![b"x".to_vec()]),
+            final_newline: Some(true),
+            ..TmpOptions::default()
+        };
+
+        // `A` and `a` are the same once the hook ran
+        let data = b"a\x1DA\x1Db\x1Da";
+        let mut output = Vec::new();
+        let stats = exorcize_csv(Cursor::new(data), &mut output, 3, &opts, &mut Upper(0)).unwrap();
+        assert_eq!(output, b"\"x\"\n\"A\"\n\"B\"\n");
+        assert_eq!((stats.rows, stats.duplicate_rows), (4, Some(2)));
+    }
+
+    #[rstest]
+    #[case(2, true)]
+    #[case(1, false)]
+    fn test_exorcize_csv_dedup_max_rows(#[case] max_rows: usize, #[case] ok: bool) {
+        let opts = TmpOptions {
+            dedup: Some(max_rows),
+            ..TmpOptions::default()
+        };
+
+        let data = b"a\x1Db\x1Da\x1Db";
+        let result = exorcize_csv(Cursor::new(data), Vec::new(), 4, &opts, &mut NoHook);
+        match ok {
+            true => assert_eq!(result.unwrap().duplicate_rows, Some(2)),
+            false => {
+                let e = result.unwrap_err();
+                assert_eq!(e.kind(), io::ErrorKind::InvalidInput);
+                assert_eq!(e.to_string(), "more than 1 distinct rows to dedup");
+            }
+        }
+    }
+
+    #[rstest]
+    #[case(b"a\x1Eb\x1Dc\x1D", None, vec!["\"a\",\"b\"", "\"c\""])]
+    #[case(b"a\x1D\x1Dx\ny\x1E", None, vec!["\"a\"", "\"\"", "\"x\ny\",\"\""])]
+    #[case(b"a\x1Da\x1Db", None, vec!["\"a\"", "\"a\"", "\"b\""])]
+    #[case(b"a\x1Da\x1Db", Some(16), vec!["\"a\"", "\"b\""])]
+    #[case(b"", None, vec![])]
+    fn test_exorcize_csv_length_prefixed(
+        #[case] data: &[u8],
+        #[case] dedup: Option<usize>,
+        #[case] rows: Vec<&str>,
+    ) {
+        let opts = TmpOptions {
+            length_prefixed: true,
+            dedup,
+            ..TmpOptions::default()
+        };
+
+        for chunk_size in 1..=data.len().max(1) {
+            let mut output = Vec::new();
+            let input = Cursor::new(data);
+            exorcize_csv(input, &mut output, chunk_size, &opts, &mut NoHook).unwrap();
+
+            let mut frames = Vec::new();
+            let mut rest = &output[..];
+            while !rest.is_empty() {
+                let (len, tail) = rest.split_at(4);
+                let len = u32::from_le_bytes(len.try_into().unwrap()) as usize;
+                let (frame, tail) = tail.split_at(len);
+                frames.push(String::from_utf8(frame.to_vec()).unwrap());
+                rest = tail;
+            }
+            assert_eq!(frames, rows, "chunk_size {chunk_size}");
+        }
+    }
+
+    #[rstest]
+    #[case(b"a\x1ENULL\x1DNULL\x1Eb", b"NULL", "\"A\",\\N\n\\N,\"b\"")]
+    // only a whole field matches, and an empty one is still quoted; the hook skips nulls
+    #[case(b"NULLx\x1E\x1ENULL", b"NULL", "\"NULLX\",\"\",\\N")]
+    #[case(b"a\x1E\x1Db", b"", "\"A\",\\N\n\"B\"")]
+    fn test_exorcize_csv_null_token(#[case] data: &[u8], #[case] token: &[u8], #[case] exp: &str) {
+        let opts = TmpOptions {
+            null_token: Some(token.to_vec()),
+            null_output: b"\\N".to_vec(),
+            ..TmpOptions::default()
+        };
+
+        for chunk_size in 1..=data.len() {
+            let mut output = Vec::new();
+            let input = Cursor::new(data);
+            exorcize_csv(input, &mut output, chunk_size, &opts, &mut Upper(0)).unwrap();
+            assert_eq!(
+                String::from_utf8(output).unwrap(),
+                exp,
+                "chunk_size {chunk_size}"
+            );
+        }
+    }
+
+    #[rstest]
+    fn test_exorcize_csv_hook_error_aborts() {
+        let data = b"abc\x1Edef";
+        let opts = TmpOptions::default();
+
+        let result = exorcize_csv(Cursor::new(data), Vec::new(), 1024, &opts, &mut Fail);
+        assert!(result.is_err());
+    }
+
+    #[rstest]
+    fn test_exorcize_csv_error_keeps_stats() {
+        let data = b"abc\x1Ddef\x1Dghi";
+        let opts = TmpOptions::default();
+
+        // fails on the first field of the second chunk
+        let mut hook = FailOn(2);
+        let e = exorcize_csv(Cursor::new(data), Vec::new(), 4, &opts, &mut hook).unwrap_err();
+        assert_eq!(e.to_string(), "hook failed");
+
+        let stats = Partial::stats(&e).unwrap();
+        assert_eq!((stats.rows, stats.bytes_read), (1, 8));
+    }
+}
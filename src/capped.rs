@@ -0,0 +1,70 @@
+use std::io::{self, Write};
+
+/// Fails any write that would take the bytes written to `inner` past `limit`.
+///
+/// The write over the limit is refused whole, so `inner` never holds more than
+/// `limit` bytes: an input that expands without bound stops at a known size.
+#[derive(Debug)]
+pub(crate) struct Capped<W> {
+    inner: W,
+    limit: u64,
+    written: u64,
+}
+
+impl<W> Capped<W> {
+    pub(crate) fn new(inner: W, limit: u64) -> Self {
+        Capped {
+            inner,
+            limit,
+            written: 0,
+        }
+    }
+}
+
+impl<W: Write> Write for Capped<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        if self.written.saturating_add(buf.len() as u64) > self.limit {
+            let msg = format!("output exceeds max_output_bytes ({} bytes)", self.limit);
+            return Err(io::Error::new(io::ErrorKind::FileTooLarge, msg));
+        }
+        let written = self.inner.write(buf)?;
+        self.written += written as u64;
+        Ok(written)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::reader::{self, TmpOptions};
+    use crate::stats::Partial;
+    use rstest::*;
+    use std::io::Cursor;
+
+    #[rstest]
+    #[case(50, true)]
+    #[case(49, false)]
+    #[case(0, false)]
+    fn test_capped_exorcize_csv(#[case] limit: u64, #[case] fits: bool) {
+        // each quote doubles, and the run gains its wrapping quotes
+        let data = [b'"'; 24];
+        let opts = TmpOptions::default();
+
+        let mut output = Vec::new();
+        let capped = Capped::new(&mut output, limit);
+        let result = reader::exorcize_csv(Cursor::new(data), capped, 24, 4, &opts);
+        match fits {
+            true => assert_eq!(result.unwrap().bytes_written, 50),
+            false => {
+                let e = result.unwrap_err();
+                assert_eq!(e.kind(), io::ErrorKind::FileTooLarge);
+                assert!(Partial::stats(&e).is_some());
+            }
+        }
+        assert!(output.len() as u64 <= limit);
+    }
+}
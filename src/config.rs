@@ -0,0 +1,617 @@
+use serde::{Deserialize, Deserializer};
+use std::collections::HashMap;
+
+/// Reads a present key as `Some`, even when `null`, so that `null` can mean "none".
+fn present<'de, D, T>(deserializer: D) -> Result<Option<Option<T>>, D::Error>
+where
+    D: Deserializer<'de>,
+    T: Deserialize<'de>,
+{
+    Option::deserialize(deserializer).map(Some)
+}
+
+/// Bytes of an option, given as `bytes` in Python and as a string of its UTF-8 bytes in JSON.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub(crate) struct Bytes(pub(crate) Vec<u8>);
+
+impl std::ops::Deref for Bytes {
+    type Target = [u8];
+
+    fn deref(&self) -> &[u8] {
+        &self.0
+    }
+}
+
+impl<'de> Deserialize<'de> for Bytes {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        String::deserialize(deserializer).map(|text| Bytes(text.into_bytes()))
+    }
+}
+
+/// A `chunk_size` option: a byte count, or a size with a unit such as `"64KiB"`.
+#[derive(Debug, Deserialize, PartialEq)]
+#[serde(untagged)]
+pub(crate) enum ChunkSize {
+    Bytes(usize),
+    Text(String),
+}
+
+/// The options of `exorcize_csv` but its paths and Python objects, from its keyword
+/// arguments or from one JSON object.
+///
+/// Names match the keyword arguments. In JSON, bytes are strings, e.g. `"\u001e"`, and
+/// unknown keys are refused. Options that do not go together are refused by `check`.
+#[derive(Debug, Default, Deserialize, PartialEq)]
+#[serde(default, deny_unknown_fields)]
+pub(crate) struct ExorcistConfig {
+    pub(crate) delim: Option<Bytes>,
+    pub(crate) newline: Option<Bytes>,
+    pub(crate) chunk_size: Option<ChunkSize>,
+    pub(crate) preallocate: Option<bool>,
+    pub(crate) double_backslash: Option<bool>,
+    pub(crate) overwrite: Option<bool>,
+    pub(crate) follow_symlinks: Option<bool>,
+    pub(crate) in_place_truncate: Option<bool>,
+    pub(crate) retries: Option<usize>,
+    pub(crate) final_newline: Option<bool>,
+    pub(crate) pad_to: Option<usize>,
+    pub(crate) checksum: Option<String>,
+    pub(crate) checksum_sidecar: Option<bool>,
+    pub(crate) quote_columns: Option<Vec<usize>>,
+    pub(crate) allow_common_delimiter: Option<bool>,
+    pub(crate) escape_bytes: Option<HashMap<Bytes, Bytes>>,
+    pub(crate) strict_eof: Option<bool>,
+    pub(crate) progress_interval: Option<f64>,
+    pub(crate) case: Option<String>,
+    pub(crate) collapse_source_double_quotes: Option<bool>,
+    pub(crate) header: Option<Vec<String>>,
+    pub(crate) tmp_dir: Option<String>,
+    pub(crate) metrics_path: Option<String>,
+    pub(crate) repair_log: Option<String>,
+    pub(crate) discard: Option<bool>,
+    pub(crate) gzip: Option<bool>,
+    pub(crate) out_newline: Option<Bytes>,
+    pub(crate) line_ending: Option<String>,
+    pub(crate) reescape_delims: Option<bool>,
+    pub(crate) quote_empty: Option<bool>,
+    pub(crate) input_encoding: Option<String>,
+    pub(crate) escape_only: Option<bool>,
+    pub(crate) diff: Option<bool>,
+    pub(crate) footer: Option<String>,
+    pub(crate) max_output_bytes: Option<u64>,
+    pub(crate) column_widths: Option<bool>,
+    pub(crate) copy_compatible: Option<bool>,
+    pub(crate) source_quotes: Option<bool>,
+    /// `null` disables the escape byte; a missing key keeps the default.
+    #[serde(deserialize_with = "present")]
+    pub(crate) escape: Option<Option<Bytes>>,
+    pub(crate) record_prefix: Option<Bytes>,
+    pub(crate) timeout_secs: Option<u64>,
+    pub(crate) flatten_newlines: Option<bool>,
+    pub(crate) verify_rfc4180: Option<bool>,
+    pub(crate) out_delim: Option<Bytes>,
+    pub(crate) atomic: Option<bool>,
+    pub(crate) read_chunk_size: Option<ChunkSize>,
+    pub(crate) write_chunk_size: Option<ChunkSize>,
+    pub(crate) naive_quote_toggle: Option<bool>,
+    pub(crate) rejects_path: Option<String>,
+    pub(crate) expected_fields: Option<u64>,
+    pub(crate) reescape_count: Option<usize>,
+    pub(crate) gzip_output: Option<bool>,
+    pub(crate) gzip_level: Option<u32>,
+    pub(crate) column_order: Option<Vec<usize>>,
+    pub(crate) strict_column_order: Option<bool>,
+    pub(crate) dedup: Option<bool>,
+    pub(crate) dedup_max_rows: Option<usize>,
+    pub(crate) trailing_sep: Option<String>,
+    pub(crate) write_bom: Option<bool>,
+    pub(crate) temp_output: Option<bool>,
+    pub(crate) count_only: Option<bool>,
+    pub(crate) length_prefixed: Option<bool>,
+    pub(crate) null_token: Option<Bytes>,
+    pub(crate) null_output: Option<Bytes>,
+    pub(crate) column_types: Option<Vec<String>>,
+    pub(crate) require_utf8: Option<bool>,
+    pub(crate) marker: Option<bool>,
+    pub(crate) verify_content: Option<bool>,
+    pub(crate) summary_format: Option<String>,
+    pub(crate) section: Option<usize>,
+    pub(crate) expect_rows: Option<u64>,
+    pub(crate) also_jsonl: Option<String>,
+    pub(crate) quote_style: Option<String>,
+    pub(crate) quote: Option<Bytes>,
+    pub(crate) keep_backup: Option<bool>,
+    pub(crate) json_minimal_escape: Option<bool>,
+}
+
+impl ExorcistConfig {
+    pub(crate) fn from_json(json: &str) -> serde_json::Result<Self> {
+        serde_json::from_str(json)
+    }
+
+    /// Refuses options that do not go together, naming the first rule they break.
+    ///
+    /// `call` names those of `CALL_OPTIONS` the call sets.
+    pub(crate) fn check(&self, call: &[&str]) -> Result<(), String> {
+        let set = |name: &str| self.is_set(name, call);
+        for (name, others) in CONFLICTS {
+            if set(name) && others.iter().any(|other| set(other)) {
+                return Err(format!("{name} is not supported with {}", either(others)));
+            }
+        }
+        for (name, needed) in REQUIREMENTS {
+            if set(name) && !needed.iter().any(|other| set(other)) {
+                return Err(format!(
+                    "{name} is not supported without {}",
+                    either(needed)
+                ));
+            }
+        }
+        Ok(())
+    }
+
+    /// Whether the option `name` of a rule takes effect.
+    fn is_set(&self, name: &str, call: &[&str]) -> bool {
+        let on = |flag: Option<bool>| flag == Some(true);
+        match name {
+            "chunk_size" => self.chunk_size.is_some(),
+            "read_chunk_size" => self.read_chunk_size.is_some(),
+            "double_backslash" => on(self.double_backslash),
+            "in_place_truncate" => on(self.in_place_truncate),
+            "final_newline" => self.final_newline.is_some(),
+            "pad_to" => self.pad_to.is_some(),
+            "checksum" => self.checksum.is_some(),
+            "checksum_sidecar" => on(self.checksum_sidecar),
+            "quote_columns" => self.quote_columns.is_some(),
+            "escape_bytes" => self.escape_bytes.is_some(),
+            "strict_eof" => on(self.strict_eof),
+            "case" => self.case.is_some(),
+            "collapse_source_double_quotes" => on(self.collapse_source_double_quotes),
+            "header" => self.header.is_some(),
+            "repair_log" => self.repair_log.is_some(),
+            // a count writes nothing, as a discarded run
+            "discard" => on(self.discard) || on(self.count_only),
+            "out_newline" => self.out_newline.is_some(),
+            "line_ending" => self.line_ending.is_some(),
+            "reescape_delims=False" => self.reescape_delims == Some(false),
+            "quote_empty=False" => self.quote_empty == Some(false),
+            "escape_only" => on(self.escape_only),
+            "diff" => on(self.diff),
+            "footer" => self.footer.is_some(),
+            "column_widths" => on(self.column_widths),
+            "copy_compatible" => on(self.copy_compatible),
+            "source_quotes" => on(self.source_quotes),
+            "escape=None" => self.escape == Some(None),
+            "record_prefix" => self.record_prefix.as_ref().is_some_and(|p| !p.is_empty()),
+            "flatten_newlines" => on(self.flatten_newlines),
+            "verify_rfc4180" => on(self.verify_rfc4180),
+            "a multi-byte out_delim" => self.out_delim.as_ref().is_some_and(|d| d.len() > 1),
+            "naive_quote_toggle" => on(self.naive_quote_toggle),
+            "rejects_path" => self.rejects_path.is_some(),
+            "expected_fields" => self.expected_fields.is_some(),
+            "reescape_count" => self.reescape_count.is_some_and(|count| count != 1),
+            "gzip_output" => on(self.gzip_output),
+            "gzip_level" => self.gzip_level.is_some(),
+            "column_order" => self.column_order.is_some(),
+            "strict_column_order" => on(self.strict_column_order),
+            "dedup" => on(self.dedup),
+            "dedup_max_rows" => self.dedup_max_rows.is_some(),
+            "trailing_sep 'ignore'" => self.trailing_sep.as_deref() == Some("ignore"),
+            "write_bom" => on(self.write_bom),
+            "temp_output" => on(self.temp_output),
+            "count_only" => on(self.count_only),
+            "length_prefixed" => on(self.length_prefixed),
+            "null_token" => self.null_token.is_some(),
+            "null_output" => self.null_output.is_some(),
+            "column_types" => self.column_types.is_some(),
+            "marker" => on(self.marker),
+            "verify_content" => on(self.verify_content),
+            "also_jsonl" => self.also_jsonl.is_some(),
+            "json_minimal_escape" => on(self.json_minimal_escape),
+            "quote_style 'backslash'" => self.quote_style.as_deref() == Some("backslash"),
+            "quote_style 'double'" => self.quote_style.as_deref() == Some("double"),
+            name if CALL_OPTIONS.contains(&name) => call.contains(&name),
+            _ => unreachable!("unknown option '{name}'"),
+        }
+    }
+}
+
+/// The options of `exorcize_csv` set by the call rather than by the config.
+pub(crate) const CALL_OPTIONS: [&str; 6] = [
+    "output",
+    "dest",
+    "dest='-'",
+    "tee",
+    "column_transforms",
+    "extra_columns",
+];
+
+/// Each option with those it is not supported with, checked in order.
+const CONFLICTS: &[(&str, &[&str])] = &[
+    ("read_chunk_size", &["chunk_size"]),
+    ("strict_eof", &["escape=None"]),
+    ("column_types", &["quote_columns", "rejects_path"]),
+    (
+        "copy_compatible",
+        &["quote_columns", "double_backslash", "escape_only"],
+    ),
+    (
+        "escape_only",
+        &[
+            "column_transforms",
+            "quote_columns",
+            "column_types",
+            "pad_to",
+            "header",
+            "extra_columns",
+            "footer",
+            "column_widths",
+            "write_bom",
+            "quote_empty=False",
+        ],
+    ),
+    (
+        "repair_log",
+        &[
+            "column_transforms",
+            "quote_columns",
+            "column_types",
+            "column_widths",
+            "copy_compatible",
+            "quote_empty=False",
+        ],
+    ),
+    ("record_prefix", &["escape_only"]),
+    (
+        "source_quotes",
+        &[
+            "column_transforms",
+            "quote_columns",
+            "column_types",
+            "column_widths",
+            "copy_compatible",
+            "quote_empty=False",
+            "escape_only",
+        ],
+    ),
+    (
+        "naive_quote_toggle",
+        &[
+            "source_quotes",
+            "collapse_source_double_quotes",
+            "column_transforms",
+            "quote_columns",
+            "column_types",
+            "column_widths",
+            "copy_compatible",
+            "quote_empty=False",
+            "escape_only",
+        ],
+    ),
+    (
+        "rejects_path",
+        &[
+            "column_transforms",
+            "column_widths",
+            "pad_to",
+            "extra_columns",
+            "footer",
+            "escape_only",
+            "source_quotes",
+            "naive_quote_toggle",
+            "collapse_source_double_quotes",
+            "repair_log",
+        ],
+    ),
+    (
+        "column_order",
+        &[
+            "pad_to",
+            "escape_only",
+            "rejects_path",
+            "repair_log",
+            "source_quotes",
+            "naive_quote_toggle",
+        ],
+    ),
+    (
+        "dedup",
+        &[
+            "footer",
+            "verify_rfc4180",
+            "escape_only",
+            "rejects_path",
+            "repair_log",
+            "source_quotes",
+            "naive_quote_toggle",
+        ],
+    ),
+    (
+        "length_prefixed",
+        &[
+            "header",
+            "footer",
+            "write_bom",
+            "out_newline",
+            "line_ending",
+            "final_newline",
+            "escape_only",
+            "rejects_path",
+            "verify_rfc4180",
+            "diff",
+        ],
+    ),
+    (
+        "null_token",
+        &[
+            "column_order",
+            "escape_only",
+            "rejects_path",
+            "repair_log",
+            "source_quotes",
+            "naive_quote_toggle",
+        ],
+    ),
+    ("quote_style 'backslash'", &["copy_compatible"]),
+    ("quote_style 'double'", &["double_backslash"]),
+    ("trailing_sep 'ignore'", &["escape_only", "rejects_path"]),
+    ("dest", &["output"]),
+    (
+        "count_only",
+        &[
+            "output",
+            "dest",
+            "tee",
+            "diff",
+            "checksum",
+            "checksum_sidecar",
+            "repair_log",
+            "rejects_path",
+            "column_widths",
+            "dedup",
+            "verify_rfc4180",
+            "escape_only",
+            "source_quotes",
+            "naive_quote_toggle",
+            "trailing_sep 'ignore'",
+        ],
+    ),
+    (
+        "temp_output",
+        &["output", "dest", "discard", "diff", "in_place_truncate"],
+    ),
+    ("dest='-'", &["discard", "diff", "checksum_sidecar"]),
+    ("discard", &["output", "dest", "checksum_sidecar"]),
+    (
+        "diff",
+        &["output", "dest", "discard", "tee", "checksum_sidecar"],
+    ),
+    (
+        "marker",
+        &[
+            "output",
+            "dest",
+            "discard",
+            "diff",
+            "temp_output",
+            "count_only",
+            "column_transforms",
+        ],
+    ),
+    ("gzip_output", &["checksum_sidecar"]),
+    ("line_ending", &["out_newline"]),
+    (
+        "verify_rfc4180",
+        &[
+            "escape_only",
+            "footer",
+            "record_prefix",
+            "a multi-byte out_delim",
+        ],
+    ),
+    (
+        "verify_content",
+        &[
+            "column_transforms",
+            "case",
+            "escape_bytes",
+            "flatten_newlines",
+            "collapse_source_double_quotes",
+            "double_backslash",
+            "escape=None",
+            "reescape_delims=False",
+            "quote_columns",
+            "column_types",
+            "pad_to",
+            "header",
+            "extra_columns",
+            "null_token",
+            "column_order",
+            "dedup",
+            "rejects_path",
+            "source_quotes",
+            "naive_quote_toggle",
+        ],
+    ),
+    (
+        "also_jsonl",
+        &[
+            "case",
+            "escape_bytes",
+            "flatten_newlines",
+            "double_backslash",
+            "collapse_source_double_quotes",
+            "pad_to",
+            "extra_columns",
+            "null_token",
+            "column_order",
+            "dedup",
+            "rejects_path",
+            "escape_only",
+            "count_only",
+            "source_quotes",
+            "naive_quote_toggle",
+        ],
+    ),
+    (
+        "reescape_count",
+        &["reescape_delims=False", "verify_rfc4180"],
+    ),
+];
+
+/// Each option with those it needs one of.
+const REQUIREMENTS: &[(&str, &[&str])] = &[
+    ("strict_column_order", &["column_order"]),
+    ("dedup_max_rows", &["dedup"]),
+    ("null_output", &["null_token"]),
+    ("expected_fields", &["rejects_path"]),
+    ("gzip_output", &["output", "dest", "temp_output"]),
+    ("gzip_level", &["gzip_output"]),
+    ("verify_content", &["verify_rfc4180"]),
+    ("json_minimal_escape", &["also_jsonl"]),
+];
+
+/// Lists `names` as `a, b or c`.
+fn either(names: &[&str]) -> String {
+    match names.split_last() {
+        Some((last, [])) => last.to_string(),
+        Some((last, rest)) => format!("{} or {last}", rest.join(", ")),
+        None => String::new(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rstest::*;
+
+    #[rstest]
+    fn test_from_json_empty_is_default() {
+        let config = ExorcistConfig::from_json("{}").unwrap();
+        assert_eq!(config, ExorcistConfig::default());
+    }
+
+    #[rstest]
+    fn test_from_json_options() {
+        let json = r#"{
+            "delim": "\u001e",
+            "newline": "|",
+            "pad_to": 3,
+            "quote_columns": [0, 2],
+            "escape_bytes": {"\u000b": "\\v"},
+            "strict_eof": true,
+            "header": ["id", "name"]
+        }"#;
+        let config = ExorcistConfig::from_json(json).unwrap();
+
+        assert_eq!(config.delim.as_deref(), Some(&b"\x1E"[..]));
+        assert_eq!(config.newline.as_deref(), Some(&b"|"[..]));
+        assert_eq!(config.pad_to, Some(3));
+        assert_eq!(config.quote_columns, Some(vec![0, 2]));
+        assert_eq!(config.strict_eof, Some(true));
+        assert_eq!(
+            config.header,
+            Some(vec!["id".to_owned(), "name".to_owned()])
+        );
+
+        let escapes = config.escape_bytes.unwrap();
+        assert_eq!(escapes[&Bytes(b"\x0B".to_vec())], Bytes(b"\\v".to_vec()));
+    }
+
+    #[rstest]
+    #[case(r#"{}"#, None)]
+    #[case(r#"{"escape": null}"#, Some(None))]
+    #[case(r#"{"escape": "\\"}"#, Some(Some(&b"\\"[..])))]
+    fn test_from_json_escape(#[case] json: &str, #[case] exp: Option<Option<&[u8]>>) {
+        let config = ExorcistConfig::from_json(json).unwrap();
+        assert_eq!(config.escape.as_ref().map(Option::as_deref), exp);
+    }
+
+    #[rstest]
+    #[case(r#"{"crlf": true}"#)]
+    #[case(r#"{"pad_to": -1}"#)]
+    #[case(r#"{"delim": 30}"#)]
+    #[case(r#"{"delim": "\u001e""#)]
+    #[case("3")]
+    fn test_from_json_invalid(#[case] json: &str) {
+        assert!(ExorcistConfig::from_json(json).is_err());
+    }
+
+    #[rstest]
+    #[case(r#"{"chunk_size": 1024}"#, ChunkSize::Bytes(1024))]
+    #[case(r#"{"chunk_size": "64KiB"}"#, ChunkSize::Text("64KiB".to_owned()))]
+    fn test_from_json_chunk_size(#[case] json: &str, #[case] exp: ChunkSize) {
+        let config = ExorcistConfig::from_json(json).unwrap();
+        assert_eq!(config.chunk_size, Some(exp));
+    }
+
+    #[rstest]
+    #[case(r#"{}"#, &[], None)]
+    #[case(r#"{"dedup": true, "dedup_max_rows": 5}"#, &[], None)]
+    #[case(r#"{"reescape_count": 1, "verify_rfc4180": true}"#, &[], None)]
+    #[case(r#"{"gzip_output": true}"#, &["dest", "dest='-'"], None)]
+    #[case(r#"{"discard": false}"#, &["output"], None)]
+    #[case(r#"{"strict_column_order": false}"#, &[], None)]
+    #[case(r#"{"line_ending": "crlf", "final_newline": true}"#, &[], None)]
+    #[case(
+        r#"{"line_ending": "lf", "out_newline": "|\n"}"#,
+        &[],
+        Some("line_ending is not supported with out_newline")
+    )]
+    #[case(r#"{"also_jsonl": "a.jsonl", "json_minimal_escape": true}"#, &[], None)]
+    #[case(
+        r#"{"json_minimal_escape": true}"#,
+        &[],
+        Some("json_minimal_escape is not supported without also_jsonl")
+    )]
+    #[case(
+        r#"{"strict_eof": true, "escape": null}"#,
+        &[],
+        Some("strict_eof is not supported with escape=None")
+    )]
+    #[case(
+        r#"{"dedup": true, "footer": "end"}"#,
+        &[],
+        Some("dedup is not supported with footer, verify_rfc4180, escape_only, rejects_path, \
+            repair_log, source_quotes or naive_quote_toggle")
+    )]
+    #[case(
+        r#"{"count_only": true, "temp_output": true}"#,
+        &[],
+        Some("temp_output is not supported with output, dest, discard, diff or in_place_truncate")
+    )]
+    #[case(
+        r#"{"marker": true}"#,
+        &["column_transforms"],
+        Some("marker is not supported with output, dest, discard, diff, temp_output, count_only \
+            or column_transforms")
+    )]
+    #[case(
+        r#"{"reescape_count": 2, "reescape_delims": false}"#,
+        &[],
+        Some("reescape_count is not supported with reescape_delims=False or verify_rfc4180")
+    )]
+    #[case(
+        r#"{"gzip_level": 1}"#,
+        &[],
+        Some("gzip_level is not supported without gzip_output")
+    )]
+    #[case(
+        r#"{"gzip_output": true}"#,
+        &[],
+        Some("gzip_output is not supported without output, dest or temp_output")
+    )]
+    fn test_check(#[case] json: &str, #[case] call: &[&str], #[case] exp: Option<&str>) {
+        let config = ExorcistConfig::from_json(json).unwrap();
+        assert_eq!(config.check(call).err().as_deref(), exp);
+    }
+
+    #[rstest]
+    fn test_check_knows_every_option() {
+        let config = ExorcistConfig::default();
+        let rules = CONFLICTS.iter().chain(REQUIREMENTS);
+        for name in rules.flat_map(|(name, others)| std::iter::once(name).chain(*others)) {
+            assert!(!config.is_set(name, &[]), "{name}");
+        }
+    }
+}
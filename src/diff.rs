@@ -0,0 +1,238 @@
+use crate::reader::TmpOptions;
+use std::fmt::Write as _;
+use std::io::{self, BufRead, BufReader, Read};
+
+/// The most differing rows `diff_rows` shows; the others are only counted.
+pub(crate) const MAX_DIFF_ROWS: usize = 10;
+/// The most bytes shown of one row, so a giant field does not flood the summary.
+const MAX_SHOWN_BYTES: usize = 200;
+
+/// The rows a fix changes, counted in full and shown up to a limit.
+#[derive(Debug, PartialEq)]
+pub(crate) struct Summary {
+    pub(crate) changed_rows: u64,
+    /// A unified-diff-style hunk per shown row, e.g. `@@ row 0 @@\n-a\u{1e}b\n+"a","b"\n`.
+    pub(crate) text: String,
+}
+
+/// How rows are told apart in one side of the diff.
+enum Rows {
+    /// Ends a row on every `eol`, as in the raw input.
+    Raw(u8),
+    /// Ends a row on `out_eol` outside of quotes, skipping the byte after a backslash
+    /// unless quotes are doubled.
+    Quoted,
+    /// Ends a row on every `out_eol`, as written by `escape_only`.
+    Plain,
+}
+
+/// Reads the next row of `input` into `row`, without its terminator.
+///
+/// Returns `false` at the end of the input. A terminator as the last byte ends the
+/// last row instead of starting an empty one.
+fn next_row<R: BufRead>(
+    input: &mut R,
+    rows: &Rows,
+    opts: &TmpOptions,
+    row: &mut Vec<u8>,
+) -> io::Result<bool> {
+    row.clear();
+    if let Rows::Raw(eol) = *rows {
+        if input.read_until(eol, row)? == 0 {
+            return Ok(false);
+        }
+        if row.last() == Some(&eol) {
+            row.pop();
+        }
+        return Ok(true);
+    }
+
+    let quoting = matches!(rows, Rows::Quoted);
+    let (mut quoted, mut escaped, mut any) = (false, false, false);
+    loop {
+        let available = input.fill_buf()?;
+        if available.is_empty() {
+            return Ok(any);
+        }
+        any = true;
+
+        let mut used = 0;
+        for &byte in available {
+            used += 1;
+            row.push(byte);
+            match byte {
+                _ if escaped => escaped = false,
+                b'\\' if quoting && !opts.double_quotes => escaped = true,
+                _ if quoting && byte == opts.quote => quoted = !quoted,
+                _ if !quoted && row.ends_with(&opts.out_eol) => {
+                    input.consume(used);
+                    row.truncate(row.len() - opts.out_eol.len());
+                    return Ok(true);
+                }
+                _ => {}
+            }
+        }
+        input.consume(used);
+    }
+}
+
+/// Writes `row` to `text` after `sign`, with control bytes escaped and at most
+/// `MAX_SHOWN_BYTES` of it.
+fn show(text: &mut String, sign: char, row: &[u8]) {
+    let shown = String::from_utf8_lossy(&row[..row.len().min(MAX_SHOWN_BYTES)]);
+    text.push(sign);
+    for c in shown.chars() {
+        match c.is_control() {
+            true => text.extend(c.escape_default()),
+            false => text.push(c),
+        }
+    }
+    if row.len() > MAX_SHOWN_BYTES {
+        let _ = write!(text, "... ({} bytes)", row.len());
+    }
+    text.push('\n');
+}
+
+/// Compares the raw rows of `before` with the fixed rows of `after`, by position.
+///
+/// Raw rows end on `eol` and fixed rows on `out_eol` outside of quotes; with
+/// `escape_only` both sides end on every `out_eol`. A `header` row in `after` is not
+/// compared. The first `limit` differing rows are shown and every one is counted; only
+/// one row of each side is held at a time.
+pub(crate) fn diff_rows<A: Read, B: Read>(
+    before: A,
+    after: B,
+    limit: usize,
+    opts: &TmpOptions,
+) -> io::Result<Summary> {
+    let (mut before, mut after) = (BufReader::new(before), BufReader::new(after));
+    let (raw, fixed) = match opts.escape_only {
+        true => (Rows::Plain, Rows::Plain),
+        false => (Rows::Raw(opts.eol), Rows::Quoted),
+    };
+    let (mut old, mut new) = (Vec::new(), Vec::new());
+    if opts.header.is_some() {
+        next_row(&mut after, &fixed, opts, &mut new)?;
+    }
+
+    let mut summary = Summary {
+        changed_rows: 0,
+        text: String::new(),
+    };
+    for row in 0.. {
+        let has_old = next_row(&mut before, &raw, opts, &mut old)?;
+        let has_new = next_row(&mut after, &fixed, opts, &mut new)?;
+        if !has_old && !has_new {
+            break;
+        }
+        if has_old == has_new && old == new {
+            continue;
+        }
+
+        summary.changed_rows += 1;
+        if summary.changed_rows as usize > limit {
+            continue;
+        }
+        let _ = writeln!(summary.text, "@@ row {row} @@");
+        if has_old {
+            show(&mut summary.text, '-', &old);
+        }
+        if has_new {
+            show(&mut summary.text, '+', &new);
+        }
+    }
+
+    let hidden = summary.changed_rows.saturating_sub(limit as u64);
+    if hidden > 0 {
+        let _ = writeln!(summary.text, "... {hidden} more changed rows");
+    }
+    Ok(summary)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::reader;
+    use rstest::*;
+    use std::io::Cursor;
+
+    /// Diffs `data` against its own fix, as a dry run does.
+    fn dry_run(data: &[u8], limit: usize, opts: &TmpOptions) -> Summary {
+        let mut fixed = Vec::new();
+        let input = Cursor::new(data);
+        match opts.escape_only {
+            true => reader::escape_only(input, &mut fixed, 4, opts),
+            false => reader::exorcize_csv(input, &mut fixed, data.len() as u64, 4, opts),
+        }
+        .unwrap();
+        diff_rows(Cursor::new(data), Cursor::new(fixed), limit, opts).unwrap()
+    }
+
+    #[rstest]
+    fn test_diff_rows() {
+        let summary = dry_run(b"a\x1Eb\x1Dc\x1Ed", 10, &TmpOptions::default());
+        assert_eq!(summary.changed_rows, 2);
+        assert_eq!(
+            summary.text,
+            "@@ row 0 @@\n-a\\u{1e}b\n+\"a\",\"b\"\n@@ row 1 @@\n-c\\u{1e}d\n+\"c\",\"d\"\n"
+        );
+    }
+
+    #[rstest]
+    fn test_diff_rows_keeps_content_newlines_and_quotes_in_their_row() {
+        let summary = dry_run(b"a\nb\x1E\"x\"\\\x1Dc\x1D", 10, &TmpOptions::default());
+        assert_eq!(summary.changed_rows, 2);
+        assert!(summary.text.contains("@@ row 1 @@\n-c\n+\"c\"\n"));
+        assert!(!summary.text.contains("row 2"));
+    }
+
+    #[rstest]
+    #[case(0, 3)]
+    #[case(2, 1)]
+    #[case(3, 0)]
+    fn test_diff_rows_is_bounded(#[case] limit: usize, #[case] hidden: usize) {
+        let summary = dry_run(b"a\x1Db\x1Dc", limit, &TmpOptions::default());
+        assert_eq!(summary.changed_rows, 3);
+        assert_eq!(summary.text.matches("@@ row").count(), limit);
+        match hidden {
+            0 => assert!(!summary.text.contains("more changed rows")),
+            n => assert!(summary
+                .text
+                .ends_with(&format!("... {n} more changed rows\n"))),
+        }
+    }
+
+    #[rstest]
+    fn test_diff_rows_unchanged_and_uneven() {
+        let opts = TmpOptions {
+            escape_only: true,
+            ..TmpOptions::default()
+        };
+        assert_eq!(dry_run(b"a,b\nc,d\n", 10, &opts).changed_rows, 0);
+
+        let summary = dry_run(b"a\"b\nc\nd", 10, &opts);
+        assert_eq!(summary.text, "@@ row 0 @@\n-a\"b\n+a\\\"b\n");
+
+        let plain = TmpOptions::default();
+        let summary = diff_rows(Cursor::new(b"a"), Cursor::new(b"\"a\"\n\"b\""), 10, &plain);
+        assert_eq!(
+            summary.unwrap().text,
+            "@@ row 0 @@\n-a\n+\"a\"\n@@ row 1 @@\n+\"b\"\n"
+        );
+    }
+
+    #[rstest]
+    fn test_diff_rows_skips_header_and_cuts_long_rows() {
+        let opts = TmpOptions {
+            header: Some(vec![b"h".to_vec()]),
+            ..TmpOptions::default()
+        };
+        let long = vec![b'x'; MAX_SHOWN_BYTES + 1];
+        let summary = dry_run(&long, 10, &opts);
+        assert_eq!(summary.changed_rows, 1);
+        assert!(summary.text.starts_with("@@ row 0 @@\n-xx"));
+        assert!(summary
+            .text
+            .contains(&format!("x... ({} bytes)\n", long.len())));
+    }
+}
@@ -0,0 +1,1647 @@
+use crate::reader::UNKNOWN_SIZE;
+use crate::stats::{Partial, RepairEntry};
+use crate::transcode::Transcoded;
+use encoding_rs::Encoding;
+use flate2::{read::MultiGzDecoder, write::GzEncoder, Compression};
+use std::ffi::OsString;
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, BufWriter, Read, Seek, Write};
+use std::path::{Path, PathBuf};
+use std::time::UNIX_EPOCH;
+
+/// Where `exorcize_file` puts the fixed output.
+#[derive(Debug)]
+pub(crate) enum Target<'a> {
+    /// Keep the input as `.bak` and put the output in its place.
+    ///
+    /// When `atomic`, the output is written to a hidden `.<name>.tmp` next to the input and
+    /// renamed over it once complete; otherwise the input is renamed away first and the
+    /// output is created at its path.
+    InPlace { atomic: bool },
+    /// Spool the input to `.spool`, next to it or in `tmp_dir`, and rewrite the same inode.
+    Truncate { tmp_dir: Option<&'a Path> },
+    /// Leave the input untouched and write to a separate file.
+    Output { path: &'a str, overwrite: bool },
+}
+
+impl Target<'_> {
+    /// Where a successful run leaves the original content of `path`, if it keeps it.
+    pub(crate) fn backup_path(&self, path: &Path) -> Option<PathBuf> {
+        matches!(self, Target::InPlace { .. }).then(|| with_suffix(path, BACKUP))
+    }
+
+    /// Where a failed run leaves its partial output.
+    ///
+    /// In place it is moved aside as `.broken`; a truncating run overwrites it when
+    /// restoring `path`, and a separate output is removed.
+    pub(crate) fn broken_path(&self, path: &Path) -> Option<PathBuf> {
+        matches!(self, Target::InPlace { .. }).then(|| with_suffix(path, BROKEN))
+    }
+
+    /// Whether a failed run puts the original content back at `path`.
+    pub(crate) fn restores(&self) -> bool {
+        !matches!(self, Target::Output { .. })
+    }
+}
+
+const BACKUP: &str = ".bak";
+const BROKEN: &str = ".broken";
+
+/// What `exorcize_file` did on success.
+#[derive(Debug, PartialEq)]
+pub(crate) enum Outcome<T> {
+    /// The input was fixed; carries what `run` returned.
+    Exorcized(T),
+    /// The input was empty, so there was nothing to fix and no backup was made.
+    Empty,
+}
+
+/// Why `exorcize_file` failed.
+#[derive(Debug)]
+pub(crate) enum Failure {
+    /// A file operation failed; nothing was processed or recovery itself failed.
+    Io(io::Error),
+    /// Processing failed and the original content was put back.
+    Run(io::Error),
+    /// Processing failed, and so did putting the original content back: it is left at
+    /// `original`.
+    Unrestored {
+        run: io::Error,
+        restore: io::Error,
+        original: PathBuf,
+    },
+}
+
+impl From<io::Error> for Failure {
+    fn from(e: io::Error) -> Self {
+        Failure::Io(e)
+    }
+}
+
+/// Runs `run` over the file at `path`, placing its output according to `target`.
+///
+/// `run` receives the input, the output and the input size, which is `size_hint` when
+/// given, e.g. for special files whose metadata reports no length; the metadata of the
+/// input is not read for it then. An empty input is
+/// short-circuited: in place it is left alone, with `Target::Output` an empty
+/// output is created, and removed again should the run fail. On a failed in-place run the partial output is kept as
+/// `.broken` and the backup is renamed back over `path`, or copied where it cannot be
+/// renamed; should the partial output not move aside it is replaced all the same. Only
+/// when the original cannot be put back at all is the run `Failure::Unrestored`. A
+/// directory is refused.
+///
+/// An atomic in-place run links the input to `.bak` first, or copies it where links are
+/// not supported, and never touches `path` until the output is complete: a reader sees
+/// either the original or the whole fix. On failure the backup is removed again.
+///
+/// A `Target::Output` naming the input file itself is run in place: creating it would
+/// truncate the input before it is read. A named pipe can only be read, so it is refused
+/// in place.
+pub(crate) fn exorcize_file<F, T>(
+    path: &Path,
+    target: Target,
+    size_hint: Option<u64>,
+    run: F,
+) -> Result<Outcome<T>, Failure>
+where
+    F: FnOnce(File, &File, u64) -> io::Result<T>,
+{
+    let path = &platform_path(&normalize(path))?;
+    let empty = check_file(path, size_hint)?;
+
+    match target {
+        Target::Output { path: out, .. } if same_file(path, Path::new(out)) => {
+            exorcize_file(path, Target::InPlace { atomic: true }, size_hint, run)
+        }
+        Target::Output {
+            path: out,
+            overwrite,
+        } => {
+            let output = create_output(out, overwrite)?;
+            if empty {
+                return Ok(Outcome::Empty);
+            }
+            let input = File::open(path)?;
+            let size = input_size(size_hint, || input.metadata());
+
+            let done = run(input, &output, size);
+            drop(output);
+            match done {
+                Ok(done) => Ok(Outcome::Exorcized(done)),
+                Err(e) => Err(Failure::Run(remove_partial(Path::new(out), e))),
+            }
+        }
+        _ if is_fifo(path) => {
+            let msg = format!("'{}' is a named pipe; pass an output", path.display());
+            Err(io::Error::new(io::ErrorKind::InvalidInput, msg).into())
+        }
+        _ if empty => Ok(Outcome::Empty),
+        Target::Truncate { tmp_dir } => {
+            let spooled = spool_path(path, tmp_dir);
+            spool(path, &spooled)?;
+
+            let input = File::open(&spooled)?;
+            let size = input_size(size_hint, || input.metadata());
+            let output = OpenOptions::new().write(true).truncate(true).open(path)?;
+
+            match run(input, &output, size) {
+                Ok(done) => {
+                    fs::remove_file(spooled)?;
+                    Ok(Outcome::Exorcized(done))
+                }
+                Err(e) => {
+                    if let Err(restore) = restore_from(&spooled, path) {
+                        return Err(Failure::Unrestored {
+                            run: e,
+                            restore,
+                            original: spooled,
+                        });
+                    }
+                    fs::remove_file(spooled)?;
+                    Err(Failure::Run(e))
+                }
+            }
+        }
+        Target::InPlace { atomic: true } => {
+            let bak = with_suffix(path, BACKUP);
+            let tmp = hidden_tmp_path(path);
+            link_or_copy(path, &bak)?;
+
+            let input = File::open(path)?;
+            let size = input_size(size_hint, || input.metadata());
+            let output = File::create(&tmp)?;
+
+            let done = run(input, &output, size);
+            // closed before the rename, which an open handle blocks off Unix
+            drop(output);
+            match done {
+                Ok(done) => {
+                    fs::rename(tmp, path)?;
+                    Ok(Outcome::Exorcized(done))
+                }
+                // `path` was never touched, so no cleanup failure hides the run's error
+                Err(e) => {
+                    let broken = with_suffix(path, BROKEN);
+                    let e = match fs::rename(&tmp, &broken) {
+                        Ok(()) => e,
+                        Err(aside) => {
+                            let _ = fs::remove_file(tmp);
+                            let note = format!(
+                                "the partial output was dropped, as moving it to '{}' failed: \
+                                {aside}",
+                                broken.display()
+                            );
+                            Partial::annotate(e, note)
+                        }
+                    };
+                    // a backup left behind is reported, being the one file still there
+                    let _ = fs::remove_file(bak);
+                    Err(Failure::Run(e))
+                }
+            }
+        }
+        Target::InPlace { atomic: false } => {
+            let bak = with_suffix(path, BACKUP);
+            fs::rename(path, &bak)?;
+
+            let input = File::open(&bak)?;
+            let size = input_size(size_hint, || input.metadata());
+            let output = File::create(path)?;
+
+            match run(input, &output, size) {
+                Ok(done) => Ok(Outcome::Exorcized(done)),
+                Err(e) => {
+                    let broken = with_suffix(path, BROKEN);
+                    let e = match fs::rename(path, &broken) {
+                        Ok(()) => e,
+                        Err(aside) => {
+                            let note = format!(
+                                "the partial output was dropped, as moving it to '{}' failed: \
+                                {aside}",
+                                broken.display()
+                            );
+                            Partial::annotate(e, note)
+                        }
+                    };
+                    match put_back(&bak, path) {
+                        Ok(()) => Err(Failure::Run(e)),
+                        Err(restore) => Err(Failure::Unrestored {
+                            run: e,
+                            restore,
+                            original: bak,
+                        }),
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// Removes the partial output a failed run `e` left at `out`, noting in `e` when it could
+/// not be. What went into a named pipe is already read, so a pipe is left alone.
+fn remove_partial(out: &Path, e: io::Error) -> io::Error {
+    if is_fifo(out) {
+        return e;
+    }
+    match fs::remove_file(out) {
+        Ok(()) => e,
+        Err(remove) => {
+            let note = format!(
+                "the partial output is left at '{}', as removing it failed: {remove}",
+                out.display()
+            );
+            Partial::annotate(e, note)
+        }
+    }
+}
+
+/// Puts the backup `bak` back at `path`: renamed over it, or copied where that fails, as
+/// across some network shares, keeping `bak` then.
+fn put_back(bak: &Path, path: &Path) -> io::Result<()> {
+    match fs::rename(bak, path) {
+        Ok(()) => Ok(()),
+        Err(e) => fs::copy(bak, path).map(drop).map_err(|_| e),
+    }
+}
+
+/// Runs `run` over the file at `path` for its result alone, leaving no output to place.
+///
+/// `run` receives the input and its size, or `size_hint`. An empty input is
+/// short-circuited and a directory is refused, as by `exorcize_file`.
+pub(crate) fn read_file<F, T>(
+    path: &Path,
+    size_hint: Option<u64>,
+    run: F,
+) -> Result<Outcome<T>, Failure>
+where
+    F: FnOnce(File, u64) -> io::Result<T>,
+{
+    let path = &platform_path(&normalize(path))?;
+    if check_file(path, size_hint)? {
+        return Ok(Outcome::Empty);
+    }
+    let input = File::open(path)?;
+    let size = input_size(size_hint, || input.metadata());
+
+    let done = run(input, size).map_err(Failure::Run)?;
+    Ok(Outcome::Exorcized(done))
+}
+
+/// Whether `a` and `b` both exist and are the same file, also through links.
+pub(crate) fn same_file(a: &Path, b: &Path) -> bool {
+    match (fs::metadata(a), fs::metadata(b)) {
+        #[cfg(unix)]
+        (Ok(a), Ok(b)) => {
+            use std::os::unix::fs::MetadataExt;
+            (a.dev(), a.ino()) == (b.dev(), b.ino())
+        }
+        #[cfg(not(unix))]
+        (Ok(_), Ok(_)) => matches!(
+            (fs::canonicalize(a), fs::canonicalize(b)),
+            (Ok(a), Ok(b)) if a == b
+        ),
+        _ => false,
+    }
+}
+
+/// The size `run` is given: `size_hint`, or the length in the `metadata` of the input.
+///
+/// Metadata that cannot be read, as on some special files, is no reason to fail a run
+/// that already moved the input: the size is then `UNKNOWN_SIZE`, and the first read
+/// tells whether there is a field to open.
+fn input_size<F>(size_hint: Option<u64>, metadata: F) -> u64
+where
+    F: FnOnce() -> io::Result<fs::Metadata>,
+{
+    match size_hint {
+        Some(size) => size,
+        None => metadata().map_or(UNKNOWN_SIZE, |metadata| metadata.len()),
+    }
+}
+
+/// Refuses a directory at `path` and returns whether the file there is empty, or
+/// `size_hint` is 0.
+fn check_file(path: &Path, size_hint: Option<u64>) -> io::Result<bool> {
+    let metadata = fs::metadata(path)?;
+    if metadata.is_dir() {
+        let msg = format!("'{}' is a directory, not a file", path.display());
+        return Err(io::Error::new(io::ErrorKind::InvalidInput, msg));
+    }
+    Ok(size_hint.unwrap_or(metadata.len()) == 0)
+}
+
+/// Wraps `input` to decompress it when it is `gzip`-compressed, then to decode it from
+/// `encoding` into UTF-8.
+///
+/// Every member of a multi-member gzip stream is read, as one logical stream: streaming
+/// exporters often append a member per flush, and a plain decoder stops after the first.
+pub(crate) fn decoded<'a, R: Read + 'a>(
+    input: R,
+    gzip: bool,
+    encoding: Option<&'static Encoding>,
+) -> Box<dyn Read + 'a> {
+    let input: Box<dyn Read + 'a> = match gzip {
+        true => Box::new(MultiGzDecoder::new(input)),
+        false => Box::new(input),
+    };
+    match encoding {
+        Some(encoding) => Box::new(Transcoded::new(input, encoding)),
+        None => input,
+    }
+}
+
+/// The first bytes of every gzip member.
+const GZIP_MAGIC: [u8; 2] = [0x1F, 0x8B];
+
+/// Whether `path` looks gzip-compressed: named `.gz`, or a regular file starting with the
+/// gzip magic bytes. A pipe is only judged by its name, as peeking would consume it.
+pub(crate) fn is_gzip(path: &Path) -> io::Result<bool> {
+    if path
+        .extension()
+        .is_some_and(|ext| ext.eq_ignore_ascii_case("gz"))
+    {
+        return Ok(true);
+    }
+    if !fs::metadata(path)?.is_file() {
+        return Ok(false);
+    }
+    let mut magic = [0u8; 2];
+    let mut file = File::open(path)?;
+    match file.read_exact(&mut magic) {
+        Ok(()) => Ok(magic == GZIP_MAGIC),
+        Err(e) if e.kind() == io::ErrorKind::UnexpectedEof => Ok(false),
+        Err(e) => Err(e),
+    }
+}
+
+/// Wraps `output` to gzip-compress what is written to it at `level`, from 0 (stored) to 9
+/// (smallest); the stream is only complete once the encoder is finished.
+pub(crate) fn gzipped<W: Write>(output: W, level: u32) -> GzEncoder<W> {
+    GzEncoder::new(output, Compression::new(level))
+}
+
+/// Resolves the file an in-place exorcism should operate on.
+///
+/// Renaming a symlink would move the link itself and leave a regular file in its
+/// place, so a symlinked `path` is resolved to its final target. With `follow` unset
+/// symlinks are refused instead.
+pub(crate) fn resolve_input(path: &str, follow: bool) -> io::Result<PathBuf> {
+    let path = normalize(Path::new(path));
+    if !fs::symlink_metadata(&path)?.file_type().is_symlink() {
+        return Ok(path);
+    }
+    if !follow {
+        let msg = format!("refusing to exorcize symlink '{}'", path.display());
+        return Err(io::Error::new(io::ErrorKind::InvalidInput, msg));
+    }
+    fs::canonicalize(path)
+}
+
+/// Refuses Windows device names in `path` and lifts the `MAX_PATH` limit for it.
+///
+/// On Windows a name such as `CON` or `nul.csv` opens a device instead of a file, and
+/// paths near 260 characters fail once `.bak` or `.broken` is appended. Such paths get
+/// the `\\?\` extended-length prefix, which also carries over to their siblings. Elsewhere
+/// `path` is returned as is.
+pub(crate) fn platform_path(path: &Path) -> io::Result<PathBuf> {
+    #[cfg(windows)]
+    {
+        windows::platform_path(path)
+    }
+
+    #[cfg(not(windows))]
+    {
+        Ok(path.to_path_buf())
+    }
+}
+
+#[cfg(windows)]
+mod windows {
+    use std::io;
+    use std::path::{self, Component, Path, PathBuf};
+
+    const RESERVED: [&str; 22] = [
+        "CON", "PRN", "AUX", "NUL", "COM1", "COM2", "COM3", "COM4", "COM5", "COM6", "COM7", "COM8",
+        "COM9", "LPT1", "LPT2", "LPT3", "LPT4", "LPT5", "LPT6", "LPT7", "LPT8", "LPT9",
+    ];
+    const MAX_PATH: usize = 260;
+    /// room for the longest sibling suffix, `.broken`, and the terminating NUL
+    const SUFFIX_ROOM: usize = super::BROKEN.len() + 1;
+
+    /// Returns the device name a component of `path` resolves to, if any.
+    pub(super) fn reserved_name(path: &Path) -> Option<&'static str> {
+        path.components().find_map(|component| {
+            let Component::Normal(name) = component else {
+                return None;
+            };
+            let name = name.to_string_lossy();
+            // the device wins regardless of extension or trailing spaces
+            let stem = name.split('.').next().unwrap_or_default().trim_end();
+            RESERVED
+                .into_iter()
+                .find(|reserved| reserved.eq_ignore_ascii_case(stem))
+        })
+    }
+
+    pub(super) fn platform_path(path: &Path) -> io::Result<PathBuf> {
+        if let Some(name) = reserved_name(path) {
+            let msg = format!(
+                "'{}' uses the reserved device name '{name}'",
+                path.display()
+            );
+            return Err(io::Error::new(io::ErrorKind::InvalidInput, msg));
+        }
+
+        let absolute = path::absolute(path)?;
+        let Some(raw) = absolute.to_str() else {
+            return Ok(absolute);
+        };
+        if raw.starts_with(r"\\?\") || raw.len() + SUFFIX_ROOM <= MAX_PATH {
+            return Ok(path.to_path_buf());
+        }
+
+        let extended = match raw.strip_prefix(r"\\") {
+            Some(unc) => format!(r"\\?\UNC\{unc}"),
+            None => format!(r"\\?\{raw}"),
+        };
+        Ok(PathBuf::from(extended))
+    }
+}
+
+/// Drops trailing separators and `.` components, so `data.csv/` names `data.csv`.
+fn normalize(path: &Path) -> PathBuf {
+    path.components().collect()
+}
+
+/// Appends `suffix` to the file name of `path`, e.g. `data.csv` -> `data.csv.bak`.
+///
+/// `path` is normalized first: a raw `data.csv/` would give `data.csv/.bak`.
+pub(crate) fn with_suffix(path: &Path, suffix: &str) -> PathBuf {
+    let mut out = OsString::from(normalize(path));
+    out.push(suffix);
+    PathBuf::from(out)
+}
+
+/// Where an atomic in-place run writes the output for `path`: `.<name>.tmp` next to it.
+fn hidden_tmp_path(path: &Path) -> PathBuf {
+    let mut name = OsString::from(".");
+    name.push(path.file_name().unwrap_or(path.as_os_str()));
+    name.push(".tmp");
+    path.with_file_name(name)
+}
+
+/// Makes `to` a hardlink to `path`, or a copy of it where links are not supported.
+///
+/// A stale `to` is replaced, as a rename over it would.
+fn link_or_copy(path: &Path, to: &Path) -> io::Result<()> {
+    match fs::remove_file(to) {
+        Err(e) if e.kind() != io::ErrorKind::NotFound => return Err(e),
+        _ => (),
+    }
+    fs::hard_link(path, to).or_else(|_| fs::copy(path, to).map(|_| ()))
+}
+
+/// Opens `path` for the fixed output, refusing to clobber it unless `overwrite` is set.
+///
+/// A named pipe is opened for writing as it is, whatever `overwrite`: there is no content
+/// to clobber, and it blocks until a reader opens the other end. A directory is refused
+/// up front with `IsADirectory`, whatever `overwrite`.
+pub(crate) fn create_output(path: &str, overwrite: bool) -> io::Result<File> {
+    let path = platform_path(Path::new(path))?;
+    if fs::metadata(&path).is_ok_and(|m| m.is_dir()) {
+        let msg = format!("output '{}' is a directory", path.display());
+        return Err(io::Error::new(io::ErrorKind::IsADirectory, msg));
+    }
+    if is_fifo(&path) {
+        return OpenOptions::new().write(true).open(path);
+    }
+    if overwrite {
+        return File::create(path);
+    }
+    OpenOptions::new().write(true).create_new(true).open(path)
+}
+
+/// Whether `path` itself is a named pipe, not following a symlink; never off Unix.
+pub(crate) fn is_fifo(path: &Path) -> bool {
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::FileTypeExt;
+        fs::symlink_metadata(path).is_ok_and(|m| m.file_type().is_fifo())
+    }
+
+    #[cfg(not(unix))]
+    {
+        let _ = path;
+        false
+    }
+}
+
+/// Where the truncating mode spools `path`: `<path>.spool`, or the same name in `tmp_dir`.
+///
+/// The spool is copied in and back out rather than renamed, so `tmp_dir` may be on
+/// another filesystem.
+fn spool_path(path: &Path, tmp_dir: Option<&Path>) -> PathBuf {
+    let spooled = with_suffix(path, ".spool");
+    match (tmp_dir, spooled.file_name()) {
+        (Some(dir), Some(name)) => dir.join(name),
+        _ => spooled,
+    }
+}
+
+/// Creates an empty file to write the output of `path` to, named after it but unique:
+/// `.<name>.<pid>.<n>.tmp`, next to it or in `tmp_dir`.
+///
+/// `n` counts up past names already taken, e.g. left by a failed run, so none is reused.
+pub(crate) fn create_temp_output(path: &Path, tmp_dir: Option<&Path>) -> io::Result<PathBuf> {
+    let dir = tmp_dir.or(path.parent()).unwrap_or(Path::new(""));
+    let name = path.file_name().unwrap_or(path.as_os_str());
+    let mut n = 0u64;
+    loop {
+        let mut temp = OsString::from(".");
+        temp.push(name);
+        temp.push(format!(".{}.{n}.tmp", std::process::id()));
+        let temp = dir.join(temp);
+        match OpenOptions::new().write(true).create_new(true).open(&temp) {
+            Ok(_) => return Ok(temp),
+            Err(e) if e.kind() == io::ErrorKind::AlreadyExists => n += 1,
+            Err(e) => return Err(e),
+        }
+    }
+}
+
+/// Checks that files can be created in `dir`, by creating and removing a probe.
+pub(crate) fn check_writable_dir(dir: &Path) -> io::Result<()> {
+    if !fs::metadata(dir)?.is_dir() {
+        let msg = format!("'{}' is not a directory", dir.display());
+        return Err(io::Error::new(io::ErrorKind::InvalidInput, msg));
+    }
+    let probe = dir.join(format!(".bcp_exorcist_probe_{}", std::process::id()));
+    OpenOptions::new()
+        .write(true)
+        .create_new(true)
+        .open(&probe)?;
+    fs::remove_file(probe)
+}
+
+/// Copies `path` to a new `spool` file, refusing to reuse a leftover spool.
+///
+/// A stale spool may be the only remaining copy of a previous run's input.
+pub(crate) fn spool(path: &Path, spool: &Path) -> io::Result<()> {
+    let mut src = File::open(path)?;
+    let mut dst = OpenOptions::new()
+        .write(true)
+        .create_new(true)
+        .open(spool)?;
+    io::copy(&mut src, &mut dst)?;
+    dst.sync_all()
+}
+
+/// Rewrites `path` with the content of `spool`, keeping the same inode.
+pub(crate) fn restore_from(spool: &Path, path: &Path) -> io::Result<()> {
+    let mut src = File::open(spool)?;
+    let mut dst = OpenOptions::new().write(true).truncate(true).open(path)?;
+    io::copy(&mut src, &mut dst)?;
+    Ok(())
+}
+
+/// Writes `digest` next to `path` as `<path>.<algorithm>`, in the `sha256sum` format.
+pub(crate) fn write_sidecar(path: &Path, algorithm: &str, digest: &str) -> io::Result<PathBuf> {
+    let sidecar = platform_path(&with_suffix(path, &format!(".{algorithm}")))?;
+    let name = path.file_name().unwrap_or(path.as_os_str());
+    fs::write(&sidecar, format!("{digest}  {}\n", name.to_string_lossy()))?;
+    Ok(sidecar)
+}
+
+/// The marker line of `path`, fixed with the options digested as `options`, with its size
+/// and modification time: a later run is only skipped while all three match.
+fn marker_line(path: &Path, options: &str) -> io::Result<String> {
+    let meta = fs::metadata(path)?;
+    let mtime = meta
+        .modified()?
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |since| since.as_nanos());
+    Ok(format!("{options} {} {mtime}\n", meta.len()))
+}
+
+/// Marks `path` as fixed with the options digested as `options`, in `<path>.exorcised`.
+pub(crate) fn write_marker(path: &Path, options: &str) -> io::Result<()> {
+    let marker = platform_path(&with_suffix(path, ".exorcised"))?;
+    fs::write(marker, marker_line(path, options)?)
+}
+
+/// Whether `path` is marked by `write_marker` as fixed with the same `options`, and is
+/// unchanged since.
+pub(crate) fn has_marker(path: &Path, options: &str) -> io::Result<bool> {
+    let marker = platform_path(&with_suffix(path, ".exorcised"))?;
+    match fs::read_to_string(marker) {
+        Ok(line) => Ok(line == marker_line(path, options)?),
+        Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(false),
+        Err(e) => Err(e),
+    }
+}
+
+/// The files a run writes besides its output, by the option naming them.
+pub(crate) struct Sides<T> {
+    pub(crate) tee: Option<T>,
+    pub(crate) rejects: Option<T>,
+    pub(crate) jsonl: Option<T>,
+}
+
+/// Creates the side files of a run as `create_output` does, unless `marked`, the input and
+/// the options of a marker, shows the run is skipped: they are then left untouched and
+/// `None` is returned.
+pub(crate) fn create_sides(
+    paths: Sides<&str>,
+    overwrite: bool,
+    marked: Option<(&Path, &str)>,
+) -> io::Result<Option<Sides<File>>> {
+    if let Some((path, options)) = marked {
+        if has_marker(path, options)? {
+            return Ok(None);
+        }
+    }
+    let create = |path: Option<&str>| path.map(|path| create_output(path, overwrite)).transpose();
+    Ok(Some(Sides {
+        tee: create(paths.tee)?,
+        rejects: create(paths.rejects)?,
+        jsonl: create(paths.jsonl)?,
+    }))
+}
+
+/// Writes `repairs` to `path` as tab-separated lines under an `offset  row  column  repair` header.
+pub(crate) fn write_repair_log(path: &Path, repairs: &[RepairEntry]) -> io::Result<()> {
+    let mut log = BufWriter::new(File::create(platform_path(path)?)?);
+    writeln!(log, "offset\trow\tcolumn\trepair")?;
+    for entry in repairs {
+        writeln!(log, "{entry}")?;
+    }
+    log.flush()
+}
+
+/// Reserves `len` bytes for `file` ahead of a large write.
+///
+/// This is a best-effort hint: on Linux it calls `posix_fallocate` so extents are
+/// allocated up front, elsewhere it only extends the file with `set_len`. The file
+/// is expected to be trimmed with [`truncate_to_position`] once writing is done.
+pub(crate) fn preallocate(file: &File, len: u64) -> io::Result<()> {
+    #[cfg(target_os = "linux")]
+    {
+        use std::os::unix::io::AsRawFd;
+
+        let len = libc::off_t::try_from(len).unwrap_or(libc::off_t::MAX);
+        match unsafe { libc::posix_fallocate(file.as_raw_fd(), 0, len) } {
+            0 => Ok(()),
+            code => Err(io::Error::from_raw_os_error(code)),
+        }
+    }
+
+    #[cfg(not(target_os = "linux"))]
+    {
+        file.set_len(len)
+    }
+}
+
+/// Cuts `file` at its current position, dropping any preallocated tail.
+pub(crate) fn truncate_to_position(mut file: &File) -> io::Result<()> {
+    let pos = file.stream_position()?;
+    file.set_len(pos)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::reader::{self, TmpOptions};
+    use rstest::*;
+    use std::fs;
+    use std::io::Write;
+
+    fn scratch(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("bcp_exorcist_{name}"));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    fn fix(input: File, output: &File, size: u64) -> io::Result<()> {
+        reader::exorcize_csv(input, output, size, 1024, &TmpOptions::default())?;
+        Ok(())
+    }
+
+    fn fail(_input: File, output: &File, _size: u64) -> io::Result<()> {
+        let mut output = output;
+        output.write_all(b"\"field1\",")?;
+        Err(io::Error::other("boom"))
+    }
+
+    fn listing(dir: &Path) -> Vec<String> {
+        let mut names: Vec<String> = fs::read_dir(dir)
+            .unwrap()
+            .map(|e| e.unwrap().file_name().into_string().unwrap())
+            .collect();
+        names.sort();
+        names
+    }
+
+    #[rstest]
+    fn test_exorcize_file_in_place(#[values(true, false)] atomic: bool) {
+        let dir = scratch(&format!("file_in_place_{atomic}"));
+        let path = dir.join("data.csv");
+        fs::write(&path, b"field1\x1Efield2").unwrap();
+
+        let outcome = exorcize_file(&path, Target::InPlace { atomic }, None, fix).unwrap();
+        assert_eq!(outcome, Outcome::Exorcized(()));
+        assert_eq!(fs::read(&path).unwrap(), b"\"field1\",\"field2\"");
+        assert_eq!(listing(&dir), vec!["data.csv", "data.csv.bak"]);
+        assert_eq!(
+            fs::read(dir.join("data.csv.bak")).unwrap(),
+            b"field1\x1Efield2"
+        );
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[rstest]
+    fn test_exorcize_file_in_place_failure(#[values(true, false)] atomic: bool) {
+        let dir = scratch(&format!("file_in_place_failure_{atomic}"));
+        let path = dir.join("data.csv");
+        fs::write(&path, b"field1\x1Efield2").unwrap();
+
+        let result = exorcize_file(&path, Target::InPlace { atomic }, None, fail);
+        assert!(matches!(result, Err(Failure::Run(_))));
+        assert_eq!(fs::read(&path).unwrap(), b"field1\x1Efield2");
+        assert_eq!(listing(&dir), vec!["data.csv", "data.csv.broken"]);
+        assert_eq!(
+            fs::read(dir.join("data.csv.broken")).unwrap(),
+            b"\"field1\","
+        );
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    // a partial output that cannot be moved aside does not keep the original from going back
+    #[rstest]
+    fn test_exorcize_file_in_place_failure_blocked_broken(#[values(true, false)] atomic: bool) {
+        let dir = scratch(&format!("file_in_place_failure_blocked_{atomic}"));
+        let path = dir.join("data.csv");
+        fs::write(&path, b"field1\x1Efield2").unwrap();
+        fs::create_dir_all(dir.join("data.csv.broken").join("taken")).unwrap();
+
+        let result = exorcize_file(&path, Target::InPlace { atomic }, None, fail);
+        let Err(Failure::Run(e)) = result else {
+            panic!("expected a failed run");
+        };
+        assert!(
+            e.to_string().contains("the partial output was dropped"),
+            "{e}"
+        );
+        assert_eq!(fs::read(&path).unwrap(), b"field1\x1Efield2");
+        assert_eq!(listing(&dir), vec!["data.csv", "data.csv.broken"]);
+        assert!(dir.join("data.csv.broken").is_dir());
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    // the paths a failed run reports: the partial output is kept, the backup is not
+    #[rstest]
+    fn test_target_paths_after_in_place_failure(#[values(true, false)] atomic: bool) {
+        let dir = scratch(&format!("target_paths_after_failure_{atomic}"));
+        let path = dir.join("data.csv");
+        fs::write(&path, b"field1\x1Efield2").unwrap();
+
+        let target = Target::InPlace { atomic };
+        let (backup, broken) = (target.backup_path(&path), target.broken_path(&path));
+        assert!(exorcize_file(&path, target, None, fail).is_err());
+        assert!(broken.unwrap().exists());
+        assert!(!backup.unwrap().exists());
+        assert_eq!(fs::read(&path).unwrap(), b"field1\x1Efield2");
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[rstest]
+    #[case(2, true)]
+    #[case(1, false)]
+    #[case(3, false)]
+    fn test_exorcize_file_expect_rows(#[case] expected: u64, #[case] ok: bool) {
+        let dir = scratch(&format!("file_expect_rows_{expected}"));
+        let path = dir.join("data.csv");
+        fs::write(&path, b"a\x1Eb\x1Dc\x1Ed").unwrap();
+
+        let result = exorcize_file(
+            &path,
+            Target::InPlace { atomic: true },
+            None,
+            |input, output, size| {
+                reader::exorcize_csv(input, output, size, 3, &TmpOptions::default())?
+                    .check_rows(expected)
+            },
+        );
+        match ok {
+            true => {
+                assert_eq!(result.unwrap(), Outcome::Exorcized(()));
+                assert_eq!(fs::read(&path).unwrap(), b"\"a\",\"b\"\n\"c\",\"d\"");
+            }
+            false => {
+                let Err(Failure::Run(e)) = result else {
+                    panic!("expected a failed run");
+                };
+                assert_eq!(
+                    e.to_string(),
+                    format!("expected {expected} rows but wrote 2")
+                );
+                assert_eq!(fs::read(&path).unwrap(), b"a\x1Eb\x1Dc\x1Ed");
+                assert_eq!(listing(&dir), vec!["data.csv", "data.csv.broken"]);
+            }
+        }
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    // what a reader of the directory sees while an atomic run is still writing, and after
+    #[rstest]
+    #[case(true, b"\"field1\",", vec!["data.csv", "data.csv.bak"])]
+    #[case(false, b"field1\x1Efield2", vec!["data.csv", "data.csv.broken"])]
+    fn test_exorcize_file_atomic_mid_write(
+        #[case] succeed: bool,
+        #[case] content: &[u8],
+        #[case] exp: Vec<&str>,
+    ) {
+        let dir = scratch(&format!("file_atomic_mid_write_{succeed}"));
+        let path = dir.join("data.csv");
+        fs::write(&path, b"field1\x1Efield2").unwrap();
+        fs::write(dir.join("data.csv.bak"), b"stale").unwrap();
+
+        let target = Target::InPlace { atomic: true };
+        let result = exorcize_file(&path, target, None, |_, mut output: &File, _| {
+            output.write_all(b"\"field1\",")?;
+            assert_eq!(fs::read(&path)?, b"field1\x1Efield2");
+            assert_eq!(fs::read(dir.join("data.csv.bak"))?, b"field1\x1Efield2");
+            assert_eq!(fs::read(dir.join(".data.csv.tmp"))?, b"\"field1\",");
+            let during = vec![".data.csv.tmp", "data.csv", "data.csv.bak"];
+            assert_eq!(listing(&dir), during);
+            match succeed {
+                true => Ok(()),
+                false => Err(io::Error::other("boom")),
+            }
+        });
+        assert_eq!(result.is_ok(), succeed);
+        assert_eq!(fs::read(&path).unwrap(), content);
+        assert_eq!(listing(&dir), exp);
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[rstest]
+    #[case(Target::InPlace { atomic: true }, Some("data.csv.bak"))]
+    #[case(Target::Truncate { tmp_dir: None }, None)]
+    fn test_target_backup_path(#[case] target: Target, #[case] exp: Option<&str>) {
+        let dir = scratch(&format!("target_backup_{target:?}"));
+        let path = dir.join("data.csv");
+        fs::write(&path, b"field1").unwrap();
+
+        let backup = target.backup_path(&path);
+        assert_eq!(backup, exp.map(|name| dir.join(name)));
+        assert!(exorcize_file(&path, target, None, fix).is_ok());
+
+        let mut exp_listing = vec!["data.csv"];
+        exp_listing.extend(exp);
+        assert_eq!(listing(&dir), exp_listing);
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[rstest]
+    #[case(Target::InPlace { atomic: true }, Some("data.csv.broken"))]
+    #[case(Target::Truncate { tmp_dir: None }, None)]
+    fn test_target_broken_path(#[case] target: Target, #[case] exp: Option<&str>) {
+        let dir = scratch(&format!("target_broken_{target:?}"));
+        let path = dir.join("data.csv");
+        fs::write(&path, b"field1").unwrap();
+
+        assert_eq!(target.broken_path(&path), exp.map(|name| dir.join(name)));
+        assert!(target.restores());
+        assert!(exorcize_file(&path, target, None, fail).is_err());
+
+        let mut exp_listing = vec!["data.csv"];
+        exp_listing.extend(exp);
+        assert_eq!(listing(&dir), exp_listing);
+        assert_eq!(fs::read(&path).unwrap(), b"field1");
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[rstest]
+    fn test_exorcize_file_truncate_failure() {
+        let dir = scratch("file_truncate_failure");
+        let path = dir.join("data.csv");
+        fs::write(&path, b"field1\x1Efield2").unwrap();
+
+        let result = exorcize_file(&path, Target::Truncate { tmp_dir: None }, None, fail);
+        assert!(matches!(result, Err(Failure::Run(_))));
+        assert_eq!(fs::read(&path).unwrap(), b"field1\x1Efield2");
+        assert_eq!(listing(&dir), vec!["data.csv"]);
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[rstest]
+    #[case(true)]
+    #[case(false)]
+    fn test_exorcize_file_truncate_tmp_dir(#[case] succeed: bool) {
+        let dir = scratch(&format!("file_truncate_tmp_dir_{succeed}"));
+        let tmp = dir.join("tmp");
+        fs::create_dir(&tmp).unwrap();
+        let path = dir.join("data.csv");
+        fs::write(&path, b"field1\x1Efield2").unwrap();
+
+        let target = Target::Truncate {
+            tmp_dir: Some(&tmp),
+        };
+        let result = exorcize_file(&path, target, None, |input, output, size| {
+            assert_eq!(listing(&tmp), vec!["data.csv.spool"]);
+            match succeed {
+                true => fix(input, output, size),
+                false => fail(input, output, size),
+            }
+        });
+
+        assert_eq!(result.is_ok(), succeed);
+        let exp: &[u8] = match succeed {
+            true => b"\"field1\",\"field2\"",
+            false => b"field1\x1Efield2",
+        };
+        assert_eq!(fs::read(&path).unwrap(), exp);
+        assert_eq!(listing(&dir), vec!["data.csv", "tmp"]);
+        assert!(listing(&tmp).is_empty());
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[rstest]
+    fn test_check_writable_dir() {
+        let dir = scratch("writable_dir");
+        assert!(check_writable_dir(&dir).is_ok());
+        assert!(listing(&dir).is_empty());
+
+        let file = dir.join("data.csv");
+        fs::write(&file, b"").unwrap();
+        let err = check_writable_dir(&file).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidInput);
+        let err = check_writable_dir(&dir.join("missing")).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::NotFound);
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[rstest]
+    fn test_marker() {
+        let dir = scratch("marker");
+        let path = dir.join("data.csv");
+        fs::write(&path, b"field1\x1Efield2").unwrap();
+        assert!(!has_marker(&path, "opts").unwrap());
+
+        exorcize_file(&path, Target::InPlace { atomic: true }, None, fix).unwrap();
+        write_marker(&path, "opts").unwrap();
+        assert!(has_marker(&path, "opts").unwrap());
+        // other options run again
+        assert!(!has_marker(&path, "other").unwrap());
+
+        // as does a file exported again over the fixed one
+        fs::write(&path, b"field1\x1Efield2\x1Dfield3").unwrap();
+        assert!(!has_marker(&path, "opts").unwrap());
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[rstest]
+    fn test_create_sides_skipped_by_marker() {
+        let dir = scratch("sides_marker");
+        let path = dir.join("data.csv");
+        fs::write(&path, b"field1\x1Efield2").unwrap();
+        write_marker(&path, "opts").unwrap();
+        let names = ["tee.csv", "rejects.csv", "rows.jsonl"].map(|name| dir.join(name));
+        for name in &names {
+            fs::write(name, b"kept").unwrap();
+        }
+        let [tee, rejects, jsonl] = names.each_ref().map(|name| name.to_str().unwrap());
+        let paths = || Sides {
+            tee: Some(tee),
+            rejects: Some(rejects),
+            jsonl: Some(jsonl),
+        };
+
+        // even with overwrite, a skipped run leaves them as they were
+        assert!(create_sides(paths(), true, Some((&path, "opts")))
+            .unwrap()
+            .is_none());
+        for name in &names {
+            assert_eq!(fs::read(name).unwrap(), b"kept");
+        }
+        // and without it, does not leave them in the way of the next run
+        assert!(create_sides(paths(), false, Some((&path, "opts")))
+            .unwrap()
+            .is_none());
+
+        // other options run, creating them anew
+        let sides = create_sides(paths(), true, Some((&path, "other")))
+            .unwrap()
+            .unwrap();
+        assert!(sides.tee.is_some() && sides.rejects.is_some() && sides.jsonl.is_some());
+        for name in &names {
+            assert_eq!(fs::read(name).unwrap(), b"");
+        }
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[rstest]
+    #[case(false)]
+    #[case(true)]
+    fn test_create_temp_output(#[case] in_tmp_dir: bool) {
+        let dir = scratch(&format!("temp_output_{in_tmp_dir}"));
+        let path = dir.join("data.csv");
+        fs::write(&path, b"field1\x1Efield2").unwrap();
+        let tmp = dir.join("tmp");
+        fs::create_dir(&tmp).unwrap();
+        let tmp_dir = in_tmp_dir.then_some(tmp.as_path());
+
+        let first = create_temp_output(&path, tmp_dir).unwrap();
+        let second = create_temp_output(&path, tmp_dir).unwrap();
+        assert_ne!(first, second);
+        assert_eq!(first.parent(), Some(tmp_dir.unwrap_or(&dir)));
+        let pid = std::process::id();
+        assert_eq!(
+            first.file_name().unwrap(),
+            &*format!(".data.csv.{pid}.0.tmp")
+        );
+
+        let out = first.to_str().unwrap();
+        let target = Target::Output {
+            path: out,
+            overwrite: true,
+        };
+        exorcize_file(&path, target, None, fix).unwrap();
+        assert_eq!(fs::read(&first).unwrap(), b"\"field1\",\"field2\"");
+        assert_eq!(fs::read(&path).unwrap(), b"field1\x1Efield2");
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[rstest]
+    #[case(Target::InPlace { atomic: true }, vec!["data.csv"])]
+    #[case(Target::Truncate { tmp_dir: None }, vec!["data.csv"])]
+    fn test_exorcize_file_empty_in_place(#[case] target: Target, #[case] exp: Vec<&str>) {
+        let dir = scratch(&format!("file_empty_{target:?}"));
+        let path = dir.join("data.csv");
+        fs::write(&path, b"").unwrap();
+
+        let outcome = exorcize_file(&path, target, None, fix).unwrap();
+        assert_eq!(outcome, Outcome::Empty);
+        assert_eq!(listing(&dir), exp);
+        assert_eq!(fs::read(&path).unwrap(), b"");
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[rstest]
+    #[case(b"", Outcome::Empty, b"")]
+    #[case(b"field1\x1Efield2", Outcome::Exorcized(()), b"\"field1\",\"field2\"")]
+    fn test_exorcize_file_output(
+        #[case] data: &[u8],
+        #[case] exp_outcome: Outcome<()>,
+        #[case] exp: &[u8],
+    ) {
+        let dir = scratch(&format!("file_output_{}", data.len()));
+        let path = dir.join("data.csv");
+        let out = dir.join("fixed.csv");
+        fs::write(&path, data).unwrap();
+
+        let target = Target::Output {
+            path: out.to_str().unwrap(),
+            overwrite: false,
+        };
+        let outcome = exorcize_file(&path, target, None, fix).unwrap();
+        assert_eq!(outcome, exp_outcome);
+        assert_eq!(fs::read(&out).unwrap(), exp);
+        assert_eq!(fs::read(&path).unwrap(), data);
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    // the input is never renamed nor written, and an existing output is refused unread
+    #[rstest]
+    fn test_exorcize_file_output_leaves_input_alone() {
+        let dir = scratch("file_output_leaves_input_alone");
+        let path = dir.join("data.csv");
+        let out = dir.join("fixed.csv");
+        fs::write(&path, b"field1\x1Efield2").unwrap();
+
+        let target = Target::Output {
+            path: out.to_str().unwrap(),
+            overwrite: false,
+        };
+        let broken = target.broken_path(&path);
+        assert!(matches!(
+            exorcize_file(&path, target, None, fail),
+            Err(Failure::Run(_))
+        ));
+        assert_eq!(fs::read(&path).unwrap(), b"field1\x1Efield2");
+        // the partial output is removed
+        assert_eq!(broken, None);
+        assert_eq!(listing(&dir), vec!["data.csv"]);
+
+        fs::write(&out, b"\"field1\",").unwrap();
+
+        let target = Target::Output {
+            path: out.to_str().unwrap(),
+            overwrite: false,
+        };
+        let result = exorcize_file(&path, target, None, |_, _, _| -> io::Result<()> {
+            panic!("the input was read")
+        });
+        assert!(matches!(
+            result,
+            Err(Failure::Io(e)) if e.kind() == io::ErrorKind::AlreadyExists
+        ));
+        assert_eq!(fs::read(&out).unwrap(), b"\"field1\",");
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    // the leading quote opens the first field, which then starts with an escaped quote
+    // exactly as a field opened by a separator would
+    #[rstest]
+    #[case(b"\"", b"\"\\\"\"")]
+    #[case(b"\"abc", b"\"\\\"abc\"")]
+    #[case(b"\"a\"\x1E\"b\"", b"\"\\\"a\\\"\",\"\\\"b\\\"\"")]
+    #[case(b"\"\x1D\"", b"\"\\\"\"\n\"\\\"\"")]
+    fn test_exorcize_file_leading_quote(#[case] data: &[u8], #[case] exp: &[u8]) {
+        let dir = scratch(&format!("file_leading_quote_{}", data.len()));
+        let path = dir.join("data.csv");
+        fs::write(&path, data).unwrap();
+
+        let outcome = exorcize_file(&path, Target::InPlace { atomic: true }, None, fix).unwrap();
+        assert_eq!(outcome, Outcome::Exorcized(()));
+        assert_eq!(fs::read(&path).unwrap(), exp);
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[rstest]
+    #[case(b"", Outcome::Empty)]
+    #[case(b"field1\x1Efield2", Outcome::Exorcized(17))]
+    fn test_read_file(#[case] data: &[u8], #[case] exp: Outcome<u64>) {
+        let dir = scratch(&format!("read_file_{}", data.len()));
+        let path = dir.join("data.csv");
+        fs::write(&path, data).unwrap();
+
+        let outcome = read_file(&path, None, |input, size| {
+            let opts = TmpOptions::default();
+            let stats = reader::exorcize_csv(input, io::sink(), size, 4, &opts)?;
+            Ok(stats.bytes_written)
+        });
+        assert_eq!(outcome.unwrap(), exp);
+        assert_eq!(listing(&dir), vec!["data.csv"]);
+        assert_eq!(fs::read(&path).unwrap(), data);
+
+        let result = read_file(&dir, None, |_, _| Ok(0));
+        assert!(matches!(result, Err(Failure::Io(e)) if e.kind() == io::ErrorKind::InvalidInput));
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[rstest]
+    #[case(None, Outcome::Exorcized(13))]
+    #[case(Some(3), Outcome::Exorcized(3))]
+    fn test_exorcize_file_size_hint(#[case] size_hint: Option<u64>, #[case] exp: Outcome<u64>) {
+        let dir = scratch(&format!("file_size_hint_{size_hint:?}"));
+        let path = dir.join("data.csv");
+        fs::write(&path, b"field1\x1Efield2").unwrap();
+        let out = dir.join("out.csv");
+        let target = Target::Output {
+            path: out.to_str().unwrap(),
+            overwrite: false,
+        };
+
+        let outcome = exorcize_file(&path, target, size_hint, |_, _, size| Ok(size));
+        assert_eq!(outcome.unwrap(), exp);
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[rstest]
+    #[case(b"a\x1Eb\x1D\"c")]
+    #[case(b"\x1D")]
+    #[case(b"")]
+    fn test_input_size_without_metadata(#[case] data: &[u8]) {
+        let failed = || Err(io::Error::other("metadata unavailable"));
+        let size = input_size(None, failed);
+        assert_eq!(size, UNKNOWN_SIZE);
+        assert_eq!(input_size(Some(3), failed), 3);
+
+        // the run opens the first field on the first read instead
+        let opts = TmpOptions::default();
+        let (mut fixed, mut unknown) = (Vec::new(), Vec::new());
+        let known = data.len() as u64;
+        reader::exorcize_csv(io::Cursor::new(data), &mut fixed, known, 2, &opts).unwrap();
+        reader::exorcize_csv(io::Cursor::new(data), &mut unknown, size, 2, &opts).unwrap();
+        assert_eq!(unknown, fixed);
+    }
+
+    // procfs files report a length of 0 but are not empty
+    #[cfg(target_os = "linux")]
+    #[rstest]
+    fn test_read_file_size_hint_reads_special_file() {
+        let path = Path::new("/proc/self/stat");
+        let read_all = |mut input: File, size: u64| {
+            let mut content = Vec::new();
+            input.read_to_end(&mut content)?;
+            Ok((size, content.is_empty()))
+        };
+
+        assert_eq!(read_file(path, None, read_all).unwrap(), Outcome::Empty);
+        let outcome = read_file(path, Some(1), read_all).unwrap();
+        assert_eq!(outcome, Outcome::Exorcized((1, false)));
+    }
+
+    #[rstest]
+    #[case("data.csv", true)]
+    #[case("./data.csv", false)]
+    #[case("data.csv", false)]
+    fn test_exorcize_file_output_is_input(#[case] out: &str, #[case] overwrite: bool) {
+        let dir = scratch(&format!("file_output_is_input_{}_{overwrite}", out.len()));
+        let path = dir.join("data.csv");
+        fs::write(&path, b"field1\x1Efield2").unwrap();
+
+        let out = dir.join(out);
+        let target = Target::Output {
+            path: out.to_str().unwrap(),
+            overwrite,
+        };
+        let outcome = exorcize_file(&path, target, None, fix).unwrap();
+        assert_eq!(outcome, Outcome::Exorcized(()));
+        assert_eq!(listing(&dir), vec!["data.csv", "data.csv.bak"]);
+        assert_eq!(fs::read(&path).unwrap(), b"\"field1\",\"field2\"");
+        assert_eq!(
+            fs::read(dir.join("data.csv.bak")).unwrap(),
+            b"field1\x1Efield2"
+        );
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[rstest]
+    fn test_same_file() {
+        let dir = scratch("same_file");
+        let path = dir.join("data.csv");
+        let other = dir.join("other.csv");
+        fs::write(&path, b"a").unwrap();
+        fs::write(&other, b"a").unwrap();
+
+        assert!(same_file(&path, &dir.join(".").join("data.csv")));
+        assert!(!same_file(&path, &other));
+        assert!(!same_file(&path, &dir.join("missing.csv")));
+        #[cfg(unix)]
+        {
+            let link = dir.join("link.csv");
+            std::os::unix::fs::symlink(&path, &link).unwrap();
+            assert!(same_file(&path, &link));
+        }
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[rstest]
+    fn test_is_gzip() {
+        let dir = scratch("is_gzip");
+        let mut gz = GzEncoder::new(Vec::new(), Compression::fast());
+        gz.write_all(b"field1\x1Efield2").unwrap();
+        let compressed = gz.finish().unwrap();
+
+        for (name, content, exp) in [
+            ("data.csv", &compressed[..], true),
+            ("data.bin", b"field1\x1Efield2", false),
+            ("short.csv", b"\x1F", false),
+            ("empty.csv", b"", false),
+            // the name alone is enough, as for a pipe
+            ("plain.csv.GZ", b"field1", true),
+        ] {
+            let path = dir.join(name);
+            fs::write(&path, content).unwrap();
+            assert_eq!(is_gzip(&path).unwrap(), exp, "{name}");
+        }
+        assert!(is_gzip(&dir.join("missing.csv")).is_err());
+
+        // a detected input fixes as its plain text does
+        let path = dir.join("data.csv");
+        let input = decoded(File::open(&path).unwrap(), is_gzip(&path).unwrap(), None);
+        let mut output = Vec::new();
+        let opts = TmpOptions::default();
+        reader::exorcize_csv(input, &mut output, 1, 3, &opts).unwrap();
+        let mut plain = Vec::new();
+        let data = &b"field1\x1Efield2"[..];
+        reader::exorcize_csv(data, &mut plain, data.len() as u64, 3, &opts).unwrap();
+        assert_eq!(output, plain);
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[rstest]
+    #[case(false, b"field1\x1Efield2\x1Dab")]
+    #[case(true, b"field1\x1Efield2\x1Dab")]
+    fn test_decoded(#[case] gzip: bool, #[case] data: &[u8]) {
+        let input = match gzip {
+            // two members, split inside `field2`
+            true => [&data[..10], &data[10..]]
+                .iter()
+                .flat_map(|part| {
+                    let mut member = GzEncoder::new(Vec::new(), Compression::fast());
+                    member.write_all(part).unwrap();
+                    member.finish().unwrap()
+                })
+                .collect(),
+            false => data.to_vec(),
+        };
+
+        let mut output = Vec::new();
+        let opts = TmpOptions::default();
+        let input = decoded(io::Cursor::new(input), gzip, None);
+        let stats = reader::exorcize_csv(input, &mut output, 1, 4, &opts).unwrap();
+        assert_eq!(stats.bytes_read, data.len() as u64);
+        assert_eq!(output, b"\"field1\",\"field2\"\n\"ab\"");
+    }
+
+    #[rstest]
+    fn test_gzipped(#[values(0, 1, 6, 9)] level: u32) {
+        let data = b"field1\x1Efield2\x1Dab".repeat(100);
+        let opts = TmpOptions::default();
+        let size = data.len() as u64;
+
+        let mut plain = Vec::new();
+        reader::exorcize_csv(io::Cursor::new(&data), &mut plain, size, 64, &opts).unwrap();
+
+        let mut encoder = gzipped(Vec::new(), level);
+        reader::exorcize_csv(io::Cursor::new(&data), &mut encoder, size, 64, &opts).unwrap();
+        let compressed = encoder.finish().unwrap();
+        if level > 0 {
+            assert!(compressed.len() < plain.len() / 4, "level {level}");
+        }
+
+        let mut output = Vec::new();
+        decoded(io::Cursor::new(compressed), true, None)
+            .read_to_end(&mut output)
+            .unwrap();
+        assert_eq!(output, plain);
+    }
+
+    #[rstest]
+    fn test_create_output_overwrite() {
+        let path = std::env::temp_dir().join("bcp_exorcist_create_output.csv");
+        fs::write(&path, b"precious").unwrap();
+        let path_str = path.to_str().unwrap();
+
+        let err = create_output(path_str, false).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::AlreadyExists);
+        assert_eq!(fs::read(&path).unwrap(), b"precious");
+
+        assert!(create_output(path_str, true).is_ok());
+        assert_eq!(fs::read(&path).unwrap(), b"");
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[rstest]
+    #[case("dir/data.csv")]
+    #[case("dir/data.csv/")]
+    #[case("dir/./data.csv//")]
+    fn test_with_suffix(#[case] path: &str) {
+        let path = with_suffix(Path::new(path), ".bak");
+        assert_eq!(path, Path::new("dir/data.csv.bak"));
+    }
+
+    #[rstest]
+    fn test_exorcize_file_trailing_slash() {
+        let dir = scratch("file_trailing_slash");
+        fs::write(dir.join("data.csv"), b"field1\x1Efield2").unwrap();
+
+        let path = PathBuf::from(format!("{}/", dir.join("data.csv").display()));
+        let outcome = exorcize_file(&path, Target::InPlace { atomic: true }, None, fix).unwrap();
+        assert_eq!(outcome, Outcome::Exorcized(()));
+        assert_eq!(listing(&dir), vec!["data.csv", "data.csv.bak"]);
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[rstest]
+    #[case(Target::InPlace { atomic: true }, "")]
+    #[case(Target::InPlace { atomic: true }, "/")]
+    #[case(Target::Truncate { tmp_dir: None }, "/")]
+    fn test_exorcize_file_refuses_directory(#[case] target: Target, #[case] slash: &str) {
+        let dir = scratch(&format!("file_directory_{target:?}{}", slash.len()));
+        fs::create_dir(dir.join("data")).unwrap();
+
+        let path = PathBuf::from(format!("{}{slash}", dir.join("data").display()));
+        let Err(Failure::Io(e)) = exorcize_file(&path, target, None, fix) else {
+            panic!("directory accepted");
+        };
+        assert_eq!(e.kind(), io::ErrorKind::InvalidInput);
+        assert_eq!(listing(&dir), vec!["data"]);
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[rstest]
+    #[case(false)]
+    #[case(true)]
+    fn test_exorcize_file_refuses_directory_output(#[case] overwrite: bool) {
+        let dir = scratch(&format!("output_dir_{overwrite}"));
+        let path = dir.join("data.csv");
+        let out = dir.join("out");
+        fs::write(&path, b"field1\x1Efield2").unwrap();
+        fs::create_dir(&out).unwrap();
+
+        let target = Target::Output {
+            path: out.to_str().unwrap(),
+            overwrite,
+        };
+        let result = exorcize_file(&path, target, None, |_, _, _| Ok(()));
+        assert!(matches!(result, Err(Failure::Io(e)) if e.kind() == io::ErrorKind::IsADirectory));
+
+        assert_eq!(fs::read(&path).unwrap(), b"field1\x1Efield2");
+        assert!(fs::read_dir(&out).unwrap().next().is_none());
+        assert_eq!(listing(&dir), vec!["data.csv", "out"]);
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[cfg(unix)]
+    #[rstest]
+    #[case(false)]
+    #[case(true)]
+    fn test_exorcize_file_into_fifo(#[case] overwrite: bool) {
+        let dir = scratch(&format!("fifo_{overwrite}"));
+        let path = dir.join("data.csv");
+        let fifo = dir.join("out.fifo");
+        fs::write(&path, b"field1\x1Efield2\x1Dfield3").unwrap();
+        let made = std::process::Command::new("mkfifo").arg(&fifo).status();
+        assert!(made.unwrap().success());
+        assert!(is_fifo(&fifo));
+
+        let drain = {
+            let fifo = fifo.clone();
+            std::thread::spawn(move || fs::read(fifo).unwrap())
+        };
+        let target = Target::Output {
+            path: fifo.to_str().unwrap(),
+            overwrite,
+        };
+        let outcome = exorcize_file(&path, target, None, |input, output, size| {
+            reader::exorcize_csv(input, output, size, 4, &TmpOptions::default())
+        });
+        assert!(matches!(outcome, Ok(Outcome::Exorcized(_))));
+
+        assert_eq!(drain.join().unwrap(), b"\"field1\",\"field2\"\n\"field3\"");
+        assert!(is_fifo(&fifo));
+        assert_eq!(fs::read(&path).unwrap(), b"field1\x1Efield2\x1Dfield3");
+
+        let in_place = exorcize_file(&fifo, Target::InPlace { atomic: true }, None, |_, _, _| {
+            Ok(())
+        });
+        assert!(matches!(in_place, Err(Failure::Io(e)) if e.kind() == io::ErrorKind::InvalidInput));
+        assert!(is_fifo(&fifo));
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[cfg(unix)]
+    #[rstest]
+    fn test_resolve_input_symlink() {
+        let dir = scratch("resolve_input");
+
+        let target = dir.join("target.csv");
+        let link = dir.join("link.csv");
+        fs::write(&target, b"field1").unwrap();
+        std::os::unix::fs::symlink(&target, &link).unwrap();
+
+        let target_str = target.to_str().unwrap();
+        let link_str = link.to_str().unwrap();
+        let canonical = fs::canonicalize(&target).unwrap();
+
+        assert_eq!(resolve_input(target_str, false).unwrap(), target);
+        assert_eq!(resolve_input(link_str, true).unwrap(), canonical);
+
+        let err = resolve_input(link_str, false).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidInput);
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[cfg(unix)]
+    #[rstest]
+    fn test_spool_and_restore_keep_hardlinks() {
+        let dir = scratch("spool");
+
+        let path = dir.join("data.csv");
+        let link = dir.join("hardlink.csv");
+        let spooled = dir.join("data.csv.spool");
+        fs::write(&path, b"original").unwrap();
+        fs::hard_link(&path, &link).unwrap();
+
+        assert!(spool(&path, &spooled).is_ok());
+        assert_eq!(
+            spool(&path, &spooled).unwrap_err().kind(),
+            io::ErrorKind::AlreadyExists
+        );
+
+        fs::write(&path, b"half-writ").unwrap();
+        assert!(restore_from(&spooled, &path).is_ok());
+        assert_eq!(fs::read(&link).unwrap(), b"original");
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[rstest]
+    fn test_preallocate_then_truncate() {
+        let path = std::env::temp_dir().join("bcp_exorcist_preallocate.csv");
+        let mut file = OpenOptions::new()
+            .read(true)
+            .write(true)
+            .create(true)
+            .truncate(true)
+            .open(&path)
+            .unwrap();
+
+        assert!(preallocate(&file, 4096).is_ok());
+        file.write_all(b"\"field1\"").unwrap();
+        assert!(truncate_to_position(&file).is_ok());
+
+        assert_eq!(fs::read(&path).unwrap(), b"\"field1\"");
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[rstest]
+    fn test_write_sidecar() {
+        let dir = scratch("sidecar");
+        let path = dir.join("data.csv");
+
+        let sidecar = write_sidecar(&path, "sha256", "abc123").unwrap();
+        assert_eq!(sidecar, dir.join("data.csv.sha256"));
+        assert_eq!(fs::read(&sidecar).unwrap(), b"abc123  data.csv\n");
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[cfg(windows)]
+    #[rstest]
+    #[case(r"C:\data\CON", "CON")]
+    #[case(r"C:\data\nul.csv", "NUL")]
+    #[case(r"C:\Com1 .txt\data.csv", "COM1")]
+    #[case(r"lpt9", "LPT9")]
+    fn test_platform_path_reserved(#[case] path: &str, #[case] exp: &str) {
+        assert_eq!(windows::reserved_name(Path::new(path)), Some(exp));
+
+        let err = platform_path(Path::new(path)).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidInput);
+    }
+
+    #[cfg(windows)]
+    #[rstest]
+    #[case(r"C:\data\console.csv")]
+    #[case(r"C:\data\COM10.csv")]
+    fn test_platform_path_not_reserved(#[case] path: &str) {
+        assert_eq!(platform_path(Path::new(path)).unwrap(), Path::new(path));
+    }
+
+    #[cfg(windows)]
+    #[rstest]
+    #[case(r"C:\", r"\\?\C:\")]
+    #[case(r"\\server\share\", r"\\?\UNC\server\share\")]
+    fn test_platform_path_over_length(#[case] root: &str, #[case] prefix: &str) {
+        let path = format!(r"{root}{}\data.csv", "d".repeat(300));
+
+        let extended = platform_path(Path::new(&path)).unwrap();
+        let extended = extended.to_str().unwrap();
+        assert!(extended.starts_with(prefix));
+        assert!(extended.ends_with(r"\data.csv"));
+        assert_eq!(
+            platform_path(Path::new(extended)).unwrap(),
+            Path::new(extended)
+        );
+    }
+
+    #[cfg(windows)]
+    #[rstest]
+    fn test_exorcize_file_over_length_path() {
+        let dir = scratch("file_over_length");
+        let deep = platform_path(&dir.join("d".repeat(240))).unwrap();
+        fs::create_dir_all(&deep).unwrap();
+        let path = deep.join("data.csv");
+        fs::write(&path, b"field1\x1Efield2").unwrap();
+
+        let outcome = exorcize_file(&path, Target::InPlace { atomic: true }, None, fix).unwrap();
+        assert_eq!(outcome, Outcome::Exorcized(()));
+        assert_eq!(fs::read(&path).unwrap(), b"\"field1\",\"field2\"");
+        assert!(with_suffix(&path, ".bak").exists());
+
+        fs::remove_dir_all(platform_path(&dir).unwrap()).unwrap();
+    }
+}
@@ -0,0 +1,157 @@
+use crate::reader::TmpOptions;
+use crate::rows;
+use std::io::{self, BufWriter, Read, Write};
+
+/// How each field is laid out in its fixed-width column.
+#[derive(Debug)]
+pub(crate) struct Layout {
+    /// The width in bytes of each column, one per field of a row.
+    pub(crate) widths: Vec<usize>,
+    /// Written after a short field up to its width.
+    pub(crate) pad: u8,
+    /// Cuts a field over its width; when off such a field is an error.
+    pub(crate) truncate: bool,
+}
+
+fn invalid(msg: String) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, msg)
+}
+
+/// Writes raw input as fixed-width records, each field padded or cut to its column width.
+///
+/// Fields are split as by `rows::split_rows` and written with no delimiter between them;
+/// every record ends with `out_eol`. A row without exactly one field per width is
+/// `InvalidData` naming its row and byte offset, as is an over-wide field without
+/// `truncate`; the records before it are written. Returns the number of records.
+pub(crate) fn write_fixed_width<R, W>(
+    input: R,
+    output: W,
+    layout: &Layout,
+    chunk_size: usize,
+    opts: &TmpOptions,
+) -> io::Result<u64>
+where
+    R: Read,
+    W: Write,
+{
+    let mut writer = BufWriter::new(output);
+    let mut record = Vec::with_capacity(layout.widths.iter().sum::<usize>() + opts.out_eol.len());
+    let (mut row, mut offset) = (0, 0);
+
+    rows::split_rows(input, chunk_size, opts, |fields| {
+        if fields.len() != layout.widths.len() {
+            return Err(invalid(format!(
+                "row {row} at byte {offset} has {} fields; expected {}",
+                fields.len(),
+                layout.widths.len()
+            )));
+        }
+
+        record.clear();
+        for (col, (field, &width)) in fields.iter().zip(&layout.widths).enumerate() {
+            if field.len() > width && !layout.truncate {
+                return Err(invalid(format!(
+                    "row {row} at byte {offset}, field {col} is {} bytes; its width is {width}",
+                    field.len()
+                )));
+            }
+            let kept = &field[..field.len().min(width)];
+            record.extend_from_slice(kept);
+            record.resize(record.len() + width - kept.len(), layout.pad);
+        }
+        record.extend_from_slice(&opts.out_eol);
+        writer.write_all(&record)?;
+
+        // every field is followed by its `sep` or `eol`
+        offset += fields
+            .iter()
+            .map(|field| field.len() as u64 + 1)
+            .sum::<u64>();
+        row += 1;
+        Ok(())
+    })?;
+
+    writer.flush()?;
+    Ok(row)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rstest::*;
+    use std::io::Cursor;
+
+    fn layout(widths: &[usize], truncate: bool) -> Layout {
+        Layout {
+            widths: widths.to_vec(),
+            pad: b' ',
+            truncate,
+        }
+    }
+
+    fn fixed(data: &[u8], layout: &Layout) -> io::Result<String> {
+        let mut output = Vec::new();
+        for chunk_size in [1, 2, 1024] {
+            output.clear();
+            let opts = TmpOptions::default();
+            write_fixed_width(Cursor::new(data), &mut output, layout, chunk_size, &opts)?;
+        }
+        Ok(String::from_utf8(output).unwrap())
+    }
+
+    #[rstest]
+    #[case(b"a\x1Ebc\x1Dde\x1Ef", "a  bc \nde f  \n")]
+    #[case(b"a\x1Eb\x1D", "a  b  \n")]
+    #[case(b"\x1E", "      \n")]
+    #[case(b"", "")]
+    fn test_write_fixed_width_pads(#[case] data: &[u8], #[case] exp: &str) {
+        assert_eq!(fixed(data, &layout(&[3, 3], false)).unwrap(), exp);
+    }
+
+    #[rstest]
+    fn test_write_fixed_width_truncates() {
+        let data = b"abcdef\x1Exy\x1Dab\x1Ewxyz";
+        assert_eq!(
+            fixed(data, &layout(&[4, 2], true)).unwrap(),
+            "abcdxy\nab  wx\n"
+        );
+
+        let e = fixed(data, &layout(&[4, 2], false)).unwrap_err();
+        assert_eq!(e.kind(), io::ErrorKind::InvalidData);
+        assert_eq!(
+            e.to_string(),
+            "row 0 at byte 0, field 0 is 6 bytes; its width is 4"
+        );
+
+        let e = fixed(data, &layout(&[6, 2], false)).unwrap_err();
+        assert_eq!(
+            e.to_string(),
+            "row 1 at byte 10, field 1 is 4 bytes; its width is 2"
+        );
+    }
+
+    #[rstest]
+    #[case(b"a\x1Eb\x1Dc", "row 1 at byte 4 has 1 fields; expected 2")]
+    #[case(b"a\x1Eb\x1Dc\x1Ed\x1Ee", "row 1 at byte 4 has 3 fields; expected 2")]
+    fn test_write_fixed_width_ragged(#[case] data: &[u8], #[case] msg: &str) {
+        let e = fixed(data, &layout(&[1, 1], true)).unwrap_err();
+        assert_eq!(e.kind(), io::ErrorKind::InvalidData);
+        assert_eq!(e.to_string(), msg);
+    }
+
+    #[rstest]
+    fn test_write_fixed_width_pad_byte_and_out_eol() {
+        let opts = TmpOptions {
+            out_eol: b"\r\n".to_vec(),
+            ..TmpOptions::default()
+        };
+        let layout = Layout {
+            pad: b'0',
+            ..layout(&[2, 1], true)
+        };
+        let mut output = Vec::new();
+        let rows = write_fixed_width(Cursor::new(b"1\x1E\x1D"), &mut output, &layout, 4, &opts);
+        assert_eq!(rows.unwrap(), 1);
+        assert_eq!(output, b"100\r\n");
+    }
+}
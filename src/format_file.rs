@@ -0,0 +1,328 @@
+use std::io;
+
+/// The field and row terminators read from a SQL Server BCP format file.
+#[derive(Debug, PartialEq)]
+pub(crate) struct Terminators {
+    /// Ends every field but the last of a row; `None` when rows have a single field.
+    pub(crate) sep: Option<u8>,
+    /// Ends the last field of a row, and so the row.
+    pub(crate) eol: u8,
+}
+
+fn invalid(msg: String) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, msg)
+}
+
+/// Reads the terminators of a `.fmt` file, in the non-XML or the XML format.
+///
+/// A file whose first character is `<` is read as XML. Every field must end with a
+/// terminator; all but the last field must share one, which becomes `sep`, and the
+/// last one becomes `eol`. Both must be a single ASCII byte, as `exorcize_csv` takes
+/// them. Any other file is `InvalidData` saying why.
+pub(crate) fn parse_terminators(text: &str) -> io::Result<Terminators> {
+    let text = text.trim_start_matches('\u{feff}').trim_start();
+    let fields = match text.starts_with('<') {
+        true => xml_fields(text)?,
+        false => non_xml_fields(text)?,
+    };
+
+    let Some(((last, eol), fields)) = fields.split_last() else {
+        return Err(invalid("format file describes no fields".to_string()));
+    };
+    let sep = match fields.first() {
+        Some((first, sep)) => {
+            if let Some((id, term)) = fields.iter().find(|(_, term)| term != sep) {
+                return Err(invalid(format!(
+                    "field {id} ends with {:?}, but field {first} with {:?}",
+                    String::from_utf8_lossy(term),
+                    String::from_utf8_lossy(sep)
+                )));
+            }
+            Some(one_byte(first, sep)?)
+        }
+        None => None,
+    };
+    Ok(Terminators {
+        sep,
+        eol: one_byte(last, eol)?,
+    })
+}
+
+fn one_byte(id: &str, term: &[u8]) -> io::Result<u8> {
+    match term {
+        [] => Err(invalid(format!("field {id} has no terminator"))),
+        [byte] if byte.is_ascii() => Ok(*byte),
+        _ => Err(invalid(format!(
+            "field {id} ends with {:?}; only a single ASCII byte is supported",
+            String::from_utf8_lossy(term)
+        ))),
+    }
+}
+
+/// Decodes the backslash escapes BCP writes in a terminator: `\t`, `\n`, `\r`, `\0`,
+/// `\\` and `\"`. Other characters stand for themselves.
+fn unescape(id: &str, term: &str) -> io::Result<Vec<u8>> {
+    let mut bytes = Vec::with_capacity(term.len());
+    let mut chars = term.chars();
+    while let Some(c) = chars.next() {
+        let c = match c {
+            '\\' => match chars.next() {
+                Some('t') => '\t',
+                Some('n') => '\n',
+                Some('r') => '\r',
+                Some('0') => '\0',
+                Some(c @ ('\\' | '"')) => c,
+                other => {
+                    return Err(invalid(format!(
+                        "field {id} has an unknown escape \\{} in its terminator",
+                        other.map_or(String::new(), String::from)
+                    )))
+                }
+            },
+            c => c,
+        };
+        bytes.extend_from_slice(c.encode_utf8(&mut [0; 4]).as_bytes());
+    }
+    Ok(bytes)
+}
+
+/// Takes the next whitespace-separated token off `rest`.
+fn token<'a>(rest: &mut &'a str) -> Option<&'a str> {
+    let text = rest.trim_start();
+    let end = text.find(char::is_whitespace).unwrap_or(text.len());
+    *rest = &text[end..];
+    Some(&text[..end]).filter(|token| !token.is_empty())
+}
+
+/// Reads the fields of a non-XML format file: a version line, a field count and one
+/// line per field, whose fifth column is the quoted terminator.
+///
+/// Fields are returned in host file order, labelled by their position in it.
+fn non_xml_fields(text: &str) -> io::Result<Vec<(String, Vec<u8>)>> {
+    let mut rest = text;
+    token(&mut rest).ok_or_else(|| invalid("format file is empty".to_string()))?;
+    let count = token(&mut rest)
+        .and_then(|count| count.parse::<usize>().ok())
+        .ok_or_else(|| invalid("format file has no field count on its second line".to_string()))?;
+
+    let mut fields = Vec::with_capacity(count);
+    for n in 1..=count {
+        let columns: Vec<_> = (0..4).map_while(|_| token(&mut rest)).collect();
+        let order = match columns[..] {
+            [order, _, _, _] => order
+                .parse::<u64>()
+                .map_err(|_| invalid(format!("field {n} has a host field order of {order:?}")))?,
+            _ => {
+                return Err(invalid(format!(
+                    "format file ends after {} of {count} fields",
+                    n - 1
+                )))
+            }
+        };
+
+        // the terminator may hold whitespace, so it is read up to its closing quote
+        let quoted = rest.trim_start();
+        let body = quoted
+            .strip_prefix('"')
+            .and_then(|body| body.find(unescaped_quote()).map(|end| &body[..end]))
+            .ok_or_else(|| invalid(format!("field {n} has no quoted terminator")))?;
+        let term = unescape(&n.to_string(), body)?;
+        rest = quoted[body.len() + 2..]
+            .split_once('\n')
+            .map_or("", |(_, next)| next);
+        fields.push((order, term));
+    }
+
+    fields.sort_by_key(|&(order, _)| order);
+    Ok(fields
+        .into_iter()
+        .enumerate()
+        .map(|(i, (_, term))| ((i + 1).to_string(), term))
+        .collect())
+}
+
+/// Finds the first `"` not escaped by a backslash.
+fn unescaped_quote() -> impl FnMut(char) -> bool {
+    let mut escaped = false;
+    move |c| match c {
+        _ if escaped => {
+            escaped = false;
+            false
+        }
+        '\\' => {
+            escaped = true;
+            false
+        }
+        c => c == '"',
+    }
+}
+
+/// Reads the value of the attribute `name` of the start tag `tag`.
+fn attribute<'a>(tag: &'a str, name: &str) -> Option<&'a str> {
+    let mut rest = tag;
+    while let Some(at) = rest.find(name) {
+        let before = rest[..at].chars().next_back();
+        let after = rest[at + name.len()..].trim_start();
+        rest = &rest[at + name.len()..];
+        if !before.is_some_and(char::is_whitespace) {
+            continue;
+        }
+        let Some(value) = after.strip_prefix('=').map(str::trim_start) else {
+            continue;
+        };
+        let quote = value.chars().next().filter(|&c| c == '"' || c == '\'')?;
+        return value[1..].split(quote).next();
+    }
+    None
+}
+
+/// Replaces the XML character and entity references of an attribute value.
+fn unentity(id: &str, value: &str) -> io::Result<String> {
+    let mut text = String::with_capacity(value.len());
+    let mut rest = value;
+    while let Some(at) = rest.find('&') {
+        text.push_str(&rest[..at]);
+        let (entity, next) = rest[at + 1..].split_once(';').ok_or_else(|| {
+            invalid(format!(
+                "field {id} has an unterminated & in its terminator"
+            ))
+        })?;
+        let c = match entity {
+            "lt" => Some('<'),
+            "gt" => Some('>'),
+            "amp" => Some('&'),
+            "quot" => Some('"'),
+            "apos" => Some('\''),
+            _ => match entity.strip_prefix("#x").or(entity.strip_prefix("#X")) {
+                Some(hex) => u32::from_str_radix(hex, 16).ok(),
+                None => entity.strip_prefix('#').and_then(|dec| dec.parse().ok()),
+            }
+            .and_then(char::from_u32),
+        };
+        text.push(c.ok_or_else(|| {
+            invalid(format!(
+                "field {id} has an unknown reference &{entity}; in its terminator"
+            ))
+        })?);
+        rest = next;
+    }
+    text.push_str(rest);
+    Ok(text)
+}
+
+/// Reads the `FIELD` elements of the `RECORD` of an XML format file, in document
+/// order, labelled by their `ID`.
+fn xml_fields(text: &str) -> io::Result<Vec<(String, Vec<u8>)>> {
+    let record = text
+        .find("<RECORD")
+        .map(|start| &text[start..])
+        .ok_or_else(|| invalid("format file has no RECORD element".to_string()))?;
+    let record = &record[..record.find("</RECORD>").unwrap_or(record.len())];
+
+    let mut fields = Vec::new();
+    for (n, tag) in record.split("<FIELD").skip(1).enumerate() {
+        let tag = &tag[..tag.find('>').unwrap_or(tag.len())];
+        let id = attribute(tag, "ID").map_or((n + 1).to_string(), str::to_string);
+        let term = match attribute(tag, "TERMINATOR") {
+            Some(value) => unescape(&id, &unentity(&id, value)?)?,
+            None => Vec::new(),
+        };
+        fields.push((id, term));
+    }
+    Ok(fields)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rstest::*;
+
+    const NON_XML: &str = "14.0
+3
+1       SQLCHAR             0       12      \"\\t\"      1     id                   \"\"
+2       SQLCHAR             0       50      \"\\t\"      2     full name            SQL_Latin1_General_CP1_CI_AS
+3       SQLCHAR             0       100     \"\\n\"      3     note                 SQL_Latin1_General_CP1_CI_AS
+";
+
+    const XML: &str = r#"<?xml version="1.0"?>
+<BCPFORMAT xmlns="http://schemas.microsoft.com/sqlserver/2004/bulkload/format" xmlns:xsi="http://www.w3.org/2001/XMLSchema-instance">
+ <RECORD>
+  <FIELD ID="1" xsi:type="CharTerm" TERMINATOR="&#x1E;" MAX_LENGTH="12"/>
+  <FIELD ID="2" xsi:type="CharTerm" TERMINATOR="&#x1E;" MAX_LENGTH="50" COLLATION="SQL_Latin1_General_CP1_CI_AS"/>
+  <FIELD ID="3" xsi:type="CharTerm" TERMINATOR="&#x1D;" MAX_LENGTH="100" COLLATION="SQL_Latin1_General_CP1_CI_AS"/>
+ </RECORD>
+ <ROW>
+  <COLUMN SOURCE="1" NAME="id" xsi:type="SQLINT"/>
+  <COLUMN SOURCE="2" NAME="full name" xsi:type="SQLVARYCHAR"/>
+  <COLUMN SOURCE="3" NAME="note" xsi:type="SQLVARYCHAR"/>
+ </ROW>
+</BCPFORMAT>
+"#;
+
+    fn terminators(sep: Option<u8>, eol: u8) -> Terminators {
+        Terminators { sep, eol }
+    }
+
+    #[rstest]
+    #[case(NON_XML, terminators(Some(b'\t'), b'\n'))]
+    #[case(XML, terminators(Some(0x1E), 0x1D))]
+    #[case(&format!("\u{feff}{XML}"), terminators(Some(0x1E), 0x1D))]
+    #[case(&XML.replace("&#x1E;", "|").replace("&#x1D;", "\\n"), terminators(Some(b'|'), b'\n'))]
+    #[case(&XML.replace("&#x1E;", "&quot;"), terminators(Some(b'"'), 0x1D))]
+    #[case(
+        "9.0\n1\n1 SQLCHAR 0 0 \"\\r\" 1 only \"\"\n",
+        terminators(None, b'\r')
+    )]
+    #[case(
+        "9.0\n2\n2 SQLCHAR 0 0 \"\\n\" 2 b \"\"\n1 SQLCHAR 0 0 \"|\" 1 a \"\"\n",
+        terminators(Some(b'|'), b'\n')
+    )]
+    #[case(
+        "9.0\n2\n1 SQLCHAR 0 0 \" \" 1 a \"\"\n2 SQLCHAR 0 0 \"\\\"\" 2 b \"\"",
+        terminators(Some(b' '), b'"')
+    )]
+    #[case(
+        "9.0\n2\n1 SQLCHAR 0 0 \"\x1E\" 1 a \"\"\n2 SQLCHAR 0 0 \"\x1D\" 2 b \"\"",
+        terminators(Some(0x1E), 0x1D)
+    )]
+    fn test_parse_terminators(#[case] text: &str, #[case] exp: Terminators) {
+        assert_eq!(parse_terminators(text).unwrap(), exp);
+    }
+
+    #[rstest]
+    #[case("", "format file is empty")]
+    #[case("14.0\n", "format file has no field count on its second line")]
+    #[case("14.0\n0\n", "format file describes no fields")]
+    #[case(
+        "14.0\n2\n1 SQLCHAR 0 12 \"\\t\" 1 a \"\"\n",
+        "format file ends after 1 of 2 fields"
+    )]
+    #[case(
+        "14.0\n1\n1 SQLCHAR 0 12 \\t 1 a \"\"\n",
+        "field 1 has no quoted terminator"
+    )]
+    #[case("14.0\n1\n1 SQLINT 4 4 \"\" 1 a \"\"\n", "field 1 has no terminator")]
+    #[case(
+        "14.0\n1\n1 SQLCHAR 0 12 \"\\r\\n\" 1 a \"\"\n",
+        "field 1 ends with \"\\r\\n\"; only a single ASCII byte is supported"
+    )]
+    #[case(
+        "14.0\n1\n1 SQLCHAR 0 12 \"\\x\" 1 a \"\"\n",
+        "field 1 has an unknown escape \\x in its terminator"
+    )]
+    #[case(
+        &NON_XML.replacen("\"\\t\"", "\",\"", 1),
+        "field 2 ends with \"\\t\", but field 1 with \",\""
+    )]
+    #[case("<BCPFORMAT/>", "format file has no RECORD element")]
+    #[case(
+        &XML.replace(r#"TERMINATOR="&#x1D;""#, ""),
+        "field 3 has no terminator"
+    )]
+    #[case(&XML.replace("&#x1D;", "&nbsp;"), "field 3 has an unknown reference &nbsp; in its terminator")]
+    fn test_parse_terminators_invalid(#[case] text: &str, #[case] msg: &str) {
+        let e = parse_terminators(text).unwrap_err();
+        assert_eq!(e.kind(), io::ErrorKind::InvalidData);
+        assert_eq!(e.to_string(), msg);
+    }
+}
@@ -0,0 +1,98 @@
+use std::io::{self, BufRead, Write};
+
+/// Compares the bytes written to it against `expected`, read as they come, keeping the
+/// offset of the first one that differs.
+///
+/// Nothing is stored: once a difference is found later writes are only counted, so a
+/// fix of any size can be checked against a golden file in constant memory.
+#[derive(Debug)]
+pub(crate) struct Golden<R> {
+    expected: R,
+    compared: u64,
+    difference: Option<u64>,
+}
+
+impl<R: BufRead> Golden<R> {
+    pub(crate) fn new(expected: R) -> Self {
+        Golden {
+            expected,
+            compared: 0,
+            difference: None,
+        }
+    }
+
+    /// The offset of the first differing byte, `None` when every byte matched. An expected
+    /// side longer than what was written differs where the written one ended.
+    pub(crate) fn first_difference(mut self) -> io::Result<Option<u64>> {
+        if self.difference.is_none() && !self.expected.fill_buf()?.is_empty() {
+            self.difference = Some(self.compared);
+        }
+        Ok(self.difference)
+    }
+
+    fn compare(&mut self, mut buf: &[u8]) -> io::Result<Option<u64>> {
+        let mut offset = self.compared;
+        while !buf.is_empty() {
+            let expected = self.expected.fill_buf()?;
+            if expected.is_empty() {
+                return Ok(Some(offset));
+            }
+            let len = expected.len().min(buf.len());
+            if let Some(pos) = (0..len).find(|&i| expected[i] != buf[i]) {
+                return Ok(Some(offset + pos as u64));
+            }
+            self.expected.consume(len);
+            buf = &buf[len..];
+            offset += len as u64;
+        }
+        Ok(None)
+    }
+}
+
+impl<R: BufRead> Write for Golden<R> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        if self.difference.is_none() {
+            self.difference = self.compare(buf)?;
+        }
+        self.compared += buf.len() as u64;
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::reader::{self, TmpOptions};
+    use rstest::*;
+    use std::io::{BufReader, Cursor};
+
+    #[rstest]
+    #[case(b"\"a\",\"b\"\n\"c\"", None)]
+    #[case(b"\"a\",\"b\"\n\"x\"", Some(9))]
+    #[case(b"\"a\",\"b\"\n", Some(8))]
+    #[case(b"\"a\",\"b\"\n\"c\"\n", Some(11))]
+    #[case(b"", Some(0))]
+    fn test_golden_exorcize_csv(
+        #[case] expected: &[u8],
+        #[case] exp: Option<u64>,
+        #[values(1, 2, 5, 64)] capacity: usize,
+    ) {
+        let data = b"a\x1Eb\x1Dc";
+        let opts = TmpOptions::default();
+
+        for chunk_size in 1..=data.len() {
+            let mut golden = Golden::new(BufReader::with_capacity(capacity, Cursor::new(expected)));
+            let size = data.len() as u64;
+            reader::exorcize_csv(Cursor::new(data), &mut golden, size, chunk_size, &opts).unwrap();
+            assert_eq!(
+                golden.first_difference().unwrap(),
+                exp,
+                "chunk_size {chunk_size}"
+            );
+        }
+    }
+}
@@ -0,0 +1,112 @@
+use std::io::{self, Read};
+
+/// Opens the body of `url` for reading, following redirects.
+///
+/// Anything but a final `200 OK` is an error naming the status, as are a failed
+/// connection and too many redirects. The body may be of any length, announced or not.
+pub(crate) fn open(url: &str) -> io::Result<impl Read + Send> {
+    let response = match ureq::get(url).call() {
+        Ok(response) => response,
+        Err(ureq::Error::Status(code, response)) => {
+            return Err(status_error(url, code, response.status_text()))
+        }
+        Err(e) => {
+            let msg = format!("GET {url} failed: {e}");
+            return Err(io::Error::new(io::ErrorKind::ConnectionAborted, msg));
+        }
+    };
+    match response.status() {
+        200 => Ok(response.into_reader()),
+        code => Err(status_error(url, code, response.status_text())),
+    }
+}
+
+fn status_error(url: &str, code: u16, text: &str) -> io::Error {
+    let msg = format!("GET {url} returned {code} {text}, expected 200 OK");
+    io::Error::other(msg)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::reader::{self, TmpOptions};
+    use rstest::*;
+    use std::io::{BufRead, BufReader, Write};
+    use std::net::TcpListener;
+    use std::thread;
+
+    /// Serves `responses` in turn, one per connection, from a local port; returns its URL.
+    fn serve(responses: Vec<Vec<u8>>) -> String {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        thread::spawn(move || {
+            for response in responses {
+                let (mut stream, _) = listener.accept().unwrap();
+                let mut request = BufReader::new(stream.try_clone().unwrap());
+                let mut line = String::new();
+                while request.read_line(&mut line).unwrap() > 2 {
+                    line.clear();
+                }
+                stream.write_all(&response).unwrap();
+            }
+        });
+        url
+    }
+
+    fn ok(body: &[u8], length: bool) -> Vec<u8> {
+        let head = match length {
+            true => format!("HTTP/1.1 200 OK\r\nContent-Length: {}\r\n\r\n", body.len()),
+            false => "HTTP/1.1 200 OK\r\nConnection: close\r\n\r\n".to_string(),
+        };
+        [head.as_bytes(), body].concat()
+    }
+
+    #[rstest]
+    #[case(b"a\x1Eb\x1D\"c\\\x1Ed", "\"a\",\"b\"\n\"\\\"c\\\\\",\"d\"")]
+    #[case(b"", "")]
+    fn test_open(#[case] body: &[u8], #[case] exp: &str, #[values(true, false)] length: bool) {
+        let url = serve(vec![ok(body, length)]);
+
+        let mut output = Vec::new();
+        let opts = TmpOptions::default();
+        reader::exorcize_stream(open(&url).unwrap(), &mut output, 4, &opts).unwrap();
+        assert_eq!(String::from_utf8(output).unwrap(), exp);
+    }
+
+    #[rstest]
+    fn test_open_follows_redirect() {
+        let target = serve(vec![ok(b"a\x1Eb", true)]);
+        let moved =
+            format!("HTTP/1.1 302 Found\r\nLocation: {target}/dump\r\nContent-Length: 0\r\n\r\n");
+        let url = serve(vec![moved.into_bytes()]);
+
+        let mut body = Vec::new();
+        open(&url).unwrap().read_to_end(&mut body).unwrap();
+        assert_eq!(body, b"a\x1Eb");
+    }
+
+    #[rstest]
+    #[case(
+        b"HTTP/1.1 404 Not Found\r\nContent-Length: 0\r\n\r\n",
+        "returned 404 Not Found"
+    )]
+    #[case(b"HTTP/1.1 204 No Content\r\n\r\n", "returned 204 No Content")]
+    fn test_open_status_error(#[case] response: &[u8], #[case] msg: &str) {
+        let url = serve(vec![response.to_vec()]);
+
+        let e = open(&url).err().unwrap();
+        assert!(e.to_string().contains(msg), "{e}");
+    }
+
+    #[rstest]
+    fn test_open_connection_error() {
+        // bound then dropped, so nothing listens there
+        let addr = TcpListener::bind("127.0.0.1:0")
+            .unwrap()
+            .local_addr()
+            .unwrap();
+
+        let e = open(&format!("http://{addr}")).err().unwrap();
+        assert_eq!(e.kind(), io::ErrorKind::ConnectionAborted);
+    }
+}
@@ -0,0 +1,257 @@
+use crate::buffered::FieldHook;
+use crate::reader::TmpOptions;
+use std::io::{self, BufWriter, Write};
+
+/// A field hook writing every row it sees to `out` as a line of JSON, an array of its
+/// fields as strings, after running `inner` on them.
+///
+/// Fields are taken as the buffered driver hands them over, so the lines hold the same
+/// values as the CSV written alongside, with backslash escapes resolved when the dialect
+/// has them. Bytes that are not UTF-8 become U+FFFD. Strings are escaped by `write_string`,
+/// fully or, with `minimal`, only as JSON requires. `finish` writes the last row.
+pub(crate) struct JsonLines<'h, W: Write> {
+    inner: &'h mut dyn FieldHook,
+    out: BufWriter<W>,
+    unescapes: bool,
+    minimal: bool,
+    row: Vec<String>,
+}
+
+impl<'h, W: Write> JsonLines<'h, W> {
+    pub(crate) fn new(
+        inner: &'h mut dyn FieldHook,
+        out: W,
+        opts: &TmpOptions,
+        minimal: bool,
+    ) -> Self {
+        JsonLines {
+            inner,
+            out: BufWriter::new(out),
+            unescapes: opts.escape.is_some() && !opts.double_quotes,
+            minimal,
+            row: Vec::new(),
+        }
+    }
+
+    /// Writes the row still held, if any, and flushes.
+    pub(crate) fn finish(mut self) -> io::Result<()> {
+        if !self.row.is_empty() {
+            self.write_row()?;
+        }
+        self.out.flush()
+    }
+
+    fn write_row(&mut self) -> io::Result<()> {
+        self.out.write_all(b"[")?;
+        for (i, value) in self.row.iter().enumerate() {
+            if i > 0 {
+                self.out.write_all(b",")?;
+            }
+            write_string(&mut self.out, value, self.minimal)?;
+        }
+        self.out.write_all(b"]\n")?;
+        self.row.clear();
+        Ok(())
+    }
+
+    fn value(&self, field: &[u8]) -> String {
+        if !self.unescapes {
+            return String::from_utf8_lossy(field).into_owned();
+        }
+        let mut value = Vec::with_capacity(field.len());
+        let mut escaped = false;
+        for &byte in field {
+            match byte {
+                _ if std::mem::take(&mut escaped) => value.push(byte),
+                b'\\' => escaped = true,
+                _ => value.push(byte),
+            }
+        }
+        // a backslash escaping nothing of its field is content
+        if escaped {
+            value.push(b'\\');
+        }
+        String::from_utf8_lossy(&value).into_owned()
+    }
+}
+
+/// Writes `value` as a JSON string. `"`, `\` and the control characters below U+0020 are
+/// always escaped; unless `minimal`, so are `/` and every non-ASCII character, as `\uXXXX`,
+/// for readers that take ASCII only. Runs needing no escape are written at once.
+fn write_string(out: &mut impl Write, value: &str, minimal: bool) -> io::Result<()> {
+    out.write_all(b"\"")?;
+    let mut start = 0;
+    for (i, c) in value.char_indices() {
+        let short: &[u8] = match c {
+            '"' => b"\\\"",
+            '\\' => b"\\\\",
+            '\n' => b"\\n",
+            '\r' => b"\\r",
+            '\t' => b"\\t",
+            '\u{8}' => b"\\b",
+            '\u{c}' => b"\\f",
+            '/' if !minimal => b"\\/",
+            '\0'..='\u{1f}' => b"",
+            _ if minimal || c.is_ascii() => continue,
+            _ => b"",
+        };
+        out.write_all(&value.as_bytes()[start..i])?;
+        start = i + c.len_utf8();
+        match short {
+            [] => {
+                for unit in c.encode_utf16(&mut [0; 2]) {
+                    write!(out, "\\u{unit:04x}")?;
+                }
+            }
+            short => out.write_all(short)?,
+        }
+    }
+    out.write_all(&value.as_bytes()[start..])?;
+    out.write_all(b"\"")
+}
+
+impl<W: Write> FieldHook for JsonLines<'_, W> {
+    fn on_field(&mut self, col: usize, field: &mut Vec<u8>) -> io::Result<()> {
+        self.inner.on_field(col, field)?;
+        if col == 0 && !self.row.is_empty() {
+            self.write_row()?;
+        }
+        let value = self.value(field);
+        self.row.push(value);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::buffered::{self, NoHook};
+    use rstest::*;
+    use std::io::Cursor;
+
+    /// Splits output written with the default options back into its values.
+    fn csv_values(csv: &[u8]) -> Vec<Vec<String>> {
+        let text = String::from_utf8(csv.to_vec()).unwrap();
+        text.split_terminator('\n')
+            .map(|row| {
+                let mut values = Vec::new();
+                let mut value = String::new();
+                let (mut quoted, mut escaped) = (false, false);
+                for c in row.chars() {
+                    match c {
+                        _ if std::mem::take(&mut escaped) => value.push(c),
+                        '\\' if quoted => escaped = true,
+                        '"' => quoted = !quoted,
+                        ',' if !quoted => values.push(std::mem::take(&mut value)),
+                        _ => value.push(c),
+                    }
+                }
+                values.push(value);
+                values
+            })
+            .collect()
+    }
+
+    #[rstest]
+    #[case(b"a\x1Eb\x1Dc\x1Ed", vec![vec!["a", "b"], vec!["c", "d"]])]
+    #[case(b"x\x1E\x1E\x1Dy", vec![vec!["x", "", ""], vec!["y"]])]
+    #[case(b"say \"hi\"\x1Eback\\\\slash\x1Dlast\x1D", vec![vec!["say \"hi\"", "back\\slash"], vec!["last"]])]
+    #[case(b"caf\xC3\xA9,\x1Eend\\", vec![vec!["café,", "end\\"]])]
+    fn test_jsonl_matches_csv(#[case] data: &[u8], #[case] exp: Vec<Vec<&str>>) {
+        let opts = TmpOptions::default();
+        for chunk_size in 1..=data.len() {
+            let (mut csv, mut jsonl) = (Vec::new(), Vec::new());
+            let mut hook = NoHook;
+            let mut lines = JsonLines::new(&mut hook, &mut jsonl, &opts, false);
+            buffered::exorcize_csv(Cursor::new(data), &mut csv, chunk_size, &opts, &mut lines)
+                .unwrap();
+            lines.finish().unwrap();
+
+            let rows: Vec<Vec<String>> = String::from_utf8(jsonl)
+                .unwrap()
+                .lines()
+                .map(|line| serde_json::from_str(line).unwrap())
+                .collect();
+            assert_eq!(rows, exp, "chunk_size {chunk_size}");
+            assert_eq!(csv_values(&csv), rows, "chunk_size {chunk_size}");
+        }
+    }
+
+    #[rstest]
+    #[case("plain", false, "\"plain\"")]
+    #[case("say \"hi\"", true, r#""say \"hi\"""#)]
+    #[case("back\\slash\\", true, r#""back\\slash\\""#)]
+    #[case("a\nb\rc\td\u{8}\u{c}", true, r#""a\nb\rc\td\b\f""#)]
+    #[case("\0\u{1}\u{1f}", false, r#""\u0000\u0001\u001f""#)]
+    #[case(" ~", true, "\" ~\"")]
+    #[case("a/b", true, "\"a/b\"")]
+    #[case("a/b", false, r#""a\/b""#)]
+    #[case("café €𝄞", true, "\"café €𝄞\"")]
+    #[case("café €𝄞", false, r#""caf\u00e9 \u20ac\ud834\udd1e""#)]
+    fn test_write_string(#[case] value: &str, #[case] minimal: bool, #[case] exp: &str) {
+        let mut out = Vec::new();
+        write_string(&mut out, value, minimal).unwrap();
+        assert_eq!(String::from_utf8(out.clone()).unwrap(), exp);
+        // a strict parser reads the value back
+        let parsed: String = serde_json::from_slice(&out).unwrap();
+        assert_eq!(parsed, value);
+    }
+
+    #[rstest]
+    fn test_write_string_every_char_below_0x80() {
+        let value: String = (0..0x80u8).map(char::from).collect();
+        for minimal in [false, true] {
+            let mut out = Vec::new();
+            write_string(&mut out, &value, minimal).unwrap();
+            assert!(out[1..out.len() - 1]
+                .iter()
+                .all(|&b| (0x20..0x80).contains(&b)));
+            let parsed: String = serde_json::from_slice(&out).unwrap();
+            assert_eq!(parsed, value, "minimal {minimal}");
+        }
+    }
+
+    #[rstest]
+    #[case(false)]
+    #[case(true)]
+    fn test_jsonl_escapes_quotes_backslashes_and_newlines(#[case] minimal: bool) {
+        let data = b"say \"hi\"\x1Eback\\\\slash\x1Dline\nbreak\x1Etab\there\x1D";
+        let opts = TmpOptions::default();
+        let (mut csv, mut jsonl) = (Vec::new(), Vec::new());
+        let mut hook = NoHook;
+        let mut lines = JsonLines::new(&mut hook, &mut jsonl, &opts, minimal);
+        buffered::exorcize_csv(Cursor::new(data), &mut csv, 3, &opts, &mut lines).unwrap();
+        lines.finish().unwrap();
+
+        let text = String::from_utf8(jsonl).unwrap();
+        let rows: Vec<Vec<String>> = text
+            .lines()
+            .map(|line| serde_json::from_str(line).unwrap())
+            .collect();
+        assert_eq!(
+            rows,
+            [["say \"hi\"", "back\\slash"], ["line\nbreak", "tab\there"]]
+        );
+        assert_eq!(text.lines().count(), 2);
+    }
+
+    #[rstest]
+    fn test_jsonl_runs_inner_hook() {
+        struct Upper;
+        impl FieldHook for Upper {
+            fn on_field(&mut self, _col: usize, field: &mut Vec<u8>) -> io::Result<()> {
+                field.make_ascii_uppercase();
+                Ok(())
+            }
+        }
+
+        let opts = TmpOptions::default();
+        let (mut csv, mut jsonl) = (Vec::new(), Vec::new());
+        let mut hook = Upper;
+        let mut lines = JsonLines::new(&mut hook, &mut jsonl, &opts, false);
+        buffered::exorcize_csv(Cursor::new(b"a\x1Eb"), &mut csv, 2, &opts, &mut lines).unwrap();
+        lines.finish().unwrap();
+        assert_eq!(csv, b"\"A\",\"B\"");
+        assert_eq!(jsonl, b"[\"A\",\"B\"]\n");
+    }
+}
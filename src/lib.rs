@@ -1,16 +1,75 @@
+mod batch;
+mod buffered;
+mod capped;
+mod config;
+mod diff;
+mod digest;
+mod files;
+mod fixed_width;
+mod format_file;
+mod golden;
+#[cfg(feature = "http")]
+mod http;
+mod jsonl;
+mod metrics;
+mod progress;
 mod reader;
+mod rejects;
+mod retry;
+mod rows;
+mod section;
+mod sorted;
+mod stats;
+#[cfg(feature = "arrow")]
+mod table;
+mod tee;
+mod transcode;
+mod transpose;
+mod units;
+mod unquote;
+mod utf8;
+mod verify;
+use buffered::{FieldHook, NoHook};
+use capped::Capped;
+use config::{Bytes, ChunkSize, ExorcistConfig};
+use digest::{Checksum, Hashing};
+use files::{Failure, Outcome, Target};
+use golden::Golden;
+use jsonl::JsonLines;
+use metrics::SummaryFormat;
+use progress::{Beat, Deadline, Heartbeat};
 use pyo3::{
-    exceptions::{PyRuntimeError, PyTypeError},
+    create_exception,
+    exceptions::{PyRuntimeError, PyTimeoutError, PyTypeError, PyUnicodeDecodeError, PyValueError},
     prelude::*,
+    types::{PyByteArray, PyBytes, PyDict},
 };
-use reader::TmpOptions;
-use std::fs::{self, File};
+use reader::{Case, ColType, Escapes, ExtraCol, TmpOptions, DEFAULT_CHUNK_SIZE, UNKNOWN_SIZE};
+use retry::Retry;
+use section::Section;
+use stats::{Partial, Stats};
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::{self, BufReader, Read, Write};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+use tee::Tee;
+use transcode::Errors;
+use utf8::Utf8Checked;
+use verify::{Rfc4180, SourceContent, Sourced, Verified};
+
+create_exception!(
+    bcp_exorcist,
+    CorruptionError,
+    PyRuntimeError,
+    "Raised when an exorcism fails partway; `stats` holds the report of the rows processed before the failure, or `None`."
+);
 
 fn unwrap_byte(input: Option<&[u8]>, default: u8) -> PyResult<u8> {
     let out = match input {
         Some(cs) if cs.len() == 1 => cs[0],
         None => default,
-        Some(cs) if cs.len() == 0 => default,
+        Some([]) => default,
         Some(cs) => {
             let msg = format!(
                 "Input b'{}' should be a single byte; len: {}",
@@ -23,31 +82,712 @@ fn unwrap_byte(input: Option<&[u8]>, default: u8) -> PyResult<u8> {
     Ok(out)
 }
 
+/// Reads the `escape` argument: a backslash, the only escape byte supported, or none.
+fn unwrap_escape(escape: Option<&[u8]>) -> PyResult<Option<u8>> {
+    match escape {
+        Some(b"\\") => Ok(Some(b'\\')),
+        Some(_) => Err(PyValueError::new_err("escape should be b'\\\\' or None")),
+        None => Ok(None),
+    }
+}
+
+/// An `extra_columns` entry: literal `bytes`, or the name of a computed column.
+#[derive(FromPyObject)]
+enum ExtraColumn {
+    Literal(Vec<u8>),
+    Name(String),
+}
+
+fn unwrap_extra_columns(columns: Vec<ExtraColumn>, filepath: &str) -> PyResult<Vec<ExtraCol>> {
+    columns
+        .into_iter()
+        .map(|column| match column {
+            ExtraColumn::Literal(value) => Ok(ExtraCol::Literal(value)),
+            ExtraColumn::Name(name) => match name.as_str() {
+                "row_number" => Ok(ExtraCol::RowNumber),
+                "filename" => Ok(ExtraCol::Literal(filepath.as_bytes().to_vec())),
+                _ => {
+                    let msg = format!(
+                        "unknown extra column '{name}'; expected bytes, 'row_number' or 'filename'"
+                    );
+                    Err(PyValueError::new_err(msg))
+                }
+            },
+        })
+        .collect()
+}
+
+fn unwrap_errors(errors: Option<&str>) -> PyResult<Errors> {
+    match errors {
+        None => Ok(Errors::Strict),
+        Some(name) => Errors::from_name(name).ok_or_else(|| {
+            let msg = format!(
+                "unknown errors '{name}'; expected one of {:?}",
+                Errors::NAMES
+            );
+            PyValueError::new_err(msg)
+        }),
+    }
+}
+
+impl<'py> FromPyObject<'py> for Bytes {
+    fn extract_bound(ob: &Bound<'py, PyAny>) -> PyResult<Self> {
+        Ok(Bytes(ob.downcast::<PyBytes>()?.as_bytes().to_vec()))
+    }
+}
+
+impl<'py> FromPyObject<'py> for ChunkSize {
+    fn extract_bound(ob: &Bound<'py, PyAny>) -> PyResult<Self> {
+        match ob.extract() {
+            Ok(bytes) => Ok(ChunkSize::Bytes(bytes)),
+            Err(_) => ob.extract().map(ChunkSize::Text),
+        }
+    }
+}
+
+fn unwrap_chunk_size(input: Option<ChunkSize>) -> PyResult<Option<usize>> {
+    unwrap_size("chunk_size", input)
+}
+
+/// Reads a size argument named `name`, given as a `chunk_size` is.
+fn unwrap_size(name: &str, input: Option<ChunkSize>) -> PyResult<Option<usize>> {
+    match input {
+        None => Ok(None),
+        Some(ChunkSize::Bytes(bytes)) => Ok(Some(bytes)),
+        Some(ChunkSize::Text(text)) => units::parse_size(&text).map(Some).ok_or_else(|| {
+            let msg = format!(
+                "{name} '{text}' is not a size; expected e.g. 4194304, \"4MiB\" or \"64KB\""
+            );
+            PyValueError::new_err(msg)
+        }),
+    }
+}
+
+/// Builds the `escape_bytes` table, refusing keys the batch already handles.
+fn unwrap_escapes(escapes: HashMap<Vec<u8>, Vec<u8>>, reserved: [u8; 3]) -> PyResult<Escapes> {
+    let mut table = Vec::with_capacity(escapes.len());
+    for (byte, replacement) in escapes {
+        let byte = unwrap_byte(Some(&byte), 0)?;
+        if reserved.contains(&byte) {
+            let msg = format!(
+                "escape_bytes cannot rewrite b'{}'; delim, newline and quotes are always handled",
+                std::ascii::escape_default(byte),
+            );
+            return Err(PyValueError::new_err(msg));
+        }
+        table.push((byte, replacement));
+    }
+    Ok(Escapes::new(table))
+}
+
+/// Refuses a `sep` or `eol` that is also the `escape` byte, whatever `allow` says.
+fn check_escape(opts: &TmpOptions) -> PyResult<()> {
+    match opts.escape_collision() {
+        Some(name) => {
+            let msg =
+                format!("{name} should differ from escape b'\\\\'; pass escape=None to use it");
+            Err(PyValueError::new_err(msg))
+        }
+        None => Ok(()),
+    }
+}
+
+/// Flags a `sep` or `eol` that commonly occurs in field content, after `check_escape`.
+///
+/// By default a warning goes to the `bcp_exorcist` logger; `allow` set to `False` raises
+/// instead and `True` silences the check.
+fn check_common_bytes(py: Python<'_>, opts: &TmpOptions, allow: Option<bool>) -> PyResult<()> {
+    check_escape(opts)?;
+    if allow == Some(true) {
+        return Ok(());
+    }
+
+    for (name, byte, kind) in [("delim", opts.sep, "field"), ("newline", opts.eol, "row")] {
+        if !reader::is_common_byte(byte) {
+            continue;
+        }
+        let msg = format!(
+            "{name}=b'{}' is a common byte; every occurrence in the data becomes a {kind} break",
+            std::ascii::escape_default(byte),
+        );
+        if allow == Some(false) {
+            return Err(PyValueError::new_err(msg));
+        }
+        log_warning(py, &msg)?;
+    }
+    Ok(())
+}
+
+/// The default options with the `delim` and `newline` arguments, checked by
+/// `check_common_bytes` as every entry point taking them does.
+fn common_opts(
+    py: Python<'_>,
+    delim: Option<&[u8]>,
+    newline: Option<&[u8]>,
+) -> PyResult<TmpOptions> {
+    let defaults = TmpOptions::default();
+    let opts = TmpOptions {
+        sep: unwrap_byte(delim, defaults.sep)?,
+        eol: unwrap_byte(newline, defaults.eol)?,
+        ..defaults
+    };
+    check_common_bytes(py, &opts, None)?;
+    Ok(opts)
+}
+
+/// Logs `msg` as a warning to the `bcp_exorcist` logger.
+fn log_warning(py: Python<'_>, msg: &str) -> PyResult<()> {
+    py.import("logging")?
+        .call_method1("getLogger", ("bcp_exorcist",))?
+        .call_method1("warning", (msg,))?;
+    Ok(())
+}
+
+/// Python callables applied to field content, keyed by column index.
+struct ColumnTransforms<'py>(HashMap<usize, Bound<'py, PyAny>>);
+
+impl FieldHook for ColumnTransforms<'_> {
+    fn on_field(&mut self, col: usize, field: &mut Vec<u8>) -> io::Result<()> {
+        let Some(transform) = self.0.get(&col) else {
+            return Ok(());
+        };
+
+        let out = transform
+            .call1((PyBytes::new(transform.py(), field),))
+            .and_then(|out| Ok(out.downcast_into::<PyBytes>()?))
+            .map_err(io::Error::other)?;
+
+        field.clear();
+        field.extend_from_slice(out.as_bytes());
+        Ok(())
+    }
+}
+
+/// Wraps a failed run in a `CorruptionError` whose `stats` attribute reports its progress,
+/// or in a `TimeoutError` when it ran out of time.
+fn corruption_error(py: Python<'_>, e: io::Error) -> PyErr {
+    let err = match e.kind() {
+        io::ErrorKind::TimedOut => PyTimeoutError::new_err(format!("✝️ exorcism timed out: {e}")),
+        _ => CorruptionError::new_err(format!("✝️ exorcism failed: {e}")),
+    };
+    with_stats(py, err, &e)
+}
+
+/// Wraps a failed run whose original content could not be put back at `path` in a
+/// `CorruptionError` naming where it was left.
+fn unrestored_error(
+    py: Python<'_>,
+    path: &Path,
+    run: io::Error,
+    restore: io::Error,
+    original: &Path,
+) -> PyErr {
+    let msg = format!(
+        "✝️ exorcism failed: {run}; restoring '{}' failed too: {restore}; the original is left \
+        at '{}'",
+        path.display(),
+        original.display()
+    );
+    with_stats(py, CorruptionError::new_err(msg), &run)
+}
+
+/// Sets the `stats` and the cause of `err` from the run error `e`.
+fn with_stats(py: Python<'_>, err: PyErr, e: &io::Error) -> PyErr {
+    // an exception raised by a callback, such as `progress`, is kept as the cause
+    err.set_cause(py, python_error(py, e));
+    let stats = match Partial::stats(e).map(|stats| stats_dict(py, stats)) {
+        Some(Ok(dict)) => dict.into_any(),
+        Some(Err(e)) => return e,
+        None => py.None().into_bound(py),
+    };
+    match err.value(py).setattr("stats", stats) {
+        Ok(()) => err,
+        Err(e) => e,
+    }
+}
+
+/// Builds the report returned by `exorcize_csv`.
+fn stats_dict<'py>(py: Python<'py>, stats: &Stats) -> PyResult<Bound<'py, PyDict>> {
+    let dict = PyDict::new(py);
+    dict.set_item("rows", stats.rows)?;
+    dict.set_item("fields", stats.fields)?;
+    dict.set_item("bytes_written", stats.bytes_written)?;
+    if let Some(rejected) = stats.rejected_rows {
+        dict.set_item("accepted_rows", stats.rows)?;
+        dict.set_item("rejected_rows", rejected)?;
+    }
+    if let Some(duplicates) = stats.duplicate_rows {
+        dict.set_item("duplicate_rows", duplicates)?;
+    }
+    if let Some(checksum) = &stats.checksum {
+        dict.set_item("checksum", checksum)?;
+    }
+
+    if let Some(widths) = &stats.column_widths {
+        dict.set_item("column_widths", widths)?;
+    }
+    dict.set_item("field_count_histogram", &stats.field_counts)?;
+
+    let anomalies: Vec<String> = stats.anomalies.iter().map(|a| a.to_string()).collect();
+    dict.set_item("anomalies", anomalies)?;
+    Ok(dict)
+}
+
+/// Builds the options passed to the `on_start` callable of `exorcize_csv`.
+fn options_dict<'py>(py: Python<'py>, job: &Job<'_>) -> PyResult<Bound<'py, PyDict>> {
+    let opts = &job.opts;
+    let dict = PyDict::new(py);
+    dict.set_item("delim", PyBytes::new(py, &[opts.sep]))?;
+    dict.set_item("newline", PyBytes::new(py, &[opts.eol]))?;
+    dict.set_item("quote", PyBytes::new(py, &[opts.quote]))?;
+    dict.set_item("out_delim", PyBytes::new(py, &opts.out_delim))?;
+    dict.set_item("out_newline", PyBytes::new(py, &opts.out_eol))?;
+    dict.set_item("chunk_size", job.chunk_size)?;
+    dict.set_item("write_chunk_size", opts.write_size)?;
+    dict.set_item("driver", job.driver())?;
+    Ok(dict)
+}
+
 /// Fixes a broken CSV file by processing it in batches.
 ///
 /// This function receives a broken CSV file and fixes it by processing it in chunks.
 /// The `filepath` is the path to the file that needs to be fixed. The `delim` and `newline`
 /// parameters are the ASCII characters used as delimiters and newline characters in the broken CSV.
-/// These characters are suggested to be uncommon ASCII characters. The default values are `\x1E`
+/// These characters are suggested to be uncommon ASCII characters: any occurrence inside a field
+/// is taken as a break, so a printable byte such as `,` or `|` splits every field containing it.
+/// Such choices are logged as a warning. The default values are `\x1E`
 /// for `delim` and `\x1D` for `newline`. The `chunk_size` parameter specifies the size of the batches
 /// to process, with a default value of 4 MB.
 ///
+/// An empty file has nothing to fix: it is left untouched without a backup (or, with `output`,
+/// an empty output file is created).
+///
+/// Every raw `"` in the content is escaped, wherever it falls in its field: a file starting
+/// with `"abc` gives `"\"abc"`, the same as a field opened after a delimiter.
+///
+/// Returns a report dict with the `rows` and `fields` found, the `bytes_written` and a list of
+/// `anomalies`: warnings for a byte order mark after the first byte, NUL bytes, rows whose field
+/// count differs from the most common one, and an unusually high share of raw quotes. Anomalies
+/// never fail the run. Its `field_count_histogram` maps each field count to the number of rows
+/// with it: one key for a healthy file, more for a ragged one. With `checksum`, the report also
+/// holds the hex digest of the output, and with `column_widths` the `column_widths` list.
+/// Its `status` is `"exorcized"` or `"empty"`, and `backup_path` names the `.bak` copy of the
+/// original that an in-place run keeps, or is `None`.
+///
+/// A failed run raises `CorruptionError`. Its `status` is `"restored"` when `filepath` was put back
+/// as it was, or `"failed"` with `output`, whose partial file is removed. Its `broken_path` names
+/// the partial output an in-place run left behind, `<filepath>.broken`, and is `None` when nothing
+/// is left; a partial output that could not be moved there or removed is named in the message
+/// instead. Its `backup_path` names the `.bak` copy of the original when one is still on disk, as
+/// when restoring had to copy it back rather than rename it, and is `None` otherwise. A run
+/// stopped by `timeout_secs` raises `TimeoutError` instead, with the same attributes. Should the
+/// original not go back, its `status` is `"unrestored"`, its message tells why and `backup_path`
+/// names where the original was left.
+///
+/// The options after `chunk_size` are keyword-only, and an unknown one raises `TypeError`. They
+/// are all checked together before any file is touched: two options that do not go together
+/// raise `ValueError` naming the first one and those it is not supported with.
+///
 /// # Arguments
 ///
 /// * `filepath` - A string slice that holds the path to the file to be fixed.
 /// * `delim` - An optional ASCII character used as the delimiter in the broken CSV. Default is `\x1E`.
 /// * `newline` - An optional ASCII character used as the newline character in the broken CSV. Default is `\x1D`.
-/// * `chunk_size` - An optional size for the batch size to process. Default is 4 MB.
+/// * `chunk_size` - An optional size for the batch size to process, in bytes or as a string with a
+///   unit such as `"64KiB"` or `"4MB"`. Default is 4 MiB. Output is written in writes of up to
+///   `write_chunk_size` whatever the chunk size, so a small one does not mean small writes.
+/// * `read_chunk_size` - An optional size of each read of the input, given as `chunk_size` is, for
+///   tuning reads apart from writes, e.g. large reads from a slow network share. Not supported with
+///   `chunk_size`, which it replaces. Default is `chunk_size`.
+/// * `write_chunk_size` - An optional size the output is coalesced to before each write, given as
+///   `chunk_size` is, e.g. small to keep a pipe reader fed or large for a high-latency target.
+///   Either size must be at least 1, or it raises `ValueError`. Default is 256 KiB.
+/// * `preallocate` - An optional flag to reserve the expected output size on disk before writing.
+///   This is a best-effort hint for multi-GB files and is ignored where unsupported. Default is `False`.
+/// * `double_backslash` - An optional flag to double every backslash in field content, for loaders
+///   that interpret backslash escapes. Default is `False`.
+/// * `column_transforms` - An optional dict mapping column indices to callables that receive a field's
+///   raw `bytes` and return its replacement. Fields are then buffered in full and every transform is a
+///   call into Python, so expect a severe slowdown. An exception aborts and restores the backup.
+/// * `output` - An optional path to write the fixed CSV to. When given, `filepath` is only read and
+///   no backup is made. An `output` naming `filepath` itself, even through a link, is an in-place
+///   run. On Unix `output` may be a named pipe: it is opened for writing as it is, with or without
+///   `overwrite`, and the call blocks until a reader opens it. A pipe is only supported as
+///   `output` or `tee`, never as `filepath` of an in-place run. An existing directory raises
+///   `IsADirectoryError` before anything is written.
+/// * `temp_output` - An optional flag to write the fixed CSV to a new, uniquely named
+///   `.<filename>.<pid>.<n>.tmp` next to `filepath`, or in `tmp_dir`, whose path the report
+///   returns as `output_path` for the caller to move where it wants; `filepath` is only read, as
+///   with `output`. Keep it on the filesystem of its destination for the move to be a rename. Not
+///   supported with `output`, `dest`, `discard`, `diff` or `in_place_truncate`, which raise
+///   `ValueError`. Default is `False`.
+/// * `overwrite` - An optional flag to replace an existing `output`. Default is `False`, which raises
+///   `FileExistsError` instead. The in-place mode always overwrites `filepath`.
+/// * `follow_symlinks` - An optional flag for in-place runs on a symlinked `filepath`. By default the
+///   link is resolved and its target is fixed (and backed up next to the target), leaving the link
+///   intact. When `False`, symlinks are refused with an `OSError`.
+/// * `in_place_truncate` - An optional flag to rewrite `filepath` without renaming it, preserving its
+///   inode, hardlinks and open handles. The original is first copied to `<filepath>.spool`, then the
+///   file is truncated and rewritten; on failure it is restored from the spool. There is no atomic
+///   swap, so a crash mid-write leaves the spool as the only intact copy. Ignored with `output`.
+///   Default is `False`.
+/// * `tmp_dir` - An optional directory for the `in_place_truncate` spool, e.g. a fast scratch disk or
+///   a writable `/tmp` when the input's directory is read-only. The spool is copied rather than
+///   renamed, so it may be on another filesystem. It is checked to be writable before the run.
+///   Only used with `in_place_truncate` and `temp_output`. Default is `None`, which spools next
+///   to `filepath`.
+/// * `atomic` - An optional flag for the in-place mode to write the fix to a hidden
+///   `.<filename>.tmp` next to `filepath` and rename it over `filepath` once complete, so a reader
+///   never sees a partially written file. The `.bak` is made first, as a hardlink to the original
+///   or a copy where links are not supported, and removed again on failure. `False` gives the
+///   previous behavior: `filepath` is renamed to `.bak` and the fix is written at its path. Ignored
+///   with `output` and `in_place_truncate`. Default is `True`.
+/// * `keep_backup` - An optional flag for the in-place mode to keep the `.bak` of a successful run.
+///   When `False` it is deleted once the fix is in place, and the report's `backup_path` is `None`;
+///   a `.bak` that cannot be deleted is logged as a warning and kept as `backup_path`, the run
+///   still succeeding. A failed run restores from it as usual. Ignored where no `.bak` is made.
+///   Default is `True`.
+/// * `retries` - An optional number of extra attempts for reads and writes that fail with a transient
+///   error (`EINTR`, `EAGAIN`, timeouts, `EIO`), as seen on networked filesystems. Other errors fail
+///   immediately. Default is 0.
+/// * `tee` - An optional second path that receives a copy of the fixed output in the same pass. It
+///   follows the `overwrite` rule of `output`; errors writing it are reported with a `tee:` prefix.
+/// * `final_newline` - An optional flag for the end of the output. `True` always ends the last row
+///   with a newline, `False` never does. Default is `None`, which keeps the terminator only if the
+///   input ended with `newline`.
+/// * `pad_to` - An optional number of fields every row must have. Shorter rows are padded with empty
+///   fields; fields past the last one are dropped. Default is `None`, which keeps rows as they are.
+/// * `checksum` - An optional digest of the fixed output to compute while writing it, `"crc32"` or
+///   `"sha256"`. Unknown names raise `ValueError`. The `tee` copy is identical and not hashed again.
+/// * `checksum_sidecar` - An optional flag to also write the digest next to the output as
+///   `<output>.<checksum>`, in the format of `sha256sum`. Default is `False`.
+/// * `column_order` - An optional list of the input columns to write, by 0-based index, in
+///   output order, e.g. `[2, 0]` writes the third column then the first and drops the others. A
+///   column may be listed twice. `column_transforms` get the input columns, while
+///   `quote_columns`, `header` and `column_widths` refer to the output ones. A row lacking a listed
+///   column gets it empty. Like `column_transforms`, this buffers every field, and each row whole.
+///   An empty list raises `ValueError`, as do `pad_to`, `escape_only`, `rejects_path`,
+///   `repair_log`, `source_quotes` and `naive_quote_toggle` with it. Default is `None`.
+/// * `strict_column_order` - An optional flag to fail on a row lacking a column of
+///   `column_order` instead, with `CorruptionError` naming the row; in place the original is
+///   restored. Not supported without `column_order`, which raises `ValueError`. Default is `False`.
+/// * `dedup` - An optional flag to drop every row written exactly as an earlier one, e.g. to
+///   prepare a lookup table; rows are compared after every other option applied, and the report
+///   gains `duplicate_rows`, the number dropped. Only a 128-bit digest of each distinct row is
+///   held, not the row; like `column_transforms`, this buffers every field. Not supported with
+///   `footer`, `verify_rfc4180`, `escape_only`, `rejects_path`, `repair_log`, `source_quotes` or
+///   `naive_quote_toggle`, which raise `ValueError`. Default is `False`.
+/// * `dedup_max_rows` - An optional limit on the distinct rows `dedup` holds, about 40 bytes each.
+///   One more fails the run with `CorruptionError`; in place the original is restored. 0, or the
+///   limit without `dedup`, raises `ValueError`. Default is 10,000,000.
+/// * `trailing_sep` - An optional handling of a `delim` right before a `newline` or the end of
+///   the input: `"field"` opens an empty last field, as any `delim` does, and `"ignore"` drops it,
+///   for exports ending every field with one. Each dropped one is logged by `repair_log`. Unknown
+///   names raise `ValueError`, as do `escape_only` and `rejects_path` with `"ignore"`. Default is
+///   `"field"`.
+/// * `length_prefixed` - An optional flag to frame every row for length-delimited readers: it is
+///   written after its byte length, as a 4-byte little-endian integer, and without a newline. Like
+///   `column_transforms`, this buffers every field, and each row whole; a row of 4 GiB or more
+///   fails the run with `CorruptionError`. Not supported with `header`, `footer`, `write_bom`,
+///   `out_newline`, `line_ending`, `final_newline`, `escape_only`, `rejects_path`,
+///   `verify_rfc4180` or `diff`, which raise `ValueError`. Default is `False`.
+/// * `null_token` - An optional field value marking a null in the input, e.g. `b'NULL'`: a field
+///   holding exactly it is written as `null_output`, unquoted, and is not passed to
+///   `column_transforms`. Like `column_transforms`, this buffers every field. Not supported with
+///   `column_order`, `escape_only`, `rejects_path`, `repair_log`, `source_quotes` or
+///   `naive_quote_toggle`, which raise `ValueError`. Default is `None`.
+/// * `null_output` - An optional representation of the nulls `null_token` finds, written as it
+///   is, e.g. `b'\\N'` for the text format of Postgres. Bytes that would need quoting, or the
+///   option without `null_token`, raise `ValueError`. Default is `b''`, an unquoted empty field.
+/// * `quote_columns` - An optional list of column indices to quote; fields of other columns are
+///   written verbatim, without quotes or quote escaping. A field there holding `,`, `"` or a newline
+///   is still quoted so the row stays intact. Like `column_transforms`, this buffers every field.
+///   Default is `None`, which quotes every column.
+/// * `column_types` - An optional type for each output column, deciding its quoting in place of
+///   `quote_columns`: `"text"` is always quoted, `"number"` never, and `"auto"` only when the
+///   field would break the row otherwise, as a column left out of `quote_columns` is. A number
+///   field holding `,`, `"` or a newline fails the run with `CorruptionError`; in place the
+///   original is restored. Columns past the list are `"auto"`. Like `column_transforms`, this
+///   buffers every field. Unknown names raise `ValueError`, as do `quote_columns` and
+///   `rejects_path` with it. Default is `None`.
+/// * `allow_common_delimiter` - An optional flag for a printable or whitespace `delim`/`newline`.
+///   Default is `None`, which logs a warning to the `bcp_exorcist` logger; `False` raises
+///   `ValueError` instead and `True` accepts it silently.
+/// * `escape_bytes` - An optional dict mapping single bytes of field content to the bytes written in
+///   their place, e.g. `{b'\x0B': b'\\v'}`. `delim`, `newline` and `"` cannot be remapped. A
+///   backslash key replaces the default handling of a trailing backslash, so its replacement must
+///   not leave the closing quote escaped. Default is `None`.
+/// * `flatten_newlines` - An optional flag for loaders that take no multi-line field, even quoted.
+///   Every `\n` and `\r` in field content is written as the two characters `\n` or `\r`, so each
+///   record stays on one line; a `delim` or `newline` that is one of them is left alone. It adds
+///   to `escape_bytes`, whose entries win; the record terminator is still `out_newline`. Default
+///   is `False`.
+/// * `strict_eof` - An optional flag to raise `CorruptionError` when the input ends with an odd run of
+///   backslashes, which escapes a byte that never came and usually means the file was cut off.
+///   Default is `False`, which fixes such input like any other.
+/// * `progress` - An optional callable receiving `(bytes_read, total_bytes, mb_per_s)`, where
+///   `mb_per_s` is the input throughput since the start, and `total_bytes` is 0 when the size of
+///   the input cannot be read. It is called at most once per `progress_interval` and once more at
+///   the end of the input; an exception aborts the run.
+/// * `progress_interval` - An optional number of seconds between two `progress` calls, independent
+///   of `chunk_size`. Default is 1.0.
+/// * `case` - An optional `"upper"` or `"lower"` to ASCII-case all field content. Quotes, delimiters
+///   and non-ASCII bytes are left as they are. Default is `None`.
+/// * `collapse_source_double_quotes` - An optional flag for sources that escape a quote by doubling
+///   it: each `""` in field content is written as one escaped quote instead of two. A run of quotes
+///   pairs up from its start. Default is `False`.
+/// * `header` - An optional list of column names written as a first row, quoted and escaped like a
+///   data row of the same values. An empty input stays empty. Default is `None`.
+/// * `write_bom` - An optional flag to start the output with a UTF-8 BOM, before the `header`,
+///   for Excel on Windows to read it as UTF-8. An empty input stays empty. Not supported with
+///   `escape_only`, which raises `ValueError`. Default is `False`.
+/// * `marker` - An optional flag to make an in-place run safe to retry: a successful one writes
+///   `<filepath>.exorcised`, recording a digest of the options with the size and modification
+///   time of the fixed file, and a later run finding it unchanged is skipped, with the status
+///   `"skipped"`, instead of escaping its quotes twice. Other options, a newer version, or a file
+///   exported again over the fixed one run as usual. Not supported with `output`, `dest`,
+///   `discard`, `diff`, `temp_output`, `count_only` or `column_transforms`, whose callables
+///   cannot be told apart, which raise `ValueError`. Default is `False`.
+/// * `metrics_path` - An optional path where a successful run writes its stats in the Prometheus
+///   textfile format (`bcp_exorcist_rows_total`, `_bytes_total`, `_quotes_escaped_total` and
+///   `_duration_seconds`), for node_exporter's textfile collector. The file is replaced atomically.
+///   A failed write is logged as a warning and does not fail the run. Default is `None`.
+/// * `repair_log` - An optional path where a successful run writes every repair it applied beyond
+///   plain quoting, for audits: escaped and collapsed quotes, re-escaped backslashes, rows padded
+///   by `pad_to` and fields it dropped. Each tab-separated line holds the input byte offset, the
+///   0-based row and column and the repair. The entries are held in memory until the end of the
+///   run. Not supported with `column_transforms`, `quote_columns`, `column_types`,
+///   `column_widths`, `copy_compatible` or `quote_empty=False`, which raise `ValueError`.
+///   Default is `None`.
+/// * `discard` - An optional flag to run the whole fix, writes included, into a sink instead of a
+///   file, e.g. to time reading and processing without disk writes. `filepath` is only read and
+///   no backup is made; `tee`, the report and the checksum work as usual. Not supported with
+///   `output`, `dest` or `checksum_sidecar`, which raise `ValueError`. Default is `False`.
+/// * `count_only` - An optional flag to only count the rows and fields of `filepath` for its report
+///   and anomalies, without fixing it: nothing is quoted, escaped or written, and `bytes_written`
+///   is 0. Much faster than `discard`; `filepath` is only read. Options that only shape the output
+///   are ignored; those that change the report or need an output are not supported: `output`,
+///   `dest`, `tee`, `diff`, `checksum`, `checksum_sidecar`, `repair_log`, `rejects_path`,
+///   `column_widths`, `dedup`, `verify_rfc4180`, `escape_only`, `source_quotes`,
+///   `naive_quote_toggle` and `trailing_sep='ignore'`, which raise `ValueError`. Default is `False`.
+/// * `gzip` - An optional flag to read `filepath` as gzip-compressed, including files made of
+///   several concatenated gzip members. The output is plain CSV, so it needs `output` or `discard`;
+///   an in-place run raises `ValueError`. `progress` counts compressed bytes. Default is to detect
+///   it: a `filepath` ending in `.gz`, or a regular file starting with the gzip magic bytes, is read
+///   as gzip; `False` reads any file as it is.
+/// * `gzip_output` - An optional flag to write the output gzip-compressed, to `output` or `dest`.
+///   `tee`, `checksum`, `max_output_bytes` and the reported `bytes_written` see the CSV before
+///   compression. Not supported without `output`, `dest` or `temp_output`, nor with
+///   `checksum_sidecar`, which raise `ValueError`. Default is `True` for an `output` ending in
+///   `.gz`, `False` otherwise.
+/// * `gzip_level` - An optional compression level of `gzip_output`, from 0 (stored) to 9
+///   (smallest); 1 is much faster on large outputs. Higher values, or the level without
+///   `gzip_output`, raise `ValueError`. Default is 6.
+/// * `out_newline` - An optional row terminator for the output, one or more bytes such as
+///   `b"\r\n"` or `b"|\n"`. It replaces `\n` everywhere, `final_newline` included. It must not
+///   be empty or hold the `quote`, which raise `ValueError`. Default is `b"\n"`.
+/// * `line_ending` - An optional name of the output row terminator, for consumers that expect
+///   one: `"lf"` writes `\n`, and `"crlf"` writes `\r\n` as Windows tools do, `final_newline`
+///   included. Not supported with `out_newline`, which names any terminator, nor with
+///   `length_prefixed`; these and any other name raise `ValueError`. Default is `"lf"`.
+/// * `out_delim` - An optional field separator for the output, one or more bytes such as `b"\t"`
+///   or `b"|;|"`, written between the closing and opening quotes of two fields. It must not be
+///   empty or hold the `quote`, which raise `ValueError`. Default is `b","`.
+/// * `quote` - An optional ASCII character wrapping each field and escaped within it, e.g. `b"'"`
+///   for sources whose content is full of `"`. It must differ from `delim`, `newline` and the
+///   `escape` byte, which raise `ValueError`. Default is `b'"'`.
+/// * `reescape_delims` - An optional flag for fields ending in an odd run of backslashes, e.g. `a\`
+///   before a `delim`. By default the last backslash is doubled so it cannot escape the closing
+///   quote. Likewise a quote after an odd run of backslashes, e.g. `a\"`, is taken as already
+///   escaped and kept as it is, unless `collapse_source_double_quotes` is set. `False` passes
+///   such fields through unchanged and escapes every quote, for loaders that read the backslash
+///   literally. Default is `True`.
+/// * `reescape_count` - An optional number of backslashes written by that doubling, for
+///   unescapers of other conventions: 0 writes none, e.g. `"a\",`, and 2 gives `"a\\\",`. Not
+///   supported with `reescape_delims=False` or `verify_rfc4180` unless 1, which raise `ValueError`.
+///   Default is 1.
+/// * `escape` - The byte escaping the one after it in the source, `b"\\"`, or `None` for a source
+///   with no escape byte at all. `None` makes every backslash plain content: fields ending in
+///   backslashes and quotes after them are written like any other, as with
+///   `reescape_delims=False`, and `strict_eof` raises `ValueError`. Other bytes raise
+///   `ValueError`, as does a `delim` or `newline` of `b"\\"` with it. Default is `b"\\"`.
+/// * `quote_style` - An optional name of how a quote in field content is escaped: `"backslash"`
+///   writes `\"`, and `"double"` writes `""` as RFC 4180 readers such as pandas, Python's `csv` and
+///   Excel expect. A backslash is then plain content, even before a quote or a `delim`: it is never
+///   re-escaped and never escapes a quote. `"double"` is not supported with `double_backslash`, nor
+///   `"backslash"` with `copy_compatible`, which doubles quotes; these and any other name raise
+///   `ValueError`. Default is `"backslash"`.
+/// * `quote_empty` - An optional flag for empty fields, including those added by `pad_to` and empty
+///   `header` names. `False` writes them as nothing at all, e.g. `a,,b`, for loaders that read an
+///   empty slot as NULL and `""` as an empty string; a row of one empty field becomes a blank line.
+///   It applies on top of `quote_columns`, and like it buffers every field. Default is `True`.
+/// * `extra_columns` - An optional list of fields appended to every row, for lineage when merging
+///   many files: `bytes` for a literal value, `"row_number"` for the 1-based row number or
+///   `"filename"` for `filepath` as given. They are always quoted and come after the fields added
+///   by `pad_to`; a `header` should name them too. Unknown names raise `ValueError`. Default is
+///   `None`.
+/// * `input_encoding` - An optional `"utf-16le"` or `"utf-16be"` for exports that are not UTF-8,
+///   such as Windows bcp dumps in UTF-16LE, or `"utf-8"`, the default, which reads the input as
+///   is. Each name may also be given without its dash, as `"utf16le"`. The input is decoded to
+///   UTF-8 before fixing, so `delim` and `newline` name the decoded characters and the output is
+///   UTF-8; a leading BOM is dropped and malformed code units become U+FFFD. After `gzip`, if both
+///   are set. `progress` counts the raw bytes. Unknown names raise `ValueError`. Default is
+///   `None`, as `"utf-8"`.
+/// * `require_utf8` - An optional flag to check that the input is valid UTF-8 as it is read, after
+///   `gzip` and `input_encoding`, at a fraction of the cost of decoding it. The first invalid or
+///   truncated sequence fails the run with `CorruptionError` naming its byte offset; in place the
+///   original is restored. Default is `False`, which passes any bytes through.
+/// * `section` - An optional 0-based index of the one section to fix, for dumps that put tables
+///   one after another separated by form feeds (`\x0C`). The other sections and the separators are
+///   dropped, so in place the file keeps only this section. An input with fewer sections fails
+///   with `CorruptionError`. Not supported with a `delim` or `newline` of `\x0C`, which raises
+///   `ValueError`. Default is the whole input.
+/// * `also_jsonl` - An optional path also receiving the rows as JSON Lines, in the same pass: one
+///   array of strings per row, holding the values of the CSV fields with backslash escapes
+///   resolved, after `column_transforms`. Bytes that are not UTF-8 become U+FFFD, and `/` and
+///   non-ASCII characters are escaped unless `json_minimal_escape`. The header, footer and quoting
+///   do not appear in it. It follows the `overwrite` rule of `output`, and a failed write to it
+///   fails the run as one to the output does. Like `column_transforms`, this buffers every field.
+///   Not supported with options that change values after the fields are split: `case`,
+///   `escape_bytes`, `flatten_newlines`, `double_backslash`, `collapse_source_double_quotes`,
+///   `pad_to`, `extra_columns`, `null_token`, `column_order`, `dedup`, `rejects_path`,
+///   `escape_only`, `count_only`, `source_quotes` or `naive_quote_toggle`, which raise
+///   `ValueError`. Default is `None`.
+/// * `json_minimal_escape` - An optional flag for `also_jsonl` to escape only `"`, `\` and the
+///   control characters, as JSON requires, writing `/` and non-ASCII characters as UTF-8. The
+///   lines are then shorter and readable, for readers that take UTF-8. Without `also_jsonl` it
+///   raises `ValueError`. Default is `False`, which escapes them as `\/` and `\uXXXX`.
+/// * `expect_rows` - An optional number of rows the output must have, checked once the fix is
+///   done. Any other count, an empty input counting 0, fails with `CorruptionError` giving both
+///   numbers, to catch a truncated or duplicated export; in place the original is restored.
+///   Default is no check.
+/// * `size_hint` - An optional input size in bytes to use instead of the file's metadata, for
+///   special files or descriptors whose reported length is 0 or wrong. The input is then read to
+///   its end whatever its length. It is the `progress` total and, as any non-zero size, opens the
+///   first field; an actually empty input still gives an empty output. 0 raises `ValueError`, as
+///   it would skip the input. Default is `None`, which reads the size from the metadata.
+/// * `escape_only` - An optional flag for files whose delimiters are already real commas and
+///   newlines but whose quotes are not escaped. Every raw `"` is escaped and every other byte is
+///   kept, so rows and fields pass through unchanged and are not quoted; `delim` and `newline`
+///   are ignored, as is `final_newline`, and rows are counted on the output bytes, `out_newline`
+///   included. Not supported with options that restructure rows: `column_transforms`,
+///   `quote_columns`, `column_types`, `pad_to`, `header`, `extra_columns`, `footer`,
+///   `column_widths` or `quote_empty=False`, which raise `ValueError`. Default is `False`.
+/// * `diff` - An optional flag for a dry run that previews the fix: the output is written to a
+///   temporary `filepath.diff` next to the input, compared row by row with the input and removed,
+///   leaving `filepath` untouched. The report gains `changed_rows`, the number of rows that differ,
+///   and `diff`, a unified-diff-style text with a `@@ row N @@` hunk for each of the first 10 of
+///   them, control bytes escaped and long rows cut. Fixed rows are told apart by their quoting, so
+///   a newline inside a field stays in its row; a `header` row is not compared. Not supported with
+///   `output`, `dest`, `discard`, `tee` or `checksum_sidecar`, which raise `ValueError`. Default is
+///   `False`.
+/// * `dest` - An optional destination in one argument: a path writes a new file exactly as
+///   `output` does, with no backup, and `"-"` writes the fixed CSV to the standard output of the
+///   process, also with no backup and without the completion message. The standard output is
+///   written directly, not through `sys.stdout`, which should be flushed first. `None` keeps the
+///   default, the in-place run with its `.bak` backup, unless `output` is set. Not supported with
+///   `output`; `"-"` is also not supported with `discard`, `diff` or `checksum_sidecar`. These
+///   raise `ValueError`. Default is `None`.
+/// * `footer` - An optional raw line written after the last row, e.g. `"#rows={rows}"` for
+///   ingestion formats that expect a trailer. `{rows}` is replaced by the number of data rows,
+///   the `header` left out; nothing else is quoted or escaped. The last row is terminated before
+///   it, and the footer ends as the last row would have, by `final_newline` or as the input ends.
+///   An empty input stays empty. Not supported with `escape_only`, which raises `ValueError`.
+///   Default is `None`.
+/// * `max_output_bytes` - An optional cap on the bytes written to the output, for untrusted dumps
+///   that expand without bound, such as a gzip bomb of quotes. A write past it fails the run with
+///   `CorruptionError`, and the in-place run restores `filepath` from its backup. The output never
+///   exceeds the cap; `tee` is a copy and not counted. 0 raises `ValueError`. Default is `None`.
+/// * `column_widths` - An optional flag to report the longest field of each column, in bytes, as
+///   a `column_widths` list in the report, e.g. to size `VARCHAR(n)` columns for the load. Widths
+///   are taken after `column_transforms` and before quoting and escaping; a column first seen in
+///   a longer row extends the list, and fields added by `pad_to` count as empty. Like
+///   `quote_columns` it buffers every field. Default is `False`.
+/// * `copy_compatible` - An optional preset for Postgres `COPY ... CSV`, whose output it loads
+///   directly. It sets `quote_columns=[]`: no field is quoted unless it holds a `,`, a `"` or a
+///   newline, so an empty field is read as NULL. It also escapes a quote by doubling it, `""`,
+///   instead of with a backslash. A backslash is then plain content: it is never re-escaped and
+///   never escapes a quote, as `reescape_delims` would do. Everything else keeps its own setting,
+///   `out_newline` and `header` included. Not supported with `quote_columns`, `double_backslash`,
+///   `escape_only` or `repair_log`, which raise `ValueError`. Default is `False`.
+/// * `source_quotes` - An optional flag for sources that quote some of their fields. A `"`
+///   starting a field opens a quoted region and the next `"` closes it, `""` within it
+///   included; a `delim` or `newline` within the region is content of the field instead of
+///   splitting it. The quotes themselves are escaped as any other, or collapsed with
+///   `collapse_source_double_quotes`. A region left open runs to the end of the input. Not
+///   supported with `column_transforms`, `quote_columns`, `column_types`, `column_widths`,
+///   `copy_compatible`, `quote_empty=False` or `escape_only`, which raise `ValueError`. Default
+///   is `False`.
+/// * `naive_quote_toggle` - An optional flag for sources that wrap fields in bare quotes and never
+///   escape them, a simpler alternative to `source_quotes` for well-behaved sources. Every `"`
+///   flips a quoted region on or off and is dropped, wherever it is in a field; a `delim` or
+///   `newline` within the region is content of the field. So `a"x\x1Ey"` gives the field `ax\x1Ey`
+///   and a literal quote cannot be expressed. The quotes must balance: a region still open at the
+///   end of the input fails the run with `CorruptionError` naming the quote that opened it, and in
+///   place the original is restored. Not supported with `source_quotes`,
+///   `collapse_source_double_quotes`, `column_transforms`, `quote_columns`, `column_types`,
+///   `column_widths`, `copy_compatible`, `quote_empty=False` or `escape_only`, which raise
+///   `ValueError`. Default is `False`.
+/// * `rejects_path` - An optional path receiving the rows without `expected_fields` fields instead
+///   of the output, to quarantine bad rows and keep going rather than fail. They are written in
+///   their original broken form, fields joined by `delim` and each row ended by `newline`, so the
+///   file can be fixed once repaired; the good rows make up the output. It follows the `overwrite`
+///   rule of `output`. The report then has `accepted_rows`, the same as `rows`, and
+///   `rejected_rows`. Only quoting options apply to the rows: not supported with
+///   `column_transforms`, `column_widths`, `pad_to`, `extra_columns`, `footer`, `escape_only`,
+///   `source_quotes`, `naive_quote_toggle`, `collapse_source_double_quotes` or `repair_log`,
+///   which raise `ValueError`. Default is `None`.
+/// * `expected_fields` - An optional number of fields a row needs to be accepted with
+///   `rejects_path`; without it the first row sets the count, as a header does. Not supported
+///   without `rejects_path`, and 0 raises `ValueError`. Default is `None`.
+/// * `record_prefix` - An optional ASCII character written before every output row, the header
+///   included, for framed formats such as RFC 7464 JSON text sequences (`b"\x1E"`). It is
+///   independent of `delim`, which may be the same byte. Not supported with `escape_only`, which
+///   raises `ValueError`. Default is none.
+/// * `timeout_secs` - An optional limit on the processing time, in seconds. The time is checked
+///   before each read of the input, and a run still going past the limit stops there and raises
+///   `TimeoutError`; in place the original is restored, as for any failed run. Its `stats`
+///   report the rows and bytes done. It must be at least 1, or it raises `ValueError`. Default is
+///   no limit.
+/// * `verify_rfc4180` - An optional self-check of the output, parsed back as RFC 4180 CSV in its
+///   own dialect while it is written: quotes must balance, a quoted field must end before a
+///   delimiter or newline, and every input row must give one record with the fields it had, as
+///   changed by `pad_to` and `extra_columns`. A backslash escapes the byte after it in a quoted
+///   field unless quotes are doubled. Any inconsistency is a bug of the fixer: the run fails with
+///   `CorruptionError` naming the byte, and in place the original is restored. Not supported with
+///   `escape_only`, `footer`, `record_prefix` or a multi-byte `out_delim`, which raise `ValueError`.
+///   Default is `False`.
+/// * `verify_content` - An optional flag to also check, in the same pass, that the fix changed
+///   only structure: a CRC-32 of the field content of the input, every byte but `delim` and
+///   `newline` with backslash escapes resolved, must match that of the parsed output. A mismatch
+///   fails the run as `verify_rfc4180` does. Not supported without `verify_rfc4180`, nor with the
+///   options rewriting content: `column_transforms`, `case`, `escape_bytes`, `flatten_newlines`,
+///   `collapse_source_double_quotes`, `double_backslash`, `escape=None`, `reescape_delims=False`,
+///   `quote_columns`, `column_types`, `pad_to`, `header`, `extra_columns`, `null_token`,
+///   `column_order`, `dedup`, `rejects_path`, `source_quotes` or `naive_quote_toggle`, which raise
+///   `ValueError`. Default is `False`.
+/// * `summary_format` - An optional name of how a finished run is reported: `"human"` prints a
+///   banner to stdout, `"json"` writes one line of JSON to stderr with `path`, `status`, `rows`,
+///   `fields`, `quotes`, `bytes_read`, `bytes_written`, `anomalies` and `duration_seconds`, and
+///   `"none"` prints nothing. Nothing is printed for a failed run, which raises. Any other name
+///   raises `ValueError`. Default is `"human"`.
+/// * `on_start` - An optional callable receiving the resolved options as a dict, once, after
+///   every argument was checked and before any file is touched: `delim`, `newline`, `quote`,
+///   `out_delim` and `out_newline` as bytes, `chunk_size` (the read size), `write_chunk_size`,
+///   and `driver`, one of `"streaming"`, `"buffered"`, `"escape_only"`, `"rejects"` or
+///   `"count_only"`. An exception raised by it aborts the call with the input untouched.
 ///
 ///
 /// # Example
 ///
 /// ```python
-/// from bcp_exorcist import exorcize_csv
+/// from bcp_exorcist import CorruptionError, exorcize_csv
 ///
 /// try:
-///     exorcize_csv("path/to/broken.csv", delim=b'\x1E', newline=b'\x1D', chunk_size=1024 * 1024)
-///     print("Exorcism completed successfully!")
+///     report = exorcize_csv("path/to/broken.csv", delim=b'\x1E', newline=b'\x1D', chunk_size=1024 * 1024)
+///     print(f"Exorcism completed successfully! {report['rows']} rows")
+///     for anomaly in report["anomalies"]:
+///         print(f"warning: {anomaly}")
 ///
 /// except TypeError as e:
 ///     print("params `delim` & `newline` should be a single byte;")
@@ -57,46 +797,2682 @@ fn unwrap_byte(input: Option<&[u8]>, default: u8) -> PyResult<u8> {
 ///     print("param `filepath` is not valid;")
 ///     raise e
 ///
-/// except RuntimeError as e:
-///     print(f"Exorcism process failed;")
+/// except CorruptionError as e:
+///     if e.stats is not None:
+///         print(f"Exorcism process failed after {e.stats['rows']} rows;")
 ///     raise e
 ///
 /// ```
 #[pyfunction]
-#[pyo3(text_signature = "(filepath, delim=None, newline=None, chunk_size=None)")]
-fn exorcize_csv(
+#[pyo3(signature = (filepath, delim=None, newline=None, chunk_size=None, **options))]
+fn exorcize_csv<'py>(
+    py: Python<'py>,
     filepath: &str,
-    delim: Option<&[u8]>,
-    newline: Option<&[u8]>,
-    chunk_size: Option<usize>,
+    delim: Option<Bytes>,
+    newline: Option<Bytes>,
+    chunk_size: Option<ChunkSize>,
+    options: Option<&Bound<'py, PyDict>>,
+) -> PyResult<Bound<'py, PyDict>> {
+    let mut config = ExorcistConfig {
+        delim,
+        newline,
+        chunk_size,
+        ..ExorcistConfig::default()
+    };
+    let mut call = CallOptions::default();
+    for (key, value) in options.into_iter().flatten() {
+        read_option(&mut config, &mut call, &key.extract::<String>()?, &value)?;
+    }
+    exorcize_config(py, filepath, config, call)
+}
+
+/// The options of `exorcize_csv` that stay arguments of the call: paths and Python objects.
+#[derive(Default)]
+struct CallOptions<'py> {
+    output: Option<String>,
+    dest: Option<String>,
+    tee: Option<String>,
+    column_transforms: Option<HashMap<usize, Bound<'py, PyAny>>>,
+    extra_columns: Option<Vec<ExtraColumn>>,
+    progress: Option<Bound<'py, PyAny>>,
+    on_start: Option<Bound<'py, PyAny>>,
+    size_hint: Option<u64>,
+}
+
+/// Reads the keyword argument `key` of `exorcize_csv` into the option of that name.
+fn read_option<'py>(
+    config: &mut ExorcistConfig,
+    call: &mut CallOptions<'py>,
+    key: &str,
+    value: &Bound<'py, PyAny>,
 ) -> PyResult<()> {
-    let sep = unwrap_byte(delim, b'\x1E')?;
-    let eol = unwrap_byte(newline, b'\x1D')?;
+    fn arg<'py, T: FromPyObject<'py>>(key: &str, value: &Bound<'py, PyAny>) -> PyResult<T> {
+        value
+            .extract()
+            .map_err(|e| match e.is_instance_of::<PyTypeError>(value.py()) {
+                true => PyTypeError::new_err(format!("argument '{key}': {}", e.value(value.py()))),
+                false => e,
+            })
+    }
 
-    let bak = format!("{filepath}.bak");
-    fs::rename(filepath, bak.as_str())?;
-    let chunk_size = chunk_size.unwrap_or(1024 * 1024 * 4);
+    match key {
+        "output" => call.output = arg(key, value)?,
+        "dest" => call.dest = arg(key, value)?,
+        "tee" => call.tee = arg(key, value)?,
+        "column_transforms" => call.column_transforms = arg(key, value)?,
+        "extra_columns" => call.extra_columns = arg(key, value)?,
+        "progress" => call.progress = arg(key, value)?,
+        "on_start" => call.on_start = arg(key, value)?,
+        "size_hint" => call.size_hint = arg(key, value)?,
+        "preallocate" => config.preallocate = arg(key, value)?,
+        "double_backslash" => config.double_backslash = arg(key, value)?,
+        "overwrite" => config.overwrite = arg(key, value)?,
+        "follow_symlinks" => config.follow_symlinks = arg(key, value)?,
+        "in_place_truncate" => config.in_place_truncate = arg(key, value)?,
+        "retries" => config.retries = arg(key, value)?,
+        "final_newline" => config.final_newline = arg(key, value)?,
+        "pad_to" => config.pad_to = arg(key, value)?,
+        "checksum" => config.checksum = arg(key, value)?,
+        "checksum_sidecar" => config.checksum_sidecar = arg(key, value)?,
+        "quote_columns" => config.quote_columns = arg(key, value)?,
+        "allow_common_delimiter" => config.allow_common_delimiter = arg(key, value)?,
+        "escape_bytes" => config.escape_bytes = arg(key, value)?,
+        "strict_eof" => config.strict_eof = arg(key, value)?,
+        "progress_interval" => config.progress_interval = arg(key, value)?,
+        "case" => config.case = arg(key, value)?,
+        "collapse_source_double_quotes" => config.collapse_source_double_quotes = arg(key, value)?,
+        "header" => config.header = arg(key, value)?,
+        "tmp_dir" => config.tmp_dir = arg(key, value)?,
+        "metrics_path" => config.metrics_path = arg(key, value)?,
+        "repair_log" => config.repair_log = arg(key, value)?,
+        "discard" => config.discard = arg(key, value)?,
+        "gzip" => config.gzip = arg(key, value)?,
+        "out_newline" => config.out_newline = arg(key, value)?,
+        "line_ending" => config.line_ending = arg(key, value)?,
+        "reescape_delims" => config.reescape_delims = arg(key, value)?,
+        "quote_empty" => config.quote_empty = arg(key, value)?,
+        "input_encoding" => config.input_encoding = arg(key, value)?,
+        "escape_only" => config.escape_only = arg(key, value)?,
+        "diff" => config.diff = arg(key, value)?,
+        "footer" => config.footer = arg(key, value)?,
+        "max_output_bytes" => config.max_output_bytes = arg(key, value)?,
+        "column_widths" => config.column_widths = arg(key, value)?,
+        "copy_compatible" => config.copy_compatible = arg(key, value)?,
+        "source_quotes" => config.source_quotes = arg(key, value)?,
+        // `None` disables the escape byte, a missing key keeps the default
+        "escape" => config.escape = Some(arg(key, value)?),
+        "record_prefix" => config.record_prefix = arg(key, value)?,
+        "timeout_secs" => config.timeout_secs = arg(key, value)?,
+        "flatten_newlines" => config.flatten_newlines = arg(key, value)?,
+        "verify_rfc4180" => config.verify_rfc4180 = arg(key, value)?,
+        "out_delim" => config.out_delim = arg(key, value)?,
+        "atomic" => config.atomic = arg(key, value)?,
+        "read_chunk_size" => config.read_chunk_size = arg(key, value)?,
+        "write_chunk_size" => config.write_chunk_size = arg(key, value)?,
+        "naive_quote_toggle" => config.naive_quote_toggle = arg(key, value)?,
+        "rejects_path" => config.rejects_path = arg(key, value)?,
+        "expected_fields" => config.expected_fields = arg(key, value)?,
+        "reescape_count" => config.reescape_count = arg(key, value)?,
+        "gzip_output" => config.gzip_output = arg(key, value)?,
+        "gzip_level" => config.gzip_level = arg(key, value)?,
+        "column_order" => config.column_order = arg(key, value)?,
+        "strict_column_order" => config.strict_column_order = arg(key, value)?,
+        "dedup" => config.dedup = arg(key, value)?,
+        "dedup_max_rows" => config.dedup_max_rows = arg(key, value)?,
+        "trailing_sep" => config.trailing_sep = arg(key, value)?,
+        "write_bom" => config.write_bom = arg(key, value)?,
+        "temp_output" => config.temp_output = arg(key, value)?,
+        "count_only" => config.count_only = arg(key, value)?,
+        "length_prefixed" => config.length_prefixed = arg(key, value)?,
+        "null_token" => config.null_token = arg(key, value)?,
+        "null_output" => config.null_output = arg(key, value)?,
+        "column_types" => config.column_types = arg(key, value)?,
+        "require_utf8" => config.require_utf8 = arg(key, value)?,
+        "marker" => config.marker = arg(key, value)?,
+        "verify_content" => config.verify_content = arg(key, value)?,
+        "summary_format" => config.summary_format = arg(key, value)?,
+        "section" => config.section = arg(key, value)?,
+        "expect_rows" => config.expect_rows = arg(key, value)?,
+        "also_jsonl" => config.also_jsonl = arg(key, value)?,
+        "quote_style" => config.quote_style = arg(key, value)?,
+        "quote" => config.quote = arg(key, value)?,
+        "keep_backup" => config.keep_backup = arg(key, value)?,
+        "json_minimal_escape" => config.json_minimal_escape = arg(key, value)?,
+        _ => {
+            let msg = format!("exorcize_csv() got an unexpected keyword argument '{key}'");
+            return Err(PyTypeError::new_err(msg));
+        }
+    }
+    Ok(())
+}
 
-    let input = File::open(bak.as_str())?;
-    let size = input.metadata()?.len();
-    let output = File::create(filepath)?;
-    let opts = TmpOptions { sep, eol };
+/// Runs `exorcize_csv` with the options of `config` and `call`, unset ones at their default.
+fn exorcize_config<'py>(
+    py: Python<'py>,
+    filepath: &str,
+    mut config: ExorcistConfig,
+    call: CallOptions<'py>,
+) -> PyResult<Bound<'py, PyDict>> {
+    let CallOptions {
+        output,
+        dest,
+        tee,
+        column_transforms,
+        extra_columns,
+        progress,
+        on_start,
+        size_hint,
+    } = call;
+    let stdout = dest.as_deref() == Some("-");
+    // an output naming the input is the in-place run
+    let names_input = |out: &&str| files::same_file(Path::new(filepath), Path::new(out));
+    let given = output.as_deref().filter(|out| !names_input(out));
+    let output = given.or(dest.as_deref().filter(|out| !stdout && !names_input(out)));
+    let gzip_output = config
+        .gzip_output
+        .unwrap_or_else(|| output.is_some_and(|path| path.to_ascii_lowercase().ends_with(".gz")));
+    config.gzip_output = Some(gzip_output);
+    let call = [
+        ("output", given.is_some()),
+        ("dest", dest.is_some()),
+        ("dest='-'", stdout),
+        ("tee", tee.is_some()),
+        ("column_transforms", column_transforms.is_some()),
+        ("extra_columns", extra_columns.is_some()),
+    ];
+    let call: Vec<&str> = call
+        .into_iter()
+        .filter_map(|(name, set)| set.then_some(name))
+        .collect();
+    config.check(&call).map_err(PyValueError::new_err)?;
+    let ExorcistConfig {
+        delim,
+        newline,
+        chunk_size,
+        preallocate,
+        double_backslash,
+        overwrite,
+        follow_symlinks,
+        in_place_truncate,
+        retries,
+        final_newline,
+        pad_to,
+        checksum,
+        checksum_sidecar,
+        quote_columns,
+        allow_common_delimiter,
+        escape_bytes,
+        strict_eof,
+        progress_interval,
+        case,
+        collapse_source_double_quotes,
+        header,
+        tmp_dir,
+        metrics_path,
+        repair_log,
+        discard,
+        gzip,
+        out_newline,
+        line_ending,
+        reescape_delims,
+        quote_empty,
+        input_encoding,
+        escape_only,
+        diff,
+        footer,
+        max_output_bytes,
+        column_widths,
+        copy_compatible,
+        source_quotes,
+        escape,
+        record_prefix,
+        timeout_secs,
+        flatten_newlines,
+        verify_rfc4180,
+        out_delim,
+        atomic,
+        read_chunk_size,
+        write_chunk_size,
+        naive_quote_toggle,
+        rejects_path,
+        expected_fields,
+        reescape_count,
+        gzip_output: _,
+        gzip_level,
+        column_order,
+        strict_column_order,
+        dedup,
+        dedup_max_rows,
+        trailing_sep,
+        write_bom,
+        temp_output,
+        count_only,
+        length_prefixed,
+        null_token,
+        null_output,
+        column_types,
+        require_utf8,
+        marker,
+        verify_content,
+        summary_format,
+        section,
+        expect_rows,
+        also_jsonl,
+        quote_style,
+        quote,
+        keep_backup,
+        json_minimal_escape,
+    } = config;
+    let tee = tee.as_deref();
+    let chunk_size = unwrap_chunk_size(chunk_size)?;
+    let read_size = unwrap_size("read_chunk_size", read_chunk_size)?;
+    let write_size = unwrap_size("write_chunk_size", write_chunk_size)?;
+    if read_size == Some(0) || write_size == Some(0) {
+        return Err(PyValueError::new_err(
+            "read_chunk_size and write_chunk_size should be at least 1",
+        ));
+    }
+    let chunk_size = chunk_size.or(read_size);
+    let defaults = TmpOptions::default();
+    let sep = unwrap_byte(delim.as_deref(), defaults.sep)?;
+    let eol = unwrap_byte(newline.as_deref(), defaults.eol)?;
+    let quote = unwrap_byte(quote.as_deref(), defaults.quote)?;
+    // the escapes given outright win over those of `flatten_newlines`
+    let escape_bytes = match flatten_newlines.unwrap_or(false) {
+        true => {
+            let mut escapes: HashMap<Vec<u8>, Vec<u8>> = reader::FLATTENED_NEWLINES
+                .iter()
+                .filter(|(byte, _)| *byte != sep && *byte != eol)
+                .map(|&(byte, escaped)| (vec![byte], escaped.to_vec()))
+                .collect();
+            escapes.extend(escape_bytes.into_iter().flatten().map(|(k, v)| (k.0, v.0)));
+            Some(escapes)
+        }
+        false => escape_bytes.map(|escapes| escapes.into_iter().map(|(k, v)| (k.0, v.0)).collect()),
+    };
+    let escapes = match escape_bytes {
+        Some(escapes) => Some(unwrap_escapes(escapes, [sep, eol, quote])?),
+        None => None,
+    };
+    if pad_to == Some(0) {
+        return Err(PyValueError::new_err("pad_to should be at least 1"));
+    }
+    let escape = match escape {
+        Some(escape) => unwrap_escape(escape.as_deref())?,
+        None => Some(b'\\'),
+    };
+    if quote == sep || quote == eol || Some(quote) == escape {
+        let msg = format!(
+            "quote b'{}' should differ from delim, newline and escape",
+            std::ascii::escape_default(quote)
+        );
+        return Err(PyValueError::new_err(msg));
+    }
+    if timeout_secs == Some(0) {
+        return Err(PyValueError::new_err("timeout_secs should be at least 1"));
+    }
+    if max_output_bytes == Some(0) {
+        return Err(PyValueError::new_err(
+            "max_output_bytes should be at least 1",
+        ));
+    }
+    if size_hint == Some(0) {
+        let msg = "size_hint should be at least 1; an empty input needs no hint";
+        return Err(PyValueError::new_err(msg));
+    }
+    let case = match case.as_deref() {
+        Some(name) => Some(Case::from_name(name).ok_or_else(|| {
+            let msg = format!("unknown case '{name}'; expected 'upper' or 'lower'");
+            PyValueError::new_err(msg)
+        })?),
+        None => None,
+    };
+    let summary_format = match summary_format.as_deref() {
+        Some(name) => SummaryFormat::from_name(name).ok_or_else(|| {
+            let msg =
+                format!("unknown summary_format '{name}'; expected 'none', 'human' or 'json'");
+            PyValueError::new_err(msg)
+        })?,
+        None => SummaryFormat::Human,
+    };
+    let temp_output = temp_output.unwrap_or(false);
+    let tmp_dir = tmp_dir
+        .as_deref()
+        .filter(|_| temp_output || (output.is_none() && in_place_truncate == Some(true)));
+    if let Some(dir) = tmp_dir {
+        files::check_writable_dir(Path::new(dir))?;
+    }
+    let extra_columns = match extra_columns {
+        Some(columns) => Some(unwrap_extra_columns(columns, filepath)?),
+        None => None,
+    };
+    let column_types = match column_types {
+        Some(names) => Some(
+            names
+                .iter()
+                .map(|name| {
+                    ColType::from_name(name).ok_or_else(|| {
+                        let msg = format!(
+                            "unknown column type '{name}'; expected 'text', 'number' or 'auto'"
+                        );
+                        PyValueError::new_err(msg)
+                    })
+                })
+                .collect::<PyResult<Vec<_>>>()?,
+        ),
+        None => None,
+    };
+    let quote_empty = quote_empty.unwrap_or(true);
+    let escape_only = escape_only.unwrap_or(false);
+    let column_widths = column_widths.unwrap_or(false);
+    let copy_compatible = copy_compatible.unwrap_or(false);
+    let write_bom = write_bom.unwrap_or(false);
+    let record_prefix = record_prefix
+        .filter(|prefix| !prefix.is_empty())
+        .map(|prefix| unwrap_byte(Some(&prefix), 0))
+        .transpose()?;
+    let source_quotes = source_quotes.unwrap_or(false);
+    let quote_toggle = naive_quote_toggle.unwrap_or(false);
+    if column_order.as_ref().is_some_and(Vec::is_empty) {
+        let msg = "column_order should name at least one column";
+        return Err(PyValueError::new_err(msg));
+    }
+    let dedup = dedup.unwrap_or(false);
+    let dedup = match dedup_max_rows {
+        Some(0) => return Err(PyValueError::new_err("dedup_max_rows should be at least 1")),
+        max_rows => dedup.then(|| max_rows.unwrap_or(buffered::DEFAULT_DEDUP_ROWS)),
+    };
+    let length_prefixed = length_prefixed.unwrap_or(false);
+    let double_quotes = match quote_style.as_deref() {
+        None | Some("backslash") => false,
+        Some("double") => true,
+        Some(other) => {
+            let msg = format!("unknown quote_style '{other}'; expected 'backslash' or 'double'");
+            return Err(PyValueError::new_err(msg));
+        }
+    };
+    let ignore_trailing_sep = match trailing_sep.as_deref() {
+        None | Some("field") => false,
+        Some("ignore") => true,
+        Some(other) => {
+            let msg = format!("unknown trailing_sep '{other}'; expected 'field' or 'ignore'");
+            return Err(PyValueError::new_err(msg));
+        }
+    };
+    if expected_fields == Some(0) {
+        return Err(PyValueError::new_err(
+            "expected_fields should be at least 1",
+        ));
+    }
+    let count_only = count_only.unwrap_or(false);
+    // nothing to write: counted into the sink of a discarded run
+    let discard = discard.unwrap_or(false) || count_only;
+    let diff = diff.unwrap_or(false);
+    let keep_backup = keep_backup.unwrap_or(true);
+    let in_place = output.is_none() && !temp_output && !discard && !diff && !stdout;
+    let gzip = match gzip {
+        Some(true) if in_place => {
+            let msg = "gzip input is not fixed in place; pass output, dest, discard or diff";
+            return Err(PyValueError::new_err(msg));
+        }
+        Some(gzip) => gzip,
+        // a missing file fails once it is opened
+        None => files::is_gzip(Path::new(filepath)).unwrap_or(false),
+    };
+    if gzip && in_place {
+        let msg = format!(
+            "'{filepath}' looks gzip-compressed, which is not fixed in place; pass output, dest, \
+            discard or diff, or gzip=False to read it as it is"
+        );
+        return Err(PyValueError::new_err(msg));
+    }
+    let marker = marker.unwrap_or(false);
+    if gzip_level.is_some_and(|level| level > 9) {
+        return Err(PyValueError::new_err("gzip_level should be at most 9"));
+    }
+    let out_delim = match out_delim {
+        Some(bytes) if bytes.is_empty() || bytes.contains(&quote) => {
+            let msg = format!(
+                "out_delim should be one or more bytes other than b'{}'",
+                std::ascii::escape_default(quote)
+            );
+            return Err(PyValueError::new_err(msg));
+        }
+        Some(bytes) => bytes.0,
+        None => defaults.out_delim.clone(),
+    };
+    let verify_rfc4180 = verify_rfc4180.unwrap_or(false);
+    let verify_content = verify_content.unwrap_or(false);
+    let line_ending = match line_ending.as_deref() {
+        None => None,
+        Some("lf") => Some(b"\n".to_vec()),
+        Some("crlf") => Some(b"\r\n".to_vec()),
+        Some(other) => {
+            let msg = format!("unknown line_ending '{other}'; expected 'lf' or 'crlf'");
+            return Err(PyValueError::new_err(msg));
+        }
+    };
+    let out_eol = match out_newline {
+        Some(bytes) if bytes.is_empty() || bytes.contains(&quote) => {
+            let msg = format!(
+                "out_newline should be one or more bytes other than b'{}'",
+                std::ascii::escape_default(quote)
+            );
+            return Err(PyValueError::new_err(msg));
+        }
+        Some(bytes) => bytes.0,
+        None => line_ending.unwrap_or_else(|| defaults.out_eol.clone()),
+    };
+    let encoding = match input_encoding.as_deref() {
+        Some(name) => transcode::from_name(name).ok_or_else(|| {
+            let msg = format!(
+                "unknown input_encoding '{name}'; expected one of {:?}",
+                transcode::NAMES
+            );
+            PyValueError::new_err(msg)
+        })?,
+        None => None,
+    };
+    let interval = Duration::try_from_secs_f64(progress_interval.unwrap_or(1.0))
+        .map_err(|e| PyValueError::new_err(format!("progress_interval: {e}")))?;
+    let checksum = match checksum.as_deref() {
+        Some(name) => Some(Checksum::from_name(name).ok_or_else(|| {
+            let msg = format!(
+                "unknown checksum '{name}'; expected one of {:?}",
+                Checksum::NAMES
+            );
+            PyValueError::new_err(msg)
+        })?),
+        None => None,
+    };
+
+    let opts = TmpOptions {
+        sep,
+        eol,
+        quote,
+        double_backslash: double_backslash.unwrap_or(false),
+        final_newline,
+        pad_to,
+        quote_columns,
+        escapes,
+        strict_eof: strict_eof.unwrap_or(false),
+        case,
+        collapse_double_quotes: collapse_source_double_quotes.unwrap_or(false),
+        header: header.map(|names| names.into_iter().map(String::into_bytes).collect()),
+        repair_log: repair_log.is_some(),
+        out_delim,
+        out_eol,
+        reescape_delims: reescape_delims.unwrap_or(true),
+        reescape_count: reescape_count.unwrap_or(defaults.reescape_count),
+        quote_empty,
+        extra_columns,
+        escape_only,
+        footer,
+        column_widths,
+        double_quotes,
+        source_quotes,
+        escape,
+        record_prefix,
+        write_size: write_size.unwrap_or(defaults.write_size),
+        quote_toggle,
+        column_order,
+        strict_column_order: strict_column_order.unwrap_or(false),
+        dedup,
+        ignore_trailing_sep,
+        write_bom,
+        length_prefixed,
+        null_token: null_token.map(|token| token.0),
+        null_output: null_output.map_or_else(Vec::new, |output| output.0),
+        column_types,
+    };
+    if reader::needs_quotes(&opts.null_output, &opts) {
+        let msg = "null_output should not hold the output delimiter, newline or quote";
+        return Err(PyValueError::new_err(msg));
+    }
+    let mut job = Job {
+        opts: match copy_compatible {
+            true => opts.copy_compatible(),
+            false => opts,
+        },
+        chunk_size: chunk_size.unwrap_or(DEFAULT_CHUNK_SIZE),
+        preallocate: preallocate.unwrap_or(false),
+        retries: retries.unwrap_or(0),
+        transforms: column_transforms.map(ColumnTransforms),
+        tee: None,
+        splits_rejects: rejects_path.is_some(),
+        rejects: None,
+        splits_jsonl: also_jsonl.is_some(),
+        jsonl: None,
+        jsonl_minimal: json_minimal_escape.unwrap_or(false),
+        expected_fields,
+        checksum,
+        progress: progress.map(|callback| (callback, interval)),
+        gzip,
+        gzip_level: gzip_output.then(|| gzip_level.unwrap_or(6)),
+        encoding,
+        max_output_bytes: max_output_bytes.unwrap_or(u64::MAX),
+        timeout: timeout_secs.map_or(Duration::MAX, Duration::from_secs),
+        verify: verify_rfc4180,
+        count_only,
+        require_utf8: require_utf8.unwrap_or(false),
+        verify_content,
+        section,
+        expect_rows,
+    };
+    if section.is_some() && [job.opts.sep, job.opts.eol].contains(&section::FORM_FEED) {
+        let msg = "section is not supported with a delim or newline of b'\\x0c'";
+        return Err(PyValueError::new_err(msg));
+    }
+    check_common_bytes(py, &job.opts, allow_common_delimiter)?;
+    if let Some(callback) = on_start {
+        callback.call1((options_dict(py, &job)?,))?;
+    }
+
+    let algorithm = job.checksum.as_ref().map(Checksum::name);
+    let marker = marker.then(|| {
+        let options = format!(
+            "{} {:?} {:?}",
+            env!("CARGO_PKG_VERSION"),
+            job.opts,
+            job.encoding.map(encoding_rs::Encoding::name)
+        );
+        digest::sha256_hex(options.as_bytes())
+    });
+    // a marker is only written in place, next to the input a symlink resolves to
+    let marked = match &marker {
+        Some(_) => Some(files::resolve_input(
+            filepath,
+            follow_symlinks.unwrap_or(true),
+        )?),
+        None => None,
+    };
+    let paths = files::Sides {
+        tee,
+        rejects: rejects_path.as_deref(),
+        jsonl: also_jsonl.as_deref(),
+    };
+    let overwrite_sides = overwrite.unwrap_or(false);
+    let marked_options = marked.as_deref().zip(marker.as_deref());
+    let Some(sides) = files::create_sides(paths, overwrite_sides, marked_options)? else {
+        let filepath = marked.as_deref().unwrap_or(Path::new(filepath));
+        let stats = Stats::default();
+        match summary_format {
+            SummaryFormat::Human => println!("✝️ already exorcized ✝️"),
+            SummaryFormat::Json => {
+                let line = metrics::summary_json(filepath, "skipped", &stats, Duration::ZERO);
+                io::stderr().write_all(line.as_bytes())?;
+            }
+            SummaryFormat::None => {}
+        }
+        let report = stats_dict(py, &stats)?;
+        report.set_item("status", "skipped")?;
+        report.set_item("backup_path", None::<PathBuf>)?;
+        return Ok(report);
+    };
+    job.tee = sides.tee;
+    job.rejects = sides.rejects;
+    job.jsonl = sides.jsonl;
+
+    let temp_path = match temp_output {
+        true => {
+            let path = files::create_temp_output(Path::new(filepath), tmp_dir.map(Path::new))?;
+            Some(path.to_string_lossy().into_owned())
+        }
+        false => None,
+    };
+    let output = output.or(temp_path.as_deref());
+    let (filepath, target) = match output {
+        _ if discard || diff || stdout => (PathBuf::from(filepath), None),
+        Some(path) => {
+            // the temporary output was created empty to reserve its name
+            let overwrite = overwrite.unwrap_or(false) || temp_output;
+            (
+                PathBuf::from(filepath),
+                Some(Target::Output { path, overwrite }),
+            )
+        }
+        None => {
+            let follow = follow_symlinks.unwrap_or(true);
+            let target = match in_place_truncate.unwrap_or(false) {
+                true => Target::Truncate {
+                    tmp_dir: tmp_dir.map(Path::new),
+                },
+                false => Target::InPlace {
+                    atomic: atomic.unwrap_or(true),
+                },
+            };
+            (files::resolve_input(filepath, follow)?, Some(target))
+        }
+    };
+    // in place, a resolved symlink target is where the output goes
+    let written = output.map_or_else(|| filepath.clone(), PathBuf::from);
+
+    let backup_path = target.as_ref().and_then(|t| t.backup_path(&filepath));
+    let broken_path = target.as_ref().and_then(|t| t.broken_path(&filepath));
+    let restores = target.as_ref().is_some_and(Target::restores);
 
-    match reader::exorcize_csv(input, output, size, chunk_size, &opts) {
-        Ok(_) => {
-            println!("✝️ exorcism completed ✝️");
-            Ok(())
+    let start = Instant::now();
+    let mut summary = None;
+    let outcome = match target {
+        Some(target) => {
+            files::exorcize_file(&filepath, target, size_hint, |input, output, size| {
+                job.run(input, output, size)
+            })
         }
-        Err(e) => {
-            fs::rename(filepath, format!("{filepath}.broken"))?;
-            fs::rename(bak, filepath)?;
-            Err(PyRuntimeError::new_err(format!("✝️ exorcism failed: {e}")))
+        None if diff => files::read_file(&filepath, size_hint, |input, size| {
+            let (stats, diffed) = job.run_diff(input, &filepath, size)?;
+            summary = Some(diffed);
+            Ok(stats)
+        }),
+        None if stdout => files::read_file(&filepath, size_hint, |input, size| {
+            job.run_output(input, io::stdout().lock(), size)
+        }),
+        // discarded
+        None => files::read_file(&filepath, size_hint, |input, size| {
+            job.run_into(input, io::sink(), size)
+        }),
+    };
+    let (status, stats, backup_path) = match outcome {
+        Ok(Outcome::Exorcized(stats)) => {
+            let backup_path = match backup_path {
+                // the fix is in place: a backup left behind is reported, not an error
+                Some(bak) if !keep_backup => match std::fs::remove_file(&bak) {
+                    Ok(()) => None,
+                    Err(e) => {
+                        let msg = format!("could not remove backup '{}': {e}", bak.display());
+                        log_warning(py, &msg)?;
+                        Some(bak)
+                    }
+                },
+                kept => kept,
+            };
+            if summary_format == SummaryFormat::Human && !stdout {
+                println!("✝️ exorcism completed ✝️");
+            }
+            ("exorcized", stats, backup_path)
+        }
+        Ok(Outcome::Empty) => {
+            if let Some(Err(e)) = expect_rows.map(|expected| Stats::default().check_rows(expected))
+            {
+                let err = corruption_error(py, e);
+                err.value(py).setattr("status", "failed")?;
+                err.value(py).setattr("broken_path", None::<PathBuf>)?;
+                err.value(py).setattr("backup_path", None::<PathBuf>)?;
+                return Err(err);
+            }
+            if summary_format == SummaryFormat::Human && !stdout {
+                println!("✝️ nothing to exorcize ✝️");
+            }
+            let mut stats = Stats::default();
+            stats.checksum = job.checksum.take().map(Checksum::finish);
+            ("empty", stats, None)
+        }
+        Err(Failure::Io(e)) => return Err(e.into()),
+        Err(Failure::Run(e)) => {
+            let err = corruption_error(py, e);
+            let status = if restores { "restored" } else { "failed" };
+            err.value(py).setattr("status", status)?;
+            err.value(py).setattr(
+                "broken_path",
+                broken_path.filter(|p| p.exists() && !p.is_dir()),
+            )?;
+            err.value(py)
+                .setattr("backup_path", backup_path.filter(|p| p.exists()))?;
+            return Err(err);
+        }
+        Err(Failure::Unrestored {
+            run,
+            restore,
+            original,
+        }) => {
+            let err = unrestored_error(py, &filepath, run, restore, &original);
+            err.value(py).setattr("status", "unrestored")?;
+            err.value(py).setattr(
+                "broken_path",
+                broken_path.filter(|p| p.exists() && !p.is_dir()),
+            )?;
+            err.value(py).setattr("backup_path", original)?;
+            return Err(err);
+        }
+    };
+
+    if let (true, Some(algorithm), Some(digest)) = (
+        checksum_sidecar.unwrap_or(false),
+        algorithm,
+        &stats.checksum,
+    ) {
+        files::write_sidecar(&written, algorithm, digest)?;
+    }
+    if let Some(options) = &marker {
+        files::write_marker(&filepath, options)?;
+    }
+    if let Some(path) = &repair_log {
+        let repairs = stats.repairs.as_deref().unwrap_or_default();
+        files::write_repair_log(Path::new(path), repairs)?;
+    }
+    if let Some(path) = &metrics_path {
+        let text = metrics::textfile(&stats, start.elapsed());
+        if let Err(e) = metrics::write_textfile(Path::new(path), &text) {
+            log_warning(py, &format!("could not write metrics to '{path}': {e}"))?;
         }
     }
+
+    if summary_format == SummaryFormat::Json {
+        let line = metrics::summary_json(&filepath, status, &stats, start.elapsed());
+        io::stderr().write_all(line.as_bytes())?;
+    }
+
+    let report = stats_dict(py, &stats)?;
+    report.set_item("status", status)?;
+    report.set_item("backup_path", backup_path)?;
+    if temp_output {
+        report.set_item("output_path", output)?;
+    }
+    if diff {
+        let summary = summary.unwrap_or(diff::Summary {
+            changed_rows: 0,
+            text: String::new(),
+        });
+        let name = filepath.display();
+        report.set_item("changed_rows", summary.changed_rows)?;
+        report.set_item(
+            "diff",
+            format!("--- {name}\n+++ {name} (exorcized)\n{}", summary.text),
+        )?;
+    }
+    Ok(report)
 }
 
-#[pymodule]
-fn bcp_exorcist(_py: Python<'_>, m: &Bound<'_, PyModule>) -> PyResult<()> {
+/// Runs `exorcize_csv` with its options read from a JSON object.
+///
+/// The object takes the keyword arguments of `exorcize_csv` by name, except the paths
+/// and the callables, so one stored blob applies the same options everywhere.
+/// Bytes are strings of their UTF-8 bytes, e.g. `{"delim": "\u001e", "pad_to": 3}`, and
+/// `escape_bytes` maps such strings to their replacement; sizes are given as `chunk_size`
+/// is, e.g. `"64KiB"`. Values are checked by the same code as the keyword arguments of
+/// `exorcize_csv` and raise the same exceptions; malformed JSON, a wrong value type or an
+/// unknown key raises `ValueError`.
+///
+/// Returns the same report dict as `exorcize_csv`.
+///
+/// # Arguments
+///
+/// * `filepath` - A string slice that holds the path to the file to fix.
+/// * `config_json` - The options as a JSON object.
+/// * `output` - An optional path to write the fixed CSV to, as in `exorcize_csv`.
+/// * `tee` - An optional second path receiving a copy of the output, as in `exorcize_csv`.
+/// * `progress` - An optional progress callable, as in `exorcize_csv`.
+///
+///
+/// # Example
+///
+/// ```python
+/// from bcp_exorcist import exorcize_with_config
+///
+/// config = '{"delim": "|", "allow_common_delimiter": true, "checksum": "sha256"}'
+/// report = exorcize_with_config("path/to/broken.csv", config, output="path/to/fixed.csv")
+/// ```
+#[pyfunction]
+#[pyo3(signature = (filepath, config_json, output=None, tee=None, progress=None))]
+fn exorcize_with_config<'py>(
+    py: Python<'py>,
+    filepath: &str,
+    config_json: &str,
+    output: Option<&str>,
+    tee: Option<&str>,
+    progress: Option<Bound<'py, PyAny>>,
+) -> PyResult<Bound<'py, PyDict>> {
+    let config = ExorcistConfig::from_json(config_json)
+        .map_err(|e| PyValueError::new_err(format!("invalid config: {e}")))?;
+    let call = CallOptions {
+        output: output.map(str::to_string),
+        tee: tee.map(str::to_string),
+        progress,
+        ..CallOptions::default()
+    };
+    exorcize_config(py, filepath, config, call)
+}
+
+/// Runs `exorcize_csv` with the delimiter and newline read from a BCP format file.
+///
+/// `bcp ... format nul -f` writes a `.fmt` file naming the terminator of every field, in
+/// the non-XML format or, with `-x`, the XML one; both are read. All fields but the last
+/// must end with the same terminator, taken as `delim`, and the last one is taken as
+/// `newline`; each must be a single ASCII byte. A format file that does not give them
+/// raises `ValueError`. Every other option keeps its `exorcize_csv` default.
+///
+/// Returns the same report dict as `exorcize_csv`.
+///
+/// # Arguments
+///
+/// * `data_path` - A string slice that holds the path to the file to fix.
+/// * `fmt_path` - A string slice that holds the path to its format file.
+/// * `output` - An optional path to write the fixed CSV to, as in `exorcize_csv`.
+///
+///
+/// # Example
+///
+/// ```python
+/// from bcp_exorcist import exorcize_with_fmt
+///
+/// report = exorcize_with_fmt("path/to/broken.csv", "path/to/broken.fmt", output="path/to/fixed.csv")
+/// ```
+#[pyfunction]
+#[pyo3(signature = (data_path, fmt_path, output=None))]
+fn exorcize_with_fmt<'py>(
+    py: Python<'py>,
+    data_path: &str,
+    fmt_path: &str,
+    output: Option<&str>,
+) -> PyResult<Bound<'py, PyDict>> {
+    let text = std::fs::read_to_string(fmt_path)?;
+    let terminators = format_file::parse_terminators(&text)
+        .map_err(|e| PyValueError::new_err(format!("invalid format file {fmt_path}: {e}")))?;
+
+    let config = ExorcistConfig {
+        delim: terminators.sep.map(|sep| Bytes(vec![sep])),
+        newline: Some(Bytes(vec![terminators.eol])),
+        ..ExorcistConfig::default()
+    };
+    let call = CallOptions {
+        output: output.map(str::to_string),
+        ..CallOptions::default()
+    };
+    exorcize_config(py, data_path, config, call)
+}
+
+/// Settings of one `exorcize_csv` call, shared by all of its output modes.
+struct Job<'py> {
+    opts: TmpOptions,
+    chunk_size: usize,
+    preallocate: bool,
+    retries: usize,
+    transforms: Option<ColumnTransforms<'py>>,
+    tee: Option<File>,
+    /// Whether the rows without `expected_fields` fields, or the first row's count, go to
+    /// `rejects`, which is only opened once `on_start` ran.
+    splits_rejects: bool,
+    rejects: Option<File>,
+    /// Whether the rows also go to `jsonl` as JSON Lines, which is only opened once
+    /// `on_start` ran.
+    splits_jsonl: bool,
+    jsonl: Option<File>,
+    /// Whether `jsonl` escapes only what JSON requires, keeping `/` and non-ASCII as is.
+    jsonl_minimal: bool,
+    expected_fields: Option<u64>,
+    checksum: Option<Checksum>,
+    /// Python callable receiving each `Beat`, and the least time between two calls.
+    progress: Option<(Bound<'py, PyAny>, Duration)>,
+    /// Whether the input is gzip-compressed; progress is reported on the compressed bytes.
+    gzip: bool,
+    /// The level the output is gzip-compressed at, if it is.
+    gzip_level: Option<u32>,
+    /// The encoding decoded to UTF-8 before fixing, if not already UTF-8 or ASCII.
+    encoding: Option<&'static encoding_rs::Encoding>,
+    /// The most bytes written to the output, not counting the tee.
+    max_output_bytes: u64,
+    /// The longest the run may take, from its first read.
+    timeout: Duration,
+    /// Whether the output is parsed back as RFC 4180 as it is written.
+    verify: bool,
+    /// Whether the input is only counted, not fixed.
+    count_only: bool,
+    /// Whether the input, once decoded, must be valid UTF-8.
+    require_utf8: bool,
+    /// Whether the field content of the output is checked against the input's; only with
+    /// `verify`.
+    verify_content: bool,
+    /// The form-feed separated section of the input to fix, when not all of it.
+    section: Option<usize>,
+    /// The number of rows the output must have, when checked.
+    expect_rows: Option<u64>,
+}
+
+impl Job<'_> {
+    /// Runs the driver matching the requested options from `input` into the file `output`.
+    fn run(&mut self, input: File, output: &File, size: u64) -> io::Result<Stats> {
+        // a pipe has no extents to allocate nor a tail to cut
+        let preallocate = self.preallocate && size != UNKNOWN_SIZE && output.metadata()?.is_file();
+        if preallocate {
+            // worst case expansion; the tail is truncated once the write is done
+            let _ = files::preallocate(output, size.saturating_mul(3));
+        }
+
+        let stats = self.run_output(input, output, size)?;
+        if preallocate {
+            files::truncate_to_position(output)?;
+        }
+        Ok(stats)
+    }
+
+    /// Runs `run_into`, through a gzip encoder finished once the fix is done when the output
+    /// is compressed.
+    fn run_output<W: Write>(&mut self, input: File, output: W, size: u64) -> io::Result<Stats> {
+        let Some(level) = self.gzip_level else {
+            return self.run_into(input, output, size);
+        };
+        let mut encoder = files::gzipped(output, level);
+        let stats = self.run_into(input, &mut encoder, size)?;
+        match encoder.finish() {
+            Ok(_) => Ok(stats),
+            Err(e) => Err(Partial::attach(stats, e)),
+        }
+    }
+
+    /// Runs the fix of `input` into a temporary file next to `path` and diffs it against
+    /// `path`, removing it after.
+    fn run_diff(
+        &mut self,
+        input: File,
+        path: &Path,
+        size: u64,
+    ) -> io::Result<(Stats, diff::Summary)> {
+        let tmp = files::with_suffix(path, ".diff");
+        let diffed = File::create(&tmp).and_then(|fixed| {
+            let stats = self.run_into(input, &fixed, size)?;
+            let before = files::decoded(File::open(path)?, self.gzip, self.encoding);
+            let after = File::open(&tmp)?;
+            let summary = diff::diff_rows(before, after, diff::MAX_DIFF_ROWS, &self.opts)?;
+            Ok((stats, summary))
+        });
+        let _ = std::fs::remove_file(&tmp);
+        diffed
+    }
+
+    /// The name of the driver `run_into` picks: `"count_only"`, `"rejects"`, `"buffered"`,
+    /// `"escape_only"` or `"streaming"`.
+    fn driver(&self) -> &'static str {
+        match self.transforms {
+            _ if self.count_only => "count_only",
+            _ if self.splits_rejects => "rejects",
+            _ if self.splits_jsonl => "buffered",
+            Some(_) => "buffered",
+            None if self.opts.escape_only => "escape_only",
+            None if self.opts.buffers() => "buffered",
+            None => "streaming",
+        }
+    }
+
+    /// Runs the driver matching the requested options from `input` into `output`.
+    fn run_into<W: Write>(&mut self, input: File, output: W, size: u64) -> io::Result<Stats> {
+        let (callback, interval) = match &self.progress {
+            Some((callback, interval)) => (Some(callback), *interval),
+            None => (None, Duration::MAX),
+        };
+        let input = Heartbeat::new(
+            Deadline::new(Retry::new(input, self.retries), self.timeout),
+            // reported as 0 when unknown
+            match size {
+                UNKNOWN_SIZE => 0,
+                size => size,
+            },
+            interval,
+            |beat: &Beat| match callback {
+                Some(callback) => callback
+                    .call1((beat.bytes_read, beat.total, beat.mb_per_s))
+                    .map(drop)
+                    .map_err(io::Error::other),
+                None => Ok(()),
+            },
+        );
+        let input = files::decoded(input, self.gzip, self.encoding);
+        let input: Box<dyn Read> = match self.section {
+            Some(index) => Box::new(Section::new(input, index)),
+            None => input,
+        };
+        let input: Box<dyn Read> = match self.require_utf8 {
+            true => Box::new(Utf8Checked::new(input)),
+            false => input,
+        };
+        let mut source = self.verify_content.then(|| SourceContent::new(&self.opts));
+        let input: Box<dyn Read> = match &mut source {
+            Some(source) => Box::new(Sourced::new(input, source)),
+            None => input,
+        };
+        let tee = self.tee.take().map(|tee| Retry::new(tee, self.retries));
+        let output = Capped::new(Retry::new(output, self.retries), self.max_output_bytes);
+        let tee = Tee::new(output, tee);
+        let writer = Hashing::new(tee, self.checksum.take());
+        let (chunk_size, opts) = (self.chunk_size, &self.opts);
+        let mut writer = Verified::new(writer, self.verify.then(|| Rfc4180::new(opts)));
+
+        let rejects = self
+            .rejects
+            .take()
+            .map(|file| Retry::new(file, self.retries));
+
+        let mut jsonl = self.jsonl.take();
+        let mut stats = match (self.transforms.as_mut(), rejects) {
+            _ if self.count_only => reader::count_only(input, chunk_size, opts)?,
+            (_, Some(rejects)) => {
+                let expected = self.expected_fields;
+                rejects::exorcize_csv(input, &mut writer, rejects, expected, chunk_size, opts)?
+            }
+            (hook, None) if jsonl.is_some() => {
+                let jsonl = Retry::new(jsonl.take().unwrap(), self.retries);
+                let hook: &mut dyn FieldHook = match hook {
+                    Some(hook) => hook,
+                    None => &mut NoHook,
+                };
+                let mut lines = JsonLines::new(hook, jsonl, opts, self.jsonl_minimal);
+                let stats =
+                    buffered::exorcize_csv(input, &mut writer, chunk_size, opts, &mut lines)?;
+                if let Err(e) = lines.finish() {
+                    return Err(Partial::attach(stats, e));
+                }
+                stats
+            }
+            (Some(hook), None) => {
+                buffered::exorcize_csv(input, &mut writer, chunk_size, opts, hook)?
+            }
+            (None, None) if opts.escape_only => {
+                reader::escape_only(input, &mut writer, chunk_size, opts)?
+            }
+            (None, None) if opts.buffers() => {
+                buffered::exorcize_csv(input, &mut writer, chunk_size, opts, &mut NoHook)?
+            }
+            (None, None) => reader::exorcize_csv(input, &mut writer, size, chunk_size, opts)?,
+        };
+        let (writer, parser) = writer.into_parts();
+        if let Some(parser) = parser {
+            if let Err(e) = parser.finish(&stats, opts, source.map(SourceContent::finish)) {
+                return Err(Partial::attach(stats, e));
+            }
+        }
+        if let Some(Err(e)) = self.expect_rows.map(|expected| stats.check_rows(expected)) {
+            return Err(Partial::attach(stats, e));
+        }
+        stats.checksum = writer.into_checksum().map(Checksum::finish);
+        Ok(stats)
+    }
+}
+
+/// The read buffer for an in-memory input: never larger than the input itself.
+fn in_memory_chunk(data: &[u8], chunk_size: Option<usize>) -> usize {
+    chunk_size
+        .unwrap_or(DEFAULT_CHUNK_SIZE)
+        .min(data.len())
+        .max(1)
+}
+
+/// Fixes a broken CSV held in memory and returns the fixed CSV as `bytes`.
+///
+/// This is the in-memory counterpart of `exorcize_csv`: nothing touches the filesystem,
+/// so there is no backup. Empty input returns empty `bytes`.
+///
+/// # Arguments
+///
+/// * `data` - The raw content of the broken CSV.
+/// * `delim` - An optional ASCII character used as the delimiter in the broken CSV. Default is `\x1E`.
+/// * `newline` - An optional ASCII character used as the newline character in the broken CSV. Default is `\x1D`.
+/// * `chunk_size` - An optional size for the batch size, as for `exorcize_csv`. Default is 4 MiB,
+///   capped at the length of `data`.
+///
+///
+/// # Example
+///
+/// ```python
+/// from bcp_exorcist import exorcize_bytes
+///
+/// fixed = exorcize_bytes(b"field1\x1Efield2\x1D")
+/// ```
+#[pyfunction]
+#[pyo3(signature = (data, delim=None, newline=None, chunk_size=None))]
+fn exorcize_bytes<'py>(
+    py: Python<'py>,
+    data: &[u8],
+    delim: Option<&[u8]>,
+    newline: Option<&[u8]>,
+    chunk_size: Option<ChunkSize>,
+) -> PyResult<Bound<'py, PyBytes>> {
+    let chunk_size = unwrap_chunk_size(chunk_size)?;
+    let opts = common_opts(py, delim, newline)?;
+
+    let mut out = Vec::with_capacity(data.len() + data.len() / 2 + 2);
+    let size = data.len() as u64;
+    reader::exorcize_csv(
+        data,
+        &mut out,
+        size,
+        in_memory_chunk(data, chunk_size),
+        &opts,
+    )?;
+    Ok(PyBytes::new(py, &out))
+}
+
+/// Fixes a broken CSV held in memory as `exorcize_bytes` does, also returning the state
+/// carried over each batch boundary.
+///
+/// For debugging output that depends on `chunk_size`: `data` is cut into batches of that
+/// size, and after each one the state the next starts from is reported as a dict with
+/// `offset`, the input bytes read so far, `prev_byte`, the last of them, `backslashes`, the
+/// length of the run of backslashes they end with, and `held`, the output of the batch,
+/// held back until the next one. Only built with the `debug` feature.
+///
+/// # Arguments
+///
+/// * `data` - The raw content of the broken CSV.
+/// * `chunk_size` - The batch size, as for `exorcize_csv`.
+/// * `delim` - An optional ASCII character used as the delimiter in the broken CSV. Default is `\x1E`.
+/// * `newline` - An optional ASCII character used as the newline character in the broken CSV. Default is `\x1D`.
+///
+///
+/// # Example
+///
+/// ```python
+/// from bcp_exorcist import exorcize_csv_debug
+///
+/// fixed, boundaries = exorcize_csv_debug(b"a\\\x1Db", chunk_size=2)
+/// assert boundaries[0]["backslashes"] == 1
+/// ```
+#[cfg(feature = "debug")]
+#[pyfunction]
+#[pyo3(signature = (data, chunk_size, delim=None, newline=None))]
+fn exorcize_csv_debug<'py>(
+    py: Python<'py>,
+    data: &[u8],
+    chunk_size: ChunkSize,
+    delim: Option<&[u8]>,
+    newline: Option<&[u8]>,
+) -> PyResult<(Bound<'py, PyBytes>, Vec<Bound<'py, PyDict>>)> {
+    let chunk_size = unwrap_chunk_size(Some(chunk_size))?;
+    let opts = common_opts(py, delim, newline)?;
+
+    let mut feed = reader::Feed::new(data.len() as u64, opts);
+    let (mut out, mut boundaries) = (Vec::new(), Vec::new());
+    for batch in data.chunks(in_memory_chunk(data, chunk_size)) {
+        out.extend(feed.push(batch));
+        let boundary = feed.boundary();
+        let dict = PyDict::new(py);
+        dict.set_item("offset", boundary.offset)?;
+        dict.set_item(
+            "prev_byte",
+            boundary.prev_byte.map(|byte| PyBytes::new(py, &[byte])),
+        )?;
+        dict.set_item("backslashes", boundary.backslashes)?;
+        dict.set_item("held", PyBytes::new(py, &boundary.held))?;
+        boundaries.push(dict);
+    }
+    out.extend(feed.finish()?);
+    Ok((PyBytes::new(py, &out), boundaries))
+}
+
+/// Writes over a `bytearray` from its start, growing it only when needed.
+struct ByteArrayWriter<'a, 'py> {
+    array: &'a Bound<'py, PyByteArray>,
+    pos: usize,
+}
+
+impl Write for ByteArrayWriter<'_, '_> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let end = self.pos + buf.len();
+        if end > self.array.len() {
+            self.array.resize(end).map_err(io::Error::other)?;
+        }
+        // SAFETY: the GIL is held and no Python code runs while the slice is alive
+        unsafe { self.array.as_bytes_mut()[self.pos..end].copy_from_slice(buf) };
+        self.pos = end;
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+/// Fixes a broken CSV held in memory, writing the fixed CSV into the `bytearray` `out`.
+///
+/// Behaves like `exorcize_bytes`, but replaces the content of `out` instead of returning
+/// new `bytes`, so a single buffer can be reused across many small inputs without
+/// reallocating it on every call. Returns the length of the fixed CSV.
+///
+/// # Arguments
+///
+/// * `data` - The raw content of the broken CSV.
+/// * `out` - The `bytearray` receiving the fixed CSV; its previous content is discarded.
+/// * `delim` - An optional ASCII character used as the delimiter in the broken CSV. Default is `\x1E`.
+/// * `newline` - An optional ASCII character used as the newline character in the broken CSV. Default is `\x1D`.
+/// * `chunk_size` - An optional size for the batch size, as for `exorcize_csv`. Default is 4 MiB,
+///   capped at the length of `data`.
+///
+///
+/// # Example
+///
+/// ```python
+/// from bcp_exorcist import exorcize_bytes_into
+///
+/// out = bytearray()
+/// for record in records:
+///     exorcize_bytes_into(record, out)
+///     sink.write(out)
+/// ```
+#[pyfunction]
+#[pyo3(signature = (data, out, delim=None, newline=None, chunk_size=None))]
+fn exorcize_bytes_into(
+    py: Python<'_>,
+    data: &[u8],
+    out: &Bound<'_, PyByteArray>,
+    delim: Option<&[u8]>,
+    newline: Option<&[u8]>,
+    chunk_size: Option<ChunkSize>,
+) -> PyResult<usize> {
+    let chunk_size = unwrap_chunk_size(chunk_size)?;
+    let opts = common_opts(py, delim, newline)?;
+
+    let mut writer = ByteArrayWriter { array: out, pos: 0 };
+    let size = data.len() as u64;
+    reader::exorcize_csv(
+        data,
+        &mut writer,
+        size,
+        in_memory_chunk(data, chunk_size),
+        &opts,
+    )?;
+
+    let len = writer.pos;
+    out.resize(len)?;
+    Ok(len)
+}
+
+/// Reads a Python binary file object through its `read` method.
+///
+/// Pending signals are checked before every read, so Ctrl-C stops the run at the next
+/// chunk with the `KeyboardInterrupt` as the read error.
+struct PyReader<'py>(Bound<'py, PyAny>);
+
+impl io::Read for PyReader<'_> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let chunk = self
+            .0
+            .py()
+            .check_signals()
+            .and_then(|()| self.0.call_method1("read", (buf.len(),)))
+            .and_then(|out| Ok(out.downcast_into::<PyBytes>()?))
+            .map_err(io::Error::other)?;
+
+        let chunk = chunk.as_bytes();
+        let len = chunk.len().min(buf.len());
+        buf[..len].copy_from_slice(&chunk[..len]);
+        Ok(len)
+    }
+}
+
+/// Writes to a Python binary file object through its `write` and `flush` methods.
+struct PyWriter<'py>(Bound<'py, PyAny>);
+
+impl Write for PyWriter<'_> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.0
+            .call_method1("write", (PyBytes::new(self.0.py(), buf),))
+            .map_err(io::Error::other)?;
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.0.call_method0("flush").map_err(io::Error::other)?;
+        Ok(())
+    }
+}
+
+/// Returns the Python exception behind a failed run, if Python code raised it.
+fn python_error(py: Python<'_>, e: &io::Error) -> Option<PyErr> {
+    let mut inner: &(dyn std::error::Error + 'static) = e.get_ref()?;
+    loop {
+        if let Some(err) = inner.downcast_ref::<PyErr>() {
+            return Some(err.clone_ref(py));
+        }
+        inner = match inner.downcast_ref::<io::Error>() {
+            Some(e) => e.get_ref()?,
+            None => inner.source()?,
+        };
+    }
+}
+
+/// Fixes a broken CSV read from the binary file object `src`, writing it to `dst`.
+///
+/// Unlike `exorcize_csv` the input is read once, front to back, so `src` can be a pipe
+/// such as `sys.stdin.buffer` and `dst` can be `sys.stdout.buffer`. There is no backup:
+/// if reading fails or is interrupted, e.g. by Ctrl-C, the rows fixed so far are closed
+/// as a valid CSV and flushed to `dst` before the error is raised again. An exception
+/// from `src` or `dst`, such as `KeyboardInterrupt`, is raised as is; any other failure
+/// raises `CorruptionError`.
+///
+/// # Arguments
+///
+/// * `src` - A binary file object with a `read(n)` method.
+/// * `dst` - A binary file object with `write` and `flush` methods.
+/// * `delim` - An optional ASCII character used as the delimiter in the broken CSV. Default is `\x1E`.
+/// * `newline` - An optional ASCII character used as the newline character in the broken CSV. Default is `\x1D`.
+/// * `chunk_size` - An optional size for the batch size, as for `exorcize_csv`. Default is 4 MiB.
+///
+///
+/// # Example
+///
+/// ```python
+/// import sys
+/// from bcp_exorcist import exorcize_stream
+///
+/// exorcize_stream(sys.stdin.buffer, sys.stdout.buffer)
+/// ```
+#[pyfunction]
+#[pyo3(signature = (src, dst, delim=None, newline=None, chunk_size=None))]
+fn exorcize_stream<'py>(
+    py: Python<'py>,
+    src: Bound<'py, PyAny>,
+    dst: Bound<'py, PyAny>,
+    delim: Option<&[u8]>,
+    newline: Option<&[u8]>,
+    chunk_size: Option<ChunkSize>,
+) -> PyResult<Bound<'py, PyDict>> {
+    let chunk_size = unwrap_chunk_size(chunk_size)?;
+    let opts = common_opts(py, delim, newline)?;
+
+    let chunk_size = chunk_size.unwrap_or(DEFAULT_CHUNK_SIZE);
+    match reader::exorcize_stream(PyReader(src), PyWriter(dst), chunk_size, &opts) {
+        Ok(stats) => stats_dict(py, &stats),
+        Err(e) => Err(python_error(py, &e).unwrap_or_else(|| corruption_error(py, e))),
+    }
+}
+
+/// The iterator returned by `exorcize_chunks`, yielding the fixed output as `bytes`.
+#[pyclass(module = "bcp_exorcist")]
+struct ExorcizedChunks {
+    chunks: reader::Chunks<File>,
+}
+
+#[pymethods]
+impl ExorcizedChunks {
+    fn __iter__(slf: PyRef<'_, Self>) -> PyRef<'_, Self> {
+        slf
+    }
+
+    fn __next__<'py>(&mut self, py: Python<'py>) -> PyResult<Option<Bound<'py, PyBytes>>> {
+        match py.allow_threads(|| self.chunks.next_chunk()) {
+            Ok(chunk) => Ok(chunk.map(|chunk| PyBytes::new(py, &chunk))),
+            Err(e) => Err(corruption_error(py, e)),
+        }
+    }
+}
+
+/// Fixes a broken CSV file and returns an iterator over its fixed output, as `bytes`.
+///
+/// Nothing is written: each item is the output of one batch of `chunk_size` input bytes,
+/// the closing bytes of the last row coming as the last item, so a web handler can
+/// stream a fixed file to its client without holding all of it. Together the items are
+/// what `exorcize_csv` would write; a failure partway raises `CorruptionError` from the
+/// iteration, after the items already yielded.
+///
+/// # Arguments
+///
+/// * `filepath` - A string slice that holds the path to the file to fix.
+/// * `delim` - An optional ASCII character used as the delimiter in the broken CSV. Default is `\x1E`.
+/// * `newline` - An optional ASCII character used as the newline character in the broken CSV. Default is `\x1D`.
+/// * `chunk_size` - An optional size for the batch size, as for `exorcize_csv`. Default is 4 MiB.
+///
+///
+/// # Example
+///
+/// ```python
+/// from bcp_exorcist import exorcize_chunks
+///
+/// for chunk in exorcize_chunks("path/to/broken.csv", chunk_size="64KiB"):
+///     response.write(chunk)
+/// ```
+#[pyfunction]
+#[pyo3(signature = (filepath, delim=None, newline=None, chunk_size=None))]
+fn exorcize_chunks(
+    py: Python<'_>,
+    filepath: &str,
+    delim: Option<&[u8]>,
+    newline: Option<&[u8]>,
+    chunk_size: Option<ChunkSize>,
+) -> PyResult<ExorcizedChunks> {
+    let chunk_size = unwrap_chunk_size(chunk_size)?;
+    let opts = common_opts(py, delim, newline)?;
+
+    let input = File::open(filepath)?;
+    let size = input.metadata()?.len();
+    let chunk_size = chunk_size.unwrap_or(DEFAULT_CHUNK_SIZE);
+    Ok(ExorcizedChunks {
+        chunks: reader::Chunks::new(input, size, chunk_size, opts),
+    })
+}
+
+/// Iterates over the fixed rows of several broken CSV files as one stream, as `bytes`.
+///
+/// For dumps sharded over several files that are one table. The files are read in the
+/// order given, each only once the one before is exhausted, and nothing is written. Each
+/// row is quoted on its own and carries no line terminator, so the rows of two files
+/// never run into each other, even when a file does not end with `newline`. A file that
+/// cannot be opened raises `OSError` when it is reached, after the rows before it.
+///
+/// # Arguments
+///
+/// * `paths` - The paths of the files, in order.
+/// * `delim` - An optional ASCII character used as the delimiter in the broken CSV. Default is `\x1E`.
+/// * `newline` - An optional ASCII character used as the newline character in the broken CSV. Default is `\x1D`.
+/// * `chunk_size` - An optional size for the batch size, as for `exorcize_csv`. Default is 4 MiB.
+///
+///
+/// # Example
+///
+/// ```python
+/// from bcp_exorcist import ChainedExorcist
+///
+/// for row in ChainedExorcist(["dump/part0.csv", "dump/part1.csv"]):
+///     print(row.decode())
+/// ```
+#[pyclass(module = "bcp_exorcist")]
+struct ChainedExorcist {
+    rows: rows::ChainedRows,
+}
+
+#[pymethods]
+impl ChainedExorcist {
+    #[new]
+    #[pyo3(signature = (paths, delim=None, newline=None, chunk_size=None))]
+    fn new(
+        py: Python<'_>,
+        paths: Vec<PathBuf>,
+        delim: Option<&[u8]>,
+        newline: Option<&[u8]>,
+        chunk_size: Option<ChunkSize>,
+    ) -> PyResult<Self> {
+        let chunk_size = unwrap_chunk_size(chunk_size)?;
+        let opts = common_opts(py, delim, newline)?;
+
+        let chunk_size = chunk_size.unwrap_or(DEFAULT_CHUNK_SIZE);
+        Ok(ChainedExorcist {
+            rows: rows::ChainedRows::new(paths, chunk_size, opts),
+        })
+    }
+
+    fn __iter__(slf: PyRef<'_, Self>) -> PyRef<'_, Self> {
+        slf
+    }
+
+    fn __next__<'py>(&mut self, py: Python<'py>) -> PyResult<Option<Bound<'py, PyBytes>>> {
+        let row = py.allow_threads(|| self.rows.next_row())?;
+        Ok(row.map(|row| PyBytes::new(py, &row)))
+    }
+}
+
+/// Fixes a broken CSV pushed to it buffer by buffer, returning the fixed output as `bytes`.
+///
+/// The push counterpart of `exorcize_chunks`, for callers that get their input in pieces,
+/// such as an event loop. `feed` takes the next piece, of any size, and returns the output
+/// ready so far: the output of each piece is held back until the next one comes, as the
+/// end of the input may change it. `finish` closes the last row and returns the rest. The
+/// returned buffers together are what `exorcize_csv` would write for the concatenated
+/// pieces. Once finished, either method raises `ValueError`.
+///
+/// # Arguments
+///
+/// * `delim` - An optional ASCII character used as the delimiter in the broken CSV. Default is `\x1E`.
+/// * `newline` - An optional ASCII character used as the newline character in the broken CSV. Default is `\x1D`.
+///
+///
+/// # Example
+///
+/// ```python
+/// from bcp_exorcist import StreamExorcist
+///
+/// exorcist = StreamExorcist()
+/// async for piece in response.content.iter_any():
+///     out.write(exorcist.feed(piece))
+/// out.write(exorcist.finish())
+/// ```
+#[pyclass(module = "bcp_exorcist")]
+struct StreamExorcist {
+    feed: reader::Feed,
+    finished: bool,
+}
+
+impl StreamExorcist {
+    fn check_open(&self) -> PyResult<()> {
+        match self.finished {
+            true => Err(PyValueError::new_err("StreamExorcist is already finished")),
+            false => Ok(()),
+        }
+    }
+}
+
+#[pymethods]
+impl StreamExorcist {
+    #[new]
+    #[pyo3(signature = (delim=None, newline=None))]
+    fn new(py: Python<'_>, delim: Option<&[u8]>, newline: Option<&[u8]>) -> PyResult<Self> {
+        let opts = common_opts(py, delim, newline)?;
+
+        Ok(StreamExorcist {
+            feed: reader::Feed::new(UNKNOWN_SIZE, opts),
+            finished: false,
+        })
+    }
+
+    /// Fixes `data`, returning the output ready so far.
+    fn feed<'py>(&mut self, py: Python<'py>, data: &[u8]) -> PyResult<Bound<'py, PyBytes>> {
+        self.check_open()?;
+        let out = py.allow_threads(|| self.feed.push(data));
+        Ok(PyBytes::new(py, &out))
+    }
+
+    /// Closes the last row, returning the rest of the output.
+    fn finish<'py>(&mut self, py: Python<'py>) -> PyResult<Bound<'py, PyBytes>> {
+        self.check_open()?;
+        self.finished = true;
+        match self.feed.finish() {
+            Ok(out) => Ok(PyBytes::new(py, &out)),
+            Err(e) => Err(corruption_error(py, e)),
+        }
+    }
+}
+
+/// Fixes a broken CSV file into `output` with its rows stably sorted by one column.
+///
+/// Rows are ordered by the raw bytes of field `key_column` before fixing, e.g. to
+/// prepare a sorted merge-join load; rows with equal keys keep their input order and
+/// rows too short to have the key sort first. The file and every row of it are held in
+/// memory at once, several times the size of the file for narrow fields, so more than
+/// `max_rows` rows raise `ValueError` before anything is written.
+///
+/// # Arguments
+///
+/// * `filepath` - A string slice that holds the path to the file to fix.
+/// * `key_column` - The 0-based index of the column to sort by.
+/// * `output` - The path of the sorted, fixed CSV.
+/// * `delim` - An optional ASCII character used as the delimiter in the broken CSV. Default is `\x1E`.
+/// * `newline` - An optional ASCII character used as the newline character in the broken CSV. Default is `\x1D`.
+/// * `overwrite` - An optional flag to replace an existing `output`, as for `exorcize_csv`. Default is `False`.
+/// * `max_rows` - An optional limit on the rows held for sorting. Default is 1,000,000.
+/// * `dedup` - An optional flag to also drop every fixed row equal to an earlier one, as for
+///   `exorcize_csv`; equal rows have equal keys, so only the first of each stays. The report then
+///   has `duplicate_rows`. Default is `False`.
+///
+///
+/// # Example
+///
+/// ```python
+/// from bcp_exorcist import exorcize_sorted
+///
+/// report = exorcize_sorted("path/to/broken.csv", 0, "path/to/sorted.csv")
+/// ```
+#[pyfunction]
+#[pyo3(signature = (filepath, key_column, output, delim=None, newline=None, overwrite=None, max_rows=None, dedup=None))]
+#[allow(clippy::too_many_arguments)]
+fn exorcize_sorted<'py>(
+    py: Python<'py>,
+    filepath: &str,
+    key_column: usize,
+    output: &str,
+    delim: Option<&[u8]>,
+    newline: Option<&[u8]>,
+    overwrite: Option<bool>,
+    max_rows: Option<usize>,
+    dedup: Option<bool>,
+) -> PyResult<Bound<'py, PyDict>> {
+    let max_rows = max_rows.unwrap_or(sorted::DEFAULT_MAX_ROWS);
+    let opts = TmpOptions {
+        // every row sorted is held already, so its digest adds little
+        dedup: dedup.unwrap_or(false).then_some(max_rows),
+        ..common_opts(py, delim, newline)?
+    };
+
+    let data = std::fs::read(filepath)?;
+    let rows =
+        sorted::sort_rows(&data, key_column, max_rows, &opts).map_err(|e| match e.kind() {
+            io::ErrorKind::InvalidInput => PyValueError::new_err(e.to_string()),
+            _ => e.into(),
+        })?;
+    drop(data);
+
+    let output = files::create_output(output, overwrite.unwrap_or(false))?;
+    let size = rows.len() as u64;
+    let chunk_size = in_memory_chunk(&rows, None);
+    let stats = match opts.dedup {
+        Some(_) => buffered::exorcize_csv(&rows[..], &output, chunk_size, &opts, &mut NoHook)?,
+        None => reader::exorcize_csv(&rows[..], &output, size, chunk_size, &opts)?,
+    };
+    stats_dict(py, &stats)
+}
+
+/// Writes a broken CSV file into `output` as fixed-width records, for loaders that take
+/// no delimiters.
+///
+/// Fields are split on `delim` and `newline` and kept as raw values, with no quoting or
+/// escaping. Each field is padded with `pad` to its width in `widths`, or cut to it, and
+/// written with no delimiter; every record ends with `\n`. A row without exactly one field
+/// per width raises `ValueError` naming its row and byte offset, and so does an over-wide
+/// field with `truncate=False`; `output` then holds the records before it. Returns the
+/// number of records written.
+///
+/// # Arguments
+///
+/// * `filepath` - A string slice that holds the path to the file to convert.
+/// * `widths` - The width in bytes of each column.
+/// * `output` - The path of the fixed-width file.
+/// * `delim` - An optional ASCII character used as the delimiter in the broken CSV. Default is `\x1E`.
+/// * `newline` - An optional ASCII character used as the newline character in the broken CSV. Default is `\x1D`.
+/// * `pad` - An optional ASCII character filling a field up to its width. Default is `b' '`.
+/// * `truncate` - An optional flag to cut a field over its width; `False` raises instead. Default
+///   is `True`.
+/// * `overwrite` - An optional flag to replace an existing `output`, as for `exorcize_csv`. Default is `False`.
+///
+///
+/// # Example
+///
+/// ```python
+/// from bcp_exorcist import exorcize_fixed_width
+///
+/// rows = exorcize_fixed_width("path/to/broken.csv", [10, 8, 30], "path/to/fixed.dat")
+/// ```
+#[pyfunction]
+#[pyo3(signature = (
+    filepath,
+    widths,
+    output,
+    delim=None,
+    newline=None,
+    pad=None,
+    truncate=None,
+    overwrite=None,
+))]
+#[allow(clippy::too_many_arguments)]
+fn exorcize_fixed_width(
+    py: Python<'_>,
+    filepath: &str,
+    widths: Vec<usize>,
+    output: &str,
+    delim: Option<&[u8]>,
+    newline: Option<&[u8]>,
+    pad: Option<&[u8]>,
+    truncate: Option<bool>,
+    overwrite: Option<bool>,
+) -> PyResult<u64> {
+    let opts = common_opts(py, delim, newline)?;
+    if widths.is_empty() {
+        return Err(PyValueError::new_err(
+            "widths should hold at least one width",
+        ));
+    }
+    let layout = fixed_width::Layout {
+        widths,
+        pad: unwrap_byte(pad, b' ')?,
+        truncate: truncate.unwrap_or(true),
+    };
+
+    let input = File::open(filepath)?;
+    let output = files::create_output(output, overwrite.unwrap_or(false))?;
+    let rows = py.allow_threads(|| {
+        fixed_width::write_fixed_width(input, &output, &layout, DEFAULT_CHUNK_SIZE, &opts)
+    });
+    rows.map_err(|e| match e.kind() {
+        io::ErrorKind::InvalidData => PyValueError::new_err(e.to_string()),
+        _ => e.into(),
+    })
+}
+
+/// Reads a small broken CSV file and returns it transposed, its columns as rows.
+///
+/// Fields are split on `delim` and `newline` and kept as raw values, as for
+/// `exorcize_to_arrow`. The first returned row holds the first field of every input row,
+/// and so on; short rows are padded with empty strings. The whole table is held in
+/// memory, so files above 1 MiB raise `ValueError`, as does a field that is not valid
+/// UTF-8 unless `errors` says otherwise.
+///
+/// # Arguments
+///
+/// * `filepath` - A string slice that holds the path to the file to transpose.
+/// * `delim` - An optional ASCII character used as the delimiter in the broken CSV. Default is `\x1E`.
+/// * `newline` - An optional ASCII character used as the newline character in the broken CSV. Default is `\x1D`.
+/// * `errors` - An optional handling of fields that are not valid UTF-8, as for `exorcize_tail`.
+///   Default is `"strict"`.
+///
+///
+/// # Example
+///
+/// ```python
+/// from bcp_exorcist import exorcize_transpose
+///
+/// header, *values = exorcize_transpose("path/to/lookup.csv")
+/// ```
+#[pyfunction]
+#[pyo3(signature = (filepath, delim=None, newline=None, errors=None))]
+fn exorcize_transpose(
+    py: Python<'_>,
+    filepath: &str,
+    delim: Option<&[u8]>,
+    newline: Option<&[u8]>,
+    errors: Option<&str>,
+) -> PyResult<Vec<Vec<String>>> {
+    let errors = unwrap_errors(errors)?;
+    let opts = common_opts(py, delim, newline)?;
+
+    let input = File::open(filepath)?;
+    let size = input.metadata()?.len();
+    if size > transpose::MAX_TRANSPOSE_SIZE {
+        return Err(PyValueError::new_err(format!(
+            "{filepath} is {size} bytes; exorcize_transpose takes at most {} bytes",
+            transpose::MAX_TRANSPOSE_SIZE
+        )));
+    }
+
+    let columns = transpose::read_columns(input, DEFAULT_CHUNK_SIZE, &opts, errors);
+    columns.map_err(|e| match e.kind() {
+        io::ErrorKind::InvalidData => PyValueError::new_err(e.to_string()),
+        _ => e.into(),
+    })
+}
+
+/// Parses a broken CSV file into a `pyarrow.Table`, without writing any CSV.
+///
+/// Fields are split on `delim` and `newline` and kept as raw values, with no quoting or
+/// escaping. Without a `schema` every column is a `string` named `f0`, `f1`, ...; ragged
+/// rows are null-padded to the widest row. With a `schema` the table has exactly its
+/// columns: missing fields are null, extra fields are dropped, and values are cast to the
+/// column types. A value that does not parse, or a null in a non-nullable column, raises
+/// `ValueError`.
+///
+/// Only available when built with the `arrow` feature; requires `pyarrow`.
+///
+/// # Arguments
+///
+/// * `filepath` - A string slice that holds the path to the file to parse.
+/// * `delim` - An optional ASCII character used as the delimiter in the broken CSV. Default is `\x1E`.
+/// * `newline` - An optional ASCII character used as the newline character in the broken CSV. Default is `\x1D`.
+/// * `schema` - An optional `pyarrow.Schema` for the columns.
+/// * `errors` - An optional handling of fields that are not valid UTF-8, as for `exorcize_tail`;
+///   with `"strict"` such a field raises `ValueError`. Default is `"strict"`.
+///
+///
+/// # Example
+///
+/// ```python
+/// import pyarrow as pa
+/// from bcp_exorcist import exorcize_to_arrow
+///
+/// schema = pa.schema([("id", pa.int64()), ("name", pa.string())])
+/// table = exorcize_to_arrow("path/to/broken.csv", schema=schema)
+/// ```
+#[cfg(feature = "arrow")]
+#[pyfunction]
+#[pyo3(signature = (filepath, delim=None, newline=None, schema=None, errors=None))]
+fn exorcize_to_arrow<'py>(
+    py: Python<'py>,
+    filepath: &str,
+    delim: Option<&[u8]>,
+    newline: Option<&[u8]>,
+    schema: Option<arrow::pyarrow::PyArrowType<arrow::datatypes::Schema>>,
+    errors: Option<&str>,
+) -> PyResult<Bound<'py, PyAny>> {
+    use arrow::pyarrow::ToPyArrow;
+
+    let errors = unwrap_errors(errors)?;
+    let opts = common_opts(py, delim, newline)?;
+
+    let schema = schema.map(|schema| std::sync::Arc::new(schema.0));
+    let mut columns = table::Columns::new(schema, errors);
+    let input = File::open(filepath)?;
+    rows::split_rows(input, DEFAULT_CHUNK_SIZE, &opts, |row| {
+        columns.push_row(row)
+    })?;
+
+    let batch = columns
+        .finish()
+        .map_err(|e| PyValueError::new_err(e.to_string()))?;
+    py.import("pyarrow")?
+        .getattr("Table")?
+        .call_method1("from_batches", (vec![batch.to_pyarrow(py)?],))
+}
+
+/// Fixes a broken CSV streamed from an HTTP(S) URL into the file `output`.
+///
+/// The body is fixed as it downloads, with no copy on disk, so its length need not be
+/// known: the first read opens the first field, as for `exorcize_stream`. Redirects are
+/// followed; any final status other than `200 OK` raises `OSError` naming it, before
+/// `output` is written to. A download that fails midway leaves the rows fixed so far in
+/// `output`, closed as a valid CSV. Needs the `http` feature.
+///
+/// # Arguments
+///
+/// * `url` - The `http://` or `https://` URL of the broken CSV.
+/// * `output` - The path of the file to create; an existing file raises `FileExistsError`.
+/// * `delim` - An optional ASCII character used as the delimiter in the broken CSV. Default is `\x1E`.
+/// * `newline` - An optional ASCII character used as the newline character in the broken CSV. Default is `\x1D`.
+///
+///
+/// # Example
+///
+/// ```python
+/// from bcp_exorcist import exorcize_url
+///
+/// stats = exorcize_url("https://example.com/exports/broken.csv", "fixed.csv")
+/// ```
+#[cfg(feature = "http")]
+#[pyfunction]
+#[pyo3(signature = (url, output, delim=None, newline=None))]
+fn exorcize_url<'py>(
+    py: Python<'py>,
+    url: &str,
+    output: &str,
+    delim: Option<&[u8]>,
+    newline: Option<&[u8]>,
+) -> PyResult<Bound<'py, PyDict>> {
+    let opts = common_opts(py, delim, newline)?;
+
+    let body = py.allow_threads(|| http::open(url))?;
+    let output = files::create_output(output, false)?;
+    let stats =
+        py.allow_threads(|| reader::exorcize_stream(body, &output, DEFAULT_CHUNK_SIZE, &opts))?;
+    stats_dict(py, &stats)
+}
+
+/// Fixes the byte range `start..end` of a broken CSV file into `output`.
+///
+/// This lets independent workers each fix a slice of one large file. When every range
+/// starts and ends on a row boundary, see `snap_to_row`, each one opens and closes its
+/// own rows and the outputs concatenate, in order, to what `exorcize_csv` writes for the
+/// whole file. `filepath` is only read; `end` is clamped to the file size.
+///
+/// Returns the same report dict as `exorcize_csv`, for this range only.
+///
+/// # Arguments
+///
+/// * `filepath` - A string slice that holds the path to the file to read.
+/// * `start` - The offset of the first byte to fix.
+/// * `end` - The offset after the last byte to fix.
+/// * `output` - The path to write the fixed range to.
+/// * `delim` - An optional ASCII character used as the delimiter in the broken CSV. Default is `\x1E`.
+/// * `newline` - An optional ASCII character used as the newline character in the broken CSV. Default is `\x1D`.
+/// * `overwrite` - An optional flag to replace an existing `output`. Default is `False`.
+///
+///
+/// # Example
+///
+/// ```python
+/// import os
+/// from bcp_exorcist import exorcize_range, snap_to_row
+///
+/// size = os.path.getsize("path/to/broken.csv")
+/// mid = snap_to_row("path/to/broken.csv", size // 2)
+/// exorcize_range("path/to/broken.csv", 0, mid, "part0.csv")
+/// exorcize_range("path/to/broken.csv", mid, size, "part1.csv")
+/// ```
+#[pyfunction]
+#[pyo3(signature = (filepath, start, end, output, delim=None, newline=None, overwrite=None))]
+#[allow(clippy::too_many_arguments)]
+fn exorcize_range<'py>(
+    py: Python<'py>,
+    filepath: &str,
+    start: u64,
+    end: u64,
+    output: &str,
+    delim: Option<&[u8]>,
+    newline: Option<&[u8]>,
+    overwrite: Option<bool>,
+) -> PyResult<Bound<'py, PyDict>> {
+    let opts = common_opts(py, delim, newline)?;
+
+    let input = File::open(filepath)?;
+    let end = end.min(input.metadata()?.len());
+    if start > end {
+        let msg = format!("start {start} is past end {end}");
+        return Err(PyValueError::new_err(msg));
+    }
+
+    let output = files::create_output(output, overwrite.unwrap_or(false))?;
+    let stats = reader::exorcize_range(input, &output, start, end, DEFAULT_CHUNK_SIZE, &opts)?;
+    stats_dict(py, &stats)
+}
+
+/// Returns the first row boundary at or after `offset` in a broken CSV file.
+///
+/// That is `offset` itself when it is 0 or directly follows a `newline`, else the offset
+/// after the next `newline`, or the file size when there is none. Use it to pick the
+/// `start`/`end` of `exorcize_range` calls.
+///
+/// # Arguments
+///
+/// * `filepath` - A string slice that holds the path to the file to inspect.
+/// * `offset` - The byte offset to snap.
+/// * `newline` - An optional ASCII character used as the newline character in the broken CSV. Default is `\x1D`.
+#[pyfunction]
+#[pyo3(signature = (filepath, offset, newline=None))]
+fn snap_to_row(filepath: &str, offset: u64, newline: Option<&[u8]>) -> PyResult<u64> {
+    let eol = unwrap_byte(newline, TmpOptions::default().eol)?;
+    let mut input = File::open(filepath)?;
+    let size = input.metadata()?.len();
+    Ok(reader::next_row_start(
+        &mut input,
+        size,
+        offset,
+        DEFAULT_CHUNK_SIZE,
+        eol,
+    )?)
+}
+
+/// Returns the exact size in bytes of the fixed CSV, without writing it.
+///
+/// The file is only read: the fix runs with a discarding output, so the result matches
+/// what `exorcize_csv` writes with the same `delim`, `newline` and `chunk_size`. Use it to
+/// check for free space before an in-place run, which needs room for the backup and the
+/// fixed copy at once.
+///
+/// # Arguments
+///
+/// * `filepath` - A string slice that holds the path to the file to measure.
+/// * `delim` - An optional ASCII character used as the delimiter in the broken CSV. Default is `\x1E`.
+/// * `newline` - An optional ASCII character used as the newline character in the broken CSV. Default is `\x1D`.
+/// * `chunk_size` - An optional size for the batch size to process, as for `exorcize_csv`. Default
+///   is 4 MiB.
+///
+///
+/// # Example
+///
+/// ```python
+/// import shutil
+/// from bcp_exorcist import estimate_output_size
+///
+/// needed = estimate_output_size("path/to/broken.csv")
+/// assert shutil.disk_usage("path/to").free > needed
+/// ```
+#[pyfunction]
+#[pyo3(signature = (filepath, delim=None, newline=None, chunk_size=None))]
+fn estimate_output_size(
+    py: Python<'_>,
+    filepath: &str,
+    delim: Option<&[u8]>,
+    newline: Option<&[u8]>,
+    chunk_size: Option<ChunkSize>,
+) -> PyResult<u64> {
+    let chunk_size = unwrap_chunk_size(chunk_size)?;
+    let opts = common_opts(py, delim, newline)?;
+
+    let input = File::open(filepath)?;
+    let size = input.metadata()?.len();
+    let chunk_size = chunk_size.unwrap_or(DEFAULT_CHUNK_SIZE);
+    Ok(reader::output_size(input, size, chunk_size, &opts)?)
+}
+
+/// Returns whether a file looks like a raw bcp dump that still needs fixing.
+///
+/// Only the first chunk of the file is read: it is broken when that chunk holds a `delim` or
+/// `newline` byte. This is a cheap heuristic, meant to skip files that were already fixed; a
+/// file whose first chunk happens to lack both bytes is reported as not broken whatever
+/// follows.
+///
+/// # Arguments
+///
+/// * `filepath` - A string slice that holds the path to the file to check.
+/// * `delim` - An optional ASCII character used as the delimiter in the broken CSV. Default is `\x1E`.
+/// * `newline` - An optional ASCII character used as the newline character in the broken CSV. Default is `\x1D`.
+///
+///
+/// # Example
+///
+/// ```python
+/// from bcp_exorcist import exorcize_csv, is_broken
+///
+/// if is_broken("path/to/export.csv"):
+///     exorcize_csv("path/to/export.csv")
+/// ```
+#[pyfunction]
+#[pyo3(signature = (filepath, delim=None, newline=None))]
+fn is_broken(
+    py: Python<'_>,
+    filepath: &str,
+    delim: Option<&[u8]>,
+    newline: Option<&[u8]>,
+) -> PyResult<bool> {
+    let opts = common_opts(py, delim, newline)?;
+
+    let input = File::open(filepath)?;
+    Ok(reader::is_broken(input, DEFAULT_CHUNK_SIZE, &opts)?)
+}
+
+/// Checks that every row of a broken CSV file has exactly `expected_fields` fields.
+///
+/// The file is scanned once in constant memory and left untouched. Returns `None` when
+/// every row matches; raises `ValueError` at the first row that does not, naming its
+/// 0-based row number and the byte offset where it starts. With `header` the count is not
+/// given but taken from the first row.
+///
+/// # Arguments
+///
+/// * `filepath` - A string slice that holds the path to the file to validate.
+/// * `expected_fields` - The number of fields every row must have. Required unless `header` is set.
+/// * `delim` - An optional ASCII character used as the delimiter in the broken CSV. Default is `\x1E`.
+/// * `newline` - An optional ASCII character used as the newline character in the broken CSV. Default is `\x1D`.
+/// * `header` - Whether the first row is a header whose field count every later row must have. Not supported with `expected_fields`. Default is `False`.
+///
+///
+/// # Example
+///
+/// ```python
+/// from bcp_exorcist import exorcize_csv, validate_stream
+///
+/// validate_stream("path/to/export.csv", 12)
+/// validate_stream("path/to/other.csv", header=True)
+/// exorcize_csv("path/to/export.csv")
+/// ```
+#[pyfunction]
+#[pyo3(signature = (filepath, expected_fields=None, delim=None, newline=None, header=false))]
+fn validate_stream(
+    py: Python<'_>,
+    filepath: &str,
+    expected_fields: Option<u64>,
+    delim: Option<&[u8]>,
+    newline: Option<&[u8]>,
+    header: bool,
+) -> PyResult<()> {
+    match (expected_fields, header) {
+        (Some(_), true) => {
+            return Err(PyValueError::new_err(
+                "expected_fields is not supported with header",
+            ))
+        }
+        (None, false) => {
+            return Err(PyValueError::new_err(
+                "expected_fields is required unless header is set",
+            ))
+        }
+        (Some(0), _) => return Err(PyValueError::new_err("expected_fields must be at least 1")),
+        _ => (),
+    }
+    let opts = common_opts(py, delim, newline)?;
+
+    let input = File::open(filepath)?;
+    match reader::first_ragged_row(input, expected_fields, DEFAULT_CHUNK_SIZE, &opts)? {
+        Some(ragged) => Err(PyValueError::new_err(format!(
+            "{filepath}: row {} at byte {} has {} fields; expected {}",
+            ragged.row, ragged.offset, ragged.fields, ragged.expected
+        ))),
+        None => Ok(()),
+    }
+}
+
+/// Reports every row of a broken CSV file whose field count differs from the expected one.
+///
+/// A dry run to tell whether a file is salvageable before fixing it: the rows are delimited
+/// and their fields counted as `exorcize_csv` does, in constant memory besides the row
+/// indices found, and nothing is written. The file is left untouched, with no backup. Unlike
+/// `validate_stream` the whole file is scanned. Returns a dict with the `rows` scanned, the
+/// `expected_fields` they were checked against, `None` for an empty file, and the list of
+/// 0-based `ragged_rows` indices, empty when every row matches.
+///
+/// # Arguments
+///
+/// * `filepath` - A string slice that holds the path to the file to validate.
+/// * `expected_fields` - An optional number of fields every row must have. Default is the field count of the first row.
+/// * `delim` - An optional ASCII character used as the delimiter in the broken CSV. Default is `\x1E`.
+/// * `newline` - An optional ASCII character used as the newline character in the broken CSV. Default is `\x1D`.
+/// * `chunk_size` - An optional size of each read, given as for `exorcize_csv`. It must be at least 1, or it raises `ValueError`. Default is 4 MiB.
+///
+///
+/// # Example
+///
+/// ```python
+/// from bcp_exorcist import exorcize_csv, validate_csv
+///
+/// report = validate_csv("path/to/export.csv")
+/// if not report["ragged_rows"]:
+///     exorcize_csv("path/to/export.csv")
+/// ```
+#[pyfunction]
+#[pyo3(signature = (filepath, expected_fields=None, delim=None, newline=None, chunk_size=None))]
+fn validate_csv<'py>(
+    py: Python<'py>,
+    filepath: &str,
+    expected_fields: Option<u64>,
+    delim: Option<&[u8]>,
+    newline: Option<&[u8]>,
+    chunk_size: Option<ChunkSize>,
+) -> PyResult<Bound<'py, PyDict>> {
+    if expected_fields == Some(0) {
+        return Err(PyValueError::new_err("expected_fields must be at least 1"));
+    }
+    let opts = common_opts(py, delim, newline)?;
+    let chunk_size = unwrap_chunk_size(chunk_size)?.unwrap_or(DEFAULT_CHUNK_SIZE);
+    if chunk_size == 0 {
+        return Err(PyValueError::new_err("chunk_size should be at least 1"));
+    }
+
+    let input = File::open(filepath)?;
+    let widths =
+        py.allow_threads(|| reader::ragged_rows(input, expected_fields, chunk_size, &opts))?;
+    let dict = PyDict::new(py);
+    dict.set_item("rows", widths.rows)?;
+    dict.set_item("expected_fields", widths.expected)?;
+    dict.set_item("ragged_rows", widths.ragged)?;
+    Ok(dict)
+}
+
+/// Checks whether fixing a broken CSV file gives exactly the bytes of an expected file.
+///
+/// A dry run for golden-file tests: the input is fixed with the default options and its
+/// output compared, as it is produced, with `expected_path`, both streamed in constant
+/// memory. Nothing is written and both files are left untouched. Returns a tuple of whether
+/// they match and the offset of the first differing byte, `None` when they match; an
+/// expected file that runs past the output differs where the output ends. Being a tuple it
+/// is always truthy, so unpack it rather than testing it.
+///
+/// # Arguments
+///
+/// * `input_path` - A string slice that holds the path to the broken file to fix.
+/// * `expected_path` - A string slice that holds the path to the output it should give.
+/// * `delim` - An optional ASCII character used as the delimiter in the broken CSV. Default is `\x1E`.
+/// * `newline` - An optional ASCII character used as the newline character in the broken CSV. Default is `\x1D`.
+///
+///
+/// # Example
+///
+/// ```python
+/// from bcp_exorcist import exorcize_matches
+///
+/// matches, offset = exorcize_matches("tests/export.bcp", "tests/export.csv")
+/// assert matches, f"output differs at byte {offset}"
+/// ```
+#[pyfunction]
+#[pyo3(signature = (input_path, expected_path, delim=None, newline=None))]
+fn exorcize_matches(
+    py: Python<'_>,
+    input_path: &str,
+    expected_path: &str,
+    delim: Option<&[u8]>,
+    newline: Option<&[u8]>,
+) -> PyResult<(bool, Option<u64>)> {
+    let opts = common_opts(py, delim, newline)?;
+
+    let input = File::open(input_path)?;
+    let size = input.metadata()?.len();
+    let mut golden = Golden::new(BufReader::new(File::open(expected_path)?));
+    reader::exorcize_csv(input, &mut golden, size, DEFAULT_CHUNK_SIZE, &opts)?;
+    let difference = golden.first_difference()?;
+    Ok((difference.is_none(), difference))
+}
+
+/// Formats `fields` as one CSV row, in `bytes`, exactly as `exorcize_csv` writes a row of
+/// the same values.
+///
+/// Each field is quoted and its quotes escaped, and the fields are joined by `delim`; the
+/// row gets no terminator. A field is one value, so a `\x1E` or `\x1D` in it is content.
+///
+/// # Arguments
+///
+/// * `fields` - The values of the row.
+/// * `quote` - An optional ASCII character wrapping each field. Default is `"`.
+/// * `delim` - An optional ASCII character between two fields. Default is `,`.
+/// * `escape` - The escape byte of the values, as for `exorcize_csv`. Default is `b"\\"`.
+///
+///
+/// # Example
+///
+/// ```python
+/// from bcp_exorcist import format_row
+///
+/// assert format_row([b"a", b'say "hi"']) == b'"a","say \\"hi\\""'
+/// ```
+#[pyfunction]
+#[pyo3(signature = (fields, quote=None, delim=None, escape=Some(&b"\\"[..])))]
+fn format_row<'py>(
+    py: Python<'py>,
+    fields: Vec<Vec<u8>>,
+    quote: Option<&[u8]>,
+    delim: Option<&[u8]>,
+    escape: Option<&[u8]>,
+) -> PyResult<Bound<'py, PyBytes>> {
+    let defaults = TmpOptions::default();
+    let opts = TmpOptions {
+        quote: unwrap_byte(quote, defaults.quote)?,
+        out_delim: vec![unwrap_byte(delim, defaults.out_delim[0])?],
+        escape: unwrap_escape(escape)?,
+        ..defaults
+    };
+
+    let mut row = Vec::new();
+    reader::format_row(&fields, &mut row, &opts);
+    Ok(PyBytes::new(py, &row))
+}
+
+/// Fixes one buffer of broken CSV, in `bytes`, as `exorcize_csv` fixes each batch it reads.
+///
+/// This is the core transform alone, for callers running their own streaming loop. Only
+/// the breaks within `data` are written: the caller writes the opening quote of the
+/// stream and its closing, and its last row break when the input ends on `newline`.
+/// Returns the fixed buffer and the length of the run of backslashes the input so far ends
+/// with: passed back as `backslashes` with the next buffer, it carries the escape over the
+/// boundary, so a run split anywhere gives the output of `exorcize_csv`. Should the input
+/// end with an odd run, the closing quote is escaped unless a backslash is written before
+/// it. Header, padding and the other row options do not apply.
+///
+/// # Arguments
+///
+/// * `data` - One buffer of the broken CSV.
+/// * `delim` - An optional ASCII character used as the delimiter in the broken CSV. Default is `\x1E`.
+/// * `newline` - An optional ASCII character used as the newline character in the broken CSV. Default is `\x1D`.
+/// * `quote` - An optional ASCII character wrapping each field. Default is `"`.
+/// * `escape` - The escape byte of the source, as for `exorcize_csv`. Default is `b"\\"`.
+/// * `backslashes` - The run of backslashes returned with the previous buffer. Default is 0.
+///
+///
+/// # Example
+///
+/// ```python
+/// from bcp_exorcist import exorcize_batch
+///
+/// chunks = [b"a\x1Eb\\", b'\\\x1Dc"']
+/// out, backslashes = [b'"'], 0
+/// for chunk in chunks:
+///     fixed, backslashes = exorcize_batch(chunk, backslashes=backslashes)
+///     out.append(fixed)
+/// out.append(b'\\"' if backslashes % 2 else b'"')
+/// assert b"".join(out) == b'"a","b\\\\"\n"c\\""'
+/// ```
+#[pyfunction]
+#[pyo3(signature = (
+    data,
+    delim=None,
+    newline=None,
+    quote=None,
+    escape=Some(&b"\\"[..]),
+    backslashes=0,
+))]
+fn exorcize_batch<'py>(
+    py: Python<'py>,
+    data: &[u8],
+    delim: Option<&[u8]>,
+    newline: Option<&[u8]>,
+    quote: Option<&[u8]>,
+    escape: Option<&[u8]>,
+    backslashes: usize,
+) -> PyResult<(Bound<'py, PyBytes>, usize)> {
+    let defaults = TmpOptions::default();
+    let opts = TmpOptions {
+        sep: unwrap_byte(delim, defaults.sep)?,
+        eol: unwrap_byte(newline, defaults.eol)?,
+        quote: unwrap_byte(quote, defaults.quote)?,
+        escape: unwrap_escape(escape)?,
+        ..defaults
+    };
+    check_escape(&opts)?;
+
+    let (buf, backslashes) =
+        py.allow_threads(|| reader::exorcize_csv_batch_after(data, backslashes, &opts));
+    Ok((PyBytes::new(py, &buf), backslashes))
+}
+
+/// Returns the first `limit_rows` rows of a broken CSV file, fixed, as one CSV in `bytes`.
+///
+/// Reading stops once `limit_rows` newlines were seen, so a preview of a huge file is
+/// cheap. The file is left untouched and the returned CSV is complete on its own, with
+/// the last row unterminated. With `skip_rows`, that many rows are first stepped over
+/// without being fixed, so a file can be paged through a window at a time.
+///
+/// # Arguments
+///
+/// * `filepath` - A string slice that holds the path to the file to preview.
+/// * `limit_rows` - The number of leading rows to return.
+/// * `delim` - An optional ASCII character used as the delimiter in the broken CSV. Default is `\x1E`.
+/// * `newline` - An optional ASCII character used as the newline character in the broken CSV. Default is `\x1D`.
+/// * `skip_rows` - An optional number of rows to step over before the returned ones. Default is 0.
+///
+///
+/// # Example
+///
+/// ```python
+/// from bcp_exorcist import exorcize_preview
+///
+/// print(exorcize_preview("path/to/broken.csv", 20).decode())
+/// print(exorcize_preview("path/to/broken.csv", 20, skip_rows=20).decode())
+/// ```
+#[pyfunction]
+#[pyo3(signature = (filepath, limit_rows, delim=None, newline=None, skip_rows=None))]
+fn exorcize_preview<'py>(
+    py: Python<'py>,
+    filepath: &str,
+    limit_rows: usize,
+    delim: Option<&[u8]>,
+    newline: Option<&[u8]>,
+    skip_rows: Option<u64>,
+) -> PyResult<Bound<'py, PyBytes>> {
+    let opts = common_opts(py, delim, newline)?;
+
+    let mut input = BufReader::with_capacity(DEFAULT_CHUNK_SIZE, File::open(filepath)?);
+    reader::skip_rows(&mut input, skip_rows.unwrap_or(0), opts.eol)?;
+    let out = reader::exorcize_head(input, limit_rows, DEFAULT_CHUNK_SIZE, &opts)?;
+    Ok(PyBytes::new(py, &out))
+}
+
+/// Returns the last `rows` fixed rows of a broken CSV file without processing all of it.
+///
+/// The file is scanned backward from its end for `newline` boundaries, so only the
+/// tail is read. Each returned row is quoted on its own and carries no line terminator.
+///
+/// # Arguments
+///
+/// * `filepath` - A string slice that holds the path to the file to inspect.
+/// * `rows` - The number of trailing rows to return.
+/// * `delim` - An optional ASCII character used as the delimiter in the broken CSV. Default is `\x1E`.
+/// * `newline` - An optional ASCII character used as the newline character in the broken CSV. Default is `\x1D`.
+/// * `errors` - An optional handling of rows that are not valid UTF-8, as for `bytes.decode`:
+///   `"strict"` raises `UnicodeDecodeError`, `"replace"` writes U+FFFD for each invalid
+///   sequence and `"ignore"` drops it. Another name raises `ValueError`. Default is `"strict"`.
+///
+///
+/// # Example
+///
+/// ```python
+/// from bcp_exorcist import exorcize_tail
+///
+/// for row in exorcize_tail("path/to/broken.csv", 10):
+///     print(row)
+/// ```
+#[pyfunction]
+#[pyo3(signature = (filepath, rows, delim=None, newline=None, errors=None))]
+fn exorcize_tail(
+    py: Python<'_>,
+    filepath: &str,
+    rows: usize,
+    delim: Option<&[u8]>,
+    newline: Option<&[u8]>,
+    errors: Option<&str>,
+) -> PyResult<Vec<String>> {
+    let errors = unwrap_errors(errors)?;
+    let opts = common_opts(py, delim, newline)?;
+
+    let input = File::open(filepath)?;
+    let size = input.metadata()?.len();
+
+    let out = reader::exorcize_tail(input, size, rows, DEFAULT_CHUNK_SIZE, &opts)?;
+    let out = out
+        .iter()
+        .map(|row| match errors.decode(row) {
+            Ok(text) => Ok(text.into_owned()),
+            Err(e) => Err(PyErr::from_value(
+                PyUnicodeDecodeError::new_utf8(py, row, e)?.into_any(),
+            )),
+        })
+        .collect::<PyResult<_>>()?;
+    Ok(out)
+}
+
+/// Fixes every file matching a glob pattern in place, on a bounded pool of threads.
+///
+/// Each file is handled as `exorcize_csv` handles it in place, with its own backup. Files
+/// are independent, so a failed one does not stop the batch: its error is reported in its
+/// entry. The GIL is released while the files are processed.
+///
+/// Returns one dict per matched file, in sorted path order whatever the completion order:
+/// `path` and `status` (`"exorcized"`, `"empty"`, `"restored"`, `"unrestored"` or `"failed"`),
+/// the report of `exorcize_csv` on success, and `error` with the message otherwise.
+/// `"restored"` means processing failed and the original was put back; `"unrestored"` that
+/// putting it back failed too, the message naming where it was left.
+///
+/// # Arguments
+///
+/// * `pattern` - A glob pattern such as `"exports/*.csv"`; `**` matches nested directories.
+/// * `delim` - An optional ASCII character used as the delimiter in the broken CSV. Default is `\x1E`.
+/// * `newline` - An optional ASCII character used as the newline character in the broken CSV. Default is `\x1D`.
+/// * `chunk_size` - An optional size for the batch size, as for `exorcize_csv`. Default is 4 MiB, per
+///   worker.
+/// * `max_workers` - An optional number of files processed at once. Default is the number of
+///   available CPUs.
+///
+///
+/// # Example
+///
+/// ```python
+/// from bcp_exorcist import exorcize_glob
+///
+/// for result in exorcize_glob("exports/*.csv", max_workers=4):
+///     if "error" in result:
+///         print(f"{result['path']}: {result['error']}")
+/// ```
+#[pyfunction]
+#[pyo3(signature = (pattern, delim=None, newline=None, chunk_size=None, max_workers=None))]
+fn exorcize_glob<'py>(
+    py: Python<'py>,
+    pattern: &str,
+    delim: Option<&[u8]>,
+    newline: Option<&[u8]>,
+    chunk_size: Option<ChunkSize>,
+    max_workers: Option<usize>,
+) -> PyResult<Vec<Bound<'py, PyDict>>> {
+    let chunk_size = unwrap_chunk_size(chunk_size)?;
+    let opts = common_opts(py, delim, newline)?;
+    if max_workers == Some(0) {
+        return Err(PyValueError::new_err("max_workers should be at least 1"));
+    }
+    let workers = max_workers.unwrap_or_else(|| {
+        std::thread::available_parallelism().map_or(1, std::num::NonZeroUsize::get)
+    });
+    let chunk_size = chunk_size.unwrap_or(DEFAULT_CHUNK_SIZE);
+
+    let paths = glob::glob(pattern)
+        .map_err(|e| PyValueError::new_err(format!("invalid pattern '{pattern}': {e}")))?
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(io::Error::from)?;
+    let results = py.allow_threads(|| batch::exorcize_files(&paths, workers, chunk_size, &opts));
+
+    paths
+        .iter()
+        .zip(results)
+        .map(|(path, result)| batch_entry(py, path, result))
+        .collect()
+}
+
+/// The dict reporting how one file of a batch went.
+fn batch_entry<'py>(
+    py: Python<'py>,
+    path: &Path,
+    result: Result<Outcome<Stats>, Failure>,
+) -> PyResult<Bound<'py, PyDict>> {
+    let (status, dict) = match result {
+        Ok(Outcome::Exorcized(stats)) => ("exorcized", stats_dict(py, &stats)?),
+        Ok(Outcome::Empty) => ("empty", stats_dict(py, &Stats::default())?),
+        Err(failure) => {
+            let (status, e) = match failure {
+                Failure::Io(e) => ("failed", e),
+                Failure::Run(e) => ("restored", e),
+                Failure::Unrestored {
+                    run,
+                    restore,
+                    original,
+                } => {
+                    let msg = format!(
+                        "{run}; restoring failed too: {restore}; the original is left at '{}'",
+                        original.display()
+                    );
+                    ("unrestored", io::Error::new(run.kind(), msg))
+                }
+            };
+            let dict = PyDict::new(py);
+            dict.set_item("error", e.to_string())?;
+            (status, dict)
+        }
+    };
+    dict.set_item("path", path)?;
+    dict.set_item("status", status)?;
+    Ok(dict)
+}
+
+/// Fixes every file under a directory whose path matches a glob pattern, in place, on a
+/// bounded pool of threads.
+///
+/// Files are handled as by `exorcize_glob`. Symlinks are followed; a directory that
+/// cannot be read, or is reached again through a symlink loop, is skipped and reported
+/// as a `"failed"` entry, and the rest of the tree is still fixed.
+///
+/// Returns one dict per matched file or skipped entry, in sorted path order, as
+/// `exorcize_glob` does.
+///
+/// # Arguments
+///
+/// * `root` - A string slice that holds the path to the directory to walk.
+/// * `pattern` - An optional glob pattern matched against each path relative to `root`;
+///   `*` also matches `/`, so `"*.csv"` matches CSVs at any depth. Default is `"*"`.
+/// * `delim` - An optional ASCII character used as the delimiter in the broken CSV. Default is `\x1E`.
+/// * `newline` - An optional ASCII character used as the newline character in the broken CSV. Default is `\x1D`.
+/// * `chunk_size` - An optional size for the batch size, as for `exorcize_csv`. Default is 4 MiB, per
+///   worker.
+/// * `max_workers` - An optional number of files processed at once. Default is the number of
+///   available CPUs.
+///
+///
+/// # Example
+///
+/// ```python
+/// from bcp_exorcist import exorcize_tree
+///
+/// for result in exorcize_tree("exports", "*.csv", max_workers=4):
+///     if "error" in result:
+///         print(f"{result['path']}: {result['error']}")
+/// ```
+#[pyfunction]
+#[pyo3(signature = (root, pattern=None, delim=None, newline=None, chunk_size=None, max_workers=None))]
+fn exorcize_tree<'py>(
+    py: Python<'py>,
+    root: &str,
+    pattern: Option<&str>,
+    delim: Option<&[u8]>,
+    newline: Option<&[u8]>,
+    chunk_size: Option<ChunkSize>,
+    max_workers: Option<usize>,
+) -> PyResult<Vec<Bound<'py, PyDict>>> {
+    let chunk_size = unwrap_chunk_size(chunk_size)?;
+    let opts = common_opts(py, delim, newline)?;
+    if max_workers == Some(0) {
+        return Err(PyValueError::new_err("max_workers should be at least 1"));
+    }
+    let workers = max_workers.unwrap_or_else(|| {
+        std::thread::available_parallelism().map_or(1, std::num::NonZeroUsize::get)
+    });
+    let chunk_size = chunk_size.unwrap_or(DEFAULT_CHUNK_SIZE);
+
+    let pattern = pattern.unwrap_or("*");
+    let pattern = glob::Pattern::new(pattern)
+        .map_err(|e| PyValueError::new_err(format!("invalid pattern '{pattern}': {e}")))?;
+    let root = Path::new(root);
+    if !std::fs::metadata(root)?.is_dir() {
+        let msg = format!("{} is not a directory", root.display());
+        return Err(PyValueError::new_err(msg));
+    }
+
+    let (paths, skipped) = py.allow_threads(|| batch::walk_tree(root, &pattern));
+    let results = py.allow_threads(|| batch::exorcize_files(&paths, workers, chunk_size, &opts));
+
+    let mut entries: Vec<_> = paths.into_iter().zip(results).collect();
+    entries.extend(
+        skipped
+            .into_iter()
+            .map(|(path, e)| (path, Err(Failure::Io(e)))),
+    );
+    entries.sort_by(|a, b| a.0.cmp(&b.0));
+    entries
+        .into_iter()
+        .map(|(path, result)| batch_entry(py, &path, result))
+        .collect()
+}
+
+/// Turns a CSV fixed by `exorcize_csv` back into its broken form, in place.
+///
+/// Commas and newlines outside of quotes become `delim` and `newline` again, the quotes
+/// around each field are dropped and escaped quotes are unescaped. The original is kept
+/// as `.bak`, as by `exorcize_csv`. Running it on the output of a default `exorcize_csv`
+/// gives back the input, except that a field ending in an even run of backslashes loses
+/// one and a `\"` becomes `"`, which a fix writes like other input.
+///
+/// # Arguments
+///
+/// * `filepath` - A string slice that holds the path to the fixed CSV.
+/// * `delim` - An optional ASCII character written as the delimiter. Default is `\x1E`.
+/// * `newline` - An optional ASCII character written as the newline character. Default is `\x1D`.
+///
+///
+/// # Raises
+///
+/// * `CorruptionError` - If the file is not a CSV as `exorcize_csv` writes it, e.g. with
+///   a quote left open; the original is restored.
+///
+///
+/// # Example
+///
+/// ```python
+/// from bcp_exorcist import exorcize_csv, unexorcize
+///
+/// exorcize_csv("path/to/broken.csv")
+/// unexorcize("path/to/broken.csv")
+/// ```
+#[pyfunction]
+#[pyo3(signature = (filepath, delim=None, newline=None))]
+fn unexorcize(
+    py: Python<'_>,
+    filepath: &str,
+    delim: Option<&[u8]>,
+    newline: Option<&[u8]>,
+) -> PyResult<()> {
+    let opts = common_opts(py, delim, newline)?;
+
+    let path = files::resolve_input(filepath, true)?;
+    let result = py.allow_threads(|| {
+        files::exorcize_file(
+            &path,
+            Target::InPlace { atomic: true },
+            None,
+            |input: File, output, _| unquote::unexorcize(input, output, DEFAULT_CHUNK_SIZE, &opts),
+        )
+    });
+    match result {
+        Ok(_) => Ok(()),
+        Err(Failure::Io(e)) => Err(e.into()),
+        Err(Failure::Run(e)) => Err(corruption_error(py, e)),
+        Err(Failure::Unrestored {
+            run,
+            restore,
+            original,
+        }) => Err(unrestored_error(py, &path, run, restore, &original)),
+    }
+}
+
+#[pymodule]
+fn bcp_exorcist(py: Python<'_>, m: &Bound<'_, PyModule>) -> PyResult<()> {
+    let defaults = TmpOptions::default();
+    m.add("DEFAULT_DELIM", PyBytes::new(py, &[defaults.sep]))?;
+    m.add("DEFAULT_NEWLINE", PyBytes::new(py, &[defaults.eol]))?;
+    m.add("DEFAULT_CHUNK_SIZE", DEFAULT_CHUNK_SIZE)?;
+    m.add("CorruptionError", py.get_type::<CorruptionError>())?;
+    m.add_class::<ChainedExorcist>()?;
+    m.add_class::<StreamExorcist>()?;
+
     m.add_function(wrap_pyfunction!(exorcize_csv, m)?)?;
+    m.add_function(wrap_pyfunction!(exorcize_with_config, m)?)?;
+    m.add_function(wrap_pyfunction!(exorcize_with_fmt, m)?)?;
+    m.add_function(wrap_pyfunction!(exorcize_tail, m)?)?;
+    m.add_function(wrap_pyfunction!(exorcize_preview, m)?)?;
+    m.add_function(wrap_pyfunction!(format_row, m)?)?;
+    m.add_function(wrap_pyfunction!(exorcize_batch, m)?)?;
+    m.add_function(wrap_pyfunction!(estimate_output_size, m)?)?;
+    m.add_function(wrap_pyfunction!(exorcize_bytes, m)?)?;
+    m.add_function(wrap_pyfunction!(exorcize_bytes_into, m)?)?;
+    m.add_function(wrap_pyfunction!(exorcize_range, m)?)?;
+    m.add_function(wrap_pyfunction!(snap_to_row, m)?)?;
+    m.add_function(wrap_pyfunction!(exorcize_glob, m)?)?;
+    m.add_function(wrap_pyfunction!(exorcize_tree, m)?)?;
+    m.add_function(wrap_pyfunction!(unexorcize, m)?)?;
+    m.add_function(wrap_pyfunction!(is_broken, m)?)?;
+    m.add_function(wrap_pyfunction!(validate_stream, m)?)?;
+    m.add_function(wrap_pyfunction!(validate_csv, m)?)?;
+    m.add_function(wrap_pyfunction!(exorcize_matches, m)?)?;
+    m.add_function(wrap_pyfunction!(exorcize_stream, m)?)?;
+    m.add_function(wrap_pyfunction!(exorcize_chunks, m)?)?;
+    m.add_function(wrap_pyfunction!(exorcize_transpose, m)?)?;
+    m.add_function(wrap_pyfunction!(exorcize_fixed_width, m)?)?;
+    m.add_function(wrap_pyfunction!(exorcize_sorted, m)?)?;
+    #[cfg(feature = "arrow")]
+    m.add_function(wrap_pyfunction!(exorcize_to_arrow, m)?)?;
+    #[cfg(feature = "debug")]
+    m.add_function(wrap_pyfunction!(exorcize_csv_debug, m)?)?;
+    #[cfg(feature = "http")]
+    m.add_function(wrap_pyfunction!(exorcize_url, m)?)?;
     Ok(())
 }
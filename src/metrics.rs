@@ -0,0 +1,183 @@
+use crate::stats::Stats;
+use std::ffi::OsString;
+use std::fmt::Write as _;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+/// How `exorcize_csv` reports a finished run.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub(crate) enum SummaryFormat {
+    /// Nothing is printed.
+    None,
+    /// The banner on stdout.
+    Human,
+    /// One JSON line of the stats on stderr, from `summary_json`.
+    Json,
+}
+
+impl SummaryFormat {
+    pub(crate) fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "none" => Some(SummaryFormat::None),
+            "human" => Some(SummaryFormat::Human),
+            "json" => Some(SummaryFormat::Json),
+            _ => None,
+        }
+    }
+}
+
+/// Formats the outcome of one run as a single line of JSON, newline included, for log
+/// aggregation.
+pub(crate) fn summary_json(path: &Path, status: &str, stats: &Stats, duration: Duration) -> String {
+    let anomalies: Vec<String> = stats.anomalies.iter().map(|a| a.to_string()).collect();
+    let summary = serde_json::json!({
+        "path": path.to_string_lossy(),
+        "status": status,
+        "rows": stats.rows,
+        "fields": stats.fields,
+        "quotes": stats.quotes,
+        "bytes_read": stats.bytes_read,
+        "bytes_written": stats.bytes_written,
+        "anomalies": anomalies,
+        "duration_seconds": duration.as_secs_f64(),
+    });
+    format!("{summary}\n")
+}
+
+/// Formats the stats of one run in the Prometheus text exposition format.
+///
+/// Counters hold the totals of this run only; each snapshot replaces the last.
+pub(crate) fn textfile(stats: &Stats, duration: Duration) -> String {
+    let mut out = String::new();
+    let mut metric = |name: &str, kind: &str, help: &str, samples: &[(&str, String)]| {
+        let _ = writeln!(out, "# HELP bcp_exorcist_{name} {help}");
+        let _ = writeln!(out, "# TYPE bcp_exorcist_{name} {kind}");
+        for (labels, value) in samples {
+            let _ = writeln!(out, "bcp_exorcist_{name}{labels} {value}");
+        }
+    };
+
+    metric(
+        "rows_total",
+        "counter",
+        "Rows written by the last run.",
+        &[("", stats.rows.to_string())],
+    );
+    metric(
+        "bytes_total",
+        "counter",
+        "Bytes read and written by the last run.",
+        &[
+            ("{direction=\"read\"}", stats.bytes_read.to_string()),
+            ("{direction=\"written\"}", stats.bytes_written.to_string()),
+        ],
+    );
+    metric(
+        "quotes_escaped_total",
+        "counter",
+        "Raw quotes escaped in field content by the last run.",
+        &[("", stats.quotes.to_string())],
+    );
+    metric(
+        "duration_seconds",
+        "gauge",
+        "Wall time of the last run.",
+        &[("", duration.as_secs_f64().to_string())],
+    );
+    out
+}
+
+/// Writes `content` to `path` through a temporary file renamed over it.
+///
+/// The textfile collector may read `path` at any time, so it must never see a
+/// partial snapshot.
+pub(crate) fn write_textfile(path: &Path, content: &str) -> io::Result<()> {
+    let mut tmp = OsString::from(path);
+    tmp.push(".tmp");
+    let tmp = PathBuf::from(tmp);
+
+    fs::write(&tmp, content)?;
+    fs::rename(&tmp, path).inspect_err(|_| {
+        let _ = fs::remove_file(&tmp);
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::stats::Anomaly;
+    use rstest::*;
+
+    #[rstest]
+    fn test_textfile() {
+        let mut stats = Stats::default();
+        stats.rows = 2;
+        stats.bytes_read = 13;
+        stats.bytes_written = 17;
+        stats.quotes = 1;
+
+        let text = textfile(&stats, Duration::from_millis(1500));
+        let samples: Vec<&str> = text.lines().filter(|l| !l.starts_with('#')).collect();
+        assert_eq!(
+            samples,
+            vec![
+                "bcp_exorcist_rows_total 2",
+                "bcp_exorcist_bytes_total{direction=\"read\"} 13",
+                "bcp_exorcist_bytes_total{direction=\"written\"} 17",
+                "bcp_exorcist_quotes_escaped_total 1",
+                "bcp_exorcist_duration_seconds 1.5",
+            ]
+        );
+        assert!(text.contains("# TYPE bcp_exorcist_duration_seconds gauge\n"));
+        assert!(text.ends_with('\n'));
+    }
+
+    #[rstest]
+    fn test_summary_json() {
+        let mut stats = Stats::default();
+        stats.rows = 2;
+        stats.fields = 4;
+        stats.bytes_read = 13;
+        stats.bytes_written = 17;
+        stats.anomalies.push(Anomaly::Nul { offset: 3 });
+
+        let path = Path::new("exports/a.csv");
+        let line = summary_json(path, "exorcized", &stats, Duration::from_millis(250));
+        assert!(line.ends_with('\n'));
+        assert_eq!(line.lines().count(), 1);
+
+        let summary: serde_json::Value = serde_json::from_str(&line).unwrap();
+        assert_eq!(summary["path"], "exports/a.csv");
+        assert_eq!(summary["status"], "exorcized");
+        assert_eq!(summary["rows"], 2);
+        assert_eq!(summary["fields"], 4);
+        assert_eq!(summary["quotes"], 0);
+        assert_eq!(summary["bytes_read"], 13);
+        assert_eq!(summary["bytes_written"], 17);
+        assert_eq!(
+            summary["anomalies"],
+            serde_json::json!(["nul: NUL byte at offset 3"])
+        );
+        assert_eq!(summary["duration_seconds"], 0.25);
+    }
+
+    #[rstest]
+    fn test_write_textfile() {
+        let dir = std::env::temp_dir().join("bcp_exorcist_metrics");
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("exorcist.prom");
+
+        write_textfile(&path, "first\n").unwrap();
+        write_textfile(&path, "second\n").unwrap();
+        assert_eq!(fs::read_to_string(&path).unwrap(), "second\n");
+        assert_eq!(fs::read_dir(&dir).unwrap().count(), 1);
+
+        let missing = dir.join("missing").join("exorcist.prom");
+        assert!(write_textfile(&missing, "x").is_err());
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,197 @@
+use std::io::{self, Read};
+use std::time::{Duration, Instant};
+
+/// A progress report: how far the input was read and how fast.
+#[derive(Debug, PartialEq)]
+pub(crate) struct Beat {
+    pub(crate) bytes_read: u64,
+    pub(crate) total: u64,
+    /// Input throughput since the first read, in MB/s.
+    pub(crate) mb_per_s: f64,
+}
+
+/// Reports the progress of reads on `inner` to `on_beat`, at most once per `interval`.
+///
+/// The clock is checked on every read, so the report frequency does not depend on the
+/// chunk size. End of input is always reported once, with the final throughput. An
+/// error from `on_beat` fails the read and aborts the run.
+pub(crate) struct Heartbeat<R, F> {
+    inner: R,
+    total: u64,
+    interval: Duration,
+    on_beat: F,
+    bytes_read: u64,
+    start: Option<Instant>,
+    last: Option<Instant>,
+    done: bool,
+}
+
+impl<R, F> Heartbeat<R, F>
+where
+    F: FnMut(&Beat) -> io::Result<()>,
+{
+    pub(crate) fn new(inner: R, total: u64, interval: Duration, on_beat: F) -> Self {
+        Heartbeat {
+            inner,
+            total,
+            interval,
+            on_beat,
+            bytes_read: 0,
+            start: None,
+            last: None,
+            done: false,
+        }
+    }
+
+    fn beat(&mut self, now: Instant) -> io::Result<()> {
+        self.last = Some(now);
+        let elapsed = self.start.map_or(0.0, |start| (now - start).as_secs_f64());
+        let mb_per_s = match elapsed > 0.0 {
+            true => self.bytes_read as f64 / elapsed / 1_000_000.0,
+            false => 0.0,
+        };
+        (self.on_beat)(&Beat {
+            bytes_read: self.bytes_read,
+            total: self.total,
+            mb_per_s,
+        })
+    }
+}
+
+impl<R, F> Read for Heartbeat<R, F>
+where
+    R: Read,
+    F: FnMut(&Beat) -> io::Result<()>,
+{
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let start = *self.start.get_or_insert_with(Instant::now);
+        let read = self.inner.read(buf)?;
+        self.bytes_read += read as u64;
+
+        let now = Instant::now();
+        if read == 0 {
+            if !self.done {
+                self.done = true;
+                self.beat(now)?;
+            }
+        } else if now - self.last.unwrap_or(start) >= self.interval {
+            self.beat(now)?;
+        }
+        Ok(read)
+    }
+}
+
+/// Fails the reads on `inner` that start once `limit` has passed since the first one.
+///
+/// A run reads once per chunk, so it is stopped at the next chunk boundary after the
+/// limit, and its output holds the rows fixed up to there.
+pub(crate) struct Deadline<R> {
+    inner: R,
+    limit: Duration,
+    start: Option<Instant>,
+}
+
+impl<R> Deadline<R> {
+    pub(crate) fn new(inner: R, limit: Duration) -> Self {
+        Deadline {
+            inner,
+            limit,
+            start: None,
+        }
+    }
+}
+
+impl<R: Read> Read for Deadline<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let start = *self.start.get_or_insert_with(Instant::now);
+        if start.elapsed() >= self.limit {
+            let msg = format!("processing took over {:?}", self.limit);
+            return Err(io::Error::new(io::ErrorKind::TimedOut, msg));
+        }
+        self.inner.read(buf)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::reader::{self, TmpOptions};
+    use crate::stats::Partial;
+    use rstest::*;
+    use std::io::Cursor;
+    use std::thread;
+
+    /// Takes `delay` over every read of `inner`, each of at most two bytes.
+    struct Slow<R> {
+        inner: R,
+        delay: Duration,
+    }
+
+    impl<R: Read> Read for Slow<R> {
+        fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+            thread::sleep(self.delay);
+            let len = buf.len().min(2);
+            self.inner.read(&mut buf[..len])
+        }
+    }
+
+    #[rstest]
+    #[case(Duration::ZERO, vec![4, 8, 10, 10])]
+    #[case(Duration::from_secs(3600), vec![10])]
+    fn test_heartbeat_interval(#[case] interval: Duration, #[case] exp: Vec<u64>) {
+        let mut beats = Vec::new();
+        let mut input = Heartbeat::new(Cursor::new([0u8; 10]), 10, interval, |beat: &Beat| {
+            assert_eq!(beat.total, 10);
+            assert!(beat.mb_per_s >= 0.0);
+            beats.push(beat.bytes_read);
+            Ok(())
+        });
+
+        let mut buf = [0u8; 4];
+        while input.read(&mut buf).unwrap() > 0 {}
+        // end of input is reported once
+        assert_eq!(input.read(&mut buf).unwrap(), 0);
+        assert_eq!(beats, exp);
+    }
+
+    #[rstest]
+    fn test_heartbeat_error_aborts() {
+        let data = b"field1\x1Efield2";
+        let opts = TmpOptions::default();
+
+        let input = Heartbeat::new(Cursor::new(data), 13, Duration::ZERO, |_: &Beat| {
+            Err(io::Error::other("cancelled"))
+        });
+        let result = reader::exorcize_csv(input, Vec::new(), 13, 4, &opts);
+        assert_eq!(result.unwrap_err().to_string(), "cancelled");
+    }
+
+    #[rstest]
+    fn test_deadline_stops_a_slow_run() {
+        let data = b"a\x1Db\x1Dc\x1Dd\x1De\x1Df\x1Dg\x1Dh";
+        let opts = TmpOptions::default();
+        let slow = Slow {
+            inner: Cursor::new(data),
+            delay: Duration::from_millis(20),
+        };
+
+        let input = Deadline::new(slow, Duration::from_millis(50));
+        let mut output = Vec::new();
+        let e = reader::exorcize_csv(input, &mut output, data.len() as u64, 2, &opts).unwrap_err();
+        assert_eq!(e.kind(), io::ErrorKind::TimedOut);
+        assert_eq!(e.to_string(), "processing took over 50ms");
+
+        // stopped after a few reads, with the rows read so far
+        let stats = Partial::stats(&e).unwrap();
+        assert!((1..8).contains(&stats.rows), "rows {}", stats.rows);
+        assert!(stats.bytes_read < data.len() as u64);
+    }
+
+    #[rstest]
+    fn test_deadline_passes_a_fast_run() {
+        let data = b"a\x1Eb";
+        let input = Deadline::new(Cursor::new(data), Duration::from_secs(60));
+        let stats = reader::exorcize_csv(input, Vec::new(), 3, 1, &TmpOptions::default());
+        assert_eq!(stats.unwrap().rows, 1);
+    }
+}
@@ -1,10 +1,112 @@
-use memchr::memchr3_iter;
-use std::io::{self, BufReader, BufWriter, Read, Write};
+use crate::stats::{Partial, Repair, Stats, BOM};
+use memchr::{memchr, memchr2, memchr2_iter, memchr3_iter, memchr_iter, memrchr_iter};
+use std::borrow::Cow;
+use std::io::{self, BufRead, BufReader, BufWriter, Read, Seek, SeekFrom, Write};
+
+pub(crate) const DEFAULT_CHUNK_SIZE: usize = 1024 * 1024 * 4;
+/// The fixed bytes the drivers gather before writing them out, whatever the read chunk
+/// size: a small chunk still reaches the output in large writes.
+pub(crate) const WRITE_SIZE: usize = 256 * 1024;
+/// The `size` of an input whose length is not known: like any non-zero size it opens the
+/// first field up front, and an empty input is still told apart by what it yields.
+pub(crate) const UNKNOWN_SIZE: u64 = u64::MAX;
+/// The output assumed per input byte before any was fixed: the quotes of typical rows.
+const FIRST_EXPANSION: f64 = 1.5;
+/// Room kept over the expansion seen so far, so a slightly denser batch fits too.
+const EXPANSION_MARGIN: f64 = 1.25;
 
 #[derive(Debug)]
 pub(crate) struct TmpOptions {
     pub(crate) sep: u8,
     pub(crate) eol: u8,
+    /// Output bytes wrapping each field, separating fields and ending rows.
+    pub(crate) quote: u8,
+    /// One or more bytes, e.g. `|;|`.
+    pub(crate) out_delim: Vec<u8>,
+    /// One or more bytes, e.g. `\r\n`.
+    pub(crate) out_eol: Vec<u8>,
+    pub(crate) double_backslash: bool,
+    /// Forces a terminator after the last row (`Some(true)`) or strips it (`Some(false)`);
+    /// `None` keeps whatever the input ended with.
+    pub(crate) final_newline: Option<bool>,
+    /// Makes every row exactly this many fields wide: short rows get empty fields, fields past
+    /// the last one are dropped.
+    pub(crate) pad_to: Option<usize>,
+    /// Quotes only these column indices and writes the others verbatim. Only the buffered
+    /// driver can tell columns apart, so setting this requires it.
+    pub(crate) quote_columns: Option<Vec<usize>>,
+    /// Extra content bytes to rewrite, beyond the `quote` escape.
+    pub(crate) escapes: Option<Escapes>,
+    /// Fails on input that ends with a dangling backslash escape, a sign of truncation.
+    pub(crate) strict_eof: bool,
+    /// ASCII case applied to field content; other bytes are left alone.
+    pub(crate) case: Option<Case>,
+    /// Reads `""` in field content as one literal quote, escaped once, for sources that
+    /// double their quotes.
+    pub(crate) collapse_double_quotes: bool,
+    /// Column names written as a first row, quoted like data; an empty input gets none.
+    pub(crate) header: Option<Vec<Vec<u8>>>,
+    /// Keep every `Repair` the streaming driver applies in `Stats::repairs`.
+    pub(crate) repair_log: bool,
+    /// Doubles the last backslash of a field ending in an odd run of them, so it cannot
+    /// escape the closing quote. When off such fields pass through unchanged.
+    pub(crate) reescape_delims: bool,
+    /// How many backslashes that re-escape writes, for unescapers of other conventions;
+    /// 0 writes none and logs no repair.
+    pub(crate) reescape_count: usize,
+    /// Writes empty fields as `""`; when off they are left empty, even in quoted columns.
+    /// Like `quote_columns`, turning it off requires the buffered driver.
+    pub(crate) quote_empty: bool,
+    /// Fields appended to every row, always quoted, after its own and `pad_to`'s.
+    pub(crate) extra_columns: Option<Vec<ExtraCol>>,
+    /// Only escapes raw quotes of input already split by the output bytes; see `escape_only`.
+    pub(crate) escape_only: bool,
+    /// A raw line written after the last row, with `{rows}` replaced by the row count.
+    pub(crate) footer: Option<String>,
+    /// Keeps the longest field of each column in the stats; buffered only.
+    pub(crate) column_widths: bool,
+    /// Escapes a quote by doubling it, as RFC 4180 does, instead of with a backslash, which
+    /// is then plain content.
+    pub(crate) double_quotes: bool,
+    /// Takes `sep` and `eol` within a quoted region of the source as content, for input
+    /// that quotes some of its fields. The quotes are kept and escaped as any other.
+    pub(crate) source_quotes: bool,
+    /// The byte escaping the one after it in the source, only ever `\`; `None` makes every
+    /// backslash plain content, as `reescape_delims` off does.
+    pub(crate) escape: Option<u8>,
+    /// A framing byte written before every output row, the header included, e.g. the
+    /// `\x1E` of a JSON text sequence; unrelated to `sep`.
+    pub(crate) record_prefix: Option<u8>,
+    /// How much output is coalesced before each write, whatever the read `chunk_size`.
+    pub(crate) write_size: usize,
+    /// Flips a quoted region of the source on every raw `quote`, which is dropped instead
+    /// of escaped, for sources that never escape quotes; `sep` and `eol` within a region are
+    /// content. Unlike `source_quotes` a quote anywhere in a field opens one.
+    pub(crate) quote_toggle: bool,
+    /// The source column of each output column, in order; unlisted columns are dropped.
+    /// Buffered only, as rows are held whole to be reordered.
+    pub(crate) column_order: Option<Vec<usize>>,
+    /// Fails on a row lacking a column of `column_order` instead of writing it empty.
+    pub(crate) strict_column_order: bool,
+    /// Drops rows written the same as an earlier one, keeping a digest of at most this
+    /// many distinct rows; buffered only.
+    pub(crate) dedup: Option<usize>,
+    /// Drops a `sep` right before an `eol`, or the end of the input, instead of opening an
+    /// empty last field, for sources that end every field with one.
+    pub(crate) ignore_trailing_sep: bool,
+    /// Starts the output with a UTF-8 BOM, before the header.
+    pub(crate) write_bom: bool,
+    /// Starts every row with its length as 4 little-endian bytes instead of ending it with
+    /// `out_eol`; buffered only, as rows are held whole to be measured.
+    pub(crate) length_prefixed: bool,
+    /// A source field equal to it is written as `null_output` instead, unquoted and left
+    /// out of the hook; buffered only, as fields are held whole to be compared.
+    pub(crate) null_token: Option<Vec<u8>>,
+    /// What a `null_token` field is written as, verbatim; empty by default.
+    pub(crate) null_output: Vec<u8>,
+    /// The type of each output column, deciding its quoting in place of `quote_columns`;
+    /// columns past the list are `Auto`. Buffered only.
+    pub(crate) column_types: Option<Vec<ColType>>,
 }
 
 impl Default for TmpOptions {
@@ -12,10 +114,332 @@ impl Default for TmpOptions {
         TmpOptions {
             sep: b'\x1E',
             eol: b'\x1D',
+            quote: b'"',
+            out_delim: b",".to_vec(),
+            out_eol: b"\n".to_vec(),
+            double_backslash: false,
+            final_newline: None,
+            pad_to: None,
+            quote_columns: None,
+            escapes: None,
+            strict_eof: false,
+            case: None,
+            collapse_double_quotes: false,
+            header: None,
+            repair_log: false,
+            reescape_delims: true,
+            reescape_count: 1,
+            quote_empty: true,
+            extra_columns: None,
+            escape_only: false,
+            footer: None,
+            column_widths: false,
+            double_quotes: false,
+            source_quotes: false,
+            escape: Some(b'\\'),
+            record_prefix: None,
+            write_size: WRITE_SIZE,
+            quote_toggle: false,
+            column_order: None,
+            strict_column_order: false,
+            dedup: None,
+            ignore_trailing_sep: false,
+            write_bom: false,
+            length_prefixed: false,
+            null_token: None,
+            null_output: Vec::new(),
+            column_types: None,
+        }
+    }
+}
+
+impl TmpOptions {
+    /// Closes a field and opens the next one of the row: `","` by default.
+    #[inline(always)]
+    pub(crate) fn field_break(&self) -> Vec<u8> {
+        [&[self.quote][..], &self.out_delim, &[self.quote]].concat()
+    }
+
+    /// Closes the last field of a row and opens the next row: `"\n"` by default, with the
+    /// `record_prefix` before the opening quote.
+    #[inline(always)]
+    pub(crate) fn row_break(&self) -> Vec<u8> {
+        let prefix = self.record_prefix.as_slice();
+        [&[self.quote][..], &self.out_eol, prefix, &[self.quote]].concat()
+    }
+
+    /// A raw `quote` inside a field: `\"` by default, `""` with `double_quotes`.
+    #[inline(always)]
+    pub(crate) fn escaped_quote(&self) -> [u8; 2] {
+        match self.double_quotes {
+            true => [self.quote, self.quote],
+            false => [b'\\', self.quote],
+        }
+    }
+
+    /// The break, `"delim"` or `"newline"`, that is also the `escape` byte, if any.
+    ///
+    /// The backslash lookback would then take every such break for an escape, or the other
+    /// way around, and misplace quotes without any error.
+    pub(crate) fn escape_collision(&self) -> Option<&'static str> {
+        let escape = self.escape?;
+        [("delim", self.sep), ("newline", self.eol)]
+            .into_iter()
+            .find_map(|(name, byte)| (byte == escape).then_some(name))
+    }
+
+    /// The options for Postgres `COPY ... CSV`, applied over `self`.
+    ///
+    /// Fields are quoted only when they hold a delimiter, quote or newline, so an empty
+    /// field is read as NULL, and quotes are doubled. The stream gets no quotes of its own.
+    pub(crate) fn copy_compatible(self) -> TmpOptions {
+        TmpOptions {
+            quote_columns: Some(Vec::new()),
+            double_quotes: true,
+            ..self
+        }
+    }
+
+    /// An empty field after a closed one: `,""` by default.
+    #[inline(always)]
+    pub(crate) fn empty_field(&self) -> Vec<u8> {
+        [&self.out_delim[..], &[self.quote, self.quote]].concat()
+    }
+
+    /// Whether a backslash ending a field gets doubled so it cannot escape the closing quote.
+    ///
+    /// Not needed when every backslash is already doubled or rewritten by `escapes`, nor
+    /// when quotes are doubled and backslashes escape nothing, and never done with
+    /// `reescape_delims` off or without an `escape` byte.
+    #[inline(always)]
+    pub(crate) fn reescapes(&self) -> bool {
+        self.reescape_delims
+            && self.escape.is_some()
+            && !self.double_quotes
+            && !self.double_backslash
+            && !self.escapes.as_ref().is_some_and(|e| e.contains(b'\\'))
+    }
+
+    /// Writes the backslashes of one re-escape, as set by `reescape_count`.
+    #[inline(always)]
+    pub(crate) fn push_reescape(&self, buf: &mut Vec<u8>) {
+        buf.resize(buf.len() + self.reescape_count, b'\\');
+    }
+
+    /// Whether a quote after an odd run of backslashes is already escaped in the source,
+    /// and so written as it is.
+    ///
+    /// Only when source backslashes pass through as escapes, as for `reescapes`, and
+    /// quotes are not escaped by doubling instead.
+    #[inline(always)]
+    pub(crate) fn backslash_escapes_quotes(&self) -> bool {
+        self.reescapes() && !self.collapse_double_quotes
+    }
+
+    /// Whether these options need the buffered driver, which holds whole fields.
+    #[inline(always)]
+    pub(crate) fn buffers(&self) -> bool {
+        self.quote_columns.is_some()
+            || !self.quote_empty
+            || self.column_widths
+            || self.column_order.is_some()
+            || self.dedup.is_some()
+            || self.length_prefixed
+            || self.null_token.is_some()
+            || self.column_types.is_some()
+    }
+
+    /// The type of column `col` by `column_types`, if set.
+    #[inline(always)]
+    pub(crate) fn column_type(&self, col: usize) -> Option<ColType> {
+        let types = self.column_types.as_ref()?;
+        Some(types.get(col).copied().unwrap_or(ColType::Auto))
+    }
+
+    /// Whether fields of column `col` are quoted; all are unless `quote_columns` or
+    /// `column_types` is set.
+    #[inline(always)]
+    pub(crate) fn quotes_column(&self, col: usize) -> bool {
+        if let Some(col_type) = self.column_type(col) {
+            return col_type == ColType::Text;
+        }
+        self.quote_columns
+            .as_ref()
+            .is_none_or(|cols| cols.contains(&col))
+    }
+
+    /// Whether `field` of column `col` is written quoted.
+    ///
+    /// A field that would break its row unquoted always is; otherwise `quote_columns`
+    /// decides, and `quote_empty` then for an empty field.
+    #[inline(always)]
+    pub(crate) fn quotes_field(&self, col: usize, field: &[u8]) -> bool {
+        let quoted = self.quotes_column(col) && (self.quote_empty || !field.is_empty());
+        quoted || needs_quotes(field, self)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) enum Case {
+    Upper,
+    Lower,
+}
+
+impl Case {
+    pub(crate) fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "upper" => Some(Case::Upper),
+            "lower" => Some(Case::Lower),
+            _ => None,
+        }
+    }
+
+    #[inline(always)]
+    fn apply(self, content: &mut [u8]) {
+        match self {
+            Case::Upper => content.make_ascii_uppercase(),
+            Case::Lower => content.make_ascii_lowercase(),
+        }
+    }
+}
+
+/// How the fields of a column of `column_types` are quoted.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) enum ColType {
+    /// Always quoted.
+    Text,
+    /// Never quoted; a field that would break its row unquoted fails the run.
+    Number,
+    /// Quoted only when it would break its row unquoted.
+    Auto,
+}
+
+impl ColType {
+    pub(crate) fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "text" => Some(ColType::Text),
+            "number" => Some(ColType::Number),
+            "auto" => Some(ColType::Auto),
+            _ => None,
+        }
+    }
+}
+
+/// A computed field of `extra_columns`.
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) enum ExtraCol {
+    /// The same value on every row, such as the source file name.
+    Literal(Vec<u8>),
+    /// The 1-based number of the row, not counting a `header`.
+    RowNumber,
+}
+
+impl ExtraCol {
+    /// The raw value of this column on row `row`, escaped when written like any field.
+    fn value(&self, row: u64) -> Cow<'_, [u8]> {
+        match self {
+            ExtraCol::Literal(value) => Cow::Borrowed(value),
+            ExtraCol::RowNumber => Cow::Owned(row.to_string().into_bytes()),
+        }
+    }
+}
+
+/// Appends the `extra_columns` of row `row` after its last field, each a quoted field.
+///
+/// With `open` the last field is still open and the last extra one is left open in
+/// turn, for the row break of the streaming batch to close.
+#[inline(always)]
+pub(crate) fn extend_extra_columns(buf: &mut Vec<u8>, row: u64, open: bool, opts: &TmpOptions) {
+    let Some(extra) = &opts.extra_columns else {
+        return;
+    };
+
+    for column in extra {
+        if open {
+            buf.push(opts.quote);
+        }
+        buf.extend_from_slice(&opts.out_delim);
+        quote_field(&column.value(row), buf, opts);
+        if open {
+            buf.pop();
+        }
+    }
+}
+
+/// The escapes keeping every record on one line: a newline or carriage return in content
+/// becomes the two characters `\n` or `\r`.
+pub(crate) const FLATTENED_NEWLINES: [(u8, &[u8]); 2] = [(b'\n', b"\\n"), (b'\r', b"\\r")];
+
+/// A dispatch table from content bytes to the sequences written in their place.
+///
+/// Escaped bytes are found with `memchr` for up to three of them and a table lookup
+/// per byte beyond that. `sep`, `eol` and `quote` are handled by the batch itself and
+/// must not be part of it.
+#[derive(Debug)]
+pub(crate) struct Escapes {
+    needles: Vec<u8>,
+    table: [Option<Box<[u8]>>; 256],
+}
+
+impl Escapes {
+    /// Builds the table; a byte given twice keeps its last replacement.
+    pub(crate) fn new<I: IntoIterator<Item = (u8, Vec<u8>)>>(escapes: I) -> Self {
+        let mut table = std::array::from_fn(|_| None);
+        let mut needles = Vec::new();
+        for (byte, replacement) in escapes {
+            let slot: &mut Option<Box<[u8]>> = &mut table[byte as usize];
+            if slot.replace(replacement.into_boxed_slice()).is_none() {
+                needles.push(byte);
+            }
+        }
+        Escapes { needles, table }
+    }
+
+    #[inline(always)]
+    pub(crate) fn contains(&self, byte: u8) -> bool {
+        self.table[byte as usize].is_some()
+    }
+
+    /// Appends `content` to `buf`, passing the runs between escaped bytes to `extend`.
+    #[inline(always)]
+    fn extend<F: FnMut(&mut Vec<u8>, &[u8])>(
+        &self,
+        buf: &mut Vec<u8>,
+        content: &[u8],
+        mut extend: F,
+    ) {
+        let mut idx = 0;
+        let mut escape = |buf: &mut Vec<u8>, pos: usize| {
+            extend(buf, &content[idx..pos]);
+            buf.extend_from_slice(
+                self.table[content[pos] as usize]
+                    .as_deref()
+                    .unwrap_or_default(),
+            );
+            idx = pos + 1;
+        };
+        match self.needles[..] {
+            [] => {}
+            [a] => memchr_iter(a, content).for_each(|pos| escape(buf, pos)),
+            [a, b] => memchr2_iter(a, b, content).for_each(|pos| escape(buf, pos)),
+            [a, b, c] => memchr3_iter(a, b, c, content).for_each(|pos| escape(buf, pos)),
+            _ => (0..content.len())
+                .filter(|&pos| self.contains(content[pos]))
+                .for_each(|pos| escape(buf, pos)),
         }
+        extend(buf, &content[idx..]);
     }
 }
 
+/// Whether `byte` is likely to occur naturally in field content.
+///
+/// The quoting is only safe when `sep` and `eol` never appear inside fields, which bcp
+/// exports guarantee by using control bytes. Printable ASCII and whitespace give no such
+/// guarantee: every occurrence becomes a field or row break.
+pub(crate) fn is_common_byte(byte: u8) -> bool {
+    byte.is_ascii_graphic() || matches!(byte, b' ' | b'\t' | b'\n' | b'\r')
+}
+
 #[inline(always)]
 pub(crate) fn exorcize_csv<R, W>(
     input: R,
@@ -23,23 +447,101 @@ pub(crate) fn exorcize_csv<R, W>(
     size: u64,
     chunk_size: usize,
     opts: &TmpOptions,
+) -> io::Result<Stats>
+where
+    R: Read,
+    W: Write,
+{
+    drive(input, output, size, chunk_size, opts, false)
+}
+
+/// Counterpart of `exorcize_csv` for an input of unknown length, such as a pipe.
+///
+/// When reading fails, e.g. on an interrupt, the rows fixed so far are closed as if the
+/// input had ended there and flushed to `output` before the error is returned. A stream
+/// consumer can then use what it already received as a complete CSV.
+pub(crate) fn exorcize_stream<R, W>(
+    input: R,
+    output: W,
+    chunk_size: usize,
+    opts: &TmpOptions,
+) -> io::Result<Stats>
+where
+    R: Read,
+    W: Write,
+{
+    drive(input, output, UNKNOWN_SIZE, chunk_size, opts, true)
+}
+
+#[inline(always)]
+fn drive<R, W>(
+    input: R,
+    output: W,
+    size: u64,
+    chunk_size: usize,
+    opts: &TmpOptions,
+    close_on_read_error: bool,
+) -> io::Result<Stats>
+where
+    R: Read,
+    W: Write,
+{
+    let mut stats = Stats::default();
+    stats.repairs = opts.repair_log.then(Vec::new);
+    let result = run(
+        input,
+        output,
+        size,
+        chunk_size,
+        opts,
+        close_on_read_error,
+        &mut stats,
+    );
+    match result {
+        Ok(()) => Ok(stats),
+        Err(e) => Err(Partial::attach(stats, e)),
+    }
+}
+
+/// The body of the drivers, leaving `stats` with the caller so a failure keeps them.
+fn run<R, W>(
+    input: R,
+    output: W,
+    size: u64,
+    chunk_size: usize,
+    opts: &TmpOptions,
+    close_on_read_error: bool,
+    stats: &mut Stats,
 ) -> io::Result<()>
 where
     R: Read,
     W: Write,
 {
     let mut reader = BufReader::new(input);
-    let mut writer = BufWriter::new(output);
+    let mut writer = BufWriter::with_capacity(opts.write_size, output);
 
     let mut buf = vec![0u8; chunk_size];
-    let mut out = Vec::with_capacity(chunk_size * 3);
+    let mut out = Vec::new();
+    let mut prev_byte = None;
+    let mut backslashes = 0;
 
     if size > 0 {
-        out.push(b'"');
+        write_header(&mut out, opts);
+        out.extend(opts.record_prefix);
+        out.push(opts.quote);
     }
 
     loop {
-        let read = reader.read(&mut buf)?;
+        let read = match reader.read(&mut buf) {
+            Ok(read) => read,
+            Err(e) if close_on_read_error => {
+                handle_closing(&mut out, &mut writer, opts, stats, backslashes)?;
+                stats.bytes_written += out.len() as u64;
+                stats.finish();
+                return Err(e);
+            }
+            Err(e) => return Err(e),
+        };
 
         if read == 0 {
             break;
@@ -47,128 +549,1525 @@ where
 
         // write before to truncate the last
         writer.write_all(&out)?;
+        stats.bytes_written += out.len() as u64;
 
         // clear buffer
         out.clear();
+        out.reserve_exact(out_capacity(read, stats));
 
-        exorcize_csv_batch(&buf[..read], &mut out, opts.sep, opts.eol)?;
+        stats.observe(&buf[..read]);
+        extend_batch(&buf[..read], prev_byte, backslashes, &mut out, opts, stats);
+        prev_byte = Some(buf[read - 1]);
+        backslashes = trailing_backslashes(backslashes, &buf[..read]);
     }
+    check_eof(backslashes, opts, stats)?;
 
-    handle_closing(&mut out, &mut writer)
+    handle_closing(&mut out, &mut writer, opts, stats, backslashes)?;
+    stats.bytes_written += out.len() as u64;
+    stats.finish();
+    Ok(())
 }
 
-#[inline(always)]
-fn exorcize_csv_batch(haystack: &[u8], buf: &mut Vec<u8>, sep: u8, eol: u8) -> io::Result<()> {
-    let mut idx = 0;
-    for pos in memchr3_iter(sep, eol, b'"', haystack) {
-        buf.extend_from_slice(&haystack[idx..pos]);
+/// The output of `exorcize_csv` pulled one buffer at a time instead of written.
+///
+/// Each buffer is what the driver hands its writer per batch, before any coalescing, and
+/// the closing bytes of the last row come as the last one. Empty buffers are skipped.
+pub(crate) struct Chunks<R> {
+    reader: BufReader<R>,
+    buf: Vec<u8>,
+    feed: Feed,
+    done: bool,
+}
 
-        match haystack[pos] {
-            c if c == sep => {
-                if pos > 0 && haystack[pos - 1] == b'\\' {
-                    buf.push(b'\\');
-                }
-                buf.extend_from_slice(b"\",\"");
-            }
-            c if c == eol => {
-                if pos > 0 && haystack[pos - 1] == b'\\' {
-                    buf.push(b'\\');
+impl<R: Read> Chunks<R> {
+    pub(crate) fn new(input: R, size: u64, chunk_size: usize, opts: TmpOptions) -> Self {
+        Chunks {
+            reader: BufReader::new(input),
+            buf: vec![0u8; chunk_size],
+            feed: Feed::new(size, opts),
+            done: false,
+        }
+    }
+
+    /// Returns the next buffer of output, or `None` after the last one.
+    ///
+    /// A failure carries the stats so far, as with `exorcize_csv`, and ends the output.
+    pub(crate) fn next_chunk(&mut self) -> io::Result<Option<Vec<u8>>> {
+        while !self.done {
+            match self.next_batch() {
+                Ok(out) if out.is_empty() => {}
+                Ok(out) => return Ok(Some(out)),
+                Err(e) => {
+                    self.done = true;
+                    return Err(Partial::attach(self.feed.take_stats(), e));
                 }
-                buf.extend_from_slice(b"\"\n\"");
             }
-            _ => buf.extend_from_slice(b"\\\""),
         }
+        Ok(None)
+    }
 
-        idx = pos + 1;
+    fn next_batch(&mut self) -> io::Result<Vec<u8>> {
+        let read = self.reader.read(&mut self.buf)?;
+        if read == 0 {
+            self.done = true;
+            return self.feed.finish();
+        }
+        Ok(self.feed.push(&self.buf[..read]))
     }
+}
 
-    if idx < haystack.len() {
-        buf.extend_from_slice(&haystack[idx..]);
+/// The batch loop of the drivers, handed its input by the caller instead of reading it.
+///
+/// The output of a batch is held back until the next one, or the closing, comes: the
+/// closing of a final `eol` truncates the row break it wrote, as in the drivers.
+pub(crate) struct Feed {
+    out: Vec<u8>,
+    prev_byte: Option<u8>,
+    backslashes: usize,
+    opts: TmpOptions,
+    stats: Stats,
+}
+
+impl Feed {
+    /// `size` is the input size, or `UNKNOWN_SIZE`; only whether it is 0 matters.
+    pub(crate) fn new(size: u64, opts: TmpOptions) -> Self {
+        let mut stats = Stats::default();
+        stats.repairs = opts.repair_log.then(Vec::new);
+        let mut out = Vec::new();
+        if size > 0 {
+            write_header(&mut out, &opts);
+            out.extend(opts.record_prefix);
+            out.push(opts.quote);
+        }
+        Feed {
+            out,
+            prev_byte: None,
+            backslashes: 0,
+            opts,
+            stats,
+        }
+    }
+
+    /// Fixes `batch`, returning the output held back from the batch before it. An empty
+    /// batch changes nothing.
+    pub(crate) fn push(&mut self, batch: &[u8]) -> Vec<u8> {
+        if batch.is_empty() {
+            return Vec::new();
+        }
+        let (opts, stats) = (&self.opts, &mut self.stats);
+        let capacity = out_capacity(batch.len(), stats);
+        let out = std::mem::replace(&mut self.out, Vec::with_capacity(capacity));
+        stats.bytes_written += out.len() as u64;
+
+        stats.observe(batch);
+        extend_batch(
+            batch,
+            self.prev_byte,
+            self.backslashes,
+            &mut self.out,
+            opts,
+            stats,
+        );
+        self.prev_byte = batch.last().copied();
+        self.backslashes = trailing_backslashes(self.backslashes, batch);
+        out
+    }
+
+    /// Closes the last row, returning the rest of the output. Later batches are not
+    /// expected.
+    pub(crate) fn finish(&mut self) -> io::Result<Vec<u8>> {
+        let (opts, stats) = (&self.opts, &mut self.stats);
+        check_eof(self.backslashes, opts, stats)?;
+        let mut out = std::mem::take(&mut self.out);
+        close_out(&mut out, opts, stats, self.backslashes);
+        stats.bytes_written += out.len() as u64;
+        stats.finish();
+        Ok(out)
+    }
+
+    /// The stats so far, leaving default ones.
+    pub(crate) fn take_stats(&mut self) -> Stats {
+        std::mem::take(&mut self.stats)
+    }
+
+    /// What the next batch starts from.
+    #[cfg(any(test, feature = "debug"))]
+    pub(crate) fn boundary(&self) -> Boundary {
+        Boundary {
+            offset: self.stats.bytes_read,
+            prev_byte: self.prev_byte,
+            backslashes: self.backslashes,
+            held: self.out.clone(),
+        }
     }
-    Ok(())
 }
 
-#[inline(always)]
-fn handle_closing<W>(out: &mut Vec<u8>, writer: &mut BufWriter<W>) -> io::Result<()>
+/// The state `Feed` carries over a batch boundary, to pin down bugs tied to a chunk size.
+#[cfg(any(test, feature = "debug"))]
+#[derive(Debug, PartialEq)]
+pub(crate) struct Boundary {
+    /// The input bytes fed so far.
+    pub(crate) offset: u64,
+    pub(crate) prev_byte: Option<u8>,
+    /// The length of the run of backslashes the input so far ends with.
+    pub(crate) backslashes: usize,
+    /// The output of the last batch, held back as the next one may change its end.
+    pub(crate) held: Vec<u8>,
+}
+
+/// Copies `input` to `output` with only its raw quotes escaped, for a file whose rows and
+/// fields are already split by the output bytes.
+///
+/// `sep` and `eol` play no part: the last bytes of `out_delim` and `out_eol` are taken as
+/// the structure, left untouched and only counted. Fields are not quoted, so already
+/// quoted ones get their quotes escaped too.
+pub(crate) fn escape_only<R, W>(
+    input: R,
+    output: W,
+    chunk_size: usize,
+    opts: &TmpOptions,
+) -> io::Result<Stats>
+where
+    R: Read,
+    W: Write,
+{
+    let mut stats = Stats::default();
+    stats.repairs = opts.repair_log.then(Vec::new);
+    match run_escape_only(input, output, chunk_size, opts, &mut stats) {
+        Ok(()) => Ok(stats),
+        Err(e) => Err(Partial::attach(stats, e)),
+    }
+}
+
+/// The body of `escape_only`, leaving `stats` with the caller so a failure keeps them.
+fn run_escape_only<R, W>(
+    input: R,
+    output: W,
+    chunk_size: usize,
+    opts: &TmpOptions,
+    stats: &mut Stats,
+) -> io::Result<()>
 where
+    R: Read,
     W: Write,
 {
-    let len = out.len();
-    if len > 1 {
-        match out[len - 1] {
-            b'"' => {
-                if out[len - 2] == b'\n' {
-                    out.pop();
+    let mut reader = BufReader::new(input);
+    let mut writer = BufWriter::with_capacity(opts.write_size, output);
+
+    let mut buf = vec![0u8; chunk_size];
+    let mut out = Vec::with_capacity(chunk_size * 2);
+    let row_end = *opts.out_eol.last().unwrap_or(&b'\n');
+    let field_end = *opts.out_delim.last().unwrap_or(&b',');
+
+    loop {
+        let read = reader.read(&mut buf)?;
+
+        if read == 0 {
+            break;
+        }
+
+        let haystack = &buf[..read];
+        let base = stats.bytes_read;
+        stats.observe(haystack);
+
+        let mut idx = 0;
+        for pos in memchr3_iter(field_end, row_end, opts.quote, haystack) {
+            out.extend_from_slice(&haystack[idx..pos]);
+            match haystack[pos] {
+                c if c == opts.quote => {
+                    stats.on_quote(false);
+                    out.extend_from_slice(&opts.escaped_quote());
+                    let offset = base + pos as u64;
+                    stats.repair(offset, stats.row_seps(), Repair::EscapedQuote);
+                }
+                c if c == field_end => {
+                    out.push(c);
+                    stats.on_sep();
+                }
+                c => {
+                    out.push(c);
+                    stats.on_eol();
                 }
             }
-            b'\n' => {}
-            _ => out.push(b'"'),
+            idx = pos + 1;
         }
+        if idx < haystack.len() {
+            out.extend_from_slice(&haystack[idx..]);
+            stats.on_content();
+        }
+
+        writer.write_all(&out)?;
+        stats.bytes_written += out.len() as u64;
+        out.clear();
     }
 
-    writer.write_all(&out)?;
     writer.flush()?;
+    stats.finish();
     Ok(())
 }
 
-#[cfg(test)]
-mod tests {
-    use super::*;
-    use io::{Seek, SeekFrom};
-    use rstest::*;
-    use std::io::Cursor;
+/// Returns the room to reserve for fixing `read` more bytes, from the expansion of the
+/// batches in `stats`.
+///
+/// A fixed worst case would hold three times every chunk for input that barely grows;
+/// sizing by what was seen keeps the buffer close to the real output and grows it once
+/// when the input turns out to be quote-heavy.
+pub(crate) fn out_capacity(read: usize, stats: &Stats) -> usize {
+    let expansion = match stats.bytes_read {
+        0 => FIRST_EXPANSION,
+        bytes_read => stats.bytes_written as f64 / bytes_read as f64 * EXPANSION_MARGIN,
+    };
+    (read as f64 * expansion) as usize
+}
 
-    #[rstest]
-    fn test_default_tmp_options() {
-        let opts = TmpOptions::default();
-        assert_eq!(opts.sep, b'\x1E');
-        assert_eq!(opts.eol, b'\x1D');
+/// Returns the number of backslashes ending the input once `chunk` follows `count` of them.
+#[inline(always)]
+pub(crate) fn trailing_backslashes(count: usize, chunk: &[u8]) -> usize {
+    match chunk.iter().rposition(|&b| b != b'\\') {
+        Some(pos) => chunk.len() - pos - 1,
+        None => count + chunk.len(),
     }
+}
 
-    #[rstest]
-    fn test_exorcize_csv_empty_input() {
-        let input = Cursor::new(Vec::new());
-        let output = Vec::new();
-        let opts = TmpOptions::default();
-
-        let result = exorcize_csv(input, output, 0, 1024, &opts);
-        assert!(result.is_ok());
+/// With `strict_eof`, refuses an input ending with an odd run of backslashes.
+///
+/// An even run is a complete escaped backslash; an odd one escapes a byte that never came.
+/// With `quote_toggle` an input ending within a quoted region is refused, whatever
+/// `strict_eof`: the rest of the input would have been taken as one field.
+pub(crate) fn check_eof(backslashes: usize, opts: &TmpOptions, stats: &Stats) -> io::Result<()> {
+    if opts.strict_eof && backslashes % 2 == 1 {
+        let msg = "input ends with a dangling backslash escape, it may be truncated";
+        return Err(io::Error::new(io::ErrorKind::InvalidData, msg));
     }
+    if let Some(offset) = stats.open_quote().filter(|_| opts.quote_toggle) {
+        let msg = format!("the quote at byte {offset} is never closed; quotes must balance");
+        return Err(io::Error::new(io::ErrorKind::InvalidData, msg));
+    }
+    Ok(())
+}
 
-    #[rstest]
-    fn test_exorcize_csv_basic() {
-        let input_data = b"field1\x1Efield2\x1Dfield3";
-        let input = Cursor::new(input_data.to_vec());
-        let mut output = Cursor::new(Vec::new());
-        let opts = TmpOptions::default();
-
-        let result = exorcize_csv(input, &mut output, input_data.len() as u64, 1024, &opts);
-        assert!(result.is_ok());
-
-        let result = output.seek(SeekFrom::Start(0));
-        assert!(result.is_ok());
+/// Returns the exact length `exorcize_csv` would write for `input`, without writing it.
+///
+/// The fix runs into a sink, so every expansion rule is accounted for; the result holds
+/// for the same `opts` and `chunk_size` only.
+pub(crate) fn output_size<R: Read>(
+    input: R,
+    size: u64,
+    chunk_size: usize,
+    opts: &TmpOptions,
+) -> io::Result<u64> {
+    Ok(exorcize_csv(input, io::sink(), size, chunk_size, opts)?.bytes_written)
+}
 
-        let mut result = String::new();
-        output.read_to_string(&mut result).unwrap();
-        let expected_output = "\"field1\",\"field2\"\n\"field3\"";
-        assert_eq!(result, expected_output);
+/// Counts the rows, fields and raw quotes of `input` as `exorcize_csv` reports them,
+/// without fixing it.
+///
+/// Each chunk is only scanned for `sep`, `eol` and `quote`: nothing is quoted or escaped
+/// and no output is ever buffered, so `bytes_written` stays 0. The counts are those of
+/// the plain streaming fix, whatever options only shape its output.
+pub(crate) fn count_only<R: Read>(
+    input: R,
+    chunk_size: usize,
+    opts: &TmpOptions,
+) -> io::Result<Stats> {
+    let mut stats = Stats::default();
+    match run_count_only(input, chunk_size, opts, &mut stats) {
+        Ok(()) => Ok(stats),
+        Err(e) => Err(Partial::attach(stats, e)),
     }
+}
 
-    #[rstest]
-    #[case(
-        b"field1\\\x1Efield2\\\x1Dfield3",
-        "\"field1\\\\\",\"field2\\\\\"\n\"field3\""
-    )]
-    fn test_exorcize_csv_with_escape_characters(#[case] data: &[u8], #[case] expected: &str) {
-        let input = Cursor::new(data.to_vec());
-        let mut output = Cursor::new(Vec::new());
-        let opts = TmpOptions::default();
+/// The body of `count_only`, leaving `stats` with the caller so a failure keeps them.
+fn run_count_only<R: Read>(
+    input: R,
+    chunk_size: usize,
+    opts: &TmpOptions,
+    stats: &mut Stats,
+) -> io::Result<()> {
+    let mut reader = BufReader::new(input);
+    let mut buf = vec![0u8; chunk_size];
+    let mut backslashes = 0;
 
-        let result = exorcize_csv(input, &mut output, data.len() as u64, 1024, &opts);
-        assert!(result.is_ok());
+    loop {
+        let read = reader.read(&mut buf)?;
 
-        let result = output.seek(SeekFrom::Start(0));
-        assert!(result.is_ok());
+        if read == 0 {
+            break;
+        }
+
+        let haystack = &buf[..read];
+        stats.observe(haystack);
+        backslashes = trailing_backslashes(backslashes, haystack);
+
+        let mut idx = 0;
+        for pos in memchr3_iter(opts.sep, opts.eol, opts.quote, haystack) {
+            if pos > idx {
+                stats.on_content();
+            }
+            match haystack[pos] {
+                c if c == opts.sep => stats.on_sep(),
+                c if c == opts.eol => stats.on_eol(),
+                _ => {
+                    stats.quotes += 1;
+                    stats.on_content();
+                }
+            }
+            idx = pos + 1;
+        }
+        if idx < read {
+            stats.on_content();
+        }
+    }
+    check_eof(backslashes, opts, stats)?;
+
+    stats.finish();
+    Ok(())
+}
+
+/// Whether the first `chunk_size` bytes of `input` hold a `sep` or `eol`.
+///
+/// Those bytes mark a raw bcp dump; a fixed file only uses the output bytes. This is a
+/// heuristic on the first chunk only: nothing after it is read.
+pub(crate) fn is_broken<R: Read>(
+    input: R,
+    chunk_size: usize,
+    opts: &TmpOptions,
+) -> io::Result<bool> {
+    let mut head = Vec::with_capacity(chunk_size);
+    input.take(chunk_size as u64).read_to_end(&mut head)?;
+    Ok(memchr2(opts.sep, opts.eol, &head).is_some())
+}
+
+/// A row whose field count is not the expected one, found by `first_ragged_row`.
+#[derive(Debug, PartialEq)]
+pub(crate) struct RaggedRow {
+    /// The 0-based index of the row.
+    pub(crate) row: u64,
+    /// The input offset of its first byte.
+    pub(crate) offset: u64,
+    pub(crate) fields: u64,
+    /// The field count it was checked against.
+    pub(crate) expected: u64,
+}
+
+/// Scans `input` for the first row without exactly `expected_fields` fields, stopping there.
+///
+/// Only separators are counted, nothing is written or buffered beyond one chunk. A
+/// terminator as the very last byte ends the last row instead of starting an empty one.
+/// When `expected_fields` is `None` the first row is a header: its field count is the one
+/// every later row must have.
+pub(crate) fn first_ragged_row<R: Read>(
+    input: R,
+    expected_fields: Option<u64>,
+    chunk_size: usize,
+    opts: &TmpOptions,
+) -> io::Result<Option<RaggedRow>> {
+    let mut expected_fields = expected_fields;
+    let mut ragged = None;
+    scan_rows(input, chunk_size, opts, |row, offset, fields| {
+        let expected = *expected_fields.get_or_insert(fields);
+        if fields != expected {
+            ragged = Some(RaggedRow {
+                row,
+                offset,
+                fields,
+                expected,
+            });
+        }
+        ragged.is_none()
+    })?;
+    Ok(ragged)
+}
+
+/// The rows of an input whose field count differs from the expected one, found by
+/// `ragged_rows`.
+#[derive(Debug, Default, PartialEq)]
+pub(crate) struct RowWidths {
+    pub(crate) rows: u64,
+    /// The field count rows were checked against, `None` for an empty input.
+    pub(crate) expected: Option<u64>,
+    /// The 0-based index of every row with another field count, in input order.
+    pub(crate) ragged: Vec<u64>,
+}
+
+/// Scans the whole of `input` for the rows without exactly `expected_fields` fields.
+///
+/// Rows are delimited as `first_ragged_row` does, and when `expected_fields` is `None`
+/// the first row's field count is the expected one. Only the indices of ragged rows are
+/// kept.
+pub(crate) fn ragged_rows<R: Read>(
+    input: R,
+    expected_fields: Option<u64>,
+    chunk_size: usize,
+    opts: &TmpOptions,
+) -> io::Result<RowWidths> {
+    let mut widths = RowWidths {
+        expected: expected_fields,
+        ..RowWidths::default()
+    };
+    scan_rows(input, chunk_size, opts, |row, _, fields| {
+        if fields != *widths.expected.get_or_insert(fields) {
+            widths.ragged.push(row);
+        }
+        widths.rows += 1;
+        true
+    })?;
+    Ok(widths)
+}
+
+/// Calls `on_row` with the 0-based index, start offset and field count of each row of
+/// `input`, until it returns `false`.
+///
+/// A row cut by the end of a read is carried on to the next one, so counts do not depend on
+/// `chunk_size`.
+fn scan_rows<R: Read>(
+    input: R,
+    chunk_size: usize,
+    opts: &TmpOptions,
+    mut on_row: impl FnMut(u64, u64, u64) -> bool,
+) -> io::Result<()> {
+    let mut reader = BufReader::new(input);
+    let mut buf = vec![0u8; chunk_size];
+    let (mut row, mut start, mut seps) = (0, 0, 0);
+    let mut base = 0;
+
+    loop {
+        let read = reader.read(&mut buf)?;
+
+        if read == 0 {
+            break;
+        }
+
+        let haystack = &buf[..read];
+        for pos in memchr2_iter(opts.sep, opts.eol, haystack) {
+            if haystack[pos] == opts.sep {
+                seps += 1;
+                continue;
+            }
+            if !on_row(row, start, seps + 1) {
+                return Ok(());
+            }
+            (row, start, seps) = (row + 1, base + pos as u64 + 1, 0);
+        }
+        base += read as u64;
+    }
+
+    if start < base {
+        on_row(row, start, seps + 1);
+    }
+    Ok(())
+}
+
+/// Fixes one batch of raw input into a new buffer.
+///
+/// `prev_byte` is the last byte of the preceding batch, if any: it is the only state a
+/// batch needs from its predecessor, to re-escape a backslash split from the `sep` or
+/// `eol` after it. A backslash `prev_byte` is taken as a lone one, not the end of a longer
+/// run. Row counters, and with them `pad_to` and row numbers, start from a fresh row, and with
+/// `collapse_double_quotes` a quote run continued from `prev_byte` pairs up anew. With
+/// `source_quotes` the batch starts outside of any quoted region.
+pub(crate) fn exorcize_csv_batch(
+    input: &[u8],
+    prev_byte: Option<u8>,
+    opts: &TmpOptions,
+) -> Vec<u8> {
+    let mut buf = Vec::with_capacity(input.len() + input.len() / 2);
+    let backslashes = usize::from(prev_byte == Some(b'\\'));
+    extend_batch(
+        input,
+        prev_byte,
+        backslashes,
+        &mut buf,
+        opts,
+        &mut Stats::default(),
+    );
+    buf
+}
+
+/// Fixes one batch of raw input into a new buffer, as `exorcize_csv_batch` does, following
+/// the run of `backslashes` the preceding batches ended with rather than their last byte.
+///
+/// Returns the fixed batch and the run this one ends with, to pass on to the next, so a
+/// run split across batches escapes as it would in one. No other state is carried.
+pub(crate) fn exorcize_csv_batch_after(
+    input: &[u8],
+    backslashes: usize,
+    opts: &TmpOptions,
+) -> (Vec<u8>, usize) {
+    let mut buf = Vec::with_capacity(input.len() + input.len() / 2);
+    let prev_byte = (backslashes > 0).then_some(b'\\');
+    extend_batch(
+        input,
+        prev_byte,
+        backslashes,
+        &mut buf,
+        opts,
+        &mut Stats::default(),
+    );
+    (buf, trailing_backslashes(backslashes, input))
+}
+
+/// Appends the fixed `haystack` to `buf`, the core of `exorcize_csv_batch`.
+///
+/// The sequential driver reuses `buf` across batches and threads one `stats` through
+/// them, which also carries the row state across batch boundaries. `backslashes` is the
+/// length of the backslash run ending the preceding batches.
+#[inline(always)]
+fn extend_batch(
+    haystack: &[u8],
+    prev_byte: Option<u8>,
+    backslashes: usize,
+    buf: &mut Vec<u8>,
+    opts: &TmpOptions,
+    stats: &mut Stats,
+) {
+    let (sep, eol, quote) = (opts.sep, opts.eol, opts.quote);
+    let (field_break, row_break) = (opts.field_break(), opts.row_break());
+    let escaped_quote = opts.escaped_quote();
+    // doubled backslashes can no longer escape the closing quote
+    let reescape = opts.reescapes() && opts.reescape_count > 0;
+    let backslash_quotes = opts.backslash_escapes_quotes();
+    let follows = |pos: usize, byte: u8| match pos {
+        0 => prev_byte == Some(byte),
+        _ => haystack[pos - 1] == byte,
+    };
+    // a backslash escapes the byte after it unless it is itself escaped, so only an odd
+    // run leaves a `sep` or `eol` escaped
+    let escaped = |pos: usize| trailing_backslashes(backslashes, &haystack[..pos]) % 2 == 1;
+    // the input offset of `haystack`, which was already observed; a lone batch starts at 0
+    let base = stats.bytes_read.saturating_sub(haystack.len() as u64);
+
+    if stats.take_held_sep() {
+        match haystack.first() == Some(&eol) {
+            true => stats.repair(base - 1, stats.row_seps(), Repair::DroppedTrailingSep),
+            false => write_sep(buf, &field_break, base - 1, opts, stats),
+        }
+    }
+
+    let mut idx = 0;
+    for pos in memchr3_iter(sep, eol, quote, haystack) {
+        let seps = stats.row_seps();
+        let kept = is_kept(opts, seps);
+        let offset = base + pos as u64;
+        if kept {
+            extend_content(buf, &haystack[idx..pos], opts);
+        }
+
+        match haystack[pos] {
+            c if c != quote && (opts.source_quotes || opts.quote_toggle) && stats.quoted() => {
+                if kept {
+                    extend_content(buf, &haystack[pos..=pos], opts);
+                }
+                stats.on_content();
+            }
+            c if c == sep => {
+                if kept && reescape && escaped(pos) {
+                    opts.push_reescape(buf);
+                    stats.repair(offset.saturating_sub(1), seps, Repair::ReescapedBackslash);
+                }
+                match haystack.get(pos + 1) {
+                    _ if !opts.ignore_trailing_sep => {
+                        write_sep(buf, &field_break, offset, opts, stats)
+                    }
+                    Some(&next) if next == eol => {
+                        stats.repair(offset, seps, Repair::DroppedTrailingSep);
+                        stats.on_content();
+                    }
+                    Some(_) => write_sep(buf, &field_break, offset, opts, stats),
+                    None => stats.hold_sep(),
+                }
+            }
+            c if c == eol => {
+                if kept && reescape && escaped(pos) {
+                    opts.push_reescape(buf);
+                    stats.repair(offset.saturating_sub(1), seps, Repair::ReescapedBackslash);
+                }
+                let fields = missing_fields(opts, seps);
+                for _ in 0..fields {
+                    buf.extend_from_slice(&field_break);
+                }
+                if fields > 0 {
+                    stats.repair(offset, seps, Repair::PaddedRow { fields });
+                }
+                extend_extra_columns(buf, stats.rows + 1, true, opts);
+                buf.extend_from_slice(&row_break);
+                stats.on_eol();
+            }
+            _ if opts.quote_toggle => stats.on_toggle_quote(offset),
+            // already escaped in the source: its backslash was written with the content
+            _ if backslash_quotes && escaped(pos) => {
+                stats.on_quote(false);
+                if kept {
+                    buf.push(quote);
+                }
+            }
+            _ => {
+                if opts.source_quotes {
+                    let field_start = match pos {
+                        0 => prev_byte.is_none_or(|b| b == sep || b == eol),
+                        _ => follows(pos, sep) || follows(pos, eol),
+                    };
+                    stats.on_source_quote(offset, field_start);
+                }
+                let closes = stats.on_quote(follows(pos, quote));
+                match (kept, opts.collapse_double_quotes && closes) {
+                    (false, _) => {}
+                    (true, false) => {
+                        buf.extend_from_slice(&escaped_quote);
+                        stats.repair(offset, seps, Repair::EscapedQuote);
+                    }
+                    (true, true) => stats.repair(offset, seps, Repair::CollapsedQuote),
+                }
+            }
+        }
+
+        idx = pos + 1;
+    }
+
+    if idx < haystack.len() {
+        if is_kept(opts, stats.row_seps()) {
+            extend_content(buf, &haystack[idx..], opts);
+        }
+        stats.on_content();
+    }
+}
+
+/// Writes the `field_break` of the `sep` at input `offset`, unless `pad_to` drops the
+/// field it opens.
+#[inline(always)]
+fn write_sep(
+    buf: &mut Vec<u8>,
+    field_break: &[u8],
+    offset: u64,
+    opts: &TmpOptions,
+    stats: &mut Stats,
+) {
+    let seps = stats.row_seps();
+    match is_kept(opts, seps + 1) {
+        true => buf.extend_from_slice(field_break),
+        false => stats.repair(offset, seps + 1, Repair::DroppedField),
+    }
+    stats.on_sep();
+}
+
+/// Whether content after `seps` separators of a row belongs to a written field.
+#[inline(always)]
+pub(crate) fn is_kept(opts: &TmpOptions, seps: u64) -> bool {
+    opts.pad_to.is_none_or(|n| seps < n as u64)
+}
+
+/// The number of empty fields `pad_to` appends to a row with `seps` separators.
+#[inline(always)]
+pub(crate) fn missing_fields(opts: &TmpOptions, seps: u64) -> u64 {
+    opts.pad_to
+        .map_or(0, |n| (n as u64).saturating_sub(seps + 1))
+}
+
+/// Appends the `header` row, if any, with its terminator.
+///
+/// Each name is written as a field of its column would be, so the header follows
+/// `quote_columns`, the output bytes and the content options of the data below it.
+pub(crate) fn write_header(buf: &mut Vec<u8>, opts: &TmpOptions) {
+    if opts.write_bom {
+        buf.extend_from_slice(BOM);
+    }
+    let Some(names) = &opts.header else {
+        return;
+    };
+
+    buf.extend(opts.record_prefix);
+    format_row(names, buf, opts);
+    buf.extend_from_slice(&opts.out_eol);
+}
+
+/// Appends `fields` as one row, without a terminator, quoted and escaped as a row of the
+/// same values is by the drivers.
+///
+/// Each field is one value: a `sep` or `eol` in it is content.
+pub(crate) fn format_row<F: AsRef<[u8]>>(fields: &[F], buf: &mut Vec<u8>, opts: &TmpOptions) {
+    for (col, field) in fields.iter().enumerate() {
+        let field = field.as_ref();
+        if col > 0 {
+            buf.extend_from_slice(&opts.out_delim);
+        }
+        match opts.quotes_field(col, field) {
+            true => quote_field(field, buf, opts),
+            false => extend_content(buf, field, opts),
+        }
+    }
+}
+
+/// Appends the `footer` line, if any, after the last row, which is `terminated` or not.
+///
+/// The footer gets the terminator the last row ended with, and that row one in any
+/// case. It is written raw: no quoting or escaping, only `{rows}` is replaced by the
+/// number of data rows, the header left out.
+pub(crate) fn write_footer(buf: &mut Vec<u8>, terminated: bool, stats: &Stats, opts: &TmpOptions) {
+    let Some(footer) = &opts.footer else {
+        return;
+    };
+
+    if !terminated {
+        buf.extend_from_slice(&opts.out_eol);
+    }
+    let rows = stats.rows + u64::from(stats.row_open());
+    buf.extend_from_slice(footer.replace("{rows}", &rows.to_string()).as_bytes());
+    if terminated {
+        buf.extend_from_slice(&opts.out_eol);
+    }
+}
+
+/// Whether an unquoted `field` holds a byte that would end it or its row early.
+///
+/// Any byte of a multi-byte `out_delim` or `out_eol` counts, since readers may split on
+/// part of it.
+#[inline(always)]
+pub(crate) fn needs_quotes(field: &[u8], opts: &TmpOptions) -> bool {
+    memchr2(opts.out_delim[0], opts.quote, field).is_some()
+        || opts.out_delim[1..]
+            .iter()
+            .chain(&opts.out_eol)
+            .any(|&b| memchr(b, field).is_some())
+}
+
+/// Writes `field` wrapped in quotes, escaping it as the streaming batch would.
+///
+/// `field` must not contain `sep` or `eol`; it is written as a single value.
+#[inline(always)]
+pub(crate) fn quote_field(field: &[u8], buf: &mut Vec<u8>, opts: &TmpOptions) {
+    buf.push(opts.quote);
+
+    let mut idx = 0;
+    let mut pair_open = false;
+    for pos in memchr_iter(opts.quote, field) {
+        extend_content(buf, &field[idx..pos], opts);
+        idx = pos + 1;
+        if opts.backslash_escapes_quotes() && trailing_backslashes(0, &field[..pos]) % 2 == 1 {
+            buf.push(opts.quote);
+            continue;
+        }
+        // the same pairing as `Stats::on_quote`, within a whole field
+        let closes = pair_open && pos > 0 && field[pos - 1] == opts.quote;
+        pair_open = !closes;
+        if !(opts.collapse_double_quotes && closes) {
+            buf.extend_from_slice(&opts.escaped_quote());
+        }
+    }
+    extend_content(buf, &field[idx..], opts);
+
+    if opts.reescapes() && trailing_backslashes(0, field) % 2 == 1 {
+        opts.push_reescape(buf);
+    }
+    buf.push(opts.quote);
+}
+
+#[inline(always)]
+pub(crate) fn extend_content(buf: &mut Vec<u8>, content: &[u8], opts: &TmpOptions) {
+    match &opts.escapes {
+        None => extend_raw(buf, content, opts),
+        Some(escapes) => escapes.extend(buf, content, |buf, run| extend_raw(buf, run, opts)),
+    }
+}
+
+/// Appends content that holds no escaped byte, casing it but not the escapes around it.
+#[inline(always)]
+fn extend_raw(buf: &mut Vec<u8>, content: &[u8], opts: &TmpOptions) {
+    let start = buf.len();
+    extend_backslashes(buf, content, opts.double_backslash);
+    if let Some(case) = opts.case {
+        case.apply(&mut buf[start..]);
+    }
+}
+
+#[inline(always)]
+fn extend_backslashes(buf: &mut Vec<u8>, content: &[u8], double: bool) {
+    if !double {
+        buf.extend_from_slice(content);
+        return;
+    }
+
+    let mut idx = 0;
+    for pos in memchr_iter(b'\\', content) {
+        buf.extend_from_slice(&content[idx..=pos]);
+        buf.push(b'\\');
+        idx = pos + 1;
+    }
+    buf.extend_from_slice(&content[idx..]);
+}
+
+/// Returns the last `rows` records of `input`, each fixed as a standalone row.
+///
+/// The input is scanned backward from the end for `eol` boundaries; a terminator
+/// as the very last byte belongs to the final row and is not counted. An escape
+/// byte before `eol` is field content (it gets re-escaped), so it never hides a
+/// row boundary. Each row receives its own leading and closing quotes.
+pub(crate) fn exorcize_tail<R>(
+    mut input: R,
+    size: u64,
+    rows: usize,
+    chunk_size: usize,
+    opts: &TmpOptions,
+) -> io::Result<Vec<Vec<u8>>>
+where
+    R: Read + Seek,
+{
+    let offset = tail_offset(&mut input, size, rows, chunk_size, opts.eol)?;
+    if offset == size {
+        return Ok(Vec::new());
+    }
+
+    let mut slice = Vec::with_capacity((size - offset) as usize);
+    input.seek(SeekFrom::Start(offset))?;
+    input.take(size - offset).read_to_end(&mut slice)?;
+
+    if slice.last() == Some(&opts.eol) {
+        slice.pop();
+    }
+
+    Ok(slice
+        .split(|&c| c == opts.eol)
+        .map(|row| fix_row(row, opts))
+        .collect())
+}
+
+/// Consumes the first `rows` records of `input` without fixing them, so that the next
+/// read starts at the record after.
+///
+/// Only `eol` bytes are looked for, so the cost is that of reading the bytes skipped.
+/// Returns the number of records skipped, fewer when the input ends first.
+pub(crate) fn skip_rows<R: BufRead>(input: &mut R, rows: u64, eol: u8) -> io::Result<u64> {
+    let mut skipped = 0;
+    while skipped < rows {
+        let available = input.fill_buf()?;
+        if available.is_empty() {
+            break;
+        }
+
+        let mut used = available.len();
+        for pos in memchr_iter(eol, available) {
+            skipped += 1;
+            if skipped == rows {
+                used = pos + 1;
+                break;
+            }
+        }
+        input.consume(used);
+    }
+    Ok(skipped)
+}
+
+/// Returns the first `rows` records of `input` fixed as one standalone CSV.
+///
+/// Reading stops at the `rows`-th `eol`, so the cost depends on `rows` rather than on
+/// the size of `input`. The last row is not terminated; as in `exorcize_tail`, an
+/// `eol` after an escape byte still ends a row.
+pub(crate) fn exorcize_head<R: Read>(
+    mut input: R,
+    rows: usize,
+    chunk_size: usize,
+    opts: &TmpOptions,
+) -> io::Result<Vec<u8>> {
+    if rows == 0 {
+        return Ok(Vec::new());
+    }
+
+    let mut slice = Vec::new();
+    let mut buf = vec![0u8; chunk_size];
+    let mut seen = 0;
+
+    'read: loop {
+        let read = input.read(&mut buf)?;
+        if read == 0 {
+            break;
+        }
+
+        for pos in memchr_iter(opts.eol, &buf[..read]) {
+            seen += 1;
+            if seen == rows {
+                slice.extend_from_slice(&buf[..pos]);
+                break 'read;
+            }
+        }
+        slice.extend_from_slice(&buf[..read]);
+    }
+
+    // the input ended first; its trailing `eol` closes the last row
+    if seen < rows {
+        if slice.is_empty() {
+            return Ok(Vec::new());
+        }
+        if slice.last() == Some(&opts.eol) {
+            slice.pop();
+        }
+    }
+
+    let mut out = Vec::with_capacity(slice.len() + slice.len() / 2 + 2);
+    for (i, row) in slice.split(|&c| c == opts.eol).enumerate() {
+        if i > 0 {
+            out.extend_from_slice(&opts.out_eol);
+        }
+        out.extend_from_slice(&fix_row(row, opts));
+    }
+    Ok(out)
+}
+
+/// Fixes `row`, which holds no `eol`, as a standalone quoted row.
+fn fix_row(row: &[u8], opts: &TmpOptions) -> Vec<u8> {
+    let mut buf = Vec::with_capacity(row.len() + 2);
+    buf.push(opts.quote);
+    buf.extend_from_slice(&exorcize_csv_batch(row, None, opts));
+    if opts.reescapes() && trailing_backslashes(0, row) % 2 == 1 {
+        opts.push_reescape(&mut buf);
+    }
+    buf.push(opts.quote);
+    buf
+}
+
+/// Fixes the bytes `start..end` of `input` as a standalone file.
+///
+/// With `start` and `end` on row boundaries, as returned by [`next_row_start`], each
+/// range opens and closes its own rows, so the outputs of consecutive ranges concatenate
+/// to the output of the whole file.
+pub(crate) fn exorcize_range<R, W>(
+    mut input: R,
+    output: W,
+    start: u64,
+    end: u64,
+    chunk_size: usize,
+    opts: &TmpOptions,
+) -> io::Result<Stats>
+where
+    R: Read + Seek,
+    W: Write,
+{
+    let len = end.saturating_sub(start);
+    input.seek(SeekFrom::Start(start))?;
+    let chunk_size = chunk_size.min(len as usize).max(1);
+    exorcize_csv(input.take(len), output, len, chunk_size, opts)
+}
+
+/// Returns the first row boundary at or after `offset`: `offset` itself if it follows an
+/// `eol` (or is 0), else the byte after the next `eol`, or `size` if there is none.
+pub(crate) fn next_row_start<R>(
+    input: &mut R,
+    size: u64,
+    offset: u64,
+    chunk_size: usize,
+    eol: u8,
+) -> io::Result<u64>
+where
+    R: Read + Seek,
+{
+    if offset == 0 || offset >= size {
+        return Ok(offset.min(size));
+    }
+
+    let mut buf = vec![0u8; chunk_size];
+    let mut start = offset - 1;
+    input.seek(SeekFrom::Start(start))?;
+
+    while start < size {
+        let len = chunk_size.min((size - start) as usize);
+        input.read_exact(&mut buf[..len])?;
+
+        if let Some(pos) = memchr(eol, &buf[..len]) {
+            return Ok(start + pos as u64 + 1);
+        }
+        start += len as u64;
+    }
+    Ok(size)
+}
+
+#[inline(always)]
+fn tail_offset<R>(
+    input: &mut R,
+    size: u64,
+    rows: usize,
+    chunk_size: usize,
+    eol: u8,
+) -> io::Result<u64>
+where
+    R: Read + Seek,
+{
+    if rows == 0 {
+        return Ok(size);
+    }
+
+    let mut buf = vec![0u8; chunk_size];
+    let mut found = 0;
+    let mut end = size;
+
+    while end > 0 {
+        let start = end.saturating_sub(chunk_size as u64);
+        let len = (end - start) as usize;
+
+        input.seek(SeekFrom::Start(start))?;
+        input.read_exact(&mut buf[..len])?;
+
+        for pos in memrchr_iter(eol, &buf[..len]) {
+            let abs = start + pos as u64;
+            if abs + 1 == size {
+                continue;
+            }
+            found += 1;
+            if found == rows {
+                return Ok(abs + 1);
+            }
+        }
+        end = start;
+    }
+    Ok(0)
+}
+
+/// Ends the output after the last batch, from the row state rather than the bytes of `out`.
+///
+/// Every break leaves a field open, so an open last row gets its closing quote, after
+/// re-escaping a trailing backslash and padding it, while the quote opened by a final
+/// `eol` is taken back. `final_newline` then adds or strips the terminator, which ends
+/// the `footer` instead when there is one.
+#[inline(always)]
+fn handle_closing<W>(
+    out: &mut Vec<u8>,
+    writer: &mut BufWriter<W>,
+    opts: &TmpOptions,
+    stats: &mut Stats,
+    backslashes: usize,
+) -> io::Result<()>
+where
+    W: Write,
+{
+    close_out(out, opts, stats, backslashes);
+    writer.write_all(out)?;
+    writer.flush()?;
+    Ok(())
+}
+
+/// The closing of `handle_closing`, left in `out` for the caller to write.
+fn close_out(out: &mut Vec<u8>, opts: &TmpOptions, stats: &mut Stats, backslashes: usize) {
+    let seps = stats.row_seps();
+    if stats.take_held_sep() {
+        stats.repair(stats.bytes_read - 1, seps, Repair::DroppedTrailingSep);
+    }
+    if stats.bytes_read == 0 {
+        // no input, no row: not even the leading quote
+        out.clear();
+    } else if stats.row_open() {
+        let end = stats.bytes_read;
+        if opts.reescapes()
+            && opts.reescape_count > 0
+            && backslashes % 2 == 1
+            && is_kept(opts, seps)
+        {
+            opts.push_reescape(out);
+            stats.repair(end - 1, seps, Repair::ReescapedBackslash);
+        }
+        let fields = missing_fields(opts, seps);
+        for _ in 0..fields {
+            out.extend_from_slice(&opts.field_break());
+        }
+        if fields > 0 {
+            stats.repair(end, seps, Repair::PaddedRow { fields });
+        }
+        extend_extra_columns(out, stats.rows + 1, true, opts);
+        out.push(opts.quote);
+        let terminated = opts.final_newline == Some(true);
+        if terminated {
+            out.extend_from_slice(&opts.out_eol);
+        }
+        write_footer(out, terminated, stats, opts);
+    } else {
+        // the row break of the final `eol` is whole in `out`, the last batch
+        out.truncate(out.len() - 1 - opts.record_prefix.iter().len());
+        let terminated = opts.final_newline != Some(false);
+        if !terminated {
+            out.truncate(out.len() - opts.out_eol.len());
+        }
+        write_footer(out, terminated, stats, opts);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::stats::{Anomaly, RepairEntry};
+    use rstest::*;
+    use std::io::Cursor;
+
+    #[rstest]
+    fn test_default_tmp_options() {
+        let opts = TmpOptions::default();
+        assert_eq!(opts.sep, b'\x1E');
+        assert_eq!(opts.eol, b'\x1D');
+        assert_eq!(&opts.field_break(), b"\",\"");
+        assert_eq!(&opts.row_break(), b"\"\n\"");
+        assert_eq!(&opts.escaped_quote(), b"\\\"");
+        assert_eq!(&opts.empty_field(), b",\"\"");
+    }
+
+    #[rstest]
+    #[case(b"||", b"a\x1Eb\x1Dc\x1E\x1E\\", "\"a\"||\"b\"\n\"c\"||\"\"||\"\\\\\"")]
+    #[case(
+        b"|;|",
+        b"a\x1Eb\x1Dc\x1E\x1E\\",
+        "\"a\"|;|\"b\"\n\"c\"|;|\"\"|;|\"\\\\\""
+    )]
+    #[case(b"|;|", b"a|;|b\x1E\x1D", "\"a|;|b\"|;|\"\"\n")]
+    fn test_exorcize_csv_multi_byte_out_delim(
+        #[case] out_delim: &[u8],
+        #[case] data: &[u8],
+        #[case] exp: &str,
+    ) {
+        let opts = TmpOptions {
+            out_delim: out_delim.to_vec(),
+            ..TmpOptions::default()
+        };
+        assert_eq!(opts.field_break(), [b"\"", out_delim, b"\""].concat());
+        assert_eq!(opts.empty_field(), [out_delim, b"\"\""].concat());
+
+        let size = data.len() as u64;
+        for chunk_size in 1..=data.len() {
+            let mut output = Vec::new();
+            exorcize_csv(Cursor::new(data), &mut output, size, chunk_size, &opts).unwrap();
+            assert_eq!(
+                String::from_utf8(output).unwrap(),
+                exp,
+                "chunk_size {chunk_size}"
+            );
+        }
+    }
+
+    #[rstest]
+    #[case(b"a'b\x1Ec\x1Dd", "'a\\'b';'c'|'d'")]
+    #[case(b"a\x1Eb\x1D", "'a';'b'|")]
+    #[case(b"\"a\x1D\x1D", "'\"a'|''|")]
+    fn test_exorcize_csv_custom_output_bytes(#[case] data: &[u8], #[case] exp: &str) {
+        let opts = TmpOptions {
+            quote: b'\'',
+            out_delim: b";".to_vec(),
+            out_eol: b"|".to_vec(),
+            ..TmpOptions::default()
+        };
+
+        let mut output = Vec::new();
+        let size = data.len() as u64;
+        let result = exorcize_csv(Cursor::new(data), &mut output, size, 1024, &opts);
+        assert!(result.is_ok());
+        assert_eq!(String::from_utf8(output).unwrap(), exp);
+    }
+
+    #[rstest]
+    #[case(b"\r\n", b"a\x1Db\x1D", None, "\"a\"\r\n\"b\"\r\n")]
+    #[case(b"\r\n", b"a\x1Db\x1D", Some(false), "\"a\"\r\n\"b\"")]
+    #[case(b"\r\n", b"a\x1Db", Some(true), "\"a\"\r\n\"b\"\r\n")]
+    #[case(b"|\r\n", b"a\x1Eb\x1D\x1D", None, "\"a\",\"b\"|\r\n\"\"|\r\n")]
+    #[case(b"|\r\n", b"a\x1Eb\x1D\x1D", Some(false), "\"a\",\"b\"|\r\n\"\"")]
+    #[case(b"|\r\n", b"\x1D", Some(false), "\"\"")]
+    fn test_exorcize_csv_multi_byte_out_eol(
+        #[case] out_eol: &[u8],
+        #[case] data: &[u8],
+        #[case] final_newline: Option<bool>,
+        #[case] exp: &str,
+    ) {
+        let opts = TmpOptions {
+            out_eol: out_eol.to_vec(),
+            final_newline,
+            ..TmpOptions::default()
+        };
+
+        let size = data.len() as u64;
+        for chunk_size in 1..=data.len() {
+            let mut output = Vec::new();
+            exorcize_csv(Cursor::new(data), &mut output, size, chunk_size, &opts).unwrap();
+            assert_eq!(
+                String::from_utf8(output).unwrap(),
+                exp,
+                "chunk_size {chunk_size}"
+            );
+        }
+    }
+
+    #[rstest]
+    #[case(b"a\x1E\"x\x1Ey\"\x1Eb", "\"a\",\"\\\"x\x1Ey\\\"\",\"b\"", 1)]
+    #[case(b"\"a\x1Db\"\x1Dc", "\"\\\"a\x1Db\\\"\"\n\"c\"", 2)]
+    // the second quote of a `""` closes the region and the third reopens it
+    #[case(b"\"a\"\"\x1E\"\x1Eb", "\"\\\"a\\\"\\\"\x1E\\\"\",\"b\"", 1)]
+    // a quote within a field opens no region
+    #[case(b"5\"\x1Eb\x1Dc\"", "\"5\\\"\",\"b\"\n\"c\\\"\"", 2)]
+    #[case(b"\"a\x1Eb\x1Dc", "\"\\\"a\x1Eb\x1Dc\"", 1)]
+    fn test_exorcize_csv_source_quotes(#[case] data: &[u8], #[case] exp: &str, #[case] rows: u64) {
+        let opts = TmpOptions {
+            source_quotes: true,
+            ..TmpOptions::default()
+        };
+
+        let size = data.len() as u64;
+        for chunk_size in 1..=data.len() {
+            let mut output = Vec::new();
+            let stats =
+                exorcize_csv(Cursor::new(data), &mut output, size, chunk_size, &opts).unwrap();
+            assert_eq!(
+                String::from_utf8(output).unwrap(),
+                exp,
+                "chunk_size {chunk_size}"
+            );
+            assert_eq!(stats.rows, rows);
+        }
+    }
+
+    #[rstest]
+    #[case(b"a\x1E\"x\x1Ey\"\x1Eb", "\"a\",\"x\x1Ey\",\"b\"", 1)]
+    #[case(b"\"a\x1Db\"\x1Dc", "\"a\x1Db\"\n\"c\"", 2)]
+    // a quote anywhere in a field toggles, and `""` is an empty region
+    #[case(b"5\"\x1E\"b\x1D\"\"c", "\"5\x1Eb\"\n\"c\"", 2)]
+    #[case(b"\"\"\x1E\"\"", "\"\",\"\"", 1)]
+    #[case(b"", "", 0)]
+    fn test_exorcize_csv_quote_toggle(#[case] data: &[u8], #[case] exp: &str, #[case] rows: u64) {
+        let opts = TmpOptions {
+            quote_toggle: true,
+            ..TmpOptions::default()
+        };
+
+        let size = data.len() as u64;
+        for chunk_size in 1..=data.len().max(1) {
+            let mut output = Vec::new();
+            let stats =
+                exorcize_csv(Cursor::new(data), &mut output, size, chunk_size, &opts).unwrap();
+            assert_eq!(
+                String::from_utf8(output).unwrap(),
+                exp,
+                "chunk_size {chunk_size}"
+            );
+            assert_eq!(stats.rows, rows);
+        }
+    }
+
+    #[rstest]
+    #[case(b"a\x1E\"b\x1Dc", 2)]
+    #[case(b"\"a\"\x1Eb\"\x1D", 5)]
+    fn test_exorcize_csv_quote_toggle_unbalanced(#[case] data: &[u8], #[case] offset: u64) {
+        let opts = TmpOptions {
+            quote_toggle: true,
+            ..TmpOptions::default()
+        };
+
+        let size = data.len() as u64;
+        for chunk_size in 1..=data.len() {
+            let e = exorcize_csv(Cursor::new(data), io::sink(), size, chunk_size, &opts);
+            let e = e.unwrap_err();
+            assert_eq!(e.kind(), io::ErrorKind::InvalidData);
+            let msg = format!("the quote at byte {offset} is never closed; quotes must balance");
+            assert_eq!(e.to_string(), msg, "chunk_size {chunk_size}");
+        }
+    }
+
+    #[rstest]
+    #[case(b"a\\\x1Eb\\\"c\x1D\\", "\"a\\\",\"b\\\\\"c\"\n\"\\\"")]
+    #[case(b"\\\\\\\x1D\\\x1D", "\"\\\\\\\"\n\"\\\"\n")]
+    fn test_exorcize_csv_without_escape(#[case] data: &[u8], #[case] exp: &str) {
+        let opts = TmpOptions {
+            escape: None,
+            ..TmpOptions::default()
+        };
+        assert!(!opts.reescapes() && !opts.backslash_escapes_quotes());
+
+        let size = data.len() as u64;
+        for chunk_size in 1..=data.len() {
+            let mut output = Vec::new();
+            exorcize_csv(Cursor::new(data), &mut output, size, chunk_size, &opts).unwrap();
+            assert_eq!(
+                String::from_utf8(output).unwrap(),
+                exp,
+                "chunk_size {chunk_size}"
+            );
+        }
+    }
+
+    #[rstest]
+    #[case(b"a\x1Eb\x1Dc\x1D", None, "\x1E\"a\",\"b\"\n\x1E\"c\"\n")]
+    #[case(b"a\x1Eb\x1Dc\x1D", Some(false), "\x1E\"a\",\"b\"\n\x1E\"c\"")]
+    #[case(b"a\x1Dc", Some(true), "\x1E\"a\"\n\x1E\"c\"\n")]
+    #[case(b"\x1D", None, "\x1E\"\"\n")]
+    #[case(b"", None, "")]
+    fn test_exorcize_csv_record_prefix(
+        #[case] data: &[u8],
+        #[case] final_newline: Option<bool>,
+        #[case] exp: &str,
+    ) {
+        let opts = TmpOptions {
+            record_prefix: Some(b'\x1E'),
+            final_newline,
+            ..TmpOptions::default()
+        };
+
+        let size = data.len() as u64;
+        for chunk_size in 1..=data.len().max(1) {
+            let mut output = Vec::new();
+            exorcize_csv(Cursor::new(data), &mut output, size, chunk_size, &opts).unwrap();
+            assert_eq!(
+                String::from_utf8(output).unwrap(),
+                exp,
+                "chunk_size {chunk_size}"
+            );
+        }
+    }
+
+    #[rstest]
+    #[case(&["a", "b"])]
+    #[case(&["", "x\"y", "\"\""])]
+    #[case(&["a\\", "b\\\"c", "\\\\"])]
+    #[case(&["a,b\nc"])]
+    fn test_format_row_matches_exorcize_csv(#[case] fields: &[&str]) {
+        let opts = TmpOptions::default();
+        let data = fields.join("\x1E");
+
+        let mut exp = Vec::new();
+        let size = data.len() as u64;
+        exorcize_csv(Cursor::new(data), &mut exp, size, 1024, &opts).unwrap();
+
+        let mut row = Vec::new();
+        format_row(fields, &mut row, &opts);
+        assert_eq!(
+            String::from_utf8(row).unwrap(),
+            String::from_utf8(exp).unwrap()
+        );
+    }
+
+    #[rstest]
+    fn test_format_row_keeps_sep_and_eol_as_content() {
+        let opts = TmpOptions {
+            quote: b'\'',
+            out_delim: b";".to_vec(),
+            ..TmpOptions::default()
+        };
+        let mut row = Vec::new();
+        format_row(&[&b"a\x1Eb"[..], b"'\x1D"], &mut row, &opts);
+        assert_eq!(row, b"'a\x1Eb';'\\'\x1D'");
+    }
+
+    #[rstest]
+    #[case(b"a\nb\x1Ec\r\nd\x1De", "\"a\\nb\",\"c\\r\\nd\"\n\"e\"")]
+    #[case(b"\n\x1D\r", "\"\\n\"\n\"\\r\"")]
+    fn test_exorcize_csv_flattened_newlines(#[case] data: &[u8], #[case] exp: &str) {
+        let escapes = FLATTENED_NEWLINES.map(|(byte, escaped)| (byte, escaped.to_vec()));
+        let opts = TmpOptions {
+            escapes: Some(Escapes::new(escapes)),
+            ..TmpOptions::default()
+        };
+
+        let size = data.len() as u64;
+        for chunk_size in 1..=data.len() {
+            let mut output = Vec::new();
+            exorcize_csv(Cursor::new(data), &mut output, size, chunk_size, &opts).unwrap();
+            let output = String::from_utf8(output).unwrap();
+            assert_eq!(output, exp, "chunk_size {chunk_size}");
+            assert_eq!(
+                output.lines().count(),
+                data.split(|&b| b == opts.eol).count()
+            );
+        }
+    }
+
+    #[rstest]
+    #[case(b'\\', b'\x1D', Some(b'\\'), Some("delim"))]
+    #[case(b'\x1E', b'\\', Some(b'\\'), Some("newline"))]
+    #[case(b'\\', b'\x1D', None, None)]
+    #[case(b'\x1E', b'\x1D', Some(b'\\'), None)]
+    fn test_escape_collision(
+        #[case] sep: u8,
+        #[case] eol: u8,
+        #[case] escape: Option<u8>,
+        #[case] exp: Option<&str>,
+    ) {
+        let opts = TmpOptions {
+            sep,
+            eol,
+            escape,
+            ..TmpOptions::default()
+        };
+        assert_eq!(opts.escape_collision(), exp);
+    }
+
+    #[rstest]
+    #[case(b"a\rb", true)]
+    #[case(b"a|b", true)]
+    #[case(b"ab", false)]
+    fn test_needs_quotes_any_out_eol_byte(#[case] field: &[u8], #[case] exp: bool) {
+        let opts = TmpOptions {
+            out_eol: b"|\r\n".to_vec(),
+            ..TmpOptions::default()
+        };
+        assert_eq!(needs_quotes(field, &opts), exp);
+    }
+
+    #[rstest]
+    #[case(0, 0, 1500)]
+    // typical rows grow by a tenth, and get a fourth more than that
+    #[case(4000, 4400, 1375)]
+    // every byte a quote: twice as much, and the same margin
+    #[case(4000, 8000, 2500)]
+    fn test_out_capacity(#[case] bytes_read: u64, #[case] bytes_written: u64, #[case] exp: usize) {
+        let mut stats = Stats::default();
+        stats.bytes_read = bytes_read;
+        stats.bytes_written = bytes_written;
+        assert_eq!(out_capacity(1000, &stats), exp);
+    }
+
+    #[rstest]
+    #[case(b',', true)]
+    #[case(b'|', true)]
+    #[case(b'\t', true)]
+    #[case(b'\n', true)]
+    #[case(b'\x1E', false)]
+    #[case(b'\x1D', false)]
+    #[case(b'\0', false)]
+    fn test_is_common_byte(#[case] byte: u8, #[case] exp: bool) {
+        assert_eq!(is_common_byte(byte), exp);
+    }
+
+    #[rstest]
+    fn test_exorcize_csv_empty_input() {
+        let input = Cursor::new(Vec::new());
+        let output = Vec::new();
+        let opts = TmpOptions::default();
+
+        let result = exorcize_csv(input, output, 0, 1024, &opts);
+        assert!(result.is_ok());
+    }
+
+    #[rstest]
+    fn test_exorcize_csv_basic() {
+        let input_data = b"field1\x1Efield2\x1Dfield3";
+        let input = Cursor::new(input_data.to_vec());
+        let mut output = Cursor::new(Vec::new());
+        let opts = TmpOptions::default();
+
+        let result = exorcize_csv(input, &mut output, input_data.len() as u64, 1024, &opts);
+        assert!(result.is_ok());
+
+        let result = output.seek(SeekFrom::Start(0));
+        assert!(result.is_ok());
+
+        let mut result = String::new();
+        output.read_to_string(&mut result).unwrap();
+        let expected_output = "\"field1\",\"field2\"\n\"field3\"";
+        assert_eq!(result, expected_output);
+    }
+
+    #[rstest]
+    #[case(
+        b"field1\\\x1Efield2\\\x1Dfield3",
+        "\"field1\\\\\",\"field2\\\\\"\n\"field3\""
+    )]
+    fn test_exorcize_csv_with_escape_characters(#[case] data: &[u8], #[case] expected: &str) {
+        let input = Cursor::new(data.to_vec());
+        let mut output = Cursor::new(Vec::new());
+        let opts = TmpOptions::default();
+
+        let result = exorcize_csv(input, &mut output, data.len() as u64, 1024, &opts);
+        assert!(result.is_ok());
+
+        let result = output.seek(SeekFrom::Start(0));
+        assert!(result.is_ok());
 
         let mut result = String::new();
         output.read_to_string(&mut result).unwrap();
@@ -176,39 +2075,1703 @@ mod tests {
     }
 
     #[rstest]
-    #[case("field1\x1Efield2\x1Efield3\x1D", "field1\",\"field2\",\"field3\"\n\"")]
-    #[case(
-        "field1\x1Dfield2\x1Dfield3\x1D",
-        "field1\"\n\"field2\"\n\"field3\"\n\""
-    )]
-    #[case(
-        "\"\"field\",\"field\",field\"\x1Efield3\x1D",
-        "\\\"\\\"field\\\",\\\"field\\\",field\\\"\",\"field3\"\n\""
-    )]
-    #[case("\x1E\x1E\x1D", "\",\"\",\"\"\n\"")]
-    #[case("\\\x1E\\\x1E\\\x1D", "\\\\\",\"\\\\\",\"\\\\\"\n\"")]
-    #[case("\0\x1E\0\x1E\0\x1D", "\0\",\"\0\",\"\0\"\n\"")]
-    fn test_exorcize_csv_batch(#[case] haystack: &str, #[case] expected: &str) {
+    #[case("field1\x1Efield2\x1Efield3\x1D", "field1\",\"field2\",\"field3\"\n\"")]
+    #[case(
+        "field1\x1Dfield2\x1Dfield3\x1D",
+        "field1\"\n\"field2\"\n\"field3\"\n\""
+    )]
+    #[case(
+        "\"\"field\",\"field\",field\"\x1Efield3\x1D",
+        "\\\"\\\"field\\\",\\\"field\\\",field\\\"\",\"field3\"\n\""
+    )]
+    #[case("\x1E\x1E\x1D", "\",\"\",\"\"\n\"")]
+    #[case("\\\x1E\\\x1E\\\x1D", "\\\\\",\"\\\\\",\"\\\\\"\n\"")]
+    #[case("\0\x1E\0\x1E\0\x1D", "\0\",\"\0\",\"\0\"\n\"")]
+    fn test_exorcize_csv_batch(#[case] haystack: &str, #[case] expected: &str) {
+        let opts = TmpOptions::default();
+
+        let buf = exorcize_csv_batch(haystack.as_bytes(), None, &opts);
+        assert_eq!(buf, expected.as_bytes());
+    }
+
+    #[rstest]
+    #[case("a'b\x1Ec\x1D", "a\\'b','c'\n'")]
+    #[case("a\\\x1E\\'", "a\\\\','\\'")]
+    fn test_exorcize_csv_batch_quote(#[case] haystack: &str, #[case] expected: &str) {
+        let opts = TmpOptions {
+            quote: b'\'',
+            ..TmpOptions::default()
+        };
+
+        let buf = exorcize_csv_batch(haystack.as_bytes(), None, &opts);
+        assert_eq!(String::from_utf8(buf).unwrap(), expected);
+    }
+
+    #[rstest]
+    #[case("a\\\x1Eb\\\"", "a\\\",\"b\\\\\"")]
+    #[case("\x1Ea", "\",\"a")]
+    fn test_exorcize_csv_batch_without_escape(#[case] haystack: &str, #[case] expected: &str) {
+        let opts = TmpOptions {
+            escape: None,
+            ..TmpOptions::default()
+        };
+
+        let buf = exorcize_csv_batch(haystack.as_bytes(), Some(b'\\'), &opts);
+        assert_eq!(String::from_utf8(buf).unwrap(), expected);
+    }
+
+    #[rstest]
+    #[case("\x1Ea", Some(b'\\'), "\\\",\"a")]
+    #[case("\x1Da", Some(b'\\'), "\\\"\n\"a")]
+    #[case("\x1Ea", Some(b'a'), "\",\"a")]
+    #[case("\x1Ea", None, "\",\"a")]
+    #[case("\\\x1Ea", Some(b'\\'), "\\\",\"a")]
+    fn test_exorcize_csv_batch_prev_byte(
+        #[case] haystack: &str,
+        #[case] prev_byte: Option<u8>,
+        #[case] expected: &str,
+    ) {
+        let opts = TmpOptions::default();
+
+        let buf = exorcize_csv_batch(haystack.as_bytes(), prev_byte, &opts);
+        assert_eq!(buf, expected.as_bytes());
+    }
+
+    // resuming mid-record: no quote of its own, the fixed content of the row it continues
+    #[rstest]
+    #[case(b"ab\x1Ec\\\x1Dd\"e")]
+    #[case(b"\x1D\x1Ea\x1D")]
+    fn test_exorcize_csv_batch_resumes_mid_record(#[case] data: &[u8]) {
+        let opts = TmpOptions::default();
+        let whole = exorcize_csv_batch(data, None, &opts);
+
+        for split in 1..data.len() {
+            let (head, tail) = data.split_at(split);
+            let resumed = exorcize_csv_batch(tail, Some(head[split - 1]), &opts);
+            if ![opts.sep, opts.eol, opts.quote].contains(&tail[0]) {
+                assert_eq!(resumed[0], tail[0], "split at {split}");
+            }
+            let joined = [exorcize_csv_batch(head, None, &opts), resumed].concat();
+            assert_eq!(joined, whole, "split at {split}");
+        }
+    }
+
+    // a backslash run split anywhere escapes as it does in one pass
+    #[rstest]
+    #[case(b"a\\\\\x1Eb\\\\\\\x1Dc")]
+    #[case(b"\\\\\\\x1E\\\\\x1D\"\\")]
+    #[case(b"a\\\\\\\\")]
+    fn test_exorcize_csv_batch_after_matches_exorcize_csv(#[case] data: &[u8]) {
+        let opts = TmpOptions::default();
+        let mut whole = Vec::new();
+        let size = data.len() as u64;
+        exorcize_csv(Cursor::new(data), &mut whole, size, 1024, &opts).unwrap();
+
+        for split in 1..data.len() {
+            let (head, tail) = data.split_at(split);
+            let (mut joined, backslashes) = exorcize_csv_batch_after(head, 0, &opts);
+            let (resumed, backslashes) = exorcize_csv_batch_after(tail, backslashes, &opts);
+            joined.extend_from_slice(&resumed);
+            if backslashes % 2 == 1 {
+                opts.push_reescape(&mut joined);
+            }
+            let joined = [&[opts.quote], &joined[..], &[opts.quote]].concat();
+            assert_eq!(joined, whole, "split at {split}");
+        }
+    }
+
+    // a read ending on the escape, with the byte it escapes opening the next read
+    #[rstest]
+    #[case(b"a\\", b"\x1Eb", "\"a\\\\\",\"b\"")]
+    #[case(b"a\\", b"\x1Db", "\"a\\\\\"\n\"b\"")]
+    #[case(b"\\", b"\x1E", "\"\\\\\",\"\"")]
+    #[case(b"a\\\\\\", b"\x1Eb", "\"a\\\\\\\\\",\"b\"")]
+    #[case(b"a\\\\", b"\x1Eb", "\"a\\\\\",\"b\"")]
+    fn test_exorcize_csv_escape_ends_read(
+        #[case] head: &[u8],
+        #[case] tail: &[u8],
+        #[case] exp: &str,
+    ) {
+        let opts = TmpOptions::default();
+        let data = [head, tail].concat();
+        let size = data.len() as u64;
+
+        let mut whole = Vec::new();
+        exorcize_csv(Cursor::new(&data), &mut whole, size, data.len(), &opts).unwrap();
+        assert_eq!(String::from_utf8(whole).unwrap(), exp);
+
+        // the short read of `chain` splits the input there whatever the chunk size
+        for chunk_size in [head.len(), data.len(), DEFAULT_CHUNK_SIZE] {
+            let split = || Cursor::new(head).chain(Cursor::new(tail));
+            let mut output = Vec::new();
+            exorcize_csv(split(), &mut output, size, chunk_size, &opts).unwrap();
+            assert_eq!(output, exp.as_bytes(), "chunk_size {chunk_size}");
+
+            let mut streamed = Vec::new();
+            exorcize_stream(split(), &mut streamed, chunk_size, &opts).unwrap();
+            assert_eq!(streamed, exp.as_bytes(), "chunk_size {chunk_size}");
+
+            let mut chunks = Chunks::new(split(), size, chunk_size, TmpOptions::default());
+            let mut pulled = Vec::new();
+            while let Some(chunk) = chunks.next_chunk().unwrap() {
+                pulled.extend(chunk);
+            }
+            assert_eq!(pulled, exp.as_bytes(), "chunk_size {chunk_size}");
+        }
+    }
+
+    // only an odd run of backslashes escapes what follows; an even one is escaped pairs
+    #[rstest]
+    #[case(b"a\x1Eb", "\"a\",\"b\"")]
+    #[case(b"a\\\x1Eb", "\"a\\\\\",\"b\"")]
+    #[case(b"a\\\\\x1Eb", "\"a\\\\\",\"b\"")]
+    #[case(b"a\\\\\\\x1Eb", "\"a\\\\\\\\\",\"b\"")]
+    #[case(b"a\x1Db", "\"a\"\n\"b\"")]
+    #[case(b"a\\\x1Db", "\"a\\\\\"\n\"b\"")]
+    #[case(b"\\\\\x1D\\\\\\", "\"\\\\\"\n\"\\\\\\\\\"")]
+    #[case(b"a\\\\\\\x1Db", "\"a\\\\\\\\\"\n\"b\"")]
+    #[case(b"a\\\\", "\"a\\\\\"")]
+    fn test_exorcize_csv_escaped_backslash(#[case] data: &[u8], #[case] exp: &str) {
+        let opts = TmpOptions::default();
+        let size = data.len() as u64;
+
+        for chunk_size in 1..=data.len() {
+            let mut output = Vec::new();
+            exorcize_csv(Cursor::new(data), &mut output, size, chunk_size, &opts).unwrap();
+            assert_eq!(
+                String::from_utf8(output).unwrap(),
+                exp,
+                "chunk_size {chunk_size}"
+            );
+        }
+    }
+
+    #[rstest]
+    #[case("a\x1Eb\x1Dc\x1D", "a\",\"b\",\"x\"\n\"c\",\"x\"\n\"")]
+    #[case("a\"\x1D", "a\\\"\",\"x\"\n\"")]
+    #[case("a\x1Eb", "a\",\"b")]
+    fn test_exorcize_csv_batch_extra_literal(#[case] haystack: &str, #[case] expected: &str) {
+        let opts = TmpOptions {
+            extra_columns: Some(vec![ExtraCol::Literal(b"x".to_vec())]),
+            ..TmpOptions::default()
+        };
+
+        let buf = exorcize_csv_batch(haystack.as_bytes(), None, &opts);
+        assert_eq!(String::from_utf8(buf).unwrap(), expected);
+    }
+
+    #[rstest]
+    #[case(
+        b"a\x1Db\x1Dc",
+        "\"a\",\"1\",\"f\\\"q\"\n\"b\",\"2\",\"f\\\"q\"\n\"c\",\"3\",\"f\\\"q\""
+    )]
+    #[case(b"a\x1Db\x1D", "\"a\",\"1\",\"f\\\"q\"\n\"b\",\"2\",\"f\\\"q\"\n")]
+    #[case(
+        b"a\x1E\x1D\x1D",
+        "\"a\",\"\",\"1\",\"f\\\"q\"\n\"\",\"2\",\"f\\\"q\"\n"
+    )]
+    #[case(b"", "")]
+    fn test_exorcize_csv_extra_columns(#[case] data: &[u8], #[case] exp: &str) {
+        let opts = TmpOptions {
+            extra_columns: Some(vec![
+                ExtraCol::RowNumber,
+                ExtraCol::Literal(b"f\"q".to_vec()),
+            ]),
+            ..TmpOptions::default()
+        };
+        let size = data.len() as u64;
+
+        for chunk_size in 1..=data.len().max(1) {
+            let mut output = Vec::new();
+            exorcize_csv(Cursor::new(data), &mut output, size, chunk_size, &opts).unwrap();
+            assert_eq!(
+                String::from_utf8(output).unwrap(),
+                exp,
+                "chunk_size {chunk_size}"
+            );
+        }
+    }
+
+    #[rstest]
+    #[case(b"a\\\x1Eb", true, "\"a\\\\\",\"b\"")]
+    #[case(b"a\\\x1Eb", false, "\"a\\\",\"b\"")]
+    #[case(b"a\\\x1Db\\", true, "\"a\\\\\"\n\"b\\\\\"")]
+    #[case(b"a\\\x1Db\\", false, "\"a\\\"\n\"b\\\"")]
+    #[case(b"a\\\\\x1Eb", false, "\"a\\\\\",\"b\"")]
+    fn test_exorcize_csv_reescape_delims(
+        #[case] data: &[u8],
+        #[case] reescape_delims: bool,
+        #[case] exp: &str,
+    ) {
+        let opts = TmpOptions {
+            reescape_delims,
+            ..TmpOptions::default()
+        };
+        let size = data.len() as u64;
+
+        for chunk_size in 1..=data.len() {
+            let mut output = Vec::new();
+            exorcize_csv(Cursor::new(data), &mut output, size, chunk_size, &opts).unwrap();
+            assert_eq!(
+                String::from_utf8(output).unwrap(),
+                exp,
+                "chunk_size {chunk_size}"
+            );
+        }
+    }
+
+    #[rstest]
+    #[case(
+        b"a\x1Eb\x1E\x1Dc\x1E",
+        "\"a\",\"b\",\"\"\n\"c\",\"\"",
+        "\"a\",\"b\"\n\"c\""
+    )]
+    #[case(b"a\x1E\x1E\x1D", "\"a\",\"\",\"\"\n", "\"a\",\"\"\n")]
+    #[case(b"\x1E\x1D\x1E", "\"\",\"\"\n\"\",\"\"", "\"\"\n\"\"")]
+    #[case(b"a\\\x1E\x1Db", "\"a\\\\\",\"\"\n\"b\"", "\"a\\\\\"\n\"b\"")]
+    #[case(b"a\x1Eb\x1Ec", "\"a\",\"b\",\"c\"", "\"a\",\"b\",\"c\"")]
+    fn test_exorcize_csv_trailing_sep(
+        #[case] data: &[u8],
+        #[case] field: &str,
+        #[case] ignored: &str,
+    ) {
+        let size = data.len() as u64;
+        for (ignore_trailing_sep, exp) in [(false, field), (true, ignored)] {
+            let opts = TmpOptions {
+                ignore_trailing_sep,
+                repair_log: true,
+                ..TmpOptions::default()
+            };
+            let whole =
+                exorcize_csv(Cursor::new(data), Vec::new(), size, data.len(), &opts).unwrap();
+
+            for chunk_size in 1..=data.len() {
+                let context =
+                    format!("ignore_trailing_sep {ignore_trailing_sep}, chunk_size {chunk_size}");
+                let mut output = Vec::new();
+                let stats =
+                    exorcize_csv(Cursor::new(data), &mut output, size, chunk_size, &opts).unwrap();
+                assert_eq!(String::from_utf8(output).unwrap(), exp, "{context}");
+                assert_eq!(stats.field_counts, whole.field_counts, "{context}");
+                assert_eq!(stats.repairs, whole.repairs, "{context}");
+
+                let mut buffered = Vec::new();
+                let opts = TmpOptions {
+                    ignore_trailing_sep,
+                    quote_columns: Some((0..4).collect()),
+                    ..TmpOptions::default()
+                };
+                let stats = crate::buffered::exorcize_csv(
+                    Cursor::new(data),
+                    &mut buffered,
+                    chunk_size,
+                    &opts,
+                    &mut crate::buffered::NoHook,
+                )
+                .unwrap();
+                assert_eq!(
+                    String::from_utf8(buffered).unwrap(),
+                    exp,
+                    "buffered, {context}"
+                );
+                assert_eq!(
+                    stats.field_counts, whole.field_counts,
+                    "buffered, {context}"
+                );
+            }
+        }
+    }
+
+    #[rstest]
+    #[case(0, "\"a\\\",\"b\"\n\"c\\\"")]
+    #[case(1, "\"a\\\\\",\"b\"\n\"c\\\\\"")]
+    #[case(2, "\"a\\\\\\\",\"b\"\n\"c\\\\\\\"")]
+    fn test_exorcize_csv_reescape_count(#[case] reescape_count: usize, #[case] exp: &str) {
+        let data = b"a\\\x1Eb\x1Dc\\";
+        let opts = TmpOptions {
+            reescape_count,
+            repair_log: true,
+            ..TmpOptions::default()
+        };
+        let size = data.len() as u64;
+
+        for chunk_size in 1..=data.len() {
+            let mut output = Vec::new();
+            let stats =
+                exorcize_csv(Cursor::new(data), &mut output, size, chunk_size, &opts).unwrap();
+            assert_eq!(
+                String::from_utf8(output).unwrap(),
+                exp,
+                "chunk_size {chunk_size}"
+            );
+            let repairs = stats.repairs.as_ref().map_or(0, Vec::len);
+            assert_eq!(
+                repairs,
+                2 * reescape_count.min(1),
+                "chunk_size {chunk_size}"
+            );
+        }
+
+        // whole fields, as the buffered driver and the tail fix see them
+        let mut buf = Vec::new();
+        quote_field(b"a\\", &mut buf, &opts);
+        assert_eq!(
+            buf,
+            [b"\"a", &b"\\".repeat(1 + reescape_count)[..], b"\""].concat()
+        );
+    }
+
+    #[rstest]
+    #[case(b"field1\\\x1Efield2\\\x1Dfield3")]
+    #[case(b"a\\\x1D\"b\\\x1E\\\x1Ec")]
+    #[case(b"\"\"a\x1E\"")]
+    fn test_exorcize_csv_chunking_invariant(#[case] data: &[u8]) {
+        let opts = TmpOptions::default();
+        let size = data.len() as u64;
+
+        let mut exp = Vec::new();
+        exorcize_csv(Cursor::new(data), &mut exp, size, 1024, &opts).unwrap();
+
+        for chunk_size in 1..data.len() {
+            let mut output = Vec::new();
+            exorcize_csv(Cursor::new(data), &mut output, size, chunk_size, &opts).unwrap();
+            assert_eq!(output, exp, "chunk_size {chunk_size}");
+        }
+    }
+
+    // every byte that depends on its neighbours, at every split point
+    const BOUNDARY_CORPUS: &[&[u8]] = &[
+        b"a\\\x1Eb\\\\\x1Ec\\\\\\\x1Dd\\",
+        b"\"\"\"a\"\"\x1E\"\x1D\"\"",
+        b"\xEF\xBB\xBFa\x1Eb\xEF\xBB\xBF\x1D\0c",
+        b"a\x1E\x1E\x1D\x1D\x1Eb\x1Ec\x1Ed\x1D",
+        b"\\\x1D\\\"\\\\\"\x0B\x1E\x0B\\",
+    ];
+
+    fn boundary_options() -> Vec<TmpOptions> {
+        vec![
+            TmpOptions {
+                repair_log: true,
+                ..TmpOptions::default()
+            },
+            TmpOptions {
+                collapse_double_quotes: true,
+                repair_log: true,
+                ..TmpOptions::default()
+            },
+            TmpOptions {
+                pad_to: Some(2),
+                final_newline: Some(true),
+                repair_log: true,
+                ..TmpOptions::default()
+            },
+            TmpOptions {
+                double_backslash: true,
+                final_newline: Some(false),
+                ..TmpOptions::default()
+            },
+            TmpOptions {
+                escapes: Some(Escapes::new([(b'\x0B', b"\\v".to_vec())])),
+                out_eol: b"\r\n".to_vec(),
+                extra_columns: Some(vec![ExtraCol::RowNumber]),
+                ..TmpOptions::default()
+            },
+            TmpOptions {
+                header: Some(vec![b"h".to_vec()]),
+                reescape_delims: false,
+                ..TmpOptions::default()
+            },
+        ]
+    }
+
+    // each construct shifted by `pad` bytes, so that every chunk_size cuts it at every point:
+    // backslash runs split from the sep, eol or EOF they precede, and the multi-byte output
+    // tokens written between two chunks
+    #[rstest]
+    #[case(b"a\\\x1Eb", "\"a\\\\\"|;|\"b\"")]
+    #[case(b"a\\\\\x1Eb", "\"a\\\\\"|;|\"b\"")]
+    #[case(b"a\\\\\\\x1Db", "\"a\\\\\\\\\"\r\n\"b\"")]
+    #[case(b"a\\", "\"a\\\\\"")]
+    #[case(b"a\"\x1E\"b", "\"a\\\"\"|;|\"\\\"b\"")]
+    #[case(b"a\\\"\x1Db\\\\\"", "\"a\\\"\"\r\n\"b\\\\\\\"\"")]
+    #[case(b"\"a\x1Eb\"\x1Dc", "\"\\\"a\"|;|\"b\\\"\"\r\n\"c\"")]
+    fn test_exorcize_csv_boundary_split_at_every_offset(
+        #[case] construct: &[u8],
+        #[case] exp: &str,
+    ) {
+        let opts = TmpOptions {
+            out_delim: b"|;|".to_vec(),
+            out_eol: b"\r\n".to_vec(),
+            ..TmpOptions::default()
+        };
+        for pad in 0..8 {
+            let data = [&b"xxxxxxxx"[..pad], construct].concat();
+            let exp = format!("\"{}{}", &"xxxxxxxx"[..pad], &exp[1..]);
+            for chunk_size in 1..=data.len() + 1 {
+                let mut output = Vec::new();
+                let size = data.len() as u64;
+                exorcize_csv(Cursor::new(&data), &mut output, size, chunk_size, &opts).unwrap();
+                assert_eq!(
+                    String::from_utf8(output).unwrap(),
+                    exp,
+                    "pad {pad} chunk_size {chunk_size}"
+                );
+            }
+        }
+    }
+
+    #[rstest]
+    fn test_exorcize_csv_every_split_matches_one_batch() {
+        for opts in boundary_options() {
+            for &data in BOUNDARY_CORPUS {
+                let size = data.len() as u64;
+                let mut exp = Vec::new();
+                let whole = exorcize_csv(Cursor::new(data), &mut exp, size, 1024, &opts).unwrap();
+
+                for chunk_size in 1..data.len() {
+                    let mut output = Vec::new();
+                    let input = Cursor::new(data);
+                    let stats = exorcize_csv(input, &mut output, size, chunk_size, &opts).unwrap();
+                    let context = format!("{opts:?} {data:?} chunk_size {chunk_size}");
+                    assert_eq!(output, exp, "{context}");
+                    assert_eq!(
+                        (stats.rows, stats.fields, stats.quotes),
+                        (whole.rows, whole.fields, whole.quotes),
+                        "{context}"
+                    );
+                    assert_eq!(stats.anomalies, whole.anomalies, "{context}");
+                    assert_eq!(stats.repairs, whole.repairs, "{context}");
+                }
+            }
+        }
+    }
+
+    #[rstest]
+    #[case(b"field1\x1Efield2\x1D\x1D")]
+    #[case(b"field1\"\x1E\"")]
+    #[case(b"a\\\\\x1Eb\\\\\\\x1Dc\\\\")]
+    #[case(b"a\\\"b\x1Ea\\\\\"b\x1E\\\"")]
+    #[case(b"\"\"field\",\"field\",field\"\x1Efield3\x1D")]
+    #[case(b"\xEF\xBB\xBFa\x1D\"\"\"")]
+    fn test_drivers_match_at_small_chunk_sizes(
+        #[case] data: &[u8],
+        #[values(1, 2, 3, 1024)] chunk_size: usize,
+    ) {
+        let size = data.len() as u64;
+        for opts in boundary_options() {
+            let mut exp = Vec::new();
+            exorcize_csv(Cursor::new(data), &mut exp, size, 1024, &opts).unwrap();
+            let mut output = Vec::new();
+            exorcize_csv(Cursor::new(data), &mut output, size, chunk_size, &opts).unwrap();
+            assert_eq!(output, exp, "{opts:?}");
+        }
+
+        let opts = TmpOptions {
+            escape_only: true,
+            ..TmpOptions::default()
+        };
+        let mut exp = Vec::new();
+        escape_only(Cursor::new(data), &mut exp, 1024, &opts).unwrap();
+        let mut output = Vec::new();
+        escape_only(Cursor::new(data), &mut output, chunk_size, &opts).unwrap();
+        assert_eq!(output, exp);
+    }
+
+    #[rstest]
+    #[case("a\\b\x1Ec", "a\\\\b\",\"c")]
+    #[case("a\\\\b\x1Ec", "a\\\\\\\\b\",\"c")]
+    #[case("a\\\x1Eb\\\\\x1D", "a\\\\\",\"b\\\\\\\\\"\n\"")]
+    #[case("\\\"\\", "\\\\\\\"\\\\")]
+    #[case("field1\x1Efield2", "field1\",\"field2")]
+    fn test_exorcize_csv_batch_double_backslash(#[case] haystack: &str, #[case] expected: &str) {
+        let opts = TmpOptions {
+            double_backslash: true,
+            ..TmpOptions::default()
+        };
+
+        let buf = exorcize_csv_batch(haystack.as_bytes(), Some(b'\\'), &opts);
+        assert_eq!(buf, expected.as_bytes());
+    }
+
+    #[rstest]
+    #[case("a\x0Bb\x1Ec", vec![(b'\x0B', "\\v")], "a\\vb\",\"c")]
+    #[case("a\x0B\x0B\x1D", vec![(b'\x0B', "")], "a\"\n\"")]
+    #[case("a\\\x1Eb\\", vec![(b'\\', "\\\\")], "a\\\\\",\"b\\\\")]
+    #[case(
+        "\x0B\x0C\t\0x\"y",
+        vec![(b'\x0B', "\\v"), (b'\x0C', "\\f"), (b'\t', "\\t"), (b'\0', "")],
+        "\\v\\f\\tx\\\"y"
+    )]
+    fn test_exorcize_csv_batch_escapes(
+        #[case] haystack: &str,
+        #[case] escapes: Vec<(u8, &str)>,
+        #[case] expected: &str,
+    ) {
+        let escapes = escapes.into_iter().map(|(b, r)| (b, r.as_bytes().to_vec()));
+        let opts = TmpOptions {
+            escapes: Some(Escapes::new(escapes)),
+            ..TmpOptions::default()
+        };
+
+        let buf = exorcize_csv_batch(haystack.as_bytes(), None, &opts);
+        assert_eq!(String::from_utf8(buf).unwrap(), expected);
+    }
+
+    #[rstest]
+    #[case(Case::Upper, "aB\x1Ec\"d\x1Déz", "AB\",\"C\\\"D\"\n\"éZ")]
+    #[case(Case::Lower, "aB\x1EC\"D\x1DÉZ", "ab\",\"c\\\"d\"\n\"Éz")]
+    #[case(Case::Upper, "a\x0Bb", "A\\vB")]
+    fn test_exorcize_csv_batch_case(
+        #[case] case: Case,
+        #[case] haystack: &str,
+        #[case] expected: &str,
+    ) {
+        let opts = TmpOptions {
+            case: Some(case),
+            escapes: Some(Escapes::new([(b'\x0B', b"\\v".to_vec())])),
+            ..TmpOptions::default()
+        };
+
+        let buf = exorcize_csv_batch(haystack.as_bytes(), None, &opts);
+        assert_eq!(String::from_utf8(buf).unwrap(), expected);
+    }
+
+    #[rstest]
+    fn test_exorcize_csv_case_custom_output_bytes() {
+        let data = b"ab\x1Ecd";
+        let opts = TmpOptions {
+            quote: b'q',
+            out_delim: b"x".to_vec(),
+            case: Some(Case::Upper),
+            ..TmpOptions::default()
+        };
+
+        let mut output = Vec::new();
+        let result = exorcize_csv(Cursor::new(data), &mut output, 5, 1024, &opts);
+        assert!(result.is_ok());
+        assert_eq!(output, b"qABqxqCDq");
+    }
+
+    #[rstest]
+    #[case(
+        "\"\"field\",\"field\",field\"",
+        "\\\"field\\\",\\\"field\\\",field\\\""
+    )]
+    #[case("a\"\"b\x1E\"\"", "a\\\"b\",\"\\\"")]
+    #[case("\"\"\"\x1E\"\"\"\"", "\\\"\\\"\",\"\\\"\\\"")]
+    #[case("\"\x1E\"", "\\\"\",\"\\\"")]
+    fn test_exorcize_csv_batch_collapse_double_quotes(
+        #[case] haystack: &str,
+        #[case] expected: &str,
+    ) {
+        let opts = TmpOptions {
+            collapse_double_quotes: true,
+            ..TmpOptions::default()
+        };
+
+        let buf = exorcize_csv_batch(haystack.as_bytes(), None, &opts);
+        assert_eq!(String::from_utf8(buf).unwrap(), expected);
+    }
+
+    #[rstest]
+    #[case(b"\"\"field\",\"field\",field\"\x1Efield3\x1D")]
+    #[case(b"a\"\"\"\"\"b\x1E\"\"\x1Dc\"\"\"")]
+    fn test_exorcize_csv_collapse_double_quotes_chunking_invariant(#[case] data: &[u8]) {
+        let opts = TmpOptions {
+            collapse_double_quotes: true,
+            ..TmpOptions::default()
+        };
+        let size = data.len() as u64;
+
+        let mut exp = Vec::new();
+        let stats = exorcize_csv(Cursor::new(data), &mut exp, size, 1024, &opts).unwrap();
+        assert_eq!(stats.quotes, memchr_iter(b'"', data).count() as u64);
+
+        for chunk_size in 1..data.len() {
+            let mut output = Vec::new();
+            exorcize_csv(Cursor::new(data), &mut output, size, chunk_size, &opts).unwrap();
+            assert_eq!(output, exp, "chunk_size {chunk_size}");
+        }
+    }
+
+    // a backslash is plain content, even before a quote or a break
+    #[rstest]
+    #[case(
+        b"say \"hi\"\x1Ea\\\"\x1Dend\\",
+        b"\"say \"\"hi\"\"\",\"a\\\"\"\"\n\"end\\\""
+    )]
+    #[case(b"\"\x1E\\\x1E\"\"", b"\"\"\"\",\"\\\",\"\"\"\"\"\"")]
+    #[case(b"a\\\x1Db", b"\"a\\\"\n\"b\"")]
+    fn test_exorcize_csv_double_quotes(#[case] data: &[u8], #[case] exp: &[u8]) {
+        let opts = TmpOptions {
+            double_quotes: true,
+            ..TmpOptions::default()
+        };
+        let size = data.len() as u64;
+
+        for chunk_size in 1..=data.len() {
+            let mut output = Vec::new();
+            exorcize_csv(Cursor::new(data), &mut output, size, chunk_size, &opts).unwrap();
+            assert_eq!(output, exp, "chunk_size {chunk_size}");
+        }
+    }
+
+    #[rstest]
+    fn test_escapes_keep_last_replacement() {
+        let escapes = Escapes::new([(b'\x0B', b"a".to_vec()), (b'\x0B', b"b".to_vec())]);
+        assert!(escapes.contains(b'\x0B'));
+        assert!(!escapes.contains(b'\x0C'));
+
         let mut buf = Vec::new();
-        let sep = b'\x1E';
-        let eol = b'\x1D';
+        escapes.extend(&mut buf, b"\x0Bx", |buf, run| buf.extend_from_slice(run));
+        assert_eq!(buf, b"bx");
+    }
+
+    #[rstest]
+    #[case(0, b"ab", 0)]
+    #[case(0, b"a\\", 1)]
+    #[case(3, b"a\\\\", 2)]
+    #[case(3, b"\\\\", 5)]
+    #[case(1, b"", 1)]
+    fn test_trailing_backslashes(#[case] count: usize, #[case] chunk: &[u8], #[case] exp: usize) {
+        assert_eq!(trailing_backslashes(count, chunk), exp);
+    }
+
+    #[rstest]
+    #[case(b"a\x1Eb\\", 1024, false)]
+    #[case(b"a\x1Eb\\", 1, false)]
+    #[case(b"a\x1E\\\\\\", 2, false)]
+    #[case(b"a\x1Eb\\\\", 1, true)]
+    #[case(b"a\x1Eb\\\x1D", 1024, true)]
+    #[case(b"a\x1Eb\x1D", 1024, true)]
+    #[case(b"a\x1Eb", 1024, true)]
+    fn test_exorcize_csv_strict_eof(
+        #[case] data: &[u8],
+        #[case] chunk_size: usize,
+        #[case] ok: bool,
+    ) {
+        let opts = TmpOptions {
+            strict_eof: true,
+            ..TmpOptions::default()
+        };
+
+        let size = data.len() as u64;
+        let result = exorcize_csv(Cursor::new(data), Vec::new(), size, chunk_size, &opts);
+        assert_eq!(result.is_ok(), ok);
+        if let Err(e) = result {
+            assert_eq!(e.kind(), io::ErrorKind::InvalidData);
+        }
+    }
+
+    #[rstest]
+    #[case(b"field1\x1Efield2", "\"field1\",\"field2\"")]
+    #[case(b"field1\x1Efield2\x1D", "\"field1\",\"field2\"\n")]
+    #[case(b"field1\x1Efield2\x1D\x1D", "\"field1\",\"field2\"\n\"\"\n")]
+    #[case(b"field1\x1E", "\"field1\",\"\"")]
+    #[case(b"field1\"", "\"field1\\\"\"")]
+    #[case(b"field1\n", "\"field1\n\"")]
+    #[case(b"field1\\", "\"field1\\\\\"")]
+    #[case(b"a", "\"a\"")]
+    fn test_handle_closing(#[case] data: &[u8], #[case] exp: &str) {
+        let opts = TmpOptions::default();
+        let size = data.len() as u64;
+
+        for chunk_size in [1, 2, 1024] {
+            let mut output = Vec::new();
+            exorcize_csv(Cursor::new(data), &mut output, size, chunk_size, &opts).unwrap();
+            assert_eq!(
+                String::from_utf8(output).unwrap(),
+                exp,
+                "chunk_size {chunk_size}"
+            );
+        }
+    }
+
+    #[rstest]
+    #[case(b"a\\\"b", false, "\"a\\\"b\"")]
+    #[case(b"a\\\\\"b", false, "\"a\\\\\\\"b\"")]
+    #[case(b"a\"b", false, "\"a\\\"b\"")]
+    #[case(b"\\\"\x1E\\\"", false, "\"\\\"\",\"\\\"\"")]
+    #[case(b"a\\\"b", true, "\"a\\\\\\\"b\"")]
+    fn test_exorcize_csv_backslash_before_quote(
+        #[case] data: &[u8],
+        #[case] double_backslash: bool,
+        #[case] exp: &str,
+    ) {
+        let opts = TmpOptions {
+            double_backslash,
+            ..TmpOptions::default()
+        };
+
+        for chunk_size in [1, 2, 1024] {
+            let mut output = Vec::new();
+            let size = data.len() as u64;
+            exorcize_csv(Cursor::new(data), &mut output, size, chunk_size, &opts).unwrap();
+            assert_eq!(
+                String::from_utf8(output).unwrap(),
+                exp,
+                "chunk_size {chunk_size}"
+            );
+        }
+        let mut quoted = Vec::new();
+        quote_field(
+            data.split(|&b| b == opts.sep).next().unwrap(),
+            &mut quoted,
+            &opts,
+        );
+        assert!(exp.as_bytes().starts_with(&quoted));
+    }
+
+    #[rstest]
+    #[case(b"a\x1Eb\x1Dc", None, "\"a\",\"b\"\n\"c\"\n#rows=2 \"raw\"")]
+    #[case(b"a\x1Dc\x1D", None, "\"a\"\n\"c\"\n#rows=2 \"raw\"\n")]
+    #[case(b"a\x1Dc\x1D", Some(false), "\"a\"\n\"c\"\n#rows=2 \"raw\"")]
+    #[case(b"a", Some(true), "\"a\"\n#rows=1 \"raw\"\n")]
+    #[case(b"\x1D", None, "\"\"\n#rows=1 \"raw\"\n")]
+    #[case(b"", Some(true), "")]
+    fn test_exorcize_csv_footer(
+        #[case] data: &[u8],
+        #[case] final_newline: Option<bool>,
+        #[case] exp: &str,
+    ) {
+        let opts = TmpOptions {
+            final_newline,
+            footer: Some("#rows={rows} \"raw\"".to_string()),
+            ..TmpOptions::default()
+        };
+        let size = data.len() as u64;
+
+        for chunk_size in [1, 2, 1024] {
+            let mut output = Vec::new();
+            exorcize_csv(Cursor::new(data), &mut output, size, chunk_size, &opts).unwrap();
+            assert_eq!(
+                String::from_utf8(output).unwrap(),
+                exp,
+                "chunk_size {chunk_size}"
+            );
+        }
+    }
+
+    #[rstest]
+    #[case(b"a\x1Eb", 1024, true)]
+    #[case(b"a\x1Db", 1024, true)]
+    #[case(b"\"a\",\"b\"\n\"c\"", 1024, false)]
+    #[case(b"", 1024, false)]
+    // only the first chunk is looked at
+    #[case(b"abc\x1E", 3, false)]
+    fn test_is_broken(#[case] data: &[u8], #[case] chunk_size: usize, #[case] exp: bool) {
+        let opts = TmpOptions::default();
+        assert_eq!(
+            is_broken(Cursor::new(data), chunk_size, &opts).unwrap(),
+            exp
+        );
+    }
+
+    #[rstest]
+    #[case(1)]
+    #[case(3)]
+    #[case(1024)]
+    fn test_exorcize_csv_repair_log(#[case] chunk_size: usize) {
+        let data = b"a\"b\x1Ec\\\x1Ex\x1Ey\x1Dz";
+        let opts = TmpOptions {
+            pad_to: Some(2),
+            repair_log: true,
+            ..TmpOptions::default()
+        };
+
+        let size = data.len() as u64;
+        let stats = exorcize_csv(Cursor::new(data), io::sink(), size, chunk_size, &opts).unwrap();
+        let entry = |offset, row, col, repair| RepairEntry {
+            offset,
+            row,
+            col,
+            repair,
+        };
+        assert_eq!(
+            stats.repairs.unwrap(),
+            vec![
+                entry(1, 0, 0, Repair::EscapedQuote),
+                entry(5, 0, 1, Repair::ReescapedBackslash),
+                entry(6, 0, 2, Repair::DroppedField),
+                entry(8, 0, 3, Repair::DroppedField),
+                entry(12, 1, 0, Repair::PaddedRow { fields: 1 }),
+            ]
+        );
+    }
+
+    #[rstest]
+    #[case(false, None)]
+    #[case(true, Some(vec!["0\t0\t0\tescaped quote", "1\t0\t0\tcollapsed quote"]))]
+    fn test_exorcize_csv_repair_log_collapse(
+        #[case] repair_log: bool,
+        #[case] exp: Option<Vec<&str>>,
+    ) {
+        let data = b"\"\"a\x1Eb";
+        let opts = TmpOptions {
+            collapse_double_quotes: true,
+            repair_log,
+            ..TmpOptions::default()
+        };
+
+        let size = data.len() as u64;
+        let stats = exorcize_csv(Cursor::new(data), io::sink(), size, 2, &opts).unwrap();
+        let lines = stats
+            .repairs
+            .map(|repairs| repairs.iter().map(ToString::to_string).collect::<Vec<_>>());
+        assert_eq!(
+            lines,
+            exp.map(|exp| exp.into_iter().map(String::from).collect())
+        );
+    }
+
+    #[rstest]
+    #[case(b"x\x1Ey", "\"id\",\"name\"\n\"x\",\"y\"")]
+    #[case(b"", "")]
+    fn test_exorcize_csv_header(#[case] data: &[u8], #[case] exp: &str) {
+        let opts = TmpOptions {
+            header: Some(vec![b"id".to_vec(), b"name".to_vec()]),
+            ..TmpOptions::default()
+        };
+
+        let mut output = Vec::new();
+        let size = data.len() as u64;
+        let stats = exorcize_csv(Cursor::new(data), &mut output, size, 3, &opts).unwrap();
+        assert_eq!(String::from_utf8(output).unwrap(), exp);
+        assert_eq!(stats.bytes_written, exp.len() as u64);
+    }
+
+    #[rstest]
+    #[case(vec!["a\"b", "c\\", "d\x0B"])]
+    #[case(vec!["", "x y", "Z"])]
+    fn test_write_header_matches_data_row(#[case] names: Vec<&str>) {
+        let opts = TmpOptions {
+            quote: b'\'',
+            out_delim: b";".to_vec(),
+            escapes: Some(Escapes::new([(b'\x0B', b"\\v".to_vec())])),
+            ..TmpOptions::default()
+        };
+        let row = names.join("\x1E");
+
+        let mut data_row = Vec::new();
+        let size = row.len() as u64;
+        exorcize_csv(Cursor::new(&row), &mut data_row, size, 1024, &opts).unwrap();
+
+        let opts = TmpOptions {
+            header: Some(names.iter().map(|n| n.as_bytes().to_vec()).collect()),
+            ..opts
+        };
+        let mut header = Vec::new();
+        write_header(&mut header, &opts);
+        assert_eq!(header.pop(), Some(b'\n'));
+        assert_eq!(
+            String::from_utf8(header).unwrap(),
+            String::from_utf8(data_row).unwrap()
+        );
+    }
+
+    #[rstest]
+    #[case(b"a\x1Eb\x1Dc", None, "\u{FEFF}\"a\",\"b\"\n\"c\"")]
+    #[case(b"a\x1Eb", Some(vec!["x", "y"]), "\u{FEFF}\"x\",\"y\"\n\"a\",\"b\"")]
+    #[case(b"", Some(vec!["x"]), "")]
+    fn test_exorcize_csv_write_bom(
+        #[case] data: &[u8],
+        #[case] header: Option<Vec<&str>>,
+        #[case] exp: &str,
+    ) {
+        let opts = TmpOptions {
+            write_bom: true,
+            header: header.map(|names| names.iter().map(|n| n.as_bytes().to_vec()).collect()),
+            ..TmpOptions::default()
+        };
+        let size = data.len() as u64;
+        for chunk_size in 1..=data.len().max(1) {
+            let mut output = Vec::new();
+            exorcize_csv(Cursor::new(data), &mut output, size, chunk_size, &opts).unwrap();
+            assert_eq!(
+                String::from_utf8(output).unwrap(),
+                exp,
+                "chunk_size {chunk_size}"
+            );
+
+            let mut buffered = Vec::new();
+            let input = Cursor::new(data);
+            let hook = &mut crate::buffered::NoHook;
+            crate::buffered::exorcize_csv(input, &mut buffered, chunk_size, &opts, hook).unwrap();
+            assert_eq!(
+                String::from_utf8(buffered).unwrap(),
+                exp,
+                "buffered, chunk_size {chunk_size}"
+            );
+        }
+    }
+
+    #[rstest]
+    #[case(b"\x1E", "\"\",\"\"")]
+    #[case(b"\x1D", "\"\"\n")]
+    #[case(b"\x1E\x1D", "\"\",\"\"\n")]
+    #[case(b"\x1D\x1E", "\"\"\n\"\",\"\"")]
+    #[case(b"\x1E\x1E\x1D\x1E\x1E\x1D", "\"\",\"\",\"\"\n\"\",\"\",\"\"\n")]
+    fn test_exorcize_csv_only_delimiters(
+        #[case] data: &[u8],
+        #[case] exp: &str,
+        #[values(1, 2, 1024)] chunk_size: usize,
+    ) {
+        let opts = TmpOptions::default();
+
+        let mut output = Vec::new();
+        let size = data.len() as u64;
+        let stats = exorcize_csv(Cursor::new(data), &mut output, size, chunk_size, &opts).unwrap();
+        assert_eq!(String::from_utf8(output).unwrap(), exp);
+        assert_eq!(stats.bytes_written, exp.len() as u64);
+    }
+
+    #[rstest]
+    #[case(b"field1\x1Efield2", None, "\"field1\",\"field2\"")]
+    #[case(b"field1\x1Efield2\x1D", None, "\"field1\",\"field2\"\n")]
+    #[case(b"field1\x1Efield2", Some(true), "\"field1\",\"field2\"\n")]
+    #[case(b"field1\x1Efield2\x1D", Some(true), "\"field1\",\"field2\"\n")]
+    #[case(b"field1\x1Efield2", Some(false), "\"field1\",\"field2\"")]
+    #[case(b"field1\x1Efield2\x1D", Some(false), "\"field1\",\"field2\"")]
+    #[case(b"field1\x1Dfield2\x1D", Some(false), "\"field1\"\n\"field2\"")]
+    // the second `eol` ends an empty row, which is kept as data
+    #[case(b"field1\x1D\x1D", Some(false), "\"field1\"\n\"\"")]
+    #[case(b"field1\x1D\x1D", Some(true), "\"field1\"\n\"\"\n")]
+    #[case(b"", Some(true), "")]
+    fn test_exorcize_csv_final_newline(
+        #[case] data: &[u8],
+        #[case] final_newline: Option<bool>,
+        #[case] exp: &str,
+    ) {
+        let opts = TmpOptions {
+            final_newline,
+            ..TmpOptions::default()
+        };
 
-        let result = exorcize_csv_batch(haystack.as_bytes(), &mut buf, sep, eol);
+        let mut output = Vec::new();
+        let result = exorcize_csv(
+            Cursor::new(data),
+            &mut output,
+            data.len() as u64,
+            1024,
+            &opts,
+        );
         assert!(result.is_ok());
-        assert_eq!(buf, expected.as_bytes());
+        assert_eq!(output, exp.as_bytes());
+    }
+
+    #[rstest]
+    #[case(b"a\x1Eb\x1Dc\x1Ed\x1De", 1024, 3, 5, vec![Anomaly::RaggedRows { rows: 1, modal_fields: 2 }])]
+    #[case(b"a\x1Eb\x1Dc\x1Ed\x1D", 3, 2, 4, vec![])]
+    #[case(b"a\x1E\xEF\xBB\xBFb", 4, 1, 2, vec![Anomaly::Bom { offset: 2 }])]
+    #[case(b"\xEF\xBB\xBFa\0\x1Eb", 2, 1, 2, vec![Anomaly::Nul { offset: 4 }])]
+    #[case(b"", 1024, 0, 0, vec![])]
+    fn test_exorcize_csv_stats(
+        #[case] data: &[u8],
+        #[case] chunk_size: usize,
+        #[case] rows: u64,
+        #[case] fields: u64,
+        #[case] anomalies: Vec<Anomaly>,
+    ) {
+        let opts = TmpOptions::default();
+
+        let mut output = Vec::new();
+        let size = data.len() as u64;
+        let stats = exorcize_csv(Cursor::new(data), &mut output, size, chunk_size, &opts).unwrap();
+
+        assert_eq!((stats.rows, stats.fields), (rows, fields));
+        assert_eq!(stats.bytes_written, output.len() as u64);
+        assert_eq!(stats.anomalies, anomalies);
+    }
+
+    #[rstest]
+    #[case(b"id,name\n1,say \"hi\"\n", "id,name\n1,say \\\"hi\\\"\n", (2, 4))]
+    #[case(b"a,b\x1Ec\x1D\"", "a,b\x1Ec\x1D\\\"", (1, 2))]
+    #[case(b"a,,\n\nb", "a,,\n\nb", (3, 5))]
+    #[case(b"", "", (0, 0))]
+    fn test_escape_only(#[case] data: &[u8], #[case] exp: &str, #[case] counts: (u64, u64)) {
+        let opts = TmpOptions::default();
+
+        for chunk_size in 1..=data.len().max(1) {
+            let mut output = Vec::new();
+            let stats = escape_only(Cursor::new(data), &mut output, chunk_size, &opts).unwrap();
+            assert_eq!(
+                String::from_utf8(output).unwrap(),
+                exp,
+                "chunk_size {chunk_size}"
+            );
+            assert_eq!((stats.rows, stats.fields), counts);
+            assert_eq!(stats.bytes_written, exp.len() as u64);
+        }
+    }
+
+    #[rstest]
+    #[case(b"a,b\\", "a,b\\")]
+    #[case(b"a,b,", "a,b,")]
+    #[case(b"a,b\n", "a,b\n")]
+    #[case(b"a\n\n", "a\n\n")]
+    #[case(b"a,\"b\"", "a,\\\"b\\\"")]
+    fn test_escape_only_writes_last_row_verbatim(#[case] data: &[u8], #[case] exp: &str) {
+        // no field was opened, so none is closed: no quote is added or taken back
+        for final_newline in [None, Some(true), Some(false)] {
+            let opts = TmpOptions {
+                final_newline,
+                ..TmpOptions::default()
+            };
+            for chunk_size in [1, 2, 1024] {
+                let mut output = Vec::new();
+                escape_only(Cursor::new(data), &mut output, chunk_size, &opts).unwrap();
+                assert_eq!(
+                    String::from_utf8(output).unwrap(),
+                    exp,
+                    "chunk_size {chunk_size}"
+                );
+            }
+        }
+    }
+
+    #[rstest]
+    fn test_escape_only_crlf_and_repairs() {
+        let data = b"a\"b,c\r\nd,\"\r\n";
+        let opts = TmpOptions {
+            out_eol: b"\r\n".to_vec(),
+            repair_log: true,
+            ..TmpOptions::default()
+        };
+
+        let mut output = Vec::new();
+        let stats = escape_only(Cursor::new(data), &mut output, 4, &opts).unwrap();
+        assert_eq!(output, b"a\\\"b,c\r\nd,\\\"\r\n");
+        assert_eq!((stats.rows, stats.quotes), (2, 2));
+
+        let offsets: Vec<(u64, u64, u64)> = stats
+            .repairs
+            .unwrap()
+            .iter()
+            .map(|entry| (entry.offset, entry.row, entry.col))
+            .collect();
+        assert_eq!(offsets, vec![(1, 0, 0), (9, 1, 1)]);
+    }
+
+    #[rstest]
+    #[case(b"a\x1Eb\x1Dc\x1Ed\x1D", 2, None)]
+    #[case(b"a\x1Eb\x1Dc\x1Ed", 2, None)]
+    #[case(b"", 2, None)]
+    #[case(b"a\x1Eb\x1Dc\x1Dd\x1Ee\x1Df", 2, Some((1, 4, 1)))]
+    #[case(b"a\x1Eb\x1Dc\x1Ed\x1E\x1Dx", 2, Some((1, 4, 3)))]
+    #[case(b"a\x1Eb\x1Dc", 2, Some((1, 4, 1)))]
+    #[case(b"a\x1Eb\x1D\x1D", 2, Some((1, 4, 1)))]
+    #[case(b"a\x1Db\x1D", 1, None)]
+    fn test_first_ragged_row(
+        #[case] data: &[u8],
+        #[case] expected_fields: u64,
+        #[case] exp: Option<(u64, u64, u64)>,
+    ) {
+        let opts = TmpOptions::default();
+        let exp = exp.map(|(row, offset, fields)| RaggedRow {
+            row,
+            offset,
+            fields,
+            expected: expected_fields,
+        });
+
+        for chunk_size in 1..=data.len().max(1) {
+            let found =
+                first_ragged_row(Cursor::new(data), Some(expected_fields), chunk_size, &opts);
+            assert_eq!(found.unwrap(), exp, "chunk_size {chunk_size}");
+        }
+    }
+
+    #[rstest]
+    #[case(b"a\x1Eb\x1Dc\x1Ed\x1D", None)]
+    #[case(b"a\x1Eb\x1Ec", None)]
+    #[case(b"", None)]
+    #[case(b"a\x1Eb\x1Dc\x1Ed\x1De\x1Df\x1Eg", Some((2, 8, 1, 2)))]
+    #[case(b"a\x1Db\x1Dc\x1Ed\x1D", Some((2, 4, 2, 1)))]
+    #[case(b"a\x1Eb\x1Ec\x1Dd\x1Ee", Some((1, 6, 2, 3)))]
+    fn test_first_ragged_row_header(
+        #[case] data: &[u8],
+        #[case] exp: Option<(u64, u64, u64, u64)>,
+    ) {
+        let opts = TmpOptions::default();
+        let exp = exp.map(|(row, offset, fields, expected)| RaggedRow {
+            row,
+            offset,
+            fields,
+            expected,
+        });
+
+        for chunk_size in 1..=data.len().max(1) {
+            let found = first_ragged_row(Cursor::new(data), None, chunk_size, &opts);
+            assert_eq!(found.unwrap(), exp, "chunk_size {chunk_size}");
+        }
+    }
+
+    #[rstest]
+    #[case(b"a\x1Eb\x1Dc\x1Ed\x1D", None, 2, Some(2), vec![])]
+    #[case(b"", None, 0, None, vec![])]
+    #[case(b"a\x1Eb\x1Dc\x1Dd\x1Ee\x1Df\x1E\x1E", None, 4, Some(2), vec![1, 3])]
+    #[case(b"a\x1Db\x1Ec\x1Dd", Some(2), 3, Some(2), vec![0, 2])]
+    #[case(b"a\x1Eb\x1D\x1D", None, 2, Some(2), vec![1])]
+    fn test_ragged_rows(
+        #[case] data: &[u8],
+        #[case] expected_fields: Option<u64>,
+        #[case] rows: u64,
+        #[case] expected: Option<u64>,
+        #[case] ragged: Vec<u64>,
+    ) {
+        let opts = TmpOptions::default();
+        let exp = RowWidths {
+            rows,
+            expected,
+            ragged,
+        };
+
+        for chunk_size in 1..=data.len().max(1) {
+            let found = ragged_rows(Cursor::new(data), expected_fields, chunk_size, &opts);
+            assert_eq!(found.unwrap(), exp, "chunk_size {chunk_size}");
+        }
+    }
+
+    #[rstest]
+    fn test_first_ragged_row_stops_early() {
+        let data = b"a\x1Db\x1Ec\x1D";
+        let opts = TmpOptions::default();
+
+        // a read after the ragged row would fail
+        let input = Cursor::new(data).chain(Broken);
+        let found = first_ragged_row(input, Some(1), 1, &opts).unwrap();
+        assert_eq!(found.map(|ragged| ragged.row), Some(1));
+        let input = Cursor::new(b"a\x1Db").chain(Broken);
+        assert!(first_ragged_row(input, Some(1), 1, &opts).is_err());
+    }
+
+    /// Counts the bytes written, the writes and the largest single write.
+    #[derive(Default)]
+    struct WriteSizes {
+        total: usize,
+        writes: usize,
+        max: usize,
+    }
+
+    impl Write for WriteSizes {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            self.total += buf.len();
+            self.writes += 1;
+            self.max = self.max.max(buf.len());
+            Ok(buf.len())
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    #[rstest]
+    #[case(b"a\x1Eb\\\x1Dc\"d\x1D")]
+    #[case(b"a\x1Eb\x1Dc")]
+    #[case(b"\x1D")]
+    #[case(b"")]
+    fn test_chunks_match_exorcize_csv(#[case] data: &[u8]) {
+        let opts = || TmpOptions {
+            header: Some(vec![b"h".to_vec()]),
+            ..TmpOptions::default()
+        };
+        let size = data.len() as u64;
+        for chunk_size in 1..=data.len().max(1) {
+            let mut exp = Vec::new();
+            let stats = exorcize_csv(Cursor::new(data), &mut exp, size, chunk_size, &opts());
+            let stats = stats.unwrap();
+
+            let mut chunks = Chunks::new(Cursor::new(data), size, chunk_size, opts());
+            let mut output = Vec::new();
+            while let Some(chunk) = chunks.next_chunk().unwrap() {
+                assert!(!chunk.is_empty());
+                assert!(chunk.len() <= exp.len());
+                output.push(chunk);
+            }
+            assert!(chunks.next_chunk().unwrap().is_none());
+            assert_eq!(output.concat(), exp, "chunk_size {chunk_size}");
+            assert_eq!(chunks.feed.stats.rows, stats.rows);
+            assert_eq!(chunks.feed.stats.bytes_written, stats.bytes_written);
+            // the last batch, closed: the final `eol` without the quote it opened
+            if chunk_size == 1 && data.ends_with(b"\x1D") {
+                assert_eq!(output.last().unwrap(), b"\"\n");
+            }
+        }
+    }
+
+    #[rstest]
+    // last batches of one to three bytes, ending in and out of a field
+    #[case(b"a\x1Eb\x1E", 2, "\"a\",\"b\",\"\"")]
+    #[case(b"a\x1Eb", 2, "\"a\",\"b\"")]
+    #[case(b"a\x1Ebc", 3, "\"a\",\"bc\"")]
+    #[case(b"a\x1Eb\x1D", 2, "\"a\",\"b\"\n")]
+    #[case(b"a\x1D\\", 2, "\"a\"\n\"\\\\\"")]
+    #[case(b"b", 1, "\"b\"")]
+    #[case(b"\"", 1, "\"\\\"\"")]
+    fn test_exorcize_csv_short_last_batch(
+        #[case] data: &[u8],
+        #[case] chunk_size: usize,
+        #[case] exp: &str,
+    ) {
+        let opts = TmpOptions::default();
+        let size = data.len() as u64;
+        let mut output = Vec::new();
+        let stats = exorcize_csv(Cursor::new(data), &mut output, size, chunk_size, &opts).unwrap();
+        assert_eq!(String::from_utf8(output).unwrap(), exp);
+        assert_eq!(stats.bytes_written, exp.len() as u64);
+
+        let mut chunks = Chunks::new(Cursor::new(data), size, chunk_size, opts);
+        let mut output = Vec::new();
+        while let Some(chunk) = chunks.next_chunk().unwrap() {
+            output.extend(chunk);
+        }
+        assert_eq!(String::from_utf8(output).unwrap(), exp);
+    }
+
+    #[rstest]
+    #[case(b"a\x1Eb\x1Dc\"d\x1Ee\x1E\x1D")]
+    #[case(b"\xEF\xBB\xBFa\x1E\xEF\xBB\xBFb\x1D\x1D\0")]
+    #[case(b"\"\"\x1D\x1E\x1Ea\\")]
+    #[case(b"\x1D")]
+    #[case(b"")]
+    fn test_count_only_matches_exorcize_csv(#[case] data: &[u8]) {
+        let opts = TmpOptions::default();
+        let size = data.len() as u64;
+        for chunk_size in 1..=data.len().max(1) {
+            let exp = exorcize_csv(Cursor::new(data), io::sink(), size, chunk_size, &opts).unwrap();
+            let stats = count_only(Cursor::new(data), chunk_size, &opts).unwrap();
+            let context = format!("chunk_size {chunk_size}");
+            assert_eq!(stats.rows, exp.rows, "{context}");
+            assert_eq!(stats.fields, exp.fields, "{context}");
+            assert_eq!(stats.quotes, exp.quotes, "{context}");
+            assert_eq!(stats.bytes_read, exp.bytes_read, "{context}");
+            assert_eq!(stats.field_counts, exp.field_counts, "{context}");
+            assert_eq!(stats.anomalies, exp.anomalies, "{context}");
+            assert_eq!(stats.bytes_written, 0);
+        }
+    }
+
+    #[rstest]
+    fn test_chunks_keep_stats_on_failure() {
+        let opts = TmpOptions {
+            strict_eof: true,
+            ..TmpOptions::default()
+        };
+        let mut chunks = Chunks::new(Cursor::new(b"a\x1Db\\"), 4, 2, opts);
+        let e = loop {
+            match chunks.next_chunk() {
+                Ok(Some(_)) => {}
+                Ok(None) => panic!("a trailing backslash under strict_eof should fail"),
+                Err(e) => break e,
+            }
+        };
+        assert_eq!(Partial::stats(&e).unwrap().bytes_read, 4);
+        assert!(chunks.next_chunk().unwrap().is_none());
+    }
+
+    #[rstest]
+    fn test_feed_boundary() {
+        let mut feed = Feed::new(UNKNOWN_SIZE, TmpOptions::default());
+        let mut boundaries = Vec::new();
+        for batch in [&b"a\\"[..], b"\\\x1E", b"\\", b"\x1Db"] {
+            feed.push(batch);
+            boundaries.push(feed.boundary());
+        }
+        let held = |offset, prev_byte, backslashes, held: &str| Boundary {
+            offset,
+            prev_byte: Some(prev_byte),
+            backslashes,
+            held: held.as_bytes().to_vec(),
+        };
+        assert_eq!(
+            boundaries,
+            [
+                held(2, b'\\', 1, "a\\"),
+                held(4, b'\x1E', 0, "\\\",\""),
+                held(5, b'\\', 1, "\\"),
+                held(7, b'b', 0, "\\\"\n\"b"),
+            ]
+        );
+    }
+
+    #[rstest]
+    #[case(b"a\x1Eb\\\x1Dc\"d\x1D")]
+    #[case(b"a\x1Eb\x1Dc\\")]
+    #[case(b"\x1D\x1D")]
+    #[case(b"")]
+    fn test_feed_pieces_match_exorcize_csv(
+        #[case] data: &[u8],
+        #[values(&[1], &[0, 2, 1], &[3, 0, 5], &[64])] pieces: &[usize],
+    ) {
+        let opts = || TmpOptions {
+            header: Some(vec![b"h".to_vec()]),
+            ..TmpOptions::default()
+        };
+        let mut exp = Vec::new();
+        let size = data.len() as u64;
+        let stats = exorcize_csv(Cursor::new(data), &mut exp, size, 1024, &opts()).unwrap();
+
+        let mut feed = Feed::new(UNKNOWN_SIZE, opts());
+        let (mut output, mut rest) = (Vec::new(), data);
+        for &len in pieces.iter().cycle() {
+            if rest.is_empty() {
+                break;
+            }
+            let (piece, tail) = rest.split_at(len.min(rest.len()));
+            output.extend(feed.push(piece));
+            rest = tail;
+        }
+        output.extend(feed.finish().unwrap());
+        assert_eq!(
+            String::from_utf8(output).unwrap(),
+            String::from_utf8(exp).unwrap()
+        );
+        assert_eq!(feed.stats.rows, stats.rows);
+        assert_eq!(feed.stats.bytes_written, stats.bytes_written);
+    }
+
+    #[rstest]
+    fn test_exorcize_csv_coalesces_writes() {
+        let data = b"a\x1E".repeat(WRITE_SIZE);
+        let opts = TmpOptions::default();
+
+        let mut sizes = WriteSizes::default();
+        let size = data.len() as u64;
+        exorcize_csv(Cursor::new(&data), &mut sizes, size, 1, &opts).unwrap();
+        // one write per `WRITE_SIZE`, less the few bytes of a batch that did not fit
+        let batch = 4;
+        assert_eq!(sizes.total, 4 * WRITE_SIZE + 2);
+        assert!(sizes.writes <= sizes.total.div_ceil(WRITE_SIZE - batch));
+        assert!(sizes.max <= WRITE_SIZE);
+    }
+
+    // small reads coalesced to `write_size`, and reads past it written as they come
+    #[rstest]
+    #[case(1, 64)]
+    #[case(1, 4096)]
+    #[case(4096, 64)]
+    #[case(4096, 1 << 20)]
+    fn test_exorcize_csv_write_size(#[case] chunk_size: usize, #[case] write_size: usize) {
+        let data = b"a\x1E".repeat(16 * 1024);
+        let opts = TmpOptions {
+            write_size,
+            ..TmpOptions::default()
+        };
+
+        let mut sizes = WriteSizes::default();
+        let size = data.len() as u64;
+        exorcize_csv(Cursor::new(&data), &mut sizes, size, chunk_size, &opts).unwrap();
+        assert_eq!(sizes.total, 2 * data.len() + 2);
+        // two output bytes per input byte, and the closing quote
+        let batch = 2 * chunk_size + 1;
+        match batch < write_size {
+            true => {
+                assert!(sizes.writes <= sizes.total.div_ceil(write_size - batch));
+                assert!(sizes.max <= write_size, "largest write {}", sizes.max);
+            }
+            // a batch past the buffer goes straight through, after what was buffered
+            false => assert!(sizes.writes <= 2 * data.len().div_ceil(chunk_size) + 1),
+        }
+    }
+
+    // no `sep` or `eol` at all: one field spanning every batch, never held whole
+    #[rstest]
+    #[case(7)]
+    #[case(4096)]
+    fn test_exorcize_csv_single_giant_field(#[case] chunk_size: usize) {
+        let content = b"ab\"c\\d".repeat(60_000);
+        let data = [&content[..], b"\\"].concat();
+        let opts = TmpOptions::default();
+
+        let mut exp = vec![b'"'];
+        for &byte in &content {
+            if byte == b'"' {
+                exp.push(b'\\');
+            }
+            exp.push(byte);
+        }
+        exp.extend_from_slice(b"\\\\\"");
+
+        let mut sizes = WriteSizes::default();
+        let size = data.len() as u64;
+        let stats = exorcize_csv(Cursor::new(&data), &mut sizes, size, chunk_size, &opts).unwrap();
+        assert_eq!((stats.rows, stats.fields), (1, 1));
+        assert_eq!(stats.bytes_written, exp.len() as u64);
+        assert_eq!(sizes.total, exp.len());
+        assert!(sizes.max <= WRITE_SIZE, "largest write {}", sizes.max);
+
+        let mut output = Vec::new();
+        exorcize_csv(Cursor::new(&data), &mut output, size, chunk_size, &opts).unwrap();
+        assert_eq!(output, exp);
+    }
+
+    struct Broken;
+
+    impl Read for Broken {
+        fn read(&mut self, _buf: &mut [u8]) -> io::Result<usize> {
+            Err(io::Error::new(io::ErrorKind::UnexpectedEof, "input gone"))
+        }
+    }
+
+    #[rstest]
+    fn test_exorcize_csv_error_keeps_stats() {
+        let data = b"a\x1Eb\x1Dc\x1Ed\x1D";
+        let opts = TmpOptions::default();
+
+        let input = Cursor::new(data).chain(Broken);
+        let e = exorcize_csv(input, Vec::new(), 32, 1024, &opts).unwrap_err();
+        assert_eq!(e.kind(), io::ErrorKind::UnexpectedEof);
+
+        let stats = Partial::stats(&e).unwrap();
+        assert_eq!((stats.rows, stats.fields, stats.bytes_read), (2, 4, 8));
+    }
+
+    #[rstest]
+    #[case(b"a\x1Eb\x1Dc\x1Ed", 3, "\"a\",\"b\"\n\"c\",\"d\"")]
+    #[case(b"a\x1Eb\x1D", 2, "\"a\",\"b\"\n")]
+    #[case(b"a\\", 1, "\"a\\\\\"")]
+    #[case(b"", 1, "")]
+    fn test_exorcize_stream_closes_output_on_read_error(
+        #[case] data: &[u8],
+        #[case] chunk_size: usize,
+        #[case] exp: &str,
+    ) {
+        let opts = TmpOptions::default();
+
+        let mut output = Vec::new();
+        let input = Cursor::new(data).chain(Broken);
+        let e = exorcize_stream(input, &mut output, chunk_size, &opts).unwrap_err();
+        assert_eq!(e.kind(), io::ErrorKind::UnexpectedEof);
+        assert_eq!(String::from_utf8(output).unwrap(), exp);
+
+        let stats = Partial::stats(&e).unwrap();
+        assert_eq!(stats.bytes_read, data.len() as u64);
+        assert_eq!(stats.bytes_written, exp.len() as u64);
+    }
+
+    #[rstest]
+    #[case(b"a\x1Eb\x1Dc")]
+    #[case(b"")]
+    fn test_exorcize_stream_matches_exorcize_csv(#[case] data: &[u8]) {
+        let opts = TmpOptions::default();
+
+        let mut streamed = Vec::new();
+        exorcize_stream(Cursor::new(data), &mut streamed, 2, &opts).unwrap();
+        let mut exp = Vec::new();
+        let size = data.len() as u64;
+        exorcize_csv(Cursor::new(data), &mut exp, size, 2, &opts).unwrap();
+        assert_eq!(streamed, exp);
+    }
+
+    #[rstest]
+    fn test_exorcize_csv_read_error_leaves_field_open() {
+        let data = b"a\x1Eb";
+        let opts = TmpOptions::default();
+
+        let mut output = Vec::new();
+        let input = Cursor::new(data).chain(Broken);
+        assert!(exorcize_csv(input, &mut output, 32, 1024, &opts).is_err());
+        assert_ne!(output, b"\"a\",\"b\"");
+    }
+
+    #[rstest]
+    #[case(b"a\x1Db\x1Ec\x1D", "\"a\",\"\",\"\"\n\"b\",\"c\",\"\"\n")]
+    #[case(b"a\x1Db\x1Ec", "\"a\",\"\",\"\"\n\"b\",\"c\",\"\"")]
+    #[case(b"a\\\x1Db", "\"a\\\\\",\"\",\"\"\n\"b\",\"\",\"\"")]
+    #[case(b"\x1D\x1D", "\"\",\"\",\"\"\n\"\",\"\",\"\"\n")]
+    #[case(b"a\x1Eb\x1Ec", "\"a\",\"b\",\"c\"")]
+    fn test_exorcize_csv_pad_to_short_rows(#[case] data: &[u8], #[case] exp: &str) {
+        let opts = TmpOptions {
+            pad_to: Some(3),
+            ..TmpOptions::default()
+        };
+
+        let mut output = Vec::new();
+        let size = data.len() as u64;
+        let result = exorcize_csv(Cursor::new(data), &mut output, size, 1024, &opts);
+        assert!(result.is_ok());
+        assert_eq!(String::from_utf8(output).unwrap(), exp);
     }
 
     #[rstest]
-    #[case("field1\",\"field2\"", "field1\",\"field2\"")]
-    #[case("field1\",\"field2\"\n", "field1\",\"field2\"\n")]
-    #[case("field1\",\"field2\"\n\"", "field1\",\"field2\"\n")]
-    #[case("field1\",\"field2", "field1\",\"field2\"")]
-    fn test_handle_closing(#[case] buf: &str, #[case] exp: &str) {
-        let mut writer = BufWriter::new(Vec::new());
+    #[case(b"a\x1Eb\x1Ec\x1Dd\x1Ee", "\"a\",\"b\"\n\"d\",\"e\"")]
+    #[case(b"a\x1Eb\x1E\"c\\\x1Dd", "\"a\",\"b\"\n\"d\",\"\"")]
+    #[case(b"a\x1Eb\\\x1Ec\x1Ed\x1D", "\"a\",\"b\\\\\"\n")]
+    #[case(b"a\x1Eb\x1E", "\"a\",\"b\"")]
+    fn test_exorcize_csv_pad_to_long_rows(#[case] data: &[u8], #[case] exp: &str) {
+        let opts = TmpOptions {
+            pad_to: Some(2),
+            ..TmpOptions::default()
+        };
 
-        let result = handle_closing(&mut buf.as_bytes().to_vec(), &mut writer);
+        let mut output = Vec::new();
+        let size = data.len() as u64;
+        let result = exorcize_csv(Cursor::new(data), &mut output, size, 1024, &opts);
         assert!(result.is_ok());
+        assert_eq!(String::from_utf8(output).unwrap(), exp);
+    }
+
+    #[rstest]
+    #[case(2, 1024, vec!["\"c\",\"d\"", "\"e\",\"f\""])]
+    #[case(2, 2, vec!["\"c\",\"d\"", "\"e\",\"f\""])]
+    #[case(1, 1, vec!["\"e\",\"f\""])]
+    #[case(5, 3, vec!["\"a\",\"b\"", "\"c\",\"d\"", "\"e\",\"f\""])]
+    #[case(0, 1024, vec![])]
+    fn test_exorcize_tail(#[case] rows: usize, #[case] chunk_size: usize, #[case] exp: Vec<&str>) {
+        let data = b"a\x1Eb\x1Dc\x1Ed\x1De\x1Ef\x1D";
+        let opts = TmpOptions::default();
+
+        let result = exorcize_tail(
+            Cursor::new(data),
+            data.len() as u64,
+            rows,
+            chunk_size,
+            &opts,
+        );
+        let exp: Vec<Vec<u8>> = exp.iter().map(|r| r.as_bytes().to_vec()).collect();
+        assert_eq!(result.unwrap(), exp);
+    }
+
+    #[rstest]
+    #[case(b"field1\x1Efield2\x1Dfield3", 1024)]
+    #[case(b"a\\\x1E\"b\"\x1D\x1D", 3)]
+    #[case(b"a\x1Eb\x1D", 1)]
+    #[case(b"", 1024)]
+    fn test_output_size(#[case] data: &[u8], #[case] chunk_size: usize) {
+        let opts = TmpOptions::default();
+        let size = data.len() as u64;
+
+        let mut output = Vec::new();
+        exorcize_csv(Cursor::new(data), &mut output, size, chunk_size, &opts).unwrap();
+
+        let estimate = output_size(Cursor::new(data), size, chunk_size, &opts).unwrap();
+        assert_eq!(estimate, output.len() as u64);
+    }
+
+    #[rstest]
+    #[case(0, 0)]
+    #[case(1, 5)]
+    #[case(4, 5)]
+    #[case(5, 5)]
+    #[case(6, 10)]
+    #[case(10, 10)]
+    #[case(11, 14)]
+    #[case(20, 14)]
+    fn test_next_row_start(#[case] offset: u64, #[case] exp: u64) {
+        let data = b"ab\x1Ec\x1Dde\x1Ef\x1Dgh\x1Ei";
+        for chunk_size in [1, 2, 1024] {
+            let size = data.len() as u64;
+            let start = next_row_start(&mut Cursor::new(data), size, offset, chunk_size, b'\x1D');
+            assert_eq!(start.unwrap(), exp, "chunk_size {chunk_size}");
+        }
+    }
+
+    #[rstest]
+    #[case(b"ab\x1Ec\x1Dde\x1Ef\x1Dgh\x1Eij")]
+    #[case(b"ab\x1Ec\\\x1Dde\x1E\"f\x1Dgh\x1Eij\x1D")]
+    fn test_exorcize_range_concatenates(#[case] data: &[u8]) {
+        let opts = TmpOptions::default();
+        let size = data.len() as u64;
+
+        let mut exp = Vec::new();
+        exorcize_csv(Cursor::new(data), &mut exp, size, 1024, &opts).unwrap();
+
+        let mut input = Cursor::new(data);
+        for split in 0..size {
+            let mid = next_row_start(&mut input, size, split, 1024, opts.eol).unwrap();
+            let mut output = Vec::new();
+            exorcize_range(&mut input, &mut output, 0, mid, 1024, &opts).unwrap();
+            exorcize_range(&mut input, &mut output, mid, size, 1024, &opts).unwrap();
+            assert_eq!(output, exp, "split at {mid}");
+        }
+    }
+
+    #[rstest]
+    #[case(b"a\x1Eb\x1Dc\x1Ed\x1De", 2, 1024, "\"a\",\"b\"\n\"c\",\"d\"")]
+    #[case(b"a\x1Eb\x1Dc\x1Ed\x1De", 2, 1, "\"a\",\"b\"\n\"c\",\"d\"")]
+    #[case(b"a\x1Eb\x1Dc\x1Ed\x1De", 5, 2, "\"a\",\"b\"\n\"c\",\"d\"\n\"e\"")]
+    #[case(b"a\x1Eb\x1Dc\x1Ed\x1D", 5, 3, "\"a\",\"b\"\n\"c\",\"d\"")]
+    #[case(b"a\\\x1D\"b\x1Dc", 2, 1024, "\"a\\\\\"\n\"\\\"b\"")]
+    #[case(b"\x1D\x1Da", 2, 1024, "\"\"\n\"\"")]
+    #[case(b"\x1D", 5, 1024, "\"\"")]
+    #[case(b"a\x1Eb", 0, 1024, "")]
+    #[case(b"", 3, 1024, "")]
+    fn test_exorcize_head(
+        #[case] data: &[u8],
+        #[case] rows: usize,
+        #[case] chunk_size: usize,
+        #[case] exp: &str,
+    ) {
+        let opts = TmpOptions::default();
+
+        let result = exorcize_head(Cursor::new(data), rows, chunk_size, &opts);
+        assert_eq!(String::from_utf8(result.unwrap()).unwrap(), exp);
+    }
+
+    #[rstest]
+    #[case(b"a\x1Eb\x1Dc\x1Dd\x1De", 0, 0, "\"a\",\"b\"\n\"c\"")]
+    #[case(b"a\x1Eb\x1Dc\x1Dd\x1De", 1, 1, "\"c\"\n\"d\"")]
+    #[case(b"a\x1Eb\x1Dc\x1Dd\x1De", 3, 3, "\"e\"")]
+    #[case(b"a\x1Eb\x1Dc\x1Dd\x1D", 3, 3, "")]
+    #[case(b"a\x1Eb\x1Dc", 5, 1, "")]
+    #[case(b"\x1D\x1D\"x\x1D", 2, 2, "\"\\\"x\"")]
+    fn test_skip_rows_then_head(
+        #[case] data: &[u8],
+        #[case] rows: u64,
+        #[case] skipped: u64,
+        #[case] exp: &str,
+    ) {
+        let opts = TmpOptions::default();
+
+        for capacity in [1, 2, 1024] {
+            let mut input = BufReader::with_capacity(capacity, Cursor::new(data));
+            assert_eq!(skip_rows(&mut input, rows, opts.eol).unwrap(), skipped);
+            let head = exorcize_head(input, 2, 3, &opts).unwrap();
+            assert_eq!(String::from_utf8(head).unwrap(), exp, "capacity {capacity}");
+        }
+    }
+
+    #[rstest]
+    fn test_exorcize_head_stops_reading() {
+        let data = b"a\x1Db\x1D";
+        let opts = TmpOptions::default();
+
+        // the first chunk holds the only row asked for, the rest is never read
+        let input = Cursor::new(data).chain(Broken);
+        let result = exorcize_head(input, 1, 2, &opts);
+        assert_eq!(result.unwrap(), b"\"a\"");
+    }
+
+    #[rstest]
+    #[case(b"a\\\x1Db\\", vec!["\"b\\\\\""])]
+    #[case(b"a\x1D\"b\"", vec!["\"\\\"b\\\"\""])]
+    #[case(b"", vec![])]
+    #[case(b"\x1D", vec!["\"\""])]
+    fn test_exorcize_tail_edges(#[case] data: &[u8], #[case] exp: Vec<&str>) {
+        let opts = TmpOptions::default();
+
+        let result = exorcize_tail(Cursor::new(data), data.len() as u64, 1, 1024, &opts);
+        let exp: Vec<Vec<u8>> = exp.iter().map(|r| r.as_bytes().to_vec()).collect();
+        assert_eq!(result.unwrap(), exp);
+    }
+
+    // a row ending in a backslash run is closed as `exorcize_csv` closes it, whatever its parity
+    #[rstest]
+    #[case(b"a\\\x1D")]
+    #[case(b"a\\\\\x1D")]
+    #[case(b"a\\\\\\\x1Eb\\\\\x1D")]
+    fn test_head_and_tail_match_exorcize_csv(#[case] data: &[u8]) {
+        let opts = TmpOptions::default();
+
+        let mut output = Vec::new();
+        let size = data.len() as u64;
+        exorcize_csv(Cursor::new(data), &mut output, size, 1024, &opts).unwrap();
+        assert_eq!(output.pop(), Some(b'\n'));
 
-        assert_eq!(writer.into_inner().unwrap(), exp.as_bytes());
+        let head = exorcize_head(Cursor::new(data), 1, 1024, &opts).unwrap();
+        assert_eq!(head, output);
+        let tail = exorcize_tail(Cursor::new(data), size, 1, 1024, &opts).unwrap();
+        assert_eq!(tail, vec![output]);
     }
 }
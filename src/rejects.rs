@@ -0,0 +1,259 @@
+use crate::reader::{check_eof, format_row, trailing_backslashes, write_header, TmpOptions};
+use crate::stats::{Partial, Stats};
+use memchr::{memchr2_iter, memchr_iter};
+use std::io::{self, BufReader, BufWriter, Read, Write};
+
+/// Fixes `input` into `output`, diverting the rows without `expected_fields` fields to
+/// `rejects` instead of writing them.
+///
+/// Each row is held whole to count its fields before it is written. Accepted rows are
+/// fixed as the drivers fix them; rejected ones go to `rejects` in their original broken
+/// form, fields joined by `sep` and each row ended by `eol`, so the file can be fixed once
+/// repaired. When `expected_fields` is `None` the first row sets the count, as a header
+/// does. Only accepted rows make up `rows`, `fields` and the histogram of the stats; the
+/// others are counted in `rejected_rows`.
+pub(crate) fn exorcize_csv<R, W, J>(
+    input: R,
+    output: W,
+    rejects: J,
+    expected_fields: Option<u64>,
+    chunk_size: usize,
+    opts: &TmpOptions,
+) -> io::Result<Stats>
+where
+    R: Read,
+    W: Write,
+    J: Write,
+{
+    let mut stats = Stats::default();
+    stats.rejected_rows = Some(0);
+    let mut split = Split {
+        expected: expected_fields,
+        terminated: false,
+        written: false,
+        rejects: BufWriter::with_capacity(opts.write_size, rejects),
+    };
+    match run(input, output, &mut split, chunk_size, opts, &mut stats) {
+        Ok(()) => Ok(stats),
+        Err(e) => Err(Partial::attach(stats, e)),
+    }
+}
+
+/// The row state of `exorcize_csv` and where its rejected rows go.
+struct Split<J: Write> {
+    expected: Option<u64>,
+    /// The last accepted row ended with an `eol`, its terminator deferred so the last one
+    /// can be dropped.
+    terminated: bool,
+    written: bool,
+    rejects: BufWriter<J>,
+}
+
+impl<J: Write> Split<J> {
+    /// Writes `row` fixed to `out` when it has the expected field count, else raw to the
+    /// rejects. `ended` is whether an `eol` closed it.
+    fn route(
+        &mut self,
+        row: &[Vec<u8>],
+        ended: bool,
+        out: &mut Vec<u8>,
+        opts: &TmpOptions,
+        stats: &mut Stats,
+    ) -> io::Result<()> {
+        let fields = row.len() as u64;
+        if *self.expected.get_or_insert(fields) != fields {
+            for (col, field) in row.iter().enumerate() {
+                if col > 0 {
+                    self.rejects.write_all(&[opts.sep])?;
+                }
+                self.rejects.write_all(field)?;
+            }
+            self.rejects.write_all(&[opts.eol])?;
+            *stats.rejected_rows.get_or_insert(0) += 1;
+            return Ok(());
+        }
+
+        if self.terminated {
+            out.extend_from_slice(&opts.out_eol);
+        }
+        out.extend(opts.record_prefix);
+        format_row(row, out, opts);
+        for _ in 1..fields {
+            stats.on_sep();
+        }
+        stats.on_eol();
+        (self.terminated, self.written) = (ended, true);
+        Ok(())
+    }
+}
+
+/// The body of `exorcize_csv`, leaving `stats` with the caller so a failure keeps them.
+fn run<R, W, J>(
+    input: R,
+    output: W,
+    split: &mut Split<J>,
+    chunk_size: usize,
+    opts: &TmpOptions,
+    stats: &mut Stats,
+) -> io::Result<()>
+where
+    R: Read,
+    W: Write,
+    J: Write,
+{
+    let mut reader = BufReader::new(input);
+    let mut writer = BufWriter::with_capacity(opts.write_size, output);
+
+    let mut buf = vec![0u8; chunk_size];
+    let mut out = Vec::new();
+    let mut row = Vec::new();
+    let mut field = Vec::new();
+    let mut backslashes = 0;
+
+    loop {
+        let read = reader.read(&mut buf)?;
+
+        if read == 0 {
+            break;
+        }
+
+        let haystack = &buf[..read];
+        if stats.bytes_read == 0 {
+            write_header(&mut out, opts);
+        }
+        stats.observe(haystack);
+        backslashes = trailing_backslashes(backslashes, haystack);
+        stats.quotes += memchr_iter(opts.quote, haystack).count() as u64;
+
+        let mut idx = 0;
+        for pos in memchr2_iter(opts.sep, opts.eol, haystack) {
+            field.extend_from_slice(&haystack[idx..pos]);
+            row.push(std::mem::take(&mut field));
+            if haystack[pos] == opts.eol {
+                split.route(&row, true, &mut out, opts, stats)?;
+                row.clear();
+            }
+            idx = pos + 1;
+        }
+        field.extend_from_slice(&haystack[idx..]);
+
+        writer.write_all(&out)?;
+        stats.bytes_written += out.len() as u64;
+        out.clear();
+    }
+
+    check_eof(backslashes, opts, stats)?;
+
+    // a trailing `eol` already closed the last row
+    if !row.is_empty() || !field.is_empty() {
+        row.push(field);
+        split.route(&row, false, &mut out, opts, stats)?;
+    }
+    if split.written && opts.final_newline.unwrap_or(split.terminated) {
+        out.extend_from_slice(&opts.out_eol);
+    }
+
+    writer.write_all(&out)?;
+    writer.flush()?;
+    split.rejects.flush()?;
+    stats.bytes_written += out.len() as u64;
+    stats.finish();
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rstest::*;
+    use std::io::Cursor;
+
+    fn split(
+        data: &[u8],
+        expected_fields: Option<u64>,
+        opts: &TmpOptions,
+    ) -> (String, String, Stats) {
+        let mut exp = None;
+        for chunk_size in 1..=data.len().max(1) {
+            let (mut output, mut rejects) = (Vec::new(), Vec::new());
+            let input = Cursor::new(data);
+            let stats = exorcize_csv(
+                input,
+                &mut output,
+                &mut rejects,
+                expected_fields,
+                chunk_size,
+                opts,
+            )
+            .unwrap();
+            let output = String::from_utf8(output).unwrap();
+            let rejects = String::from_utf8(rejects).unwrap();
+            if let Some((exp_output, exp_rejects, _)) = &exp {
+                assert_eq!(&output, exp_output, "chunk_size {chunk_size}");
+                assert_eq!(&rejects, exp_rejects, "chunk_size {chunk_size}");
+            }
+            exp = Some((output, rejects, stats));
+        }
+        exp.unwrap()
+    }
+
+    #[rstest]
+    #[case(
+        b"a\x1Eb\x1Dc\x1Dd\x1Ee\x1Ef\x1Dg\x1Eh",
+        Some(2),
+        "\"a\",\"b\"\n\"g\",\"h\"",
+        "c\x1Dd\x1Ee\x1Ef\x1D",
+        2,
+        2
+    )]
+    #[case(
+        b"a\x1Eb\x1Dc\x1Dd\x1Ee\x1D",
+        None,
+        "\"a\",\"b\"\n\"d\",\"e\"\n",
+        "c\x1D",
+        2,
+        1
+    )]
+    // the last row rejected: the accepted one before it keeps its terminator
+    #[case(b"a\x1Eb\x1Dc", Some(2), "\"a\",\"b\"\n", "c\x1D", 1, 1)]
+    #[case(b"a\x1D\"b\\\x1Dc", Some(1), "\"a\"\n\"\\\"b\\\\\"\n\"c\"", "", 3, 0)]
+    #[case(b"a", Some(2), "", "a\x1D", 0, 1)]
+    #[case(b"", Some(2), "", "", 0, 0)]
+    fn test_exorcize_csv_rejects(
+        #[case] data: &[u8],
+        #[case] expected_fields: Option<u64>,
+        #[case] output: &str,
+        #[case] rejects: &str,
+        #[case] rows: u64,
+        #[case] rejected: u64,
+    ) {
+        let (out, rej, stats) = split(data, expected_fields, &TmpOptions::default());
+        assert_eq!(out, output);
+        assert_eq!(rej, rejects);
+        assert_eq!((stats.rows, stats.rejected_rows), (rows, Some(rejected)));
+        assert_eq!(stats.bytes_written, output.len() as u64);
+        if rows > 0 {
+            assert_eq!(stats.field_counts.len(), 1);
+        }
+
+        // with nothing rejected the output is the streaming one
+        if rejected == 0 {
+            let mut streamed = Vec::new();
+            let opts = TmpOptions::default();
+            let size = data.len() as u64;
+            crate::reader::exorcize_csv(Cursor::new(data), &mut streamed, size, 4, &opts).unwrap();
+            assert_eq!(out.as_bytes(), streamed);
+        }
+    }
+
+    #[rstest]
+    fn test_exorcize_csv_rejects_header_and_final_newline() {
+        let opts = TmpOptions {
+            header: Some(vec![b"x".to_vec(), b"y".to_vec()]),
+            final_newline: Some(false),
+            ..TmpOptions::default()
+        };
+        let (out, rej, _) = split(b"a\x1Eb\x1Dc\x1D", Some(2), &opts);
+        assert_eq!(out, "\"x\",\"y\"\n\"a\",\"b\"");
+        assert_eq!(rej, "c\x1D");
+    }
+}
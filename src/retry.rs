@@ -0,0 +1,136 @@
+use std::io::{self, Read, Write};
+use std::thread;
+use std::time::Duration;
+
+#[cfg(unix)]
+const EIO: i32 = 5;
+
+/// Retries reads and writes on `inner` that fail with a transient error.
+///
+/// Up to `retries` extra attempts are made for `Interrupted`, `WouldBlock`,
+/// `TimedOut` and (on Unix) `EIO`, with a short linear backoff between them.
+/// Any other error is returned immediately.
+#[derive(Debug)]
+pub(crate) struct Retry<T> {
+    inner: T,
+    retries: usize,
+}
+
+impl<T> Retry<T> {
+    pub(crate) fn new(inner: T, retries: usize) -> Self {
+        Retry { inner, retries }
+    }
+
+    #[inline(always)]
+    fn attempt<F, O>(&mut self, mut op: F) -> io::Result<O>
+    where
+        F: FnMut(&mut T) -> io::Result<O>,
+    {
+        let mut attempt = 0;
+        loop {
+            match op(&mut self.inner) {
+                Err(e) if attempt < self.retries && is_transient(&e) => {
+                    if e.kind() != io::ErrorKind::Interrupted {
+                        thread::sleep(Duration::from_millis(10 * (attempt as u64 + 1)));
+                    }
+                    attempt += 1;
+                }
+                result => return result,
+            }
+        }
+    }
+}
+
+fn is_transient(e: &io::Error) -> bool {
+    #[cfg(unix)]
+    if e.raw_os_error() == Some(EIO) {
+        return true;
+    }
+
+    matches!(
+        e.kind(),
+        io::ErrorKind::Interrupted | io::ErrorKind::WouldBlock | io::ErrorKind::TimedOut
+    )
+}
+
+impl<R: Read> Read for Retry<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        self.attempt(|inner| inner.read(buf))
+    }
+}
+
+impl<W: Write> Write for Retry<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.attempt(|inner| inner.write(buf))
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.attempt(|inner| inner.flush())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::reader::{self, TmpOptions};
+    use rstest::*;
+    use std::io::Cursor;
+
+    struct Flaky {
+        inner: Cursor<Vec<u8>>,
+        failures: usize,
+        kind: io::ErrorKind,
+    }
+
+    impl Read for Flaky {
+        fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+            if self.failures > 0 {
+                self.failures -= 1;
+                return Err(io::Error::from(self.kind));
+            }
+            self.inner.read(buf)
+        }
+    }
+
+    fn flaky(failures: usize, kind: io::ErrorKind) -> Flaky {
+        let inner = Cursor::new(b"field1\x1Efield2".to_vec());
+        Flaky {
+            inner,
+            failures,
+            kind,
+        }
+    }
+
+    #[rstest]
+    #[case(3, 3, true)]
+    #[case(3, 2, false)]
+    #[case(0, 0, true)]
+    fn test_retry_interrupted(#[case] failures: usize, #[case] retries: usize, #[case] ok: bool) {
+        let input = Retry::new(flaky(failures, io::ErrorKind::Interrupted), retries);
+        let mut output = Vec::new();
+        let opts = TmpOptions::default();
+
+        let result = reader::exorcize_csv(input, &mut output, 13, 1024, &opts);
+        assert_eq!(result.is_ok(), ok);
+        if ok {
+            assert_eq!(output, b"\"field1\",\"field2\"");
+        }
+    }
+
+    #[rstest]
+    fn test_retry_not_found_fails_immediately() {
+        let mut input = Retry::new(flaky(1, io::ErrorKind::NotFound), 5);
+        let mut buf = [0u8; 16];
+
+        let err = input.read(&mut buf).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::NotFound);
+    }
+
+    #[rstest]
+    fn test_retry_would_block_backs_off() {
+        let mut input = Retry::new(flaky(2, io::ErrorKind::WouldBlock), 2);
+        let mut buf = [0u8; 16];
+
+        assert_eq!(input.read(&mut buf).unwrap(), 13);
+    }
+}
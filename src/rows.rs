@@ -0,0 +1,221 @@
+use crate::reader::{self, TmpOptions};
+use memchr::memchr2;
+use std::collections::VecDeque;
+use std::fs::File;
+use std::io::{self, BufReader, Read};
+use std::ops::Range;
+use std::path::PathBuf;
+
+/// Splits raw input into rows of raw field values, calling `on_row` once per row.
+///
+/// Fields are the bytes between `sep`s, without the quoting and escaping the drivers
+/// add when writing CSV. A terminator as the very last byte ends the last row instead
+/// of starting an empty one.
+pub(crate) fn split_rows<R, F>(
+    input: R,
+    chunk_size: usize,
+    opts: &TmpOptions,
+    mut on_row: F,
+) -> io::Result<()>
+where
+    R: Read,
+    F: FnMut(&[Vec<u8>]) -> io::Result<()>,
+{
+    let mut rows = Rows::new(input, chunk_size, opts);
+    while let Some(row) = rows.next_row()? {
+        on_row(&row)?;
+    }
+    Ok(())
+}
+
+/// Raw input split into rows of raw field values, pulled one at a time, as by `split_rows`.
+pub(crate) struct Rows<R> {
+    reader: BufReader<R>,
+    buf: Vec<u8>,
+    /// The bytes of `buf` read but not split yet.
+    pending: Range<usize>,
+    row: Vec<Vec<u8>>,
+    field: Vec<u8>,
+    done: bool,
+    sep: u8,
+    eol: u8,
+}
+
+impl<R: Read> Rows<R> {
+    pub(crate) fn new(input: R, chunk_size: usize, opts: &TmpOptions) -> Self {
+        Rows {
+            reader: BufReader::new(input),
+            buf: vec![0u8; chunk_size],
+            pending: 0..0,
+            row: Vec::new(),
+            field: Vec::new(),
+            done: false,
+            sep: opts.sep,
+            eol: opts.eol,
+        }
+    }
+
+    /// Returns the next row, or `None` once the input is exhausted.
+    pub(crate) fn next_row(&mut self) -> io::Result<Option<Vec<Vec<u8>>>> {
+        loop {
+            if self.pending.is_empty() {
+                if self.done {
+                    return Ok(None);
+                }
+                let read = self.reader.read(&mut self.buf)?;
+                if read == 0 {
+                    self.done = true;
+                    if self.row.is_empty() && self.field.is_empty() {
+                        return Ok(None);
+                    }
+                    self.row.push(std::mem::take(&mut self.field));
+                    return Ok(Some(std::mem::take(&mut self.row)));
+                }
+                self.pending = 0..read;
+            }
+
+            let haystack = &self.buf[self.pending.clone()];
+            let Some(pos) = memchr2(self.sep, self.eol, haystack) else {
+                self.field.extend_from_slice(haystack);
+                self.pending.start = self.pending.end;
+                continue;
+            };
+            self.field.extend_from_slice(&haystack[..pos]);
+            self.row.push(std::mem::take(&mut self.field));
+            let ends_row = haystack[pos] == self.eol;
+            self.pending.start += pos + 1;
+            if ends_row {
+                return Ok(Some(std::mem::take(&mut self.row)));
+            }
+        }
+    }
+}
+
+/// The rows of several files as one stream, each fixed as a standalone CSV row.
+///
+/// Files are opened in turn once the one before is exhausted, and each is split on its
+/// own: a last row without a terminator ends with its file instead of running into the
+/// first row of the next. A row carries no terminator.
+pub(crate) struct ChainedRows {
+    paths: VecDeque<PathBuf>,
+    rows: Option<Rows<File>>,
+    chunk_size: usize,
+    opts: TmpOptions,
+}
+
+impl ChainedRows {
+    pub(crate) fn new(paths: Vec<PathBuf>, chunk_size: usize, opts: TmpOptions) -> Self {
+        ChainedRows {
+            paths: paths.into(),
+            rows: None,
+            chunk_size,
+            opts,
+        }
+    }
+
+    /// Returns the next fixed row, from the next file when the current one is exhausted.
+    ///
+    /// A file that cannot be opened fails when it is reached, after the rows before it.
+    pub(crate) fn next_row(&mut self) -> io::Result<Option<Vec<u8>>> {
+        loop {
+            let rows = match &mut self.rows {
+                Some(rows) => rows,
+                None => match self.paths.pop_front() {
+                    Some(path) => {
+                        let input = File::open(&path).map_err(|e| {
+                            io::Error::new(e.kind(), format!("{}: {e}", path.display()))
+                        })?;
+                        self.rows
+                            .insert(Rows::new(input, self.chunk_size, &self.opts))
+                    }
+                    None => return Ok(None),
+                },
+            };
+            match rows.next_row()? {
+                Some(fields) => {
+                    let mut row = Vec::new();
+                    reader::format_row(&fields, &mut row, &self.opts);
+                    return Ok(Some(row));
+                }
+                None => self.rows = None,
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rstest::*;
+    use std::io::Cursor;
+
+    #[rstest]
+    #[case(b"a\x1Eb\x1Dc\x1Ed", 1024, vec![vec!["a", "b"], vec!["c", "d"]])]
+    #[case(b"a\x1Eb\x1Dc\x1Ed\x1D", 2, vec![vec!["a", "b"], vec!["c", "d"]])]
+    #[case(b"a\"b\\\x1E\x1D\x1Dc\x1E", 1, vec![vec!["a\"b\\", ""], vec![""], vec!["c", ""]])]
+    #[case(b"", 1024, vec![])]
+    #[case(b"\x1D", 1024, vec![vec![""]])]
+    fn test_split_rows(
+        #[case] data: &[u8],
+        #[case] chunk_size: usize,
+        #[case] exp: Vec<Vec<&str>>,
+    ) {
+        let mut rows = Vec::new();
+        let result = split_rows(
+            Cursor::new(data),
+            chunk_size,
+            &TmpOptions::default(),
+            |row| {
+                rows.push(row.to_vec());
+                Ok(())
+            },
+        );
+        assert!(result.is_ok());
+
+        let exp: Vec<Vec<Vec<u8>>> = exp
+            .iter()
+            .map(|row| row.iter().map(|f| f.as_bytes().to_vec()).collect())
+            .collect();
+        assert_eq!(rows, exp);
+    }
+
+    #[rstest]
+    fn test_chained_rows() {
+        let dir = std::env::temp_dir().join("bcp_exorcist_chained_rows");
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        let files: [&[u8]; 3] = [b"a\x1Eb\x1Dc\"\x1Ed", b"", b"e\x1Ef\x1D\x1Dg\x1E"];
+        let paths: Vec<PathBuf> = (0..files.len())
+            .map(|i| dir.join(format!("part{i}.csv")))
+            .collect();
+        for (path, data) in paths.iter().zip(files) {
+            std::fs::write(path, data).unwrap();
+        }
+
+        // the unterminated last row of the first file stays apart from the next
+        let exp = [
+            r#""a","b""#,
+            r#""c\"","d""#,
+            r#""e","f""#,
+            r#""""#,
+            r#""g","""#,
+        ];
+        for chunk_size in [1, 2, 1024] {
+            let mut chained = ChainedRows::new(paths.clone(), chunk_size, TmpOptions::default());
+            let mut rows = Vec::new();
+            while let Some(row) = chained.next_row().unwrap() {
+                rows.push(String::from_utf8(row).unwrap());
+            }
+            assert_eq!(rows, exp, "chunk_size {chunk_size}");
+        }
+
+        let missing = vec![paths[0].clone(), dir.join("missing.csv")];
+        let mut chained = ChainedRows::new(missing, 1024, TmpOptions::default());
+        assert!(chained.next_row().unwrap().is_some());
+        assert!(chained.next_row().unwrap().is_some());
+        let e = chained.next_row().unwrap_err();
+        assert_eq!(e.kind(), io::ErrorKind::NotFound);
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
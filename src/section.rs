@@ -0,0 +1,138 @@
+use memchr::memchr;
+use std::io::{self, Read};
+
+/// Separates the sections of a concatenated dump.
+pub(crate) const FORM_FEED: u8 = b'\x0C';
+
+/// Passes through only the bytes of section `index` of `inner`, 0-based, sections being
+/// separated by form feeds; the separators themselves are dropped.
+///
+/// Reading stops at the form feed ending the section. An input with fewer sections fails
+/// once it is exhausted, naming how many it has.
+#[derive(Debug)]
+pub(crate) struct Section<R> {
+    inner: R,
+    index: usize,
+    /// The section the next byte of `inner` belongs to.
+    current: usize,
+    done: bool,
+}
+
+impl<R> Section<R> {
+    pub(crate) fn new(inner: R, index: usize) -> Self {
+        Section {
+            inner,
+            index,
+            current: 0,
+            done: false,
+        }
+    }
+}
+
+impl<R: Read> Read for Section<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        if buf.is_empty() {
+            return Ok(0);
+        }
+        while !self.done {
+            let read = self.inner.read(buf)?;
+            if read == 0 {
+                if self.current < self.index {
+                    let msg = format!(
+                        "section {} is out of range; the input has {} sections",
+                        self.index,
+                        self.current + 1
+                    );
+                    return Err(io::Error::new(io::ErrorKind::InvalidData, msg));
+                }
+                self.done = true;
+                break;
+            }
+
+            let mut start = 0;
+            // skips the sections before the wanted one
+            while self.current < self.index {
+                match memchr(FORM_FEED, &buf[start..read]) {
+                    Some(pos) => {
+                        start += pos + 1;
+                        self.current += 1;
+                    }
+                    None => {
+                        start = read;
+                        break;
+                    }
+                }
+            }
+            if start == read {
+                continue;
+            }
+
+            let end = match memchr(FORM_FEED, &buf[start..read]) {
+                Some(pos) => {
+                    self.done = true;
+                    start + pos
+                }
+                None => read,
+            };
+            buf.copy_within(start..end, 0);
+            if end > start {
+                return Ok(end - start);
+            }
+        }
+        Ok(0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::reader::{self, TmpOptions};
+    use rstest::*;
+    use std::io::Cursor;
+
+    fn read_all<R: Read>(mut input: R, buf_size: usize) -> io::Result<Vec<u8>> {
+        let mut out = Vec::new();
+        let mut buf = vec![0u8; buf_size];
+        loop {
+            match input.read(&mut buf)? {
+                0 => return Ok(out),
+                read => out.extend_from_slice(&buf[..read]),
+            }
+        }
+    }
+
+    #[rstest]
+    #[case(0, Some(&b"a\x1Eb"[..]))]
+    #[case(1, Some(&b"c\x1Dd\x1Ee"[..]))]
+    #[case(2, Some(&b""[..]))]
+    #[case(3, None)]
+    fn test_section(#[case] index: usize, #[case] exp: Option<&[u8]>) {
+        let data = b"a\x1Eb\x0Cc\x1Dd\x1Ee\x0C";
+        for buf_size in 1..=data.len() {
+            let result = read_all(Section::new(Cursor::new(data), index), buf_size);
+            match exp {
+                Some(exp) => assert_eq!(result.unwrap(), exp, "buf_size {buf_size}"),
+                None => {
+                    let e = result.unwrap_err();
+                    assert_eq!(e.kind(), io::ErrorKind::InvalidData);
+                    let msg = "section 3 is out of range; the input has 3 sections";
+                    assert_eq!(e.to_string(), msg);
+                }
+            }
+        }
+    }
+
+    #[rstest]
+    fn test_exorcize_csv_section() {
+        let data = b"a\x1Eb\x0Cc\x1Dd\x1Ee\x0Cf\x1Eg";
+        let opts = TmpOptions::default();
+        for chunk_size in 1..=data.len() {
+            let mut output = Vec::new();
+            let input = Section::new(Cursor::new(data), 1);
+            let size = data.len() as u64;
+            let stats = reader::exorcize_csv(input, &mut output, size, chunk_size, &opts).unwrap();
+            assert_eq!(output, b"\"c\"\n\"d\",\"e\"", "chunk_size {chunk_size}");
+            assert_eq!(stats.rows, 2);
+        }
+    }
+}
@@ -0,0 +1,84 @@
+use crate::reader::TmpOptions;
+use crate::rows;
+use std::io;
+
+/// The number of rows `exorcize_sorted` holds by default.
+pub(crate) const DEFAULT_MAX_ROWS: usize = 1_000_000;
+
+/// Stably sorts the rows of raw input by the raw bytes of field `key_column`.
+///
+/// Returns the sorted rows as raw input again, ready for a driver, ending with `eol` only
+/// if `data` did. Rows too short to have the key sort as if it were empty. More than
+/// `max_rows` rows is an `InvalidInput` error, raised before any sorting.
+pub(crate) fn sort_rows(
+    data: &[u8],
+    key_column: usize,
+    max_rows: usize,
+    opts: &TmpOptions,
+) -> io::Result<Vec<u8>> {
+    let mut rows = Vec::new();
+    rows::split_rows(data, data.len().max(1), opts, |row| {
+        if rows.len() == max_rows {
+            let msg = format!("more than {max_rows} rows to sort");
+            return Err(io::Error::new(io::ErrorKind::InvalidInput, msg));
+        }
+        rows.push(row.to_vec());
+        Ok(())
+    })?;
+
+    let key = |row: &Vec<Vec<u8>>| row.get(key_column).map_or(&[][..], Vec::as_slice).to_vec();
+    rows.sort_by_cached_key(key);
+
+    let mut out = Vec::with_capacity(data.len());
+    for (idx, row) in rows.iter().enumerate() {
+        if idx > 0 {
+            out.push(opts.eol);
+        }
+        for (col, field) in row.iter().enumerate() {
+            if col > 0 {
+                out.push(opts.sep);
+            }
+            out.extend_from_slice(field);
+        }
+    }
+    if !rows.is_empty() && data.last() == Some(&opts.eol) {
+        out.push(opts.eol);
+    }
+    Ok(out)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rstest::*;
+
+    #[rstest]
+    #[case(
+        b"1\x1Ec\x1Ex\x1D2\x1Ea\x1Ey\x1D3\x1Eb\x1Ez",
+        1,
+        b"2\x1Ea\x1Ey\x1D3\x1Eb\x1Ez\x1D1\x1Ec\x1Ex"
+    )]
+    #[case(
+        b"1\x1Eb\x1D2\x1Ea\x1D3\x1Eb\x1D4\x1Ea\x1D",
+        1,
+        b"2\x1Ea\x1D4\x1Ea\x1D1\x1Eb\x1D3\x1Eb\x1D"
+    )]
+    #[case(b"b\x1E2\x1Da\x1Dc\x1E1", 1, b"a\x1Dc\x1E1\x1Db\x1E2")]
+    #[case(b"b\x1Da\x1D\x1D", 0, b"\x1Da\x1Db\x1D")]
+    #[case(b"", 0, b"")]
+    fn test_sort_rows(#[case] data: &[u8], #[case] key_column: usize, #[case] exp: &[u8]) {
+        let sorted = sort_rows(data, key_column, 16, &TmpOptions::default()).unwrap();
+        assert_eq!(sorted, exp);
+    }
+
+    #[rstest]
+    #[case(b"a\x1Db\x1Dc", 2, false)]
+    #[case(b"a\x1Db\x1Dc\x1D", 3, true)]
+    fn test_sort_rows_max_rows(#[case] data: &[u8], #[case] max_rows: usize, #[case] ok: bool) {
+        let result = sort_rows(data, 0, max_rows, &TmpOptions::default());
+        match ok {
+            true => assert!(result.is_ok()),
+            false => assert_eq!(result.unwrap_err().kind(), io::ErrorKind::InvalidInput),
+        }
+    }
+}
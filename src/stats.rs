@@ -0,0 +1,526 @@
+use memchr::{memchr, memmem};
+use std::collections::HashMap;
+use std::error::Error;
+use std::fmt;
+use std::io;
+
+pub(crate) const BOM: &[u8] = b"\xEF\xBB\xBF";
+
+/// Share of raw `"` bytes in the input above which the quoting looks suspicious.
+const QUOTE_DENSITY_THRESHOLD: f64 = 0.05;
+
+/// Suspicious patterns found while scanning; reported without failing the run.
+#[derive(Debug, PartialEq)]
+pub(crate) enum Anomaly {
+    /// A byte order mark somewhere other than the start of the input.
+    Bom { offset: u64 },
+    /// The first NUL byte of the input.
+    Nul { offset: u64 },
+    /// Rows whose field count differs from the most common one.
+    RaggedRows { rows: u64, modal_fields: u64 },
+    /// Raw quotes make up an unusual share of the input.
+    QuoteDensity { ratio: f64 },
+}
+
+impl fmt::Display for Anomaly {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Anomaly::Bom { offset } => write!(f, "bom: byte order mark at offset {offset}"),
+            Anomaly::Nul { offset } => write!(f, "nul: NUL byte at offset {offset}"),
+            Anomaly::RaggedRows { rows, modal_fields } => {
+                write!(f, "ragged: {rows} rows differ from {modal_fields} fields")
+            }
+            Anomaly::QuoteDensity { ratio } => {
+                write!(f, "quotes: {:.1}% of bytes are raw quotes", ratio * 100.0)
+            }
+        }
+    }
+}
+
+/// A transformation of the input beyond plain quoting, kept in a repair log.
+#[derive(Debug, PartialEq)]
+pub(crate) enum Repair {
+    /// A raw quote in field content was escaped.
+    EscapedQuote,
+    /// The closing quote of a `""` pair was dropped by `collapse_double_quotes`.
+    CollapsedQuote,
+    /// A backslash ending a field was doubled so it cannot escape the closing quote.
+    ReescapedBackslash,
+    /// Empty fields were appended to the row to reach `pad_to`.
+    PaddedRow { fields: u64 },
+    /// A field past `pad_to` was dropped.
+    DroppedField,
+    /// A `sep` ending its row was dropped rather than opening an empty last field.
+    DroppedTrailingSep,
+}
+
+impl fmt::Display for Repair {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Repair::EscapedQuote => write!(f, "escaped quote"),
+            Repair::CollapsedQuote => write!(f, "collapsed quote"),
+            Repair::ReescapedBackslash => write!(f, "re-escaped backslash"),
+            Repair::PaddedRow { fields } => write!(f, "padded {fields} fields"),
+            Repair::DroppedField => write!(f, "dropped field"),
+            Repair::DroppedTrailingSep => write!(f, "dropped trailing separator"),
+        }
+    }
+}
+
+/// A `Repair` at input byte `offset`, in the 0-based `row` and `col` it applies to.
+#[derive(Debug, PartialEq)]
+pub(crate) struct RepairEntry {
+    pub(crate) offset: u64,
+    pub(crate) row: u64,
+    pub(crate) col: u64,
+    pub(crate) repair: Repair,
+}
+
+impl fmt::Display for RepairEntry {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let RepairEntry {
+            offset,
+            row,
+            col,
+            repair,
+        } = self;
+        write!(f, "{offset}\t{row}\t{col}\t{repair}")
+    }
+}
+
+/// Counters accumulated over a single exorcism.
+#[derive(Debug, Default)]
+pub(crate) struct Stats {
+    pub(crate) rows: u64,
+    pub(crate) fields: u64,
+    pub(crate) quotes: u64,
+    pub(crate) bytes_read: u64,
+    pub(crate) bytes_written: u64,
+    pub(crate) anomalies: Vec<Anomaly>,
+    /// hex digest of the output, when a checksum was requested
+    pub(crate) checksum: Option<String>,
+    /// number of rows by field count
+    pub(crate) field_counts: HashMap<u64, u64>,
+    /// every repair applied, in input order, when a repair log was requested
+    pub(crate) repairs: Option<Vec<RepairEntry>>,
+    /// the longest field content of each column in bytes, when widths were requested
+    pub(crate) column_widths: Option<Vec<u64>>,
+    /// rows diverted to a rejects file for their field count, when one was given
+    pub(crate) rejected_rows: Option<u64>,
+    /// rows dropped as repeats of an earlier written row, when deduplicating
+    pub(crate) duplicate_rows: Option<u64>,
+    row_seps: u64,
+    row_open: bool,
+    /// the last raw quote opened a `""` pair that the next one may close
+    pair_open: bool,
+    /// within a quoted region of the source, followed with `source_quotes`
+    quoted: bool,
+    /// the offset of the raw quote that closed the last quoted region
+    closed_at: Option<u64>,
+    /// the offset of the raw quote that opened the current region, with `quote_toggle`
+    opened_at: Option<u64>,
+    /// a `sep` ending the last batch, not yet known to trail its row or not
+    held_sep: bool,
+    tail: Vec<u8>,
+}
+
+impl Stats {
+    /// Checks a raw input chunk for a misplaced BOM or NUL bytes.
+    ///
+    /// Chunks must be observed in order; a BOM split across chunks, however short, is still
+    /// found.
+    pub(crate) fn observe(&mut self, chunk: &[u8]) {
+        let base = self.bytes_read;
+
+        if !self.has(|a| matches!(a, Anomaly::Bom { .. })) {
+            let carried = self.tail.len() as u64;
+            self.tail
+                .extend_from_slice(&chunk[..chunk.len().min(BOM.len() - 1)]);
+
+            let found = memmem::find(&self.tail, BOM)
+                .map(|pos| base - carried + pos as u64)
+                .into_iter()
+                .chain(memmem::find_iter(chunk, BOM).map(|pos| base + pos as u64))
+                .find(|&offset| offset > 0);
+
+            if let Some(offset) = found {
+                self.anomalies.push(Anomaly::Bom { offset });
+            }
+
+            // the last bytes of the input so far, which may come from several short chunks
+            let keep = chunk.len().min(BOM.len() - 1);
+            self.tail.truncate(carried as usize);
+            let stale = (carried as usize + keep).saturating_sub(BOM.len() - 1);
+            self.tail.drain(..stale);
+            self.tail.extend_from_slice(&chunk[chunk.len() - keep..]);
+        }
+
+        if !self.has(|a| matches!(a, Anomaly::Nul { .. })) {
+            if let Some(pos) = memchr(0, chunk) {
+                self.anomalies.push(Anomaly::Nul {
+                    offset: base + pos as u64,
+                });
+            }
+        }
+
+        self.bytes_read += chunk.len() as u64;
+    }
+
+    /// Separators seen so far in the current row.
+    #[inline(always)]
+    pub(crate) fn row_seps(&self) -> u64 {
+        self.row_seps
+    }
+
+    /// Whether the current row has any content or separator yet.
+    #[inline(always)]
+    pub(crate) fn row_open(&self) -> bool {
+        self.row_open
+    }
+
+    #[inline(always)]
+    pub(crate) fn on_sep(&mut self) {
+        self.row_seps += 1;
+        self.row_open = true;
+    }
+
+    /// Holds a `sep` ending a batch until the next byte tells whether it ends its row;
+    /// the row is open either way.
+    #[inline(always)]
+    pub(crate) fn hold_sep(&mut self) {
+        self.held_sep = true;
+        self.row_open = true;
+    }
+
+    /// Takes the `sep` held from the last batch, if any.
+    #[inline(always)]
+    pub(crate) fn take_held_sep(&mut self) -> bool {
+        std::mem::take(&mut self.held_sep)
+    }
+
+    #[inline(always)]
+    pub(crate) fn on_eol(&mut self) {
+        self.end_row();
+    }
+
+    #[inline(always)]
+    pub(crate) fn on_content(&mut self) {
+        self.row_open = true;
+    }
+
+    /// Counts a raw quote and returns whether it closes a `""` pair.
+    ///
+    /// It does when it directly follows a quote that opened one; a run of quotes pairs up
+    /// from its start, across batches too.
+    #[inline(always)]
+    pub(crate) fn on_quote(&mut self, follows_quote: bool) -> bool {
+        self.quotes += 1;
+        self.row_open = true;
+        let closes = follows_quote && self.pair_open;
+        self.pair_open = !closes;
+        closes
+    }
+
+    /// Whether the input is within a quoted region of the source.
+    #[inline(always)]
+    pub(crate) fn quoted(&self) -> bool {
+        self.quoted
+    }
+
+    /// Follows the quoted regions of the source past the raw quote at `offset`.
+    ///
+    /// A quote starting a field opens a region and the next quote closes it, unless the
+    /// one right after reopens it, as the second quote of a `""` within a region does.
+    #[inline(always)]
+    pub(crate) fn on_source_quote(&mut self, offset: u64, field_start: bool) {
+        if self.quoted {
+            self.quoted = false;
+            self.closed_at = Some(offset);
+        } else if field_start || self.closed_at.is_some_and(|at| at + 1 == offset) {
+            self.quoted = true;
+        }
+    }
+
+    /// Flips the quoted region of the source on the raw quote at `offset`.
+    #[inline(always)]
+    pub(crate) fn on_toggle_quote(&mut self, offset: u64) {
+        self.quotes += 1;
+        self.row_open = true;
+        self.quoted = !self.quoted;
+        self.opened_at = self.quoted.then_some(offset);
+    }
+
+    /// The offset of the raw quote opening a region still open, followed with `quote_toggle`.
+    pub(crate) fn open_quote(&self) -> Option<u64> {
+        self.opened_at
+    }
+
+    /// Records a field of `width` bytes in column `col`, if column widths are kept.
+    ///
+    /// A column first seen in a longer row extends the widths, earlier rows counting as
+    /// having left it empty.
+    #[inline(always)]
+    pub(crate) fn width(&mut self, col: usize, width: usize) {
+        if let Some(widths) = &mut self.column_widths {
+            if widths.len() <= col {
+                widths.resize(col + 1, 0);
+            }
+            widths[col] = widths[col].max(width as u64);
+        }
+    }
+
+    /// Records `repair` at `offset` in column `col` of the current row, if repairs are kept.
+    #[inline(always)]
+    pub(crate) fn repair(&mut self, offset: u64, col: u64, repair: Repair) {
+        if let Some(repairs) = &mut self.repairs {
+            repairs.push(RepairEntry {
+                offset,
+                row: self.rows,
+                col,
+                repair,
+            });
+        }
+    }
+
+    #[inline(always)]
+    fn end_row(&mut self) {
+        let fields = self.row_seps + 1;
+        *self.field_counts.entry(fields).or_default() += 1;
+        self.rows += 1;
+        self.fields += fields;
+        self.row_seps = 0;
+        self.row_open = false;
+    }
+
+    /// Counts an unterminated last row and derives the row-level anomalies.
+    pub(crate) fn finish(&mut self) {
+        if self.row_open {
+            self.end_row();
+        }
+
+        if let Some((&modal_fields, &modal_rows)) = self
+            .field_counts
+            .iter()
+            .max_by_key(|&(&fields, &rows)| (rows, std::cmp::Reverse(fields)))
+        {
+            let rows = self.rows - modal_rows;
+            if rows > 0 {
+                self.anomalies
+                    .push(Anomaly::RaggedRows { rows, modal_fields });
+            }
+        }
+
+        if self.bytes_read > 0 {
+            let ratio = self.quotes as f64 / self.bytes_read as f64;
+            if ratio > QUOTE_DENSITY_THRESHOLD {
+                self.anomalies.push(Anomaly::QuoteDensity { ratio });
+            }
+        }
+    }
+
+    /// Fails unless exactly `expected` rows were written.
+    pub(crate) fn check_rows(&self, expected: u64) -> io::Result<()> {
+        if self.rows == expected {
+            return Ok(());
+        }
+        let msg = format!("expected {expected} rows but wrote {}", self.rows);
+        Err(io::Error::new(io::ErrorKind::InvalidData, msg))
+    }
+
+    fn has<F: Fn(&Anomaly) -> bool>(&self, f: F) -> bool {
+        self.anomalies.iter().any(f)
+    }
+}
+
+/// An error that interrupted a run, carrying the counters accumulated up to it.
+///
+/// It travels as the payload of an `io::Error` of the same kind and message, so the
+/// drivers keep their `io::Result` signatures and callers that ignore it see no change.
+#[derive(Debug)]
+pub(crate) struct Partial {
+    pub(crate) stats: Stats,
+    source: io::Error,
+}
+
+impl Partial {
+    /// Wraps `e` together with the `stats` accumulated before it.
+    pub(crate) fn attach(stats: Stats, e: io::Error) -> io::Error {
+        io::Error::new(e.kind(), Partial { stats, source: e })
+    }
+
+    /// Appends `note` to the message of `e`, keeping the counters attached to it.
+    pub(crate) fn annotate(e: io::Error, note: String) -> io::Error {
+        let kind = e.kind();
+        if e.get_ref().is_none() {
+            return io::Error::new(kind, Noted { note, source: e });
+        }
+        match e.into_inner().map(|inner| inner.downcast::<Partial>()) {
+            Some(Ok(partial)) => {
+                let Partial { stats, source } = *partial;
+                Partial::attach(stats, io::Error::new(kind, Noted { note, source }))
+            }
+            Some(Err(inner)) => {
+                let source = io::Error::new(kind, inner);
+                io::Error::new(kind, Noted { note, source })
+            }
+            None => unreachable!("checked to have an inner error"),
+        }
+    }
+
+    /// Returns the counters attached to `e`, if any.
+    pub(crate) fn stats(e: &io::Error) -> Option<&Stats> {
+        e.get_ref()
+            .and_then(|inner| inner.downcast_ref::<Partial>())
+            .map(|partial| &partial.stats)
+    }
+}
+
+impl fmt::Display for Partial {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.source.fmt(f)
+    }
+}
+
+impl Error for Partial {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        Some(&self.source)
+    }
+}
+
+/// An error with a note on what was done about it, shown after its message.
+#[derive(Debug)]
+struct Noted {
+    note: String,
+    source: io::Error,
+}
+
+impl fmt::Display for Noted {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}; {}", self.source, self.note)
+    }
+}
+
+impl Error for Noted {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        Some(&self.source)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rstest::*;
+
+    #[rstest]
+    #[case(b"\xEF\xBB\xBFfield1", 1024, vec![])]
+    #[case(b"field1\xEF\xBB\xBF", 1024, vec![Anomaly::Bom { offset: 6 }])]
+    #[case(b"field1\xEF\xBB\xBFfield2", 7, vec![Anomaly::Bom { offset: 6 }])]
+    #[case(b"field1\xEF\xBB\xBFfield2", 8, vec![Anomaly::Bom { offset: 6 }])]
+    #[case(b"field1\xEF\xBB\xBFfield2", 1, vec![Anomaly::Bom { offset: 6 }])]
+    #[case(b"f\xEF\xBB\xBF", 1, vec![Anomaly::Bom { offset: 1 }])]
+    #[case(b"f\xEF\xBBx\xBF", 1, vec![])]
+    #[case(b"\xEF\xBB\xBFa\xEF\xBB\xBF", 1024, vec![Anomaly::Bom { offset: 4 }])]
+    #[case(b"\xEF\xBB\xBFa\xEF\xBB\xBF", 2, vec![Anomaly::Bom { offset: 4 }])]
+    #[case(b"fi\0eld1\0", 1024, vec![Anomaly::Nul { offset: 2 }])]
+    #[case(b"field1fi\0", 6, vec![Anomaly::Nul { offset: 8 }])]
+    fn test_observe(#[case] data: &[u8], #[case] chunk_size: usize, #[case] exp: Vec<Anomaly>) {
+        let mut stats = Stats::default();
+        for chunk in data.chunks(chunk_size) {
+            stats.observe(chunk);
+        }
+        assert_eq!(stats.anomalies, exp);
+    }
+
+    #[rstest]
+    fn test_finish_counts_rows_and_ragged() {
+        let mut stats = Stats::default();
+        // a,b \n c,d \n e \n f,g (unterminated)
+        for sep_count in [1, 1, 0] {
+            for _ in 0..sep_count {
+                stats.on_sep();
+            }
+            stats.on_eol();
+        }
+        stats.on_sep();
+        stats.finish();
+
+        assert_eq!(stats.rows, 4);
+        assert_eq!(stats.fields, 7);
+        assert_eq!(
+            stats.anomalies,
+            vec![Anomaly::RaggedRows {
+                rows: 1,
+                modal_fields: 2
+            }]
+        );
+    }
+
+    #[rstest]
+    fn test_finish_quote_density() {
+        let mut stats = Stats::default();
+        stats.observe(b"\"\"\"field1");
+        stats.quotes = 3;
+        stats.on_content();
+        stats.finish();
+
+        assert_eq!(stats.rows, 1);
+        assert!(matches!(
+            stats.anomalies[..],
+            [Anomaly::QuoteDensity { .. }]
+        ));
+    }
+
+    #[rstest]
+    #[case(2, None)]
+    #[case(1, Some("expected 1 rows but wrote 2"))]
+    #[case(3, Some("expected 3 rows but wrote 2"))]
+    fn test_check_rows(#[case] expected: u64, #[case] exp: Option<&str>) {
+        let mut stats = Stats::default();
+        stats.on_eol();
+        stats.on_eol();
+
+        match (stats.check_rows(expected), exp) {
+            (Ok(()), None) => {}
+            (Err(e), Some(msg)) => {
+                assert_eq!(e.kind(), io::ErrorKind::InvalidData);
+                assert_eq!(e.to_string(), msg);
+            }
+            (result, _) => panic!("unexpected {result:?}"),
+        }
+    }
+
+    #[rstest]
+    fn test_partial_keeps_error_and_stats() {
+        let mut stats = Stats::default();
+        stats.on_eol();
+        stats.bytes_written = 8;
+
+        let e = Partial::attach(stats, io::Error::new(io::ErrorKind::BrokenPipe, "gone"));
+        assert_eq!(e.kind(), io::ErrorKind::BrokenPipe);
+        assert_eq!(e.to_string(), "gone");
+
+        let stats = Partial::stats(&e).unwrap();
+        assert_eq!((stats.rows, stats.bytes_written), (1, 8));
+        assert!(Partial::stats(&io::Error::other("bare")).is_none());
+    }
+
+    #[rstest]
+    fn test_partial_annotate() {
+        let mut stats = Stats::default();
+        stats.on_eol();
+
+        let e = Partial::attach(stats, io::Error::new(io::ErrorKind::BrokenPipe, "gone"));
+        let e = Partial::annotate(e, "noted".to_string());
+        assert_eq!(e.kind(), io::ErrorKind::BrokenPipe);
+        assert_eq!(e.to_string(), "gone; noted");
+        assert_eq!(Partial::stats(&e).map(|stats| stats.rows), Some(1));
+
+        let e = Partial::annotate(
+            io::Error::from(io::ErrorKind::NotFound),
+            "noted".to_string(),
+        );
+        assert_eq!(e.kind(), io::ErrorKind::NotFound);
+        assert!(e.to_string().ends_with("; noted"));
+        assert!(Partial::stats(&e).is_none());
+    }
+}
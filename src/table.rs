@@ -0,0 +1,183 @@
+use crate::transcode::Errors;
+use arrow::array::{ArrayRef, StringBuilder};
+use arrow::compute::{cast_with_options, CastOptions};
+use arrow::datatypes::{DataType, Field, Schema, SchemaRef};
+use arrow::error::ArrowError;
+use arrow::record_batch::{RecordBatch, RecordBatchOptions};
+use std::io;
+use std::sync::Arc;
+
+/// Collects split rows into Arrow columns.
+///
+/// Without a schema every column is a nullable string named `f0`, `f1`, ...; a row wider
+/// than the ones before it adds columns, which are null for the earlier rows. With a
+/// schema the width is fixed: missing fields are null and extra fields are dropped.
+/// Either way a short row is null-padded.
+pub(crate) struct Columns {
+    schema: Option<SchemaRef>,
+    builders: Vec<StringBuilder>,
+    rows: usize,
+    errors: Errors,
+}
+
+impl Columns {
+    pub(crate) fn new(schema: Option<SchemaRef>, errors: Errors) -> Self {
+        let width = schema.as_ref().map_or(0, |schema| schema.fields().len());
+        Columns {
+            schema,
+            builders: (0..width).map(|_| StringBuilder::new()).collect(),
+            rows: 0,
+            errors,
+        }
+    }
+
+    /// Appends one row of raw field values, decoded as UTF-8 by `errors`.
+    pub(crate) fn push_row(&mut self, row: &[Vec<u8>]) -> io::Result<()> {
+        if self.schema.is_none() {
+            while self.builders.len() < row.len() {
+                let mut builder = StringBuilder::new();
+                builder.append_nulls(self.rows);
+                self.builders.push(builder);
+            }
+        }
+
+        for (col, builder) in self.builders.iter_mut().enumerate() {
+            match row.get(col) {
+                Some(field) => {
+                    let value = self.errors.decode(field).map_err(|e| {
+                        let msg = format!("row {}, column {col}: {e}", self.rows);
+                        io::Error::new(io::ErrorKind::InvalidData, msg)
+                    })?;
+                    builder.append_value(&value);
+                }
+                None => builder.append_null(),
+            }
+        }
+        self.rows += 1;
+        Ok(())
+    }
+
+    /// Builds the record batch, casting string columns to the schema's types.
+    ///
+    /// A value that does not parse as its column type is an error, as is a null in a
+    /// non-nullable column.
+    pub(crate) fn finish(self) -> Result<RecordBatch, ArrowError> {
+        let options = RecordBatchOptions::new().with_row_count(Some(self.rows));
+        let columns = self
+            .builders
+            .into_iter()
+            .map(|mut builder| Arc::new(builder.finish()) as ArrayRef);
+
+        let Some(schema) = self.schema else {
+            let columns: Vec<ArrayRef> = columns.collect();
+            let fields: Vec<Field> = (0..columns.len())
+                .map(|col| Field::new(format!("f{col}"), DataType::Utf8, true))
+                .collect();
+            return RecordBatch::try_new_with_options(
+                Arc::new(Schema::new(fields)),
+                columns,
+                &options,
+            );
+        };
+
+        let cast = CastOptions {
+            safe: false,
+            ..CastOptions::default()
+        };
+        let columns = columns
+            .zip(schema.fields())
+            .map(|(column, field)| match field.data_type() {
+                DataType::Utf8 => Ok(column),
+                data_type => cast_with_options(&column, data_type, &cast),
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        RecordBatch::try_new_with_options(schema, columns, &options)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use arrow::array::{Array, Int64Array, StringArray};
+    use rstest::*;
+
+    fn row(fields: &[&str]) -> Vec<Vec<u8>> {
+        fields.iter().map(|f| f.as_bytes().to_vec()).collect()
+    }
+
+    #[rstest]
+    fn test_columns_ragged_without_schema() {
+        let mut columns = Columns::new(None, Errors::Strict);
+        columns.push_row(&row(&["a"])).unwrap();
+        columns.push_row(&row(&["b", "c"])).unwrap();
+        columns.push_row(&row(&["d"])).unwrap();
+
+        let batch = columns.finish().unwrap();
+        assert_eq!(batch.num_rows(), 3);
+        assert_eq!(batch.schema().field(1).name(), "f1");
+
+        let f1 = batch
+            .column(1)
+            .as_any()
+            .downcast_ref::<StringArray>()
+            .unwrap();
+        assert_eq!(f1.iter().collect::<Vec<_>>(), vec![None, Some("c"), None]);
+    }
+
+    #[rstest]
+    fn test_columns_typed_schema() {
+        let schema = Arc::new(Schema::new(vec![
+            Field::new("id", DataType::Int64, false),
+            Field::new("name", DataType::Utf8, true),
+        ]));
+        let mut columns = Columns::new(Some(schema), Errors::Strict);
+        columns.push_row(&row(&["1", "a", "dropped"])).unwrap();
+        columns.push_row(&row(&["2"])).unwrap();
+
+        let batch = columns.finish().unwrap();
+        let id = batch
+            .column(0)
+            .as_any()
+            .downcast_ref::<Int64Array>()
+            .unwrap();
+        assert_eq!(id.values(), &[1, 2]);
+        assert_eq!(batch.num_columns(), 2);
+        assert!(batch.column(1).is_null(1));
+    }
+
+    #[rstest]
+    #[case(&["x"])]
+    #[case(&[])]
+    fn test_columns_typed_schema_errors(#[case] fields: &[&str]) {
+        let schema = Arc::new(Schema::new(vec![Field::new("id", DataType::Int64, false)]));
+        let mut columns = Columns::new(Some(schema), Errors::Strict);
+        columns.push_row(&row(fields)).unwrap();
+
+        assert!(columns.finish().is_err());
+    }
+
+    #[rstest]
+    fn test_columns_invalid_utf8() {
+        let mut columns = Columns::new(None, Errors::Strict);
+        assert!(columns.push_row(&[vec![0xFF]]).is_err());
+
+        let row = [b"a\xFF".to_vec()];
+        for (errors, exp) in [(Errors::Replace, "a\u{FFFD}"), (Errors::Ignore, "a")] {
+            let mut columns = Columns::new(None, errors);
+            columns.push_row(&row).unwrap();
+            let batch = columns.finish().unwrap();
+            let values = batch
+                .column(0)
+                .as_any()
+                .downcast_ref::<StringArray>()
+                .unwrap();
+            assert_eq!(values.value(0), exp);
+        }
+    }
+
+    #[rstest]
+    fn test_columns_empty() {
+        let batch = Columns::new(None, Errors::Strict).finish().unwrap();
+        assert_eq!((batch.num_rows(), batch.num_columns()), (0, 0));
+    }
+}
@@ -0,0 +1,96 @@
+use std::io::{self, Write};
+
+/// Duplicates everything written to `primary` into an optional second sink.
+///
+/// Errors from the second sink are prefixed with `tee:` so they can be told
+/// apart from failures of the primary output.
+#[derive(Debug)]
+pub(crate) struct Tee<A, B> {
+    primary: A,
+    tee: Option<B>,
+}
+
+impl<A, B> Tee<A, B> {
+    pub(crate) fn new(primary: A, tee: Option<B>) -> Self {
+        Tee { primary, tee }
+    }
+}
+
+#[inline(always)]
+fn tee_error(e: io::Error) -> io::Error {
+    io::Error::new(e.kind(), format!("tee: {e}"))
+}
+
+impl<A: Write, B: Write> Write for Tee<A, B> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let written = self.primary.write(buf)?;
+        if let Some(tee) = self.tee.as_mut() {
+            tee.write_all(&buf[..written]).map_err(tee_error)?;
+        }
+        Ok(written)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.primary.flush()?;
+        if let Some(tee) = self.tee.as_mut() {
+            tee.flush().map_err(tee_error)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::reader::{self, TmpOptions};
+    use rstest::*;
+    use std::io::Cursor;
+
+    struct Broken;
+
+    impl Write for Broken {
+        fn write(&mut self, _buf: &[u8]) -> io::Result<usize> {
+            Err(io::Error::other("disk full"))
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    #[rstest]
+    fn test_tee_writes_both() {
+        let data = b"field1\x1Efield2\x1Dfield3";
+        let opts = TmpOptions::default();
+        let (mut primary, mut copy) = (Vec::new(), Vec::new());
+
+        let output = Tee::new(&mut primary, Some(&mut copy));
+        let result = reader::exorcize_csv(Cursor::new(data), output, 20, 4, &opts);
+        assert!(result.is_ok());
+
+        assert_eq!(primary, b"\"field1\",\"field2\"\n\"field3\"");
+        assert_eq!(primary, copy);
+    }
+
+    #[rstest]
+    fn test_tee_without_second_sink() {
+        let mut primary = Vec::new();
+
+        let mut output = Tee::new(&mut primary, None::<Vec<u8>>);
+        assert!(output.write_all(b"field1").is_ok());
+        assert_eq!(primary, b"field1");
+    }
+
+    #[rstest]
+    fn test_tee_failure_is_labelled() {
+        let mut primary = Vec::new();
+
+        let mut output = Tee::new(&mut primary, Some(Broken));
+        let err = output.write_all(b"field1").unwrap_err();
+        assert_eq!(err.to_string(), "tee: disk full");
+
+        let mut output = Tee::new(Broken, Some(Vec::new()));
+        let err = output.write_all(b"field1").unwrap_err();
+        assert_eq!(err.to_string(), "disk full");
+    }
+}
@@ -0,0 +1,254 @@
+use encoding_rs::{Decoder, Encoding, UTF_16BE, UTF_16LE};
+use std::borrow::Cow;
+use std::io::{self, Read};
+use std::str::Utf8Error;
+
+/// The names `input_encoding` accepts, each also without its dash.
+pub(crate) const NAMES: [&str; 3] = ["utf-8", "utf-16le", "utf-16be"];
+
+/// The input encoding named `name`, compared case-insensitively, or `None` when it is not
+/// a known one. UTF-8 is read as it is, so it names no encoding to decode.
+pub(crate) fn from_name(name: &str) -> Option<Option<&'static Encoding>> {
+    match name.to_ascii_lowercase().as_str() {
+        "utf-8" | "utf8" => Some(None),
+        "utf-16le" | "utf16le" => Some(Some(UTF_16LE)),
+        "utf-16be" | "utf16be" => Some(Some(UTF_16BE)),
+        _ => None,
+    }
+}
+
+/// How bytes that are not valid UTF-8 are handled when returned as `str`, as the `errors`
+/// of Python's `bytes.decode`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) enum Errors {
+    /// Fails on the first invalid sequence.
+    Strict,
+    /// Writes U+FFFD for each invalid sequence.
+    Replace,
+    /// Drops invalid sequences.
+    Ignore,
+}
+
+impl Errors {
+    /// The names `errors` accepts.
+    pub(crate) const NAMES: [&str; 3] = ["strict", "replace", "ignore"];
+
+    pub(crate) fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "strict" => Some(Errors::Strict),
+            "replace" => Some(Errors::Replace),
+            "ignore" => Some(Errors::Ignore),
+            _ => None,
+        }
+    }
+
+    /// Decodes `bytes` as UTF-8, borrowing them when they are valid.
+    pub(crate) fn decode(self, bytes: &[u8]) -> Result<Cow<'_, str>, Utf8Error> {
+        match (std::str::from_utf8(bytes), self) {
+            (Ok(text), _) => Ok(Cow::Borrowed(text)),
+            (Err(e), Errors::Strict) => Err(e),
+            (Err(_), Errors::Replace) => Ok(String::from_utf8_lossy(bytes)),
+            (Err(_), Errors::Ignore) => Ok(Cow::Owned(
+                bytes.utf8_chunks().map(|chunk| chunk.valid()).collect(),
+            )),
+        }
+    }
+}
+
+/// Wraps `input` to decode it from `encoding` into UTF-8, dropping a leading BOM.
+///
+/// Decoding is stateful, so a code unit or surrogate pair split between two reads is
+/// joined again. Malformed sequences, such as a lone surrogate, become U+FFFD.
+pub(crate) struct Transcoded<R> {
+    input: R,
+    decoder: Decoder,
+    raw: Vec<u8>,
+    out: Vec<u8>,
+    pos: usize,
+    finished: bool,
+}
+
+impl<R: Read> Transcoded<R> {
+    pub(crate) fn new(input: R, encoding: &'static Encoding) -> Self {
+        Transcoded {
+            input,
+            decoder: encoding.new_decoder_with_bom_removal(),
+            raw: vec![0u8; 8 * 1024],
+            out: Vec::new(),
+            pos: 0,
+            finished: false,
+        }
+    }
+
+    /// Decodes raw reads until some UTF-8 is pending or the input is done.
+    fn fill(&mut self) -> io::Result<()> {
+        while self.pos == self.out.len() && !self.finished {
+            let read = self.input.read(&mut self.raw)?;
+            let last = read == 0;
+            let needed = self.decoder.max_utf8_buffer_length(read).ok_or_else(|| {
+                io::Error::new(io::ErrorKind::OutOfMemory, "decoded chunk too large")
+            })?;
+
+            self.out.resize(needed, 0);
+            let (_, _, written, _) =
+                self.decoder
+                    .decode_to_utf8(&self.raw[..read], &mut self.out, last);
+            self.out.truncate(written);
+            self.pos = 0;
+            self.finished = last;
+        }
+        Ok(())
+    }
+}
+
+impl<R: Read> Read for Transcoded<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        self.fill()?;
+        let pending = &self.out[self.pos..];
+        let len = pending.len().min(buf.len());
+        buf[..len].copy_from_slice(&pending[..len]);
+        self.pos += len;
+        Ok(len)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::reader::{self, TmpOptions};
+    use rstest::*;
+    use std::io::Cursor;
+
+    /// Hands out at most `.0` bytes per read, to split code units and surrogate pairs.
+    struct Trickle<'a>(usize, &'a [u8]);
+
+    impl Read for Trickle<'_> {
+        fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+            let len = self.0.min(buf.len()).min(self.1.len());
+            buf[..len].copy_from_slice(&self.1[..len]);
+            self.1 = &self.1[len..];
+            Ok(len)
+        }
+    }
+
+    fn utf16(text: &str, little: bool) -> Vec<u8> {
+        let mut bytes = match little {
+            true => vec![0xFF, 0xFE],
+            false => vec![0xFE, 0xFF],
+        };
+        for unit in text.encode_utf16() {
+            let unit = match little {
+                true => unit.to_le_bytes(),
+                false => unit.to_be_bytes(),
+            };
+            bytes.extend_from_slice(&unit);
+        }
+        bytes
+    }
+
+    #[rstest]
+    #[case("utf-16le", Some(Some(UTF_16LE)))]
+    #[case("utf16le", Some(Some(UTF_16LE)))]
+    #[case("UTF-16BE", Some(Some(UTF_16BE)))]
+    #[case("utf16be", Some(Some(UTF_16BE)))]
+    #[case("utf-8", Some(None))]
+    #[case("UTF8", Some(None))]
+    #[case("utf-16", None)]
+    #[case("latin-1", None)]
+    fn test_from_name(#[case] name: &str, #[case] exp: Option<Option<&'static Encoding>>) {
+        assert_eq!(from_name(name), exp);
+    }
+
+    #[rstest]
+    #[case(1)]
+    #[case(3)]
+    #[case(4096)]
+    fn test_transcoded_utf16le(#[case] step: usize) {
+        let text = "id\u{1E}név\u{1D}2\u{1E}\u{1F47B} \"x\"";
+        let raw = utf16(text, true);
+
+        let mut out = String::new();
+        Transcoded::new(Trickle(step, &raw), UTF_16LE)
+            .read_to_string(&mut out)
+            .unwrap();
+        assert_eq!(out, text);
+    }
+
+    #[rstest]
+    fn test_transcoded_utf16be() {
+        let raw = utf16("a\u{1E}b", false);
+
+        let mut out = String::new();
+        Transcoded::new(Trickle(1, &raw), UTF_16BE)
+            .read_to_string(&mut out)
+            .unwrap();
+        assert_eq!(out, "a\u{1E}b");
+    }
+
+    #[rstest]
+    #[case(b"\xFF\xFE", "")]
+    #[case(b"a\x00\x00", "a\u{FFFD}")]
+    #[case(b"\x3D\xD8a\x00", "\u{FFFD}a")]
+    fn test_transcoded_malformed(#[case] raw: &[u8], #[case] exp: &str) {
+        let mut out = String::new();
+        Transcoded::new(Cursor::new(raw), UTF_16LE)
+            .read_to_string(&mut out)
+            .unwrap();
+        assert_eq!(out, exp);
+    }
+
+    #[rstest]
+    #[case(b"caf\xC3\xA9", "café", "café")]
+    #[case(b"a\xFFb", "a\u{FFFD}b", "ab")]
+    #[case(b"\xE9t\xE9", "\u{FFFD}t\u{FFFD}", "t")]
+    #[case(b"a\xE2\x82", "a\u{FFFD}", "a")]
+    #[case(b"", "", "")]
+    fn test_errors_decode(#[case] bytes: &[u8], #[case] replaced: &str, #[case] ignored: &str) {
+        assert_eq!(Errors::Replace.decode(bytes).unwrap(), replaced);
+        assert_eq!(Errors::Ignore.decode(bytes).unwrap(), ignored);
+        match std::str::from_utf8(bytes) {
+            Ok(text) => assert_eq!(Errors::Strict.decode(bytes).unwrap(), text),
+            Err(e) => assert_eq!(Errors::Strict.decode(bytes).unwrap_err(), e),
+        }
+    }
+
+    #[rstest]
+    fn test_errors_from_name() {
+        for name in Errors::NAMES {
+            assert!(Errors::from_name(name).is_some());
+        }
+        assert_eq!(Errors::from_name("Replace"), None);
+    }
+
+    #[rstest]
+    #[case(1)]
+    #[case(5)]
+    fn test_exorcize_csv_utf16le_input(#[case] chunk_size: usize) {
+        let raw = utf16("név\u{1E}\u{1F47B}\u{1D}\"q\"\u{1E}b", true);
+        let opts = TmpOptions::default();
+
+        let mut output = Vec::new();
+        let input = Transcoded::new(Trickle(3, &raw), UTF_16LE);
+        let size = raw.len() as u64;
+        reader::exorcize_csv(input, &mut output, size, chunk_size, &opts).unwrap();
+        assert_eq!(
+            String::from_utf8(output).unwrap(),
+            "\"név\",\"\u{1F47B}\"\n\"\\\"q\\\"\",\"b\""
+        );
+    }
+
+    #[rstest]
+    fn test_exorcize_csv_utf16le_quote_after_bom() {
+        // a quote right after the dropped BOM is escaped as at the start of a UTF-8 input
+        let raw = utf16("\"a\"\u{1E}b", true);
+        let opts = TmpOptions::default();
+
+        for chunk_size in 1..=raw.len() {
+            let mut output = Vec::new();
+            let input = Transcoded::new(Trickle(1, &raw), UTF_16LE);
+            let size = raw.len() as u64;
+            reader::exorcize_csv(input, &mut output, size, chunk_size, &opts).unwrap();
+            assert_eq!(output, b"\"\\\"a\\\"\",\"b\"", "chunk_size {chunk_size}");
+        }
+    }
+}
@@ -0,0 +1,76 @@
+use crate::reader::TmpOptions;
+use crate::rows;
+use crate::transcode::Errors;
+use std::io::{self, Read};
+
+/// The largest input `exorcize_transpose` accepts, as the whole table is held in memory.
+pub(crate) const MAX_TRANSPOSE_SIZE: u64 = 1 << 20;
+
+/// Reads raw input into its columns, so that each column of the input becomes a row.
+///
+/// Fields are split as by `rows::split_rows` and decoded as UTF-8 by `errors`. Short rows
+/// are padded with empty fields to the widest row, so every column has one field per row.
+pub(crate) fn read_columns<R>(
+    input: R,
+    chunk_size: usize,
+    opts: &TmpOptions,
+    errors: Errors,
+) -> io::Result<Vec<Vec<String>>>
+where
+    R: Read,
+{
+    let mut columns: Vec<Vec<String>> = Vec::new();
+    let mut rows = 0;
+
+    rows::split_rows(input, chunk_size, opts, |row| {
+        while columns.len() < row.len() {
+            columns.push(vec![String::new(); rows]);
+        }
+        for (col, column) in columns.iter_mut().enumerate() {
+            let value = match row.get(col) {
+                Some(field) => errors.decode(field).map_err(|e| {
+                    let msg = format!("row {rows}, column {col}: {e}");
+                    io::Error::new(io::ErrorKind::InvalidData, msg)
+                })?,
+                None => "".into(),
+            };
+            column.push(value.into_owned());
+        }
+        rows += 1;
+        Ok(())
+    })?;
+
+    Ok(columns)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rstest::*;
+    use std::io::Cursor;
+
+    #[rstest]
+    #[case(b"id\x1E1\x1E2\x1Dname\x1Ea\x1Eb", vec![vec!["id", "name"], vec!["1", "a"], vec!["2", "b"]])]
+    #[case(b"a\x1Eb\x1Dc\x1D", vec![vec!["a", "c"], vec!["b", ""]])]
+    #[case(b"a\x1Dc\x1Ed", vec![vec!["a", "c"], vec!["", "d"]])]
+    #[case(b"", vec![])]
+    fn test_read_columns(#[case] data: &[u8], #[case] exp: Vec<Vec<&str>>) {
+        let columns = read_columns(Cursor::new(data), 2, &TmpOptions::default(), Errors::Strict);
+        assert_eq!(columns.unwrap(), exp);
+    }
+
+    #[rstest]
+    fn test_read_columns_invalid_utf8() {
+        let opts = TmpOptions::default();
+        let data = b"a\x1D\xFF";
+        let e = read_columns(Cursor::new(data), 1024, &opts, Errors::Strict).unwrap_err();
+        assert_eq!(e.kind(), io::ErrorKind::InvalidData);
+        assert!(e.to_string().starts_with("row 1, column 0"));
+
+        let data = b"a\xFF\x1Eb\x1D\xFF";
+        let columns = read_columns(Cursor::new(data), 2, &opts, Errors::Replace).unwrap();
+        assert_eq!(columns, vec![vec!["a\u{FFFD}", "\u{FFFD}"], vec!["b", ""]]);
+        let columns = read_columns(Cursor::new(data), 2, &opts, Errors::Ignore).unwrap();
+        assert_eq!(columns, vec![vec!["a", ""], vec!["b", ""]]);
+    }
+}
@@ -0,0 +1,48 @@
+/// Parses a byte size such as `"4MiB"`, `"64 KB"` or `"512"` into bytes.
+///
+/// Units are case-insensitive: `B`, the decimal `KB`, `MB` and `GB` (powers of 1000) and
+/// the binary `KiB`, `MiB` and `GiB` (powers of 1024). No unit means bytes. Returns `None`
+/// for anything else, or a size that does not fit a `usize`.
+pub(crate) fn parse_size(text: &str) -> Option<usize> {
+    let text = text.trim();
+    let split = text
+        .find(|c: char| !c.is_ascii_digit())
+        .unwrap_or(text.len());
+    let (number, unit) = text.split_at(split);
+
+    let factor: usize = match unit.trim_start().to_ascii_lowercase().as_str() {
+        "" | "b" => 1,
+        "kb" => 1000,
+        "mb" => 1000 * 1000,
+        "gb" => 1000 * 1000 * 1000,
+        "kib" => 1 << 10,
+        "mib" => 1 << 20,
+        "gib" => 1 << 30,
+        _ => return None,
+    };
+    number.parse::<usize>().ok()?.checked_mul(factor)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rstest::*;
+
+    #[rstest]
+    #[case("512", Some(512))]
+    #[case("512B", Some(512))]
+    #[case("64KiB", Some(65536))]
+    #[case("4MiB", Some(4 * 1024 * 1024))]
+    #[case("4MB", Some(4_000_000))]
+    #[case(" 2 gib ", Some(2 << 30))]
+    #[case("1kb", Some(1000))]
+    #[case("", None)]
+    #[case("MB", None)]
+    #[case("1.5MB", None)]
+    #[case("-1KB", None)]
+    #[case("4 megabytes", None)]
+    #[case("99999999999999999999GiB", None)]
+    fn test_parse_size(#[case] text: &str, #[case] exp: Option<usize>) {
+        assert_eq!(parse_size(text), exp);
+    }
+}
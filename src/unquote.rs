@@ -0,0 +1,182 @@
+use crate::reader::TmpOptions;
+use std::io::{self, BufRead, BufReader, BufWriter, Read, Write};
+
+/// Where parsing is within the current field.
+#[derive(Clone, Copy)]
+enum State {
+    /// Before the first byte of a field.
+    Start,
+    /// Within a field written without quotes, which is copied as it is.
+    Unquoted,
+    /// Within a quoted field, holding the backslashes seen but not yet written.
+    Quoted(usize),
+    /// After the closing quote of a field.
+    Closed,
+}
+
+fn invalid(msg: String) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, msg)
+}
+
+/// Turns a CSV written by `exorcize_csv` back into raw input split by `sep` and `eol`.
+///
+/// Outside of quotes `out_delim` becomes `sep` and `out_eol` becomes `eol`; the quotes
+/// around a field are dropped and an escaped quote within one is written bare. The
+/// backslash added to a field ending in an odd run of them is taken off again. A raw
+/// field ending in an even run therefore comes back one backslash short, and a raw `\"`
+/// comes back as `"`: a fix writes both like other input. `out_delim` and `out_eol` must
+/// be one byte each.
+///
+/// A quoted field left open or a byte after a closing quote is `InvalidData`.
+pub(crate) fn unexorcize<R, W>(
+    input: R,
+    output: W,
+    chunk_size: usize,
+    opts: &TmpOptions,
+) -> io::Result<()>
+where
+    R: Read,
+    W: Write,
+{
+    let (&[out_delim], &[out_eol]) = (opts.out_delim.as_slice(), opts.out_eol.as_slice()) else {
+        let msg = "unexorcize needs a one-byte out_delim and out_eol";
+        return Err(io::Error::new(io::ErrorKind::InvalidInput, msg));
+    };
+    let mut reader = BufReader::with_capacity(chunk_size, input);
+    let mut writer = BufWriter::new(output);
+
+    let mut out = Vec::with_capacity(chunk_size);
+    let mut state = State::Start;
+    let mut offset = 0u64;
+    loop {
+        let available = reader.fill_buf()?;
+        if available.is_empty() {
+            break;
+        }
+
+        for &byte in available {
+            state = match state {
+                State::Quoted(run) if byte == b'\\' => State::Quoted(run + 1),
+                State::Quoted(run) if byte == opts.quote && run % 2 == 1 => {
+                    out.resize(out.len() + run - 1, b'\\');
+                    out.push(byte);
+                    State::Quoted(0)
+                }
+                State::Quoted(run) if byte == opts.quote => {
+                    out.resize(out.len() + run.saturating_sub(1), b'\\');
+                    State::Closed
+                }
+                State::Quoted(run) => {
+                    out.resize(out.len() + run, b'\\');
+                    out.push(byte);
+                    State::Quoted(0)
+                }
+                State::Start if byte == opts.quote => State::Quoted(0),
+                _ if byte == out_delim => {
+                    out.push(opts.sep);
+                    State::Start
+                }
+                _ if byte == out_eol => {
+                    out.push(opts.eol);
+                    State::Start
+                }
+                State::Closed => {
+                    return Err(invalid(format!(
+                        "byte {offset} ({byte:#04x}) follows a closing quote"
+                    )))
+                }
+                State::Start | State::Unquoted => {
+                    out.push(byte);
+                    State::Unquoted
+                }
+            };
+            offset += 1;
+        }
+
+        let used = available.len();
+        reader.consume(used);
+        writer.write_all(&out)?;
+        out.clear();
+    }
+
+    if let State::Quoted(_) = state {
+        return Err(invalid("the last quoted field is not closed".to_string()));
+    }
+    writer.flush()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::reader;
+    use rstest::*;
+    use std::io::Cursor;
+
+    fn unexorcized(data: &[u8], chunk_size: usize, opts: &TmpOptions) -> io::Result<Vec<u8>> {
+        let mut output = Vec::new();
+        unexorcize(Cursor::new(data), &mut output, chunk_size, opts)?;
+        Ok(output)
+    }
+
+    #[rstest]
+    #[case(b"")]
+    #[case(b"\x1D")]
+    #[case(b"field1\x1Efield2\x1Dfield3")]
+    #[case(b"field1\x1Efield2\x1Efield3\x1D")]
+    #[case(b"\x1E\x1E\x1D")]
+    #[case(b"\"\"field\",\"field\",field\"\x1Efield3\x1D")]
+    #[case(b"field1\\\x1Efield2\\\x1Dfield3")]
+    #[case(b"a\nb\x1E\"x\"\\\x1Dc\x1D")]
+    #[case(b"a\\b\x1E\\\\\"\x1E\\\\\\")]
+    fn test_unexorcize_round_trip(#[case] data: &[u8]) {
+        let opts = TmpOptions::default();
+        let mut fixed = Vec::new();
+        let size = data.len() as u64;
+        reader::exorcize_csv(Cursor::new(data), &mut fixed, size, 1024, &opts).unwrap();
+
+        for chunk_size in [1, 2, 3, 1024] {
+            let raw = unexorcized(&fixed, chunk_size, &opts).unwrap();
+            assert_eq!(raw, data, "chunk_size {chunk_size}");
+        }
+    }
+
+    #[rstest]
+    #[case(b"\"a\",,b\n", b"a\x1E\x1Eb\x1D")]
+    #[case(b"a\"b,\"\"\n", b"a\"b\x1E\x1D")]
+    // written alike by a fix, so not told apart
+    #[case(b"\"a\\\\\"", b"a\\")]
+    #[case(b"\"a\\\"\"", b"a\"")]
+    fn test_unexorcize(#[case] data: &[u8], #[case] exp: &[u8]) {
+        assert_eq!(unexorcized(data, 2, &TmpOptions::default()).unwrap(), exp);
+    }
+
+    #[rstest]
+    #[case(b"\"a\"b", "byte 3 (0x62) follows a closing quote")]
+    #[case(b"\"a\n", "the last quoted field is not closed")]
+    #[case(b"\"a\\\"", "the last quoted field is not closed")]
+    fn test_unexorcize_malformed(#[case] data: &[u8], #[case] msg: &str) {
+        let e = unexorcized(data, 1024, &TmpOptions::default()).unwrap_err();
+        assert_eq!(e.kind(), io::ErrorKind::InvalidData);
+        assert_eq!(e.to_string(), msg);
+    }
+
+    #[rstest]
+    fn test_unexorcize_custom_bytes() {
+        let opts = TmpOptions {
+            sep: b'|',
+            eol: b'\t',
+            ..TmpOptions::default()
+        };
+        assert_eq!(
+            unexorcized(b"\"a\",\"b\"\n\"c\"", 3, &opts).unwrap(),
+            b"a|b\tc"
+        );
+
+        let opts = TmpOptions {
+            out_eol: b"\r\n".to_vec(),
+            ..TmpOptions::default()
+        };
+        let e = unexorcized(b"", 3, &opts).unwrap_err();
+        assert_eq!(e.kind(), io::ErrorKind::InvalidInput);
+    }
+}
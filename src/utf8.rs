@@ -0,0 +1,115 @@
+use std::io::{self, Read};
+use std::str;
+
+/// Fails the read that brings bytes of `inner` that are not valid UTF-8, reporting the
+/// offset of the first one.
+///
+/// A sequence cut by the end of a read is carried over and checked once the next read
+/// completes it; one cut by the end of the input is invalid. Nothing is transcoded.
+#[derive(Debug)]
+pub(crate) struct Utf8Checked<R> {
+    inner: R,
+    /// The bytes known to be valid, before the carried ones.
+    checked: u64,
+    /// The start of a sequence the last read ended within.
+    carry: Vec<u8>,
+}
+
+impl<R> Utf8Checked<R> {
+    pub(crate) fn new(inner: R) -> Self {
+        Utf8Checked {
+            inner,
+            checked: 0,
+            carry: Vec::with_capacity(4),
+        }
+    }
+
+    fn invalid(&self, at: usize) -> io::Error {
+        let msg = format!(
+            "input is not valid UTF-8 at byte {}",
+            self.checked + at as u64
+        );
+        io::Error::new(io::ErrorKind::InvalidData, msg)
+    }
+
+    fn check(&mut self, mut buf: &[u8]) -> io::Result<()> {
+        if !self.carry.is_empty() {
+            // no sequence is longer than 4 bytes
+            let carried = self.carry.len();
+            let mut seq = std::mem::take(&mut self.carry);
+            seq.extend_from_slice(&buf[..buf.len().min(4 - carried)]);
+            let valid = match str::from_utf8(&seq) {
+                Ok(_) => seq.len(),
+                Err(e) if e.valid_up_to() >= carried => e.valid_up_to(),
+                Err(e) if e.error_len().is_some() => return Err(self.invalid(e.valid_up_to())),
+                // still cut, by the end of `buf`
+                Err(_) => {
+                    self.carry = seq;
+                    return Ok(());
+                }
+            };
+            buf = &buf[valid - carried..];
+            self.checked += valid as u64;
+        }
+
+        if let Err(e) = str::from_utf8(buf) {
+            if e.error_len().is_some() {
+                return Err(self.invalid(e.valid_up_to()));
+            }
+            self.carry.extend_from_slice(&buf[e.valid_up_to()..]);
+            self.checked += e.valid_up_to() as u64;
+            return Ok(());
+        }
+        self.checked += buf.len() as u64;
+        Ok(())
+    }
+}
+
+impl<R: Read> Read for Utf8Checked<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let read = self.inner.read(buf)?;
+        if read == 0 && !buf.is_empty() && !self.carry.is_empty() {
+            return Err(self.invalid(0));
+        }
+        self.check(&buf[..read])?;
+        Ok(read)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rstest::*;
+    use std::io::Cursor;
+
+    #[rstest]
+    #[case("a\x1Eé\x1D€\x1E😀".as_bytes(), None)]
+    #[case(b"", None)]
+    #[case(b"ab\x1Ec\xFFd", Some(4))]
+    #[case(b"a\xE2\x82\x1Eb", Some(1))]
+    #[case(b"\xC3\xA9\x80", Some(2))]
+    // a sequence cut by the end of the input
+    #[case(b"a\x1E\xF0\x9F\x98", Some(2))]
+    fn test_utf8_checked(#[case] data: &[u8], #[case] exp: Option<u64>) {
+        for chunk_size in 1..=data.len().max(1) {
+            let mut input = Utf8Checked::new(Cursor::new(data));
+            let mut buf = vec![0u8; chunk_size];
+            let result = loop {
+                match input.read(&mut buf) {
+                    Ok(0) => break Ok(()),
+                    Ok(_) => continue,
+                    Err(e) => break Err(e),
+                }
+            };
+            match exp {
+                None => assert!(result.is_ok(), "chunk_size {chunk_size}"),
+                Some(offset) => {
+                    let e = result.unwrap_err();
+                    assert_eq!(e.kind(), io::ErrorKind::InvalidData);
+                    let msg = format!("input is not valid UTF-8 at byte {offset}");
+                    assert_eq!(e.to_string(), msg, "chunk_size {chunk_size}");
+                }
+            }
+        }
+    }
+}
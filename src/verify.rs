@@ -0,0 +1,519 @@
+use crate::reader::TmpOptions;
+use crate::stats::{Stats, BOM};
+use std::collections::HashMap;
+use std::io::{self, Read, Write};
+
+/// Where the parse of the output stands, between two bytes.
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum State {
+    /// Before the first byte of a field.
+    FieldStart,
+    Unquoted,
+    Quoted,
+    /// After a backslash in a quoted field, which escapes the next byte.
+    Escaped,
+    /// After a quote in a quoted field whose quotes are doubled: closing, or the first half
+    /// of `""`.
+    QuoteSeen,
+    /// After the closing quote, where only `out_delim` or `out_eol` may follow.
+    Closed,
+}
+
+/// An RFC 4180 parse of the output in its own dialect, fed as it is written.
+///
+/// Fields are quoted or not; a quoted one ends on a quote followed by `out_delim`,
+/// `out_eol` or the end, and within it a quote is escaped by doubling with
+/// `double_quotes` and by a backslash otherwise, a backslash escaping any byte after it.
+/// An unquoted field holds no quote. Records end on `out_eol`, the last one also on the
+/// end of the output. `out_delim` must be one byte. The BOM of `write_bom` is skipped.
+/// The content of the fields, unescaped, is kept as a CRC-32 to compare with the source.
+#[derive(Debug)]
+pub(crate) struct Rfc4180 {
+    quote: u8,
+    delim: u8,
+    eol: Vec<u8>,
+    double_quotes: bool,
+    state: State,
+    /// The bytes of `out_eol` matched so far.
+    matched: usize,
+    /// Whether the current record has any byte yet.
+    open: bool,
+    fields: u64,
+    /// Number of records by field count.
+    records: HashMap<u64, u64>,
+    offset: u64,
+    /// The bytes of a leading BOM still to skip.
+    bom: usize,
+    /// The field content of the bytes being fed, hashed once they all are.
+    content: Vec<u8>,
+    hasher: crc32fast::Hasher,
+}
+
+fn malformed(offset: u64, what: &str) -> io::Error {
+    let msg = format!("output is not valid RFC 4180 at byte {offset}: {what}; this is a fixer bug");
+    io::Error::new(io::ErrorKind::InvalidData, msg)
+}
+
+impl Rfc4180 {
+    pub(crate) fn new(opts: &TmpOptions) -> Self {
+        Rfc4180 {
+            quote: opts.quote,
+            delim: opts.out_delim[0],
+            eol: opts.out_eol.clone(),
+            double_quotes: opts.double_quotes,
+            state: State::FieldStart,
+            matched: 0,
+            open: false,
+            fields: 1,
+            records: HashMap::new(),
+            offset: 0,
+            bom: if opts.write_bom { BOM.len() } else { 0 },
+            content: Vec::new(),
+            hasher: crc32fast::Hasher::new(),
+        }
+    }
+
+    fn end_record(&mut self) {
+        *self.records.entry(self.fields).or_default() += 1;
+        (self.state, self.matched, self.open, self.fields) = (State::FieldStart, 0, false, 1);
+    }
+
+    /// Follows `out_eol` outside of quoted content, returning whether `byte` was part of it.
+    fn eol_byte(&mut self, byte: u8) -> io::Result<bool> {
+        if byte == self.eol[self.matched] {
+            self.matched += 1;
+            if self.matched == self.eol.len() {
+                self.end_record();
+            }
+            return Ok(true);
+        }
+        if self.matched > 0 {
+            if self.state == State::Closed {
+                return Err(malformed(self.offset, "a broken newline after a field"));
+            }
+            // the bytes taken for a newline were content of an unquoted field
+            self.content.extend_from_slice(&self.eol[..self.matched]);
+            self.matched = 0;
+            self.state = State::Unquoted;
+            return self.eol_byte(byte);
+        }
+        Ok(false)
+    }
+
+    fn byte(&mut self, byte: u8) -> io::Result<()> {
+        self.open = true;
+        let outside = matches!(
+            self.state,
+            State::FieldStart | State::Unquoted | State::Closed
+        );
+        if outside && self.eol_byte(byte)? {
+            return Ok(());
+        }
+        match self.state {
+            State::FieldStart | State::Unquoted | State::Closed if byte == self.delim => {
+                self.fields += 1;
+                self.state = State::FieldStart;
+            }
+            State::FieldStart if byte == self.quote => self.state = State::Quoted,
+            State::FieldStart | State::Unquoted if byte == self.quote => {
+                return Err(malformed(self.offset, "a quote inside an unquoted field"));
+            }
+            State::FieldStart | State::Unquoted => {
+                self.content.push(byte);
+                self.state = State::Unquoted;
+            }
+            State::Closed => {
+                return Err(malformed(self.offset, "content after a closing quote"));
+            }
+            State::Quoted if byte == self.quote => {
+                self.state = match self.double_quotes {
+                    true => State::QuoteSeen,
+                    false => State::Closed,
+                }
+            }
+            State::Quoted if byte == b'\\' && !self.double_quotes => self.state = State::Escaped,
+            State::Quoted => self.content.push(byte),
+            State::Escaped => {
+                self.content.push(byte);
+                self.state = State::Quoted;
+            }
+            State::QuoteSeen if byte == self.quote => {
+                self.content.push(byte);
+                self.state = State::Quoted;
+            }
+            State::QuoteSeen => {
+                self.state = State::Closed;
+                return self.byte(byte);
+            }
+        }
+        Ok(())
+    }
+
+    pub(crate) fn feed(&mut self, buf: &[u8]) -> io::Result<()> {
+        let skipped = self.bom.min(buf.len());
+        self.bom -= skipped;
+        self.offset += skipped as u64;
+        for &byte in &buf[skipped..] {
+            self.byte(byte)?;
+            self.offset += 1;
+        }
+        self.hasher.update(&self.content);
+        self.content.clear();
+        Ok(())
+    }
+
+    /// Ends the parse and checks its records against the rows `stats` counted, and its
+    /// field content against the CRC-32 of the `source` one, if given.
+    ///
+    /// Every input row must give one record, after a `header`, with its field count
+    /// adjusted for `pad_to` and `extra_columns`, or set by `column_order`: ragged input
+    /// stays ragged, but no record may gain or lose a field on its own.
+    pub(crate) fn finish(
+        mut self,
+        stats: &Stats,
+        opts: &TmpOptions,
+        source: Option<u32>,
+    ) -> io::Result<()> {
+        match self.state {
+            State::Quoted | State::Escaped => {
+                return Err(malformed(self.offset, "the end inside a quoted field"));
+            }
+            State::Closed if self.matched > 0 => {
+                return Err(malformed(self.offset, "a broken newline at the end"));
+            }
+            _ if self.open => self.end_record(),
+            _ => {}
+        }
+
+        // an empty input gets no header
+        if let Some(names) = opts.header.as_ref().filter(|_| stats.bytes_read > 0) {
+            let fields = names.len().max(1) as u64;
+            match self.records.get_mut(&fields) {
+                Some(records) if *records > 0 => *records -= 1,
+                _ => return Err(malformed(0, "no header record")),
+            }
+        }
+        let extra = opts.extra_columns.as_ref().map_or(0, Vec::len) as u64;
+        let mut expected: HashMap<u64, u64> = HashMap::new();
+        for (&fields, &rows) in &stats.field_counts {
+            let fields = match &opts.column_order {
+                Some(order) => order.len() as u64,
+                None => opts.pad_to.map_or(fields, |n| n as u64),
+            } + extra;
+            *expected.entry(fields).or_default() += rows;
+        }
+        self.records.retain(|_, records| *records > 0);
+
+        if self.records != expected {
+            let total = |counts: &HashMap<u64, u64>| counts.values().sum::<u64>();
+            let what = format!(
+                "{} records by field count {:?} for {} rows by field count {:?}",
+                total(&self.records),
+                sorted(&self.records),
+                total(&expected),
+                sorted(&expected)
+            );
+            return Err(malformed(self.offset, &what));
+        }
+
+        // a newline left unfinished was content of the last field
+        self.hasher.update(&self.eol[..self.matched]);
+        let content = self.hasher.finalize();
+        match source {
+            Some(source) if source != content => {
+                let msg = format!(
+                    "output field content differs from the input: crc32 {content:08x} for \
+                    {source:08x}; this is a fixer bug"
+                );
+                Err(io::Error::new(io::ErrorKind::InvalidData, msg))
+            }
+            _ => Ok(()),
+        }
+    }
+}
+
+/// The field content of the source, as a CRC-32 to compare with that of the output.
+///
+/// Content is every byte but `sep` and `eol`, a backslash taken as the byte it escapes
+/// when the output escapes with backslashes, as the fixer passes such escapes through,
+/// and kept otherwise. Options that rewrite content leave nothing to compare.
+pub(crate) struct SourceContent {
+    sep: u8,
+    eol: u8,
+    unescapes: bool,
+    /// The last byte fed was an escaping backslash.
+    escaped: bool,
+    content: Vec<u8>,
+    hasher: crc32fast::Hasher,
+}
+
+impl SourceContent {
+    pub(crate) fn new(opts: &TmpOptions) -> Self {
+        SourceContent {
+            sep: opts.sep,
+            eol: opts.eol,
+            unescapes: opts.escape.is_some() && !opts.double_quotes,
+            escaped: false,
+            content: Vec::new(),
+            hasher: crc32fast::Hasher::new(),
+        }
+    }
+
+    pub(crate) fn feed(&mut self, buf: &[u8]) {
+        for &byte in buf {
+            match byte {
+                // a backslash escaping nothing of its field is content
+                _ if byte == self.sep || byte == self.eol => {
+                    if std::mem::take(&mut self.escaped) {
+                        self.content.push(b'\\');
+                    }
+                }
+                _ if std::mem::take(&mut self.escaped) => self.content.push(byte),
+                b'\\' if self.unescapes => self.escaped = true,
+                _ => self.content.push(byte),
+            }
+        }
+        self.hasher.update(&self.content);
+        self.content.clear();
+    }
+
+    pub(crate) fn finish(mut self) -> u32 {
+        if self.escaped {
+            self.hasher.update(b"\\");
+        }
+        self.hasher.finalize()
+    }
+}
+
+/// A reader feeding what it reads from `inner` to a `SourceContent`.
+pub(crate) struct Sourced<'a, R> {
+    inner: R,
+    content: &'a mut SourceContent,
+}
+
+impl<'a, R> Sourced<'a, R> {
+    pub(crate) fn new(inner: R, content: &'a mut SourceContent) -> Self {
+        Sourced { inner, content }
+    }
+}
+
+impl<R: Read> Read for Sourced<'_, R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let read = self.inner.read(buf)?;
+        self.content.feed(&buf[..read]);
+        Ok(read)
+    }
+}
+
+fn sorted(counts: &HashMap<u64, u64>) -> Vec<(u64, u64)> {
+    let mut counts: Vec<_> = counts.iter().map(|(&k, &v)| (k, v)).collect();
+    counts.sort_unstable();
+    counts
+}
+
+/// A writer parsing what it passes on to `inner` when given a parser.
+pub(crate) struct Verified<W> {
+    inner: W,
+    parser: Option<Rfc4180>,
+}
+
+impl<W> Verified<W> {
+    pub(crate) fn new(inner: W, parser: Option<Rfc4180>) -> Self {
+        Verified { inner, parser }
+    }
+
+    pub(crate) fn into_parts(self) -> (W, Option<Rfc4180>) {
+        (self.inner, self.parser)
+    }
+}
+
+impl<W: Write> Write for Verified<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let written = self.inner.write(buf)?;
+        if let Some(parser) = &mut self.parser {
+            parser.feed(&buf[..written])?;
+        }
+        Ok(written)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::buffered::{self, NoHook};
+    use crate::reader::{self, ExtraCol};
+    use rstest::*;
+    use std::io::Cursor;
+
+    /// Fixes `data` through a verifying writer, as a run with `verify_rfc4180` does.
+    fn verified_fix(data: &[u8], opts: &TmpOptions) -> io::Result<Vec<u8>> {
+        let mut writer = Verified::new(Vec::new(), Some(Rfc4180::new(opts)));
+        let input = Cursor::new(data);
+        let stats = match opts.buffers() {
+            true => buffered::exorcize_csv(input, &mut writer, 3, opts, &mut NoHook)?,
+            false => reader::exorcize_csv(input, &mut writer, data.len() as u64, 3, opts)?,
+        };
+        let (output, parser) = writer.into_parts();
+        parser.unwrap().finish(&stats, opts, None)?;
+        Ok(output)
+    }
+
+    /// Parses `output` as the output of rows counted in `field_counts`.
+    fn verify(output: &[u8], field_counts: &[(u64, u64)], opts: &TmpOptions) -> io::Result<()> {
+        let mut stats = Stats::default();
+        stats.field_counts = field_counts.iter().copied().collect();
+        let mut parser = Rfc4180::new(opts);
+        parser.feed(output)?;
+        parser.finish(&stats, opts, None)
+    }
+
+    /// Fixes `data` and compares the field content of the output to that of `data`, after
+    /// flipping the case of its first letter if `mutate`d, as a fixer bug could.
+    fn content_check(data: &[u8], opts: &TmpOptions, mutate: bool) -> io::Result<()> {
+        let mut source = SourceContent::new(opts);
+        let mut output = Vec::new();
+        let input = Sourced::new(Cursor::new(data), &mut source);
+        let stats = reader::exorcize_csv(input, &mut output, data.len() as u64, 3, opts)?;
+        if mutate {
+            let letter = output.iter_mut().find(|b| b.is_ascii_lowercase()).unwrap();
+            letter.make_ascii_uppercase();
+        }
+        let mut parser = Rfc4180::new(opts);
+        parser.feed(&output)?;
+        parser.finish(&stats, opts, Some(source.finish()))
+    }
+
+    #[rstest]
+    #[case(b"a\x1Eb\x1Dc\x1Ed\x1D")]
+    #[case(b"a\"b\x1E\\\x1Dc\\\"d\x1Ee")]
+    #[case(b"ragged\x1Dr\x1Eo\x1Ew\x1D\x1D")]
+    #[case(b"a,b\nc\r\n\x1E\x1E")]
+    #[case(b"\x1D")]
+    #[case(b"")]
+    fn test_known_good_output_passes(#[case] data: &[u8]) {
+        let defaults = || TmpOptions::default();
+        let all = [
+            defaults(),
+            TmpOptions {
+                header: Some(vec![b"x".to_vec(), b"y".to_vec()]),
+                out_eol: b"\r\n".to_vec(),
+                final_newline: Some(false),
+                ..defaults()
+            },
+            TmpOptions {
+                pad_to: Some(2),
+                extra_columns: Some(vec![ExtraCol::RowNumber]),
+                ..defaults()
+            },
+            defaults().copy_compatible(),
+            TmpOptions {
+                column_order: Some(vec![2, 0]),
+                extra_columns: Some(vec![ExtraCol::RowNumber]),
+                ..defaults()
+            },
+            TmpOptions {
+                quote_empty: false,
+                double_backslash: true,
+                ..defaults()
+            },
+            TmpOptions {
+                write_bom: true,
+                header: Some(vec![b"x".to_vec()]),
+                ..defaults()
+            },
+        ];
+        for opts in &all {
+            verified_fix(data, opts).unwrap();
+        }
+    }
+
+    #[rstest]
+    #[case(b"\"a\",\"b\"\n\"c\"", &[(2, 1), (1, 1)])]
+    #[case(b"a,,\"\"\nb\r", &[(3, 1), (1, 1)])]
+    #[case(b"\"a\\\"\",\"\\\\\"\n", &[(2, 1)])]
+    fn test_well_formed(#[case] output: &[u8], #[case] counts: &[(u64, u64)]) {
+        let opts = TmpOptions {
+            out_eol: b"\n".to_vec(),
+            ..TmpOptions::default()
+        };
+        verify(output, counts, &opts).unwrap();
+    }
+
+    #[rstest]
+    #[case(b"\"a\"b\"\n", 3, "content after a closing quote")]
+    #[case(b"\"a\\\"\n", 5, "the end inside a quoted field")]
+    #[case(b"a\"b\n", 1, "a quote inside an unquoted field")]
+    #[case(
+        b"\"a\",\"b\"\n",
+        8,
+        "1 records by field count [(2, 1)] for 1 rows by field count [(1, 1)]"
+    )]
+    #[case(
+        b"\"a\"\n\"b\"\n",
+        8,
+        "2 records by field count [(1, 2)] for 1 rows by field count [(1, 1)]"
+    )]
+    fn test_corrupted_output_fails(#[case] output: &[u8], #[case] at: u64, #[case] what: &str) {
+        let e = verify(output, &[(1, 1)], &TmpOptions::default()).unwrap_err();
+        assert_eq!(e.kind(), io::ErrorKind::InvalidData);
+        assert_eq!(
+            e.to_string(),
+            format!("output is not valid RFC 4180 at byte {at}: {what}; this is a fixer bug")
+        );
+    }
+
+    #[rstest]
+    fn test_doubled_quotes_and_crlf() {
+        let opts = TmpOptions {
+            out_eol: b"\r\n".to_vec(),
+            ..TmpOptions::default().copy_compatible()
+        };
+        verify(b"\"a\"\"b\",c\r\n", &[(2, 1)], &opts).unwrap();
+        // a backslash escapes nothing once quotes are doubled
+        let e = verify(b"\"a\\\"b\"\r\n", &[(1, 1)], &opts).unwrap_err();
+        assert!(e
+            .to_string()
+            .contains("at byte 4: content after a closing quote"));
+        let e = verify(b"\"a\"\rb", &[(1, 1)], &opts).unwrap_err();
+        assert!(e
+            .to_string()
+            .contains("at byte 4: a broken newline after a field"));
+    }
+
+    #[rstest]
+    #[case(b"a\x1Eb\x1Dc\x1Ed\x1D")]
+    #[case(b"a\"b\x1E\\\x1Dc\\\"d\x1Ee")]
+    #[case(b"\\\\\x1E\\n\\\\\"\x1D\"\"\\")]
+    #[case(b"a,b\r\n\x1E\x1E")]
+    #[case(b"")]
+    fn test_content_matches_source(#[case] data: &[u8]) {
+        let all = [
+            TmpOptions::default(),
+            TmpOptions::default().copy_compatible(),
+            TmpOptions {
+                out_eol: b"\r\n".to_vec(),
+                quote_empty: false,
+                ..TmpOptions::default()
+            },
+        ];
+        for opts in &all {
+            content_check(data, opts, false).unwrap();
+            if !data.is_empty() {
+                let e = content_check(data, opts, true).unwrap_err();
+                assert!(e.to_string().contains("field content differs"), "{e}");
+            }
+        }
+    }
+
+    #[rstest]
+    fn test_corrupted_stream_fails_on_write() {
+        let mut writer = Verified::new(Vec::new(), Some(Rfc4180::new(&TmpOptions::default())));
+        writer.write_all(b"\"a\",").unwrap();
+        let e = writer.write_all(b"b\"").unwrap_err();
+        assert_eq!(e.kind(), io::ErrorKind::InvalidData);
+    }
+}